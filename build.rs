@@ -0,0 +1,48 @@
+// Checks that a version of wgpu was picked before anything is compiled, and tells the crate
+// whether there is one with the `has_wgpu` cfg. Without wgpu, only the measure feature's
+// TextMeasurer is built, so everything that needs a device is behind that cfg.
+//
+// The compile_error!s in lib.rs say the same things, but rustc carries on after them and buries
+// them under hundreds of errors from everything that uses wgpu.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rustc-check-cfg=cfg(has_wgpu)");
+
+    let feature = |name: &str| std::env::var_os(format!("CARGO_FEATURE_{name}")).is_some();
+
+    if feature("WGPU_0_20") || feature("WGPU_22") {
+        println!("cargo:rustc-cfg=has_wgpu");
+        return;
+    }
+
+    if !feature("MEASURE") {
+        fail(
+            "kaku needs a version of wgpu to build against: enable one of the wgpu-* features \
+            (e.g. wgpu-0_20 or wgpu-22), or the measure feature to only lay text out without one",
+        );
+    }
+
+    // Features that draw, or that make or read textures
+    let gpu_features = [
+        ("CPU_RENDER", "cpu-render"),
+        ("HEADLESS", "headless"),
+        ("TEST_UTILS", "test-utils"),
+        ("COSMIC_TEXT", "cosmic-text"),
+        ("DISK_CACHE", "disk-cache"),
+        ("MSDF_JSON", "msdf-json"),
+    ];
+    for (var, name) in gpu_features {
+        if feature(var) {
+            fail(&format!(
+                "the {name} feature needs a version of wgpu: enable one of the wgpu-* features \
+                (e.g. wgpu-0_20 or wgpu-22) too"
+            ));
+        }
+    }
+}
+
+fn fail(message: &str) {
+    eprintln!("{message}");
+    std::process::exit(1);
+}
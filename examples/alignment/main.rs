@@ -59,36 +59,52 @@ impl BasicTextAppInner {
         let fira_sans = FontArc::new(
             FontRef::try_from_slice(include_bytes!("../fonts/FiraSans-Regular.ttf")).unwrap(),
         );
-        let fira_sans = text_renderer.load_font(fira_sans, FontSize::Pt(40.));
+        let fira_sans = text_renderer
+            .load_font(fira_sans, FontSize::Pt(40.))
+            .unwrap();
 
         let mut builder = TextBuilder::new("hello!", fira_sans, [50., 100.]);
         builder.vertical_align(VerticalAlignment::Baseline);
-        let valign_baseline = builder.build(&renderer.device, &renderer.queue, &mut text_renderer);
+        let valign_baseline = builder
+            .build(&renderer.device, &renderer.queue, &mut text_renderer)
+            .unwrap();
 
         builder.vertical_align(VerticalAlignment::Top);
         builder.position([230., 100.]);
-        let valign_top = builder.build(&renderer.device, &renderer.queue, &mut text_renderer);
+        let valign_top = builder
+            .build(&renderer.device, &renderer.queue, &mut text_renderer)
+            .unwrap();
 
         builder.vertical_align(VerticalAlignment::Middle);
         builder.position([430., 100.]);
-        let valign_middle = builder.build(&renderer.device, &renderer.queue, &mut text_renderer);
+        let valign_middle = builder
+            .build(&renderer.device, &renderer.queue, &mut text_renderer)
+            .unwrap();
 
         builder.vertical_align(VerticalAlignment::Bottom);
         builder.position([630., 100.]);
-        let valign_bottom = builder.build(&renderer.device, &renderer.queue, &mut text_renderer);
+        let valign_bottom = builder
+            .build(&renderer.device, &renderer.queue, &mut text_renderer)
+            .unwrap();
 
         let mut builder =
             TextBuilder::new("hello, align!", fira_sans, [WINDOW_WIDTH as f32 / 2., 300.]);
         builder.horizontal_align(HorizontalAlignment::Left);
-        let halign_left = builder.build(&renderer.device, &renderer.queue, &mut text_renderer);
+        let halign_left = builder
+            .build(&renderer.device, &renderer.queue, &mut text_renderer)
+            .unwrap();
 
         builder.horizontal_align(HorizontalAlignment::Center);
         builder.position([WINDOW_WIDTH as f32 / 2., 400.]);
-        let halign_center = builder.build(&renderer.device, &renderer.queue, &mut text_renderer);
+        let halign_center = builder
+            .build(&renderer.device, &renderer.queue, &mut text_renderer)
+            .unwrap();
 
         builder.horizontal_align(HorizontalAlignment::Right);
         builder.position([WINDOW_WIDTH as f32 / 2., 500.]);
-        let halign_right = builder.build(&renderer.device, &renderer.queue, &mut text_renderer);
+        let halign_right = builder
+            .build(&renderer.device, &renderer.queue, &mut text_renderer)
+            .unwrap();
 
         Self {
             text_renderer,
@@ -140,20 +156,27 @@ impl BasicTextAppInner {
         });
 
         self.text_renderer
-            .draw_text(&mut render_pass, &self.valign_baseline);
+            .draw_text(&mut render_pass, &self.valign_baseline)
+            .unwrap();
         self.text_renderer
-            .draw_text(&mut render_pass, &self.valign_top);
+            .draw_text(&mut render_pass, &self.valign_top)
+            .unwrap();
         self.text_renderer
-            .draw_text(&mut render_pass, &self.valign_middle);
+            .draw_text(&mut render_pass, &self.valign_middle)
+            .unwrap();
         self.text_renderer
-            .draw_text(&mut render_pass, &self.valign_bottom);
+            .draw_text(&mut render_pass, &self.valign_bottom)
+            .unwrap();
 
         self.text_renderer
-            .draw_text(&mut render_pass, &self.halign_left);
+            .draw_text(&mut render_pass, &self.halign_left)
+            .unwrap();
         self.text_renderer
-            .draw_text(&mut render_pass, &self.halign_center);
+            .draw_text(&mut render_pass, &self.halign_center)
+            .unwrap();
         self.text_renderer
-            .draw_text(&mut render_pass, &self.halign_right);
+            .draw_text(&mut render_pass, &self.halign_right)
+            .unwrap();
 
         // And that's it!
 
@@ -24,6 +24,7 @@ const WINDOW_HEIGHT: u32 = 700;
 
 use kaku::{FontSize, SdfSettings, Text, TextBuilder, TextRenderer, TextRendererBuilder};
 
+#[allow(clippy::manual_range_contains)]
 fn hsva_to_rgba(mut h: f32, mut s: f32, mut v: f32, a: f32) -> [f32; 4] {
     s = s.clamp(0., 1.);
     v = v.clamp(0., 1.);
@@ -89,43 +90,57 @@ impl BasicTextAppInner {
             FontRef::try_from_slice(include_bytes!("../fonts/FiraSans-Regular.ttf")).unwrap(),
         );
 
-        let fira_sans_sdf = text_renderer.load_font_with_sdf(
-            fira_sans.clone(),
-            FontSize::Pt(60.),
-            SdfSettings { radius: 20.0 },
-        );
-        let fira_sans = text_renderer.load_font(fira_sans, FontSize::Pt(60.));
+        let fira_sans_sdf = text_renderer
+            .load_font_with_sdf(
+                fira_sans.clone(),
+                FontSize::Pt(60.),
+                SdfSettings {
+                    radius: 20.0,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let fira_sans = text_renderer
+            .load_font(fira_sans, FontSize::Pt(60.))
+            .unwrap();
 
         // If you want to create a lot of similar text with slightly different options, you can use
         // the TextBuilder in a stateful way:
         let mut builder = TextBuilder::new("hello, world! glyph :3", fira_sans, [50., 120.]);
 
-        let hello_world = builder.build(&renderer.device, &renderer.queue, &mut text_renderer);
+        let hello_world = builder
+            .build(&renderer.device, &renderer.queue, &mut text_renderer)
+            .unwrap();
 
         builder.font(fira_sans_sdf);
         builder.position([50., 220.]);
-        let hello_world_sdf = builder.build(&renderer.device, &renderer.queue, &mut text_renderer);
+        let hello_world_sdf = builder
+            .build(&renderer.device, &renderer.queue, &mut text_renderer)
+            .unwrap();
 
         let outline_color = hsva_to_rgba(0.0, 1.0, 1.0, 1.0);
         builder.position([50., 340.]);
         builder.color([1.; 4]);
         builder.outlined(outline_color, 15.);
-        let hello_world_outline =
-            builder.build(&renderer.device, &renderer.queue, &mut text_renderer);
+        let hello_world_outline = builder
+            .build(&renderer.device, &renderer.queue, &mut text_renderer)
+            .unwrap();
 
         builder.position([50., 520.]);
         builder.font_size(Some(FontSize::Pt(120.)));
         builder.color([0., 0., 0., 1.]);
         builder.no_outline();
-        let hello_world_scaled =
-            builder.build(&renderer.device, &renderer.queue, &mut text_renderer);
+        let hello_world_scaled = builder
+            .build(&renderer.device, &renderer.queue, &mut text_renderer)
+            .unwrap();
 
         // Or you can use the builder with chained methods like this for a one-off
         let fps_text = TextBuilder::new("fps: ", fira_sans_sdf, [40., 40.])
             .color([1., 0., 1., 1.])
             .scale(0.3)
             .outlined([1., 1., 1., 1.], 2.)
-            .build(&renderer.device, &renderer.queue, &mut text_renderer);
+            .build(&renderer.device, &renderer.queue, &mut text_renderer)
+            .unwrap();
 
         Self {
             text_renderer,
@@ -148,12 +163,14 @@ impl BasicTextAppInner {
         if elapsed > FPS_POLL_TIME_LIMIT {
             let fps = self.frame_count / elapsed;
 
-            self.fps_text.set_text(
-                format!("fps: {fps:.2}"),
-                &self.renderer.device,
-                &self.renderer.queue,
-                &mut self.text_renderer,
-            );
+            self.fps_text
+                .set_text(
+                    format!("fps: {fps:.2}"),
+                    &self.renderer.device,
+                    &self.renderer.queue,
+                    &mut self.text_renderer,
+                )
+                .unwrap();
 
             self.frame_count = 0.;
             self.fps_poll_start = Instant::now();
@@ -211,15 +228,20 @@ impl BasicTextAppInner {
 
         // Now, we can simply draw our Text objects onto the render pass using the TextRenderer
         self.text_renderer
-            .draw_text(&mut render_pass, &self.fps_text);
+            .draw_text(&mut render_pass, &self.fps_text)
+            .unwrap();
         self.text_renderer
-            .draw_text(&mut render_pass, &self.hello_world);
+            .draw_text(&mut render_pass, &self.hello_world)
+            .unwrap();
         self.text_renderer
-            .draw_text(&mut render_pass, &self.hello_world_sdf);
+            .draw_text(&mut render_pass, &self.hello_world_sdf)
+            .unwrap();
         self.text_renderer
-            .draw_text(&mut render_pass, &self.hello_world_outline);
+            .draw_text(&mut render_pass, &self.hello_world_outline)
+            .unwrap();
         self.text_renderer
-            .draw_text(&mut render_pass, &self.hello_world_scaled);
+            .draw_text(&mut render_pass, &self.hello_world_scaled)
+            .unwrap();
 
         // And that's it!
 
@@ -0,0 +1,318 @@
+//! Web - an example that runs both natively and in the browser.
+//!
+//! Natively, run it with `cargo run --example web`. To run it in a browser, build it for wasm32
+//! and generate the bindings with wasm-bindgen, then serve the `examples/web` directory:
+//!
+//! ```sh
+//! cargo build --example web --target wasm32-unknown-unknown
+//! wasm-bindgen --target web --out-dir examples/web/pkg \
+//!     target/wasm32-unknown-unknown/debug/examples/web.wasm
+//! python3 -m http.server -d examples/web
+//! ```
+//!
+//! The kaku code is the same on both platforms. The differences are all in setting up wgpu and
+//! winit: on the web nothing can block, so wgpu is set up asynchronously and the finished state is
+//! sent back to the event loop, and browsers without WebGPU fall back to WebGL2, which needs
+//! kaku's compatibility mode.
+use std::sync::Arc;
+
+use ab_glyph::FontRef;
+use kaku::{
+    FontOptions, FontSize, Preload, SdfSettings, Text, TextBuilder, TextRenderer,
+    TextRendererBuilder,
+};
+use web_time::Instant;
+use winit::{
+    application::ApplicationHandler,
+    dpi::PhysicalSize,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy},
+    window::{Window, WindowId},
+};
+
+const WINDOW_WIDTH: u32 = 800;
+const WINDOW_HEIGHT: u32 = 300;
+const TITLE: &str = "Hello from kaku!";
+
+struct State {
+    window: Arc<Window>,
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    text_renderer: TextRenderer,
+    title: Text,
+    subtitle: Text,
+    subtitle_text: String,
+    start: Instant,
+}
+
+impl State {
+    async fn new(window: Arc<Window>) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::default();
+        let surface = instance.create_surface(window.clone()).unwrap();
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                compatible_surface: Some(&surface),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        // WebGL2 (and other downlevel backends) can't do everything kaku normally uses, so use
+        // compatibility mode there. It only needs the most basic limits and no features
+        let compatibility = adapter.get_info().backend == wgpu::Backend::Gl;
+        let required_limits = if compatibility {
+            wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits())
+        } else {
+            wgpu::Limits::default()
+        };
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    required_limits,
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let config = surface
+            .get_default_config(&adapter, size.width.max(1), size.height.max(1))
+            .unwrap();
+        surface.configure(&device, &config);
+
+        let mut text_renderer =
+            TextRendererBuilder::new(config.format, (config.width, config.height))
+                .with_compatibility_mode(compatibility)
+                .build(&device);
+
+        // Positions and sizes are in logical pixels, so the text is the same size on HiDPI
+        // screens (and in browsers that are zoomed in)
+        text_renderer.set_scale_factor(window.scale_factor());
+
+        // Generating glyphs takes a little time, and in the browser it happens on the main
+        // thread. Preloading the characters we need while the page loads means it doesn't happen
+        // later while animating
+        let font = text_renderer
+            .load_font_with_options(
+                FontRef::try_from_slice(include_bytes!("../fonts/FiraSans-Regular.ttf")).unwrap(),
+                FontOptions::new(FontSize::Pt(40.))
+                    .with_sdf(SdfSettings {
+                        radius: 15.,
+                        ..Default::default()
+                    })
+                    .with_preload(Preload::Ascii),
+                &device,
+                &queue,
+            )
+            .unwrap();
+
+        let title = TextBuilder::new(TITLE, font, [40., 100.])
+            .color([1., 1., 1., 1.])
+            .outlined([0.8, 0.2, 0.5, 1.], 6.)
+            .build(&device, &queue, &mut text_renderer)
+            .unwrap();
+
+        let subtitle_text = format!("Drawing with {:?}", adapter.get_info().backend);
+        let subtitle = TextBuilder::new(subtitle_text.clone(), font, [40., 200.])
+            .color([0.1, 0.1, 0.1, 1.])
+            .scale(0.5)
+            .build(&device, &queue, &mut text_renderer)
+            .unwrap();
+
+        Self {
+            window,
+            surface,
+            device,
+            queue,
+            config,
+            text_renderer,
+            title,
+            subtitle,
+            subtitle_text,
+            start: Instant::now(),
+        }
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        if size.width > 0 && size.height > 0 {
+            self.config.width = size.width;
+            self.config.height = size.height;
+            self.surface.configure(&self.device, &self.config);
+            self.text_renderer.resize(size.into(), &self.queue);
+        }
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.text_renderer.set_scale_factor(scale_factor);
+
+        // Setting the text again lays it out at the new scale factor
+        let (device, queue) = (&self.device, &self.queue);
+        self.title
+            .set_text(TITLE.into(), device, queue, &mut self.text_renderer)
+            .unwrap();
+        self.subtitle
+            .set_text(
+                self.subtitle_text.clone(),
+                device,
+                queue,
+                &mut self.text_renderer,
+            )
+            .unwrap();
+    }
+
+    fn render(&mut self) {
+        let elapsed = self.start.elapsed().as_secs_f32();
+        let width = 6. + 3. * (elapsed * 2.).sin();
+        self.title
+            .set_outline([0.8, 0.2, 0.5, 1.], width, &self.queue);
+
+        let output = match self.surface.get_current_texture() {
+            Ok(output) => output,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.config);
+                return;
+            }
+            Err(e) => {
+                log::error!("{e:?}");
+                return;
+            }
+        };
+
+        let view = output.texture.create_view(&Default::default());
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.95,
+                            g: 0.9,
+                            b: 0.8,
+                            a: 1.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+
+            self.text_renderer
+                .draw_text(&mut render_pass, &self.title)
+                .unwrap();
+            self.text_renderer
+                .draw_text(&mut render_pass, &self.subtitle)
+                .unwrap();
+        }
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+    }
+}
+
+struct App {
+    proxy: EventLoopProxy<State>,
+    state: Option<State>,
+    window_created: bool,
+}
+
+impl ApplicationHandler<State> for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window_created {
+            return;
+        }
+        self.window_created = true;
+
+        #[allow(unused_mut)]
+        let mut attributes = Window::default_attributes()
+            .with_title("kaku web example")
+            .with_inner_size(PhysicalSize::new(WINDOW_WIDTH, WINDOW_HEIGHT));
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            use winit::platform::web::WindowAttributesExtWebSys;
+            attributes = attributes.with_append(true);
+        }
+
+        let window = Arc::new(event_loop.create_window(attributes).unwrap());
+
+        // Setting up wgpu is async, and can't be blocked on in the browser
+        #[cfg(target_arch = "wasm32")]
+        {
+            let proxy = self.proxy.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let state = State::new(window).await;
+                let _ = proxy.send_event(state);
+            });
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let state = pollster::block_on(State::new(window));
+            let _ = self.proxy.send_event(state);
+        }
+    }
+
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, state: State) {
+        state.window.request_redraw();
+        self.state = Some(state);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
+
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(size) => state.resize(size),
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                state.set_scale_factor(scale_factor)
+            }
+            WindowEvent::RedrawRequested => {
+                state.render();
+                state.window.request_redraw();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn main() {
+    #[cfg(target_arch = "wasm32")]
+    {
+        std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+        console_log::init_with_level(log::Level::Warn).unwrap();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    env_logger::init();
+
+    let event_loop = EventLoop::with_user_event().build().unwrap();
+    let app = App {
+        proxy: event_loop.create_proxy(),
+        state: None,
+        window_created: false,
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        use winit::platform::web::EventLoopExtWebSys;
+        event_loop.spawn_app(app);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut app = app;
+        event_loop.run_app(&mut app).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+//! Effects that move or recolour each glyph of a text over time (see [GlyphAnimation] and
+//! [Marquee]).
+
+use crate::layout::Rect;
+
+/// An effect applied to every glyph of a [Text](crate::Text), e.g. the wavy or shaking text of
+/// RPG dialogue boxes. Set it with [TextBuilder::animation](crate::TextBuilder::animation) or
+/// [Text::set_animation](crate::Text::set_animation).
+///
+/// The effects are done in the vertex shader, so animating text doesn't upload anything. They're
+/// driven by the renderer's clock, which you advance every frame with
+/// [TextRenderer::set_time](crate::TextRenderer::set_time), and by each glyph's index in the
+/// text, so neighbouring glyphs are out of step with each other. Amplitudes are in logical
+/// pixels and speeds are in cycles per second.
+///
+/// Animated glyphs move away from where the text is laid out, so [Text::bounds](crate::Text::bounds)
+/// and the other positions a text returns don't include the effect.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub enum GlyphAnimation {
+    /// The glyphs stay still.
+    #[default]
+    None,
+    /// The glyphs bob up and down in a wave that travels along the text.
+    Wave {
+        /// How far the glyphs move up and down.
+        amplitude: f32,
+        /// How many times a second each glyph goes up and down.
+        speed: f32,
+    },
+    /// Each glyph jumps to a random offset a number of times a second.
+    Shake {
+        /// How far the glyphs can move in each direction.
+        amplitude: f32,
+        /// How many times a second the glyphs jump.
+        speed: f32,
+    },
+    /// The glyphs cycle through the hues of the rainbow, keeping the alpha of the text's colour.
+    /// Outlines keep their own colour.
+    Rainbow {
+        /// How many times a second each glyph goes through every hue.
+        speed: f32,
+    },
+}
+
+impl GlyphAnimation {
+    /// The animation's settings for the shaders: the mode, the amplitude in physical pixels and
+    /// the speed.
+    pub(crate) fn uniform(&self, scale_factor: f32) -> [f32; 4] {
+        match *self {
+            GlyphAnimation::None => [0.; 4],
+            GlyphAnimation::Wave { amplitude, speed } => [1., amplitude * scale_factor, speed, 0.],
+            GlyphAnimation::Shake { amplitude, speed } => [2., amplitude * scale_factor, speed, 0.],
+            GlyphAnimation::Rainbow { speed } => [3., 0., speed, 0.],
+        }
+    }
+}
+
+/// Settings for a text that scrolls sideways inside a box narrower than itself, like a news
+/// ticker or a long song title. Set it with [TextBuilder::marquee](crate::TextBuilder::marquee)
+/// or [Text::set_marquee](crate::Text::set_marquee).
+///
+/// The box starts at the left of the text's [bounds](crate::Text::bounds) and is as tall as the
+/// text, and anything outside it isn't drawn. A text that fits in the box doesn't scroll.
+/// Like [GlyphAnimation]s, the scrolling is done in the shaders with the renderer's clock (see
+/// [TextRenderer::set_time](crate::TextRenderer::set_time)), so nothing is uploaded while it
+/// scrolls, and a looping text comes back in from the right without a second copy of it.
+///
+/// Sizes are in logical pixels and speeds are in logical pixels per second.
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub struct Marquee {
+    /// The width of the box the text scrolls in.
+    pub width: f32,
+    /// How fast the text scrolls to the left.
+    pub speed: f32,
+    /// The space between the end of the text and its start coming round again, when looping.
+    /// It should be at least as wide as the text's widest glyph.
+    pub gap: f32,
+    /// Whether the text goes round forever. Otherwise it stops once its end is in the box.
+    pub looping: bool,
+}
+
+impl Marquee {
+    /// A looping marquee in a box of a width, scrolling at 40 pixels a second with a gap of 40
+    /// pixels.
+    pub fn new(width: f32) -> Self {
+        Self {
+            width,
+            speed: 40.,
+            gap: 40.,
+            looping: true,
+        }
+    }
+
+    /// The marquee's settings for the shaders, for a text with some bounds in physical pixels
+    /// relative to its position: the start time, the speed in physical pixels, the length of a
+    /// loop (0 when it doesn't loop) and the furthest it scrolls, then the left and right of the
+    /// box and whether there is one.
+    pub(crate) fn uniform(
+        marquee: Option<Marquee>,
+        start: f32,
+        bounds: Rect,
+        scale_factor: f32,
+    ) -> [[f32; 4]; 2] {
+        let Some(marquee) = marquee else {
+            return [[0.; 4]; 2];
+        };
+
+        let left = bounds.min[0];
+        let width = marquee.width.max(0.) * scale_factor;
+        let overflow = bounds.width() - width;
+
+        let motion = if overflow <= 0. {
+            [start, 0., 0., 0.]
+        } else if marquee.looping {
+            let period = bounds.width() + marquee.gap.max(0.) * scale_factor;
+            [start, marquee.speed * scale_factor, period, 0.]
+        } else {
+            [start, marquee.speed * scale_factor, 0., overflow]
+        };
+
+        [motion, [left, left + width, 1., 0.]]
+    }
+}
+
+/// Glyphs fading and sliding in one after another (see [Text::play_reveal](crate::Text::play_reveal)).
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub(crate) struct Reveal {
+    /// The renderer's time when the first glyph starts to appear, in seconds.
+    pub(crate) start: f32,
+    /// How long each glyph takes to appear, in seconds.
+    pub(crate) duration: f32,
+    /// How long each glyph starts after the one before it, in seconds.
+    pub(crate) stagger: f32,
+}
+
+impl Reveal {
+    /// The reveal's settings for the shaders. A duration of 0 means every glyph is shown.
+    pub(crate) fn uniform(reveal: Option<Reveal>) -> [f32; 4] {
+        match reveal {
+            Some(Reveal {
+                start,
+                duration,
+                stagger,
+            }) => [start, duration.max(0.), stagger, 0.],
+            None => [0.; 4],
+        }
+    }
+}
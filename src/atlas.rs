@@ -0,0 +1,478 @@
+//! Importing pre-generated signed distance field atlases.
+//!
+//! Tools like [msdf-atlas-gen](https://github.com/Chlumsky/msdf-atlas-gen) and BMFont-compatible
+//! generators (e.g. Hiero) can create distance fields for a whole font ahead of time. Loading
+//! these with [crate::TextRenderer::load_sdf_atlas] means no distance fields have to be generated
+//! at runtime, at the cost of only being able to draw the characters in the atlas.
+
+use std::fmt;
+
+use image::{DynamicImage, GenericImage, GenericImageView, GrayImage, Luma};
+
+use crate::{
+    bmfont::{BmChar, BmFont, BmFontError, ChannelContents},
+    sdf::SdfImage,
+    FontMetrics,
+};
+
+/// An error from importing an sdf atlas.
+#[derive(Debug)]
+pub enum AtlasError {
+    /// The BMFont descriptor couldn't be parsed.
+    BmFont(BmFontError),
+    /// The msdf-atlas-gen json descriptor couldn't be parsed.
+    #[cfg(feature = "msdf-json")]
+    Json(serde_json::Error),
+    /// The atlas is of a type that doesn't contain a distance field (e.g. a plain raster atlas).
+    UnsupportedType(String),
+    /// The descriptor refers to a page that wasn't given.
+    MissingPage(usize),
+    /// A glyph's bounds lie outside its atlas page.
+    GlyphOutOfBounds(char),
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtlasError::BmFont(e) => write!(f, "invalid BMFont descriptor: {e}"),
+            #[cfg(feature = "msdf-json")]
+            AtlasError::Json(e) => write!(f, "invalid atlas json: {e}"),
+            AtlasError::UnsupportedType(ty) => write!(f, "unsupported atlas type \"{ty}\""),
+            AtlasError::MissingPage(page) => write!(f, "atlas page {page} was not provided"),
+            AtlasError::GlyphOutOfBounds(c) => {
+                write!(f, "glyph for {c:?} lies outside of its atlas page")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {}
+
+impl From<BmFontError> for AtlasError {
+    fn from(e: BmFontError) -> Self {
+        AtlasError::BmFont(e)
+    }
+}
+
+#[cfg(feature = "msdf-json")]
+impl From<serde_json::Error> for AtlasError {
+    fn from(e: serde_json::Error) -> Self {
+        AtlasError::Json(e)
+    }
+}
+
+/// A glyph taken out of an atlas.
+#[derive(Debug, Clone)]
+pub(crate) struct AtlasGlyph {
+    pub(crate) c: char,
+    /// The distance field, in kaku's convention (values below 0.5 are inside the glyph).
+    /// None for glyphs with nothing to draw, like spaces.
+    pub(crate) image: Option<SdfImage>,
+    /// The offset of the top left of the glyph's quad from the pen position, in pixels.
+    pub(crate) position: [f32; 2],
+    /// The size of the glyph's quad, in pixels.
+    pub(crate) size: [f32; 2],
+    pub(crate) advance: f32,
+}
+
+/// A font's worth of pre-generated signed distance fields, loaded from an external tool's output.
+///
+/// Load this into the renderer with [crate::TextRenderer::load_sdf_atlas].
+#[derive(Debug, Clone)]
+pub struct SdfAtlas {
+    pub(crate) glyphs: Vec<AtlasGlyph>,
+    pub(crate) px_size: f32,
+    pub(crate) radius: f32,
+    pub(crate) metrics: FontMetrics,
+}
+
+/// How to turn the pixels of an atlas into a single distance value.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(not(feature = "msdf-json"), allow(dead_code))]
+enum Channels {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+    /// The median of the red, green and blue channels, for multi-channel distance fields.
+    Median,
+}
+
+/// Copies a region of an atlas page into a distance field, converting it to kaku's convention.
+fn crop(page: &DynamicImage, (x, y, w, h): (u32, u32, u32, u32), channels: Channels) -> SdfImage {
+    SdfImage::from_fn(w, h, |px, py| {
+        let [r, g, b, a] = page.get_pixel(x + px, y + py).0;
+
+        let value = match channels {
+            Channels::Red => r,
+            Channels::Green => g,
+            Channels::Blue => b,
+            Channels::Alpha => a,
+            Channels::Median => r.max(g).min(r.min(g).max(b)),
+        };
+
+        // These tools store the inside of the glyph as bright values, which is the opposite of
+        // what kaku uses
+        Luma([1. - value as f32 / 255.])
+    })
+}
+
+/// Where a BMFont atlas keeps a character's distance field.
+///
+/// Characters packed into one channel of the page say which with `chnl`. Otherwise the field is
+/// in whichever of red and alpha the "common" line says holds the glyph. Tools that don't say
+/// either (e.g. Hiero) draw the field into the alpha channel of white glyphs, unless the page has
+/// no alpha channel.
+fn bmfont_channels(font: &BmFont, ch: &BmChar, page: &DynamicImage) -> Channels {
+    match ch.channels {
+        Some(1) => return Channels::Blue,
+        Some(2) => return Channels::Green,
+        Some(4) => return Channels::Red,
+        Some(8) => return Channels::Alpha,
+        _ => {}
+    }
+
+    let has_glyph = |contents| {
+        matches!(
+            contents,
+            Some(ChannelContents::Glyph | ChannelContents::GlyphAndOutline)
+        )
+    };
+
+    if has_glyph(font.red_channel) {
+        Channels::Red
+    } else if has_glyph(font.alpha_channel) || page.color().has_alpha() {
+        Channels::Alpha
+    } else {
+        Channels::Red
+    }
+}
+
+/// Converts a distance field to an image in the convention used by external tools, with the
+/// inside of the glyph being bright.
+pub(crate) fn export_image(image: &SdfImage) -> GrayImage {
+    GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        Luma([((1. - image.get_pixel(x, y).0[0]) * 255.).round() as u8])
+    })
+}
+
+/// Whether a rect read from a descriptor lies inside its page. The rect can be anything the file
+/// says, so it mustn't overflow.
+fn fits(page: &DynamicImage, (x, y, w, h): (u32, u32, u32, u32)) -> bool {
+    x.checked_add(w).is_some_and(|right| right <= page.width())
+        && y.checked_add(h)
+            .is_some_and(|bottom| bottom <= page.height())
+}
+
+impl SdfAtlas {
+    /// Loads an atlas made by a BMFont-compatible tool with a distance field effect (e.g. Hiero).
+    ///
+    /// `descriptor` is the contents of the text format .fnt file, and `pages` are the page images
+    /// in the order of their ids. BMFont files don't record how far the distance field spreads,
+    /// so this needs to be given as `radius` (in pixels, half of the total spread).
+    ///
+    /// The distance fields are read from the channel the descriptor says holds the glyphs (its
+    /// `chnl`, `redChnl` and `alphaChnl` values). If it doesn't say, as with Hiero, they're read
+    /// from the alpha channel of pages that have one and the red channel of those that don't.
+    pub fn from_bmfont(
+        descriptor: &str,
+        pages: &[DynamicImage],
+        radius: f32,
+    ) -> Result<Self, AtlasError> {
+        let font = BmFont::parse(descriptor)?;
+
+        let glyphs = font
+            .chars
+            .iter()
+            .filter_map(|ch| Some((char::from_u32(ch.id)?, ch)))
+            .map(|(c, ch)| {
+                let page = pages.get(ch.page).ok_or(AtlasError::MissingPage(ch.page))?;
+                let rect = (ch.x, ch.y, ch.width, ch.height);
+
+                if !fits(page, rect) {
+                    return Err(AtlasError::GlyphOutOfBounds(c));
+                }
+
+                let channels = bmfont_channels(&font, ch, page);
+                let image = (ch.width > 0 && ch.height > 0).then(|| crop(page, rect, channels));
+
+                Ok(AtlasGlyph {
+                    c,
+                    image,
+                    position: [ch.xoffset, ch.yoffset - font.base],
+                    size: [ch.width as f32, ch.height as f32],
+                    advance: ch.xadvance,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            glyphs,
+            px_size: font.size,
+            radius,
+            metrics: FontMetrics {
+                ascent: font.base,
+                descent: font.base - font.line_height,
+                line_gap: 0.,
+            },
+        })
+    }
+
+    /// Loads an atlas made by [msdf-atlas-gen](https://github.com/Chlumsky/msdf-atlas-gen), from
+    /// its json descriptor and the atlas image.
+    ///
+    /// Atlases of type `sdf`, `psdf`, `msdf` and `mtsdf` are supported. Multi-channel fields are
+    /// converted to a single channel when they are loaded, so they will not keep their sharp
+    /// corners.
+    #[cfg(feature = "msdf-json")]
+    pub fn from_msdf_json(json: &str, atlas: &DynamicImage) -> Result<Self, AtlasError> {
+        use msdf_json::{Bounds, Descriptor};
+
+        let descriptor: Descriptor = serde_json::from_str(json)?;
+        let info = &descriptor.atlas;
+
+        let channels = match info.ty.as_str() {
+            "sdf" | "psdf" => Channels::Red,
+            "msdf" => Channels::Median,
+            "mtsdf" => Channels::Alpha,
+            ty => return Err(AtlasError::UnsupportedType(ty.to_string())),
+        };
+
+        let y_down = info.y_origin == "top";
+        let size = info.size;
+
+        // Converts bounds to (left, top, right, bottom) with y pointing down
+        let y_down_bounds = |b: &Bounds, height: f32| -> [f32; 4] {
+            if y_down {
+                [b.left, b.top, b.right, b.bottom]
+            } else {
+                [b.left, height - b.top, b.right, height - b.bottom]
+            }
+        };
+
+        let glyphs = descriptor
+            .glyphs
+            .iter()
+            .filter_map(|glyph| Some((char::from_u32(glyph.unicode)?, glyph)))
+            .map(|(c, glyph)| {
+                let advance = glyph.advance * size;
+
+                let (Some(plane), Some(bounds)) = (&glyph.plane_bounds, &glyph.atlas_bounds) else {
+                    return Ok(AtlasGlyph {
+                        c,
+                        image: None,
+                        position: [0.; 2],
+                        size: [0.; 2],
+                        advance,
+                    });
+                };
+
+                // Plane bounds are in ems relative to the baseline, so "flipping" them just
+                // means negating them
+                let [left, top, _, _] = y_down_bounds(plane, 0.);
+                let [ax0, ay0, ax1, ay1] = y_down_bounds(bounds, atlas.height() as f32);
+
+                // The atlas bounds aren't always on whole pixels, so grow them to the nearest
+                // pixel and grow the quad by the same amount
+                let (x0, y0) = (ax0.floor(), ay0.floor());
+                let (x1, y1) = (ax1.ceil(), ay1.ceil());
+
+                // Casting would clamp bounds that are negative or back to front onto the page
+                if !(x0 >= 0. && y0 >= 0. && x1 >= x0 && y1 >= y0) {
+                    return Err(AtlasError::GlyphOutOfBounds(c));
+                }
+
+                let rect = (x0 as u32, y0 as u32, (x1 - x0) as u32, (y1 - y0) as u32);
+                if !fits(atlas, rect) {
+                    return Err(AtlasError::GlyphOutOfBounds(c));
+                }
+
+                let has_area = rect.2 > 0 && rect.3 > 0;
+
+                Ok(AtlasGlyph {
+                    c,
+                    image: has_area.then(|| crop(atlas, rect, channels)),
+                    position: [left * size - (ax0 - x0), top * size - (ay0 - y0)],
+                    size: [x1 - x0, y1 - y0],
+                    advance,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let metrics = &descriptor.metrics;
+        let (ascent, descent) = if y_down {
+            (-metrics.ascender, -metrics.descender)
+        } else {
+            (metrics.ascender, metrics.descender)
+        };
+
+        Ok(Self {
+            glyphs,
+            px_size: size,
+            radius: info.distance_range / 2.,
+            metrics: FontMetrics {
+                ascent: ascent * size,
+                descent: descent * size,
+                line_gap: (metrics.line_height - (ascent - descent)) * size,
+            },
+        })
+    }
+
+    /// The characters that this atlas has glyphs for.
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.glyphs.iter().map(|glyph| glyph.c)
+    }
+
+    /// Packs this atlas into a single page image and writes a text format BMFont descriptor for
+    /// it, which refers to the page by `page_file`.
+    ///
+    /// The result can be loaded again with [SdfAtlas::from_bmfont], with the same radius as this
+    /// atlas (see [SdfAtlas::radius]).
+    pub fn to_bmfont(&self, page_file: &str) -> (String, GrayImage) {
+        // Leave a pixel between glyphs so they don't bleed into each other when sampled
+        const SPACING: u32 = 1;
+
+        let images = self
+            .glyphs
+            .iter()
+            .filter_map(|glyph| glyph.image.as_ref())
+            .collect::<Vec<_>>();
+
+        let area: u32 = images
+            .iter()
+            .map(|image| (image.width() + SPACING) * (image.height() + SPACING))
+            .sum();
+        let widest = images.iter().map(|image| image.width()).max().unwrap_or(0);
+        let page_width = ((area as f32).sqrt().ceil() as u32)
+            .next_power_of_two()
+            .max(widest + SPACING);
+
+        // Place glyphs in rows from tallest to shortest, starting a new row when one fills up
+        let mut order = (0..self.glyphs.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| {
+            std::cmp::Reverse(
+                self.glyphs[i]
+                    .image
+                    .as_ref()
+                    .map_or(0, |image| image.height()),
+            )
+        });
+
+        let mut placements = vec![(0, 0); self.glyphs.len()];
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+
+        for &i in &order {
+            let Some(image) = &self.glyphs[i].image else {
+                continue;
+            };
+
+            if x + image.width() > page_width {
+                x = 0;
+                y += row_height + SPACING;
+                row_height = 0;
+            }
+
+            placements[i] = (x, y);
+            x += image.width() + SPACING;
+            row_height = row_height.max(image.height());
+        }
+
+        let mut page = GrayImage::new(page_width, (y + row_height).max(1));
+        let metrics = &self.metrics;
+        let base = metrics.ascent;
+        let line_height = metrics.ascent - metrics.descent + metrics.line_gap;
+
+        let mut descriptor = format!(
+            "info face=\"kaku\" size={} padding={r},{r},{r},{r}\n\
+             common lineHeight={line_height} base={base} scaleW={} scaleH={} pages=1 packed=0 \
+             alphaChnl=4 redChnl=0 greenChnl=0 blueChnl=0\n\
+             page id=0 file=\"{page_file}\"\n\
+             chars count={}\n",
+            self.px_size,
+            page.width(),
+            page.height(),
+            self.glyphs.len(),
+            r = self.radius.ceil(),
+        );
+
+        for (glyph, &(x, y)) in self.glyphs.iter().zip(&placements) {
+            let (width, height) = match &glyph.image {
+                Some(image) => {
+                    // The page is big enough for every glyph by construction
+                    page.copy_from(&export_image(image), x, y).unwrap();
+                    image.dimensions()
+                }
+                None => (0, 0),
+            };
+
+            descriptor += &format!(
+                "char id={} x={x} y={y} width={width} height={height} xoffset={} yoffset={} \
+                 xadvance={} page=0 chnl=15\n",
+                glyph.c as u32,
+                glyph.position[0],
+                glyph.position[1] + base,
+                glyph.advance,
+            );
+        }
+
+        (descriptor, page)
+    }
+
+    /// The radius of the distance fields in this atlas, in pixels.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+/// The parts of the msdf-atlas-gen json format that we use.
+#[cfg(feature = "msdf-json")]
+mod msdf_json {
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    pub(super) struct Descriptor {
+        pub(super) atlas: AtlasInfo,
+        pub(super) metrics: Metrics,
+        pub(super) glyphs: Vec<Glyph>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct AtlasInfo {
+        #[serde(rename = "type")]
+        pub(super) ty: String,
+        pub(super) distance_range: f32,
+        pub(super) size: f32,
+        #[serde(default = "default_y_origin")]
+        pub(super) y_origin: String,
+    }
+
+    fn default_y_origin() -> String {
+        "bottom".to_string()
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Metrics {
+        pub(super) line_height: f32,
+        pub(super) ascender: f32,
+        pub(super) descender: f32,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Glyph {
+        pub(super) unicode: u32,
+        pub(super) advance: f32,
+        pub(super) plane_bounds: Option<Bounds>,
+        pub(super) atlas_bounds: Option<Bounds>,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct Bounds {
+        pub(super) left: f32,
+        pub(super) bottom: f32,
+        pub(super) right: f32,
+        pub(super) top: f32,
+    }
+}
@@ -0,0 +1,396 @@
+//! Texts drawn once into their own texture, which is then drawn as a single quad (see
+//! [TextRenderer::bake]).
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    blur::{BlurPipeline, BLUR_REACH},
+    frame_stats::FrameCounters,
+    layout,
+    profiling::Profiling,
+    target::OffscreenAttachments,
+    target::TargetPipelines,
+    CharacterInstance, Error, FrameStats, Rect, ScreenUniform, TargetId, Text, TextRenderer,
+};
+
+/// The settings of a baked text for the shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct BakedSettingsUniform {
+    pub(crate) text_position: [f32; 2],
+    pub(crate) relative_position: [f32; 2],
+}
+
+/// A [Text] that has been drawn into a texture of its own, made with [TextRenderer::bake] and
+/// drawn with [TextRenderer::draw_baked].
+///
+/// Drawing it is a single quad however many glyphs, outlines and effects the text has, at the
+/// cost of the texture's memory. The text is baked as it looked when it was baked: changing the
+/// [Text] afterwards doesn't change this, and animations, reveals and marquees are frozen at the
+/// renderer's [time](TextRenderer::time) at the time.
+#[derive(Debug)]
+pub struct BakedText {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    /// The quad the texture is drawn on, as one instance.
+    instance_buffer: wgpu::Buffer,
+    settings_buffer: wgpu::Buffer,
+    settings_bind_group: wgpu::BindGroup,
+    /// The quad in physical pixels, relative to the text's position.
+    quad: Rect,
+    position: [f32; 2],
+    relative_position: [f32; 2],
+    scale_factor: f32,
+    /// Whether the text's position is rounded to whole pixels (see
+    /// [TextBuilder::pixel_snap](crate::TextBuilder::pixel_snap)).
+    pixel_snap: bool,
+}
+
+impl BakedText {
+    /// The texture the text was drawn into, e.g. for post-processing it. It's in the format of
+    /// the renderer's main target, and its colours are premultiplied by their alpha.
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    /// Returns the rectangle the baked text covers on the screen, in the same units as its
+    /// position. This is the texture's size, which covers both the text's
+    /// [bounds](Text::bounds), its [ink](Text::ink_bounds) and its [panel](Text::panel_rect),
+    /// and as far as they're blurred if the text was baked with [TextRenderer::bake_blurred].
+    pub fn bounds(&self) -> Rect {
+        let rect = self.quad.translate(self.draw_position());
+
+        Rect {
+            min: rect.min.map(|p| p / self.scale_factor),
+            max: rect.max.map(|p| p / self.scale_factor),
+        }
+    }
+
+    /// The position of the text the baked text was made from, which it's drawn relative to.
+    pub fn position(&self) -> [f32; 2] {
+        self.position
+    }
+
+    /// Moves the baked text, like [Text::set_position].
+    pub fn set_position(&mut self, position: [f32; 2], queue: &wgpu::Queue) {
+        self.position = position;
+        queue.write_buffer(
+            &self.settings_buffer,
+            0,
+            bytemuck::bytes_of(&self.settings_uniform()),
+        );
+    }
+
+    /// The position the quad is drawn relative to in physical pixels, rounded like the text's.
+    fn draw_position(&self) -> [f32; 2] {
+        let position = self.position.map(|p| p * self.scale_factor);
+
+        if self.pixel_snap {
+            position.map(f32::round)
+        } else {
+            position
+        }
+    }
+
+    fn settings_uniform(&self) -> BakedSettingsUniform {
+        BakedSettingsUniform {
+            text_position: self.draw_position(),
+            relative_position: self.relative_position,
+        }
+    }
+}
+
+impl TextRenderer {
+    /// Draws a text into a texture of its own, which can then be drawn as a single quad with
+    /// [TextRenderer::draw_baked]. For static paragraphs with a lot of glyphs or heavy effects
+    /// this trades memory for less work each frame, and the texture can be post-processed (see
+    /// [BakedText::texture]).
+    ///
+    /// The texture covers the text's bounds, ink bounds and panel at the renderer's scale factor, in the
+    /// format of the renderer's main target. The text is drawn straight away, so this submits to
+    /// the queue.
+    ///
+    /// Returns an error if the text's font isn't loaded in this renderer, or if the texture would
+    /// be bigger than the device supports.
+    pub fn bake(
+        &self,
+        text: &Text,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<BakedText, Error> {
+        self.bake_blurred(text, 0., device, queue)
+    }
+
+    /// Bakes a text like [TextRenderer::bake], then blurs it with a gaussian blur, e.g. for soft
+    /// drop shadows (bake a dark copy of the text and draw it under the text, a little offset) or
+    /// frosted glass text. Unlike an sdf outline, the blur can be as wide as you like.
+    ///
+    /// `radius` is the standard deviation of the blur in logical pixels, like CSS's `blur()`. The
+    /// texture is made bigger so that nothing the blur spreads out is cut off.
+    pub fn bake_blurred(
+        &self,
+        text: &Text,
+        radius: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<BakedText, Error> {
+        self.bake_blurred_for_target(self.main_target(), text, radius, device, queue)
+    }
+
+    /// Bakes a text like [TextRenderer::bake], but with one of the renderer's other targets (see
+    /// [TextRenderer::add_target]), so the texture is in that target's format, e.g. a float
+    /// format for HDR text. The baked text can still be drawn to any target.
+    ///
+    /// Returns an error if the target wasn't added to this renderer or is a multiview target, as
+    /// well as for the reasons [TextRenderer::bake] does.
+    pub fn bake_for_target(
+        &self,
+        target: TargetId,
+        text: &Text,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<BakedText, Error> {
+        self.bake_blurred_for_target(target, text, 0., device, queue)
+    }
+
+    /// Bakes and blurs a text like [TextRenderer::bake_blurred], but with one of the renderer's
+    /// other targets like [TextRenderer::bake_for_target].
+    pub fn bake_blurred_for_target(
+        &self,
+        target: TargetId,
+        text: &Text,
+        radius: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<BakedText, Error> {
+        let id = target;
+        let target = self.target(id)?;
+        let config = &target.config;
+
+        // The text is drawn into a single texture, not one layer for each view
+        if config.multiview.is_some() {
+            return Err(Error::MultiviewTarget(id));
+        }
+
+        let data = &text.data;
+        let scale_factor = data.scale_factor;
+        let position = data.draw_position();
+
+        let sigma = radius.max(0.) * scale_factor;
+        let padding = (sigma * BLUR_REACH).ceil();
+
+        // The pixels the text covers, which the texture's pixels line up with
+        let drawn = [text.ink_bounds(), text.panel_rect()];
+        let on_screen = layout::union(
+            [text.bounds()]
+                .into_iter()
+                .chain(drawn.into_iter().flatten()),
+        )
+        .expect("there's always a bounds");
+        let min = on_screen.min.map(|p| (p * scale_factor).floor() - padding);
+        let max = on_screen.max.map(|p| (p * scale_factor).ceil() + padding);
+        let [width, height] = [0, 1].map(|i| ((max[i] - min[i]) as u32).max(1));
+
+        let max_size = device.limits().max_texture_dimension_2d;
+        if width > max_size || height > max_size {
+            return Err(Error::TextureTooLarge {
+                size: (width, height),
+                max: max_size,
+            });
+        }
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(&self.label("baked text texture")),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+
+        // The text is drawn with its top left corner at the texture's origin. Its position
+        // relative to the target is taken back off, since that's added when the quad is drawn
+        let relative =
+            [0, 1].map(|i| (data.relative_position[i] * [width, height][i] as f32).round());
+        let screen_uniform =
+            ScreenUniform::new((width, height)).translated([0, 1].map(|i| -(min[i] + relative[i])));
+        let screen_bind_group = self.create_screen_bind_group(screen_uniform, device);
+
+        let attachments = OffscreenAttachments::new(
+            config,
+            size,
+            |name| self.label(&format!("baked text {name}")),
+            device,
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some(&self.label("baked text command encoder")),
+        });
+
+        {
+            // Drawing over transparent black leaves colours multiplied by their alpha
+            let mut render_pass = attachments.begin_pass(
+                &mut encoder,
+                &view,
+                wgpu::Color::TRANSPARENT,
+                &self.label("baked text render pass"),
+            );
+            self.draw_text_with_screen(
+                &mut render_pass,
+                text,
+                &target.pipelines,
+                &screen_bind_group,
+                Profiling::NONE,
+            )?;
+        }
+
+        if sigma > 0. {
+            let blur = target
+                .blur_pipeline
+                .get_or_init(|| BlurPipeline::new(config.format, |name| self.label(name), device));
+            blur.blur(
+                &texture,
+                sigma,
+                &mut encoder,
+                |name| self.label(&format!("baked text {name}")),
+                device,
+            );
+        }
+        queue.submit([encoder.finish()]);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&self.label("baked text texture bind group")),
+            layout: &self.char_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.linear_sampler),
+                },
+            ],
+        });
+
+        let quad = Rect {
+            min: [0, 1].map(|i| min[i] - position[i]),
+            max: [0, 1].map(|i| min[i] - position[i] + [width, height][i] as f32),
+        };
+        let instance = CharacterInstance {
+            position: quad.min,
+            size: [width as f32, height as f32],
+            index: 0,
+        };
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&self.label("baked text instance buffer")),
+            contents: bytemuck::bytes_of(&instance),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let settings = BakedSettingsUniform {
+            text_position: position,
+            relative_position: data.relative_position,
+        };
+        let settings_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&self.label("baked text settings uniform buffer")),
+            contents: bytemuck::bytes_of(&settings),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let settings_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&self.label("baked text settings uniform bind group")),
+            layout: &self.baked_settings_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: settings_buffer.as_entire_binding(),
+            }],
+        });
+
+        Ok(BakedText {
+            texture,
+            bind_group,
+            instance_buffer,
+            settings_buffer,
+            settings_bind_group,
+            quad,
+            position: data.position,
+            relative_position: data.relative_position,
+            scale_factor,
+            pixel_snap: data.snaps_to_pixels(),
+        })
+    }
+
+    /// Draws a [BakedText] to a render pass, like [TextRenderer::draw_text].
+    pub fn draw_baked<'pass>(
+        &'pass self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        baked: &'pass BakedText,
+    ) {
+        let target = &self.targets[0];
+        draw_baked_with_screen(
+            &self.frame_counters,
+            render_pass,
+            baked,
+            &target.pipelines,
+            &target.screen_bind_group,
+        );
+    }
+
+    /// Draws a [BakedText] to a render pass for one of the renderer's other targets, like
+    /// [TextRenderer::draw_text_for_target].
+    ///
+    /// Returns an error if the target wasn't added to this renderer, in which case nothing is
+    /// drawn.
+    pub fn draw_baked_for_target<'pass>(
+        &'pass self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        target: TargetId,
+        baked: &'pass BakedText,
+    ) -> Result<(), Error> {
+        let target = self.target(target)?;
+        draw_baked_with_screen(
+            &self.frame_counters,
+            render_pass,
+            baked,
+            &target.pipelines,
+            &target.screen_bind_group,
+        );
+        Ok(())
+    }
+}
+
+/// Draws a baked text's quad with a target's pipelines and a screen uniform, counting it in the
+/// renderer's frame stats.
+fn draw_baked_with_screen<'pass>(
+    counters: &FrameCounters,
+    render_pass: &mut wgpu::RenderPass<'pass>,
+    baked: &'pass BakedText,
+    pipelines: &'pass TargetPipelines,
+    screen_bind_group: &'pass wgpu::BindGroup,
+) {
+    render_pass.set_pipeline(&pipelines.baked);
+    render_pass.set_bind_group(0, screen_bind_group, &[]);
+    render_pass.set_bind_group(1, &baked.bind_group, &[]);
+    render_pass.set_bind_group(2, &baked.settings_bind_group, &[]);
+    render_pass.set_vertex_buffer(0, baked.instance_buffer.slice(..));
+    render_pass.draw(0..4, 0..1);
+
+    counters.add(&FrameStats {
+        texts_drawn: 1,
+        instances: 1,
+        draw_calls: 1,
+        bind_group_switches: 3,
+        bytes_uploaded: 0,
+    });
+}
@@ -0,0 +1,674 @@
+//! Loading fonts made of pre-drawn glyph images, from classic bitmap font formats (BDF and PCF)
+//! and BMFont atlases.
+//!
+//! BDF and PCF fonts are made of 1 bit images, so they are drawn exactly as they were designed
+//! (with no anti-aliasing) and can only be scaled by whole pixels without looking off. BMFont
+//! atlases are usually anti-aliased. Load either with [crate::TextRenderer::load_bitmap_font].
+
+use std::fmt;
+
+use image::{DynamicImage, GenericImageView, GrayImage, Luma};
+
+use crate::{
+    bmfont::{BmFont, BmFontError},
+    FontMetrics, HashMap,
+};
+
+/// An error from loading a bitmap font.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BitmapFontError {
+    /// The BDF file couldn't be parsed.
+    Bdf {
+        /// The line the error was found on.
+        line: usize,
+        /// What went wrong.
+        message: String,
+    },
+    /// The PCF file couldn't be parsed.
+    Pcf(String),
+    /// The BMFont descriptor couldn't be parsed.
+    BmFont(BmFontError),
+    /// The descriptor refers to a page that wasn't given.
+    MissingPage(usize),
+    /// A glyph's bounds lie outside its atlas page.
+    GlyphOutOfBounds(char),
+}
+
+impl fmt::Display for BitmapFontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitmapFontError::Bdf { line, message } => {
+                write!(f, "invalid BDF file: line {line}: {message}")
+            }
+            BitmapFontError::Pcf(message) => write!(f, "invalid PCF file: {message}"),
+            BitmapFontError::BmFont(e) => write!(f, "invalid BMFont descriptor: {e}"),
+            BitmapFontError::MissingPage(page) => write!(f, "page {page} was not provided"),
+            BitmapFontError::GlyphOutOfBounds(c) => {
+                write!(f, "glyph for {c:?} lies outside of its page")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BitmapFontError {}
+
+impl From<BmFontError> for BitmapFontError {
+    fn from(e: BmFontError) -> Self {
+        BitmapFontError::BmFont(e)
+    }
+}
+
+/// A single glyph of a bitmap font.
+#[derive(Debug, Clone)]
+pub(crate) struct BitmapGlyph {
+    pub(crate) c: char,
+    /// The glyph's coverage. None for glyphs with nothing to draw, like spaces.
+    pub(crate) image: Option<GrayImage>,
+    /// The offset of the top left of the glyph from the pen position, in pixels.
+    pub(crate) position: [f32; 2],
+    pub(crate) advance: f32,
+}
+
+/// A font made of pre-drawn glyph images, loaded from a BDF or PCF file or a BMFont atlas.
+///
+/// Load this into the renderer with [crate::TextRenderer::load_bitmap_font].
+#[derive(Debug, Clone)]
+pub struct BitmapFont {
+    pub(crate) glyphs: Vec<BitmapGlyph>,
+    pub(crate) px_size: f32,
+    pub(crate) metrics: FontMetrics,
+    /// Extra space between pairs of characters, in pixels.
+    pub(crate) kerning: HashMap<(char, char), f32>,
+    /// How the glyphs should be sampled. 1 bit fonts use nearest neighbour filtering to stay
+    /// crisp when scaled up.
+    pub(crate) filter: wgpu::FilterMode,
+}
+
+/// Creates a glyph image from rows of packed bits, most significant bit first.
+fn unpack_bits(rows: &[&[u8]], width: u32) -> Option<GrayImage> {
+    if width == 0 || rows.is_empty() {
+        return None;
+    }
+
+    Some(GrayImage::from_fn(width, rows.len() as u32, |x, y| {
+        let byte = rows[y as usize].get(x as usize / 8).copied().unwrap_or(0);
+        let bit = (byte >> (7 - x % 8)) & 1;
+        Luma([bit * 255])
+    }))
+}
+
+impl BitmapFont {
+    /// Returns the characters in the font.
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.glyphs.iter().map(|glyph| glyph.c)
+    }
+
+    /// Loads a font from the contents of a BDF (Glyph Bitmap Distribution Format) file.
+    ///
+    /// Glyph encodings are assumed to be unicode code points (which is true for fonts encoded in
+    /// ISO 10646 or ISO 8859-1). Glyphs without an encoding are skipped.
+    pub fn from_bdf(bdf: &str) -> Result<Self, BitmapFontError> {
+        let mut glyphs = Vec::new();
+        let mut properties = HashMap::new();
+        let mut bounding_box = [0i32; 4];
+
+        let mut lines = bdf.lines().enumerate();
+
+        let error = |line: usize, message: &str| BitmapFontError::Bdf {
+            line: line + 1,
+            message: message.to_string(),
+        };
+
+        let numbers = |line: usize, values: &str| -> Result<Vec<i32>, BitmapFontError> {
+            values
+                .split_whitespace()
+                .map(|v| v.parse().map_err(|_| error(line, "invalid number")))
+                .collect()
+        };
+
+        while let Some((i, line)) = lines.next() {
+            let (keyword, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+
+            match keyword {
+                "FONTBOUNDINGBOX" => {
+                    bounding_box = numbers(i, rest)?
+                        .try_into()
+                        .map_err(|_| error(i, "FONTBOUNDINGBOX needs 4 values"))?;
+
+                    // The ascent and descent are worked out from these if there aren't properties
+                    // for them
+                    let [_, height, _, y] = bounding_box;
+                    if height.checked_add(y).is_none() || y.checked_neg().is_none() {
+                        return Err(error(i, "FONTBOUNDINGBOX is out of range"));
+                    }
+                }
+                "STARTPROPERTIES" => {
+                    for (_, line) in lines.by_ref() {
+                        if line.trim() == "ENDPROPERTIES" {
+                            break;
+                        }
+
+                        if let Some((name, value)) = line.trim().split_once(' ') {
+                            properties.insert(name.to_string(), value.trim().to_string());
+                        }
+                    }
+                }
+                "STARTCHAR" => {
+                    let mut encoding = -1;
+                    let mut advance = 0;
+                    let mut bbx = [0i32; 4];
+                    // How far the top of the glyph is below the baseline
+                    let mut top = 0i32;
+                    let mut rows = Vec::new();
+
+                    while let Some((i, line)) = lines.next() {
+                        let (keyword, rest) =
+                            line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+
+                        match keyword {
+                            "ENCODING" => {
+                                encoding = *numbers(i, rest)?
+                                    .first()
+                                    .ok_or_else(|| error(i, "missing encoding"))?
+                            }
+                            "DWIDTH" => {
+                                advance = *numbers(i, rest)?
+                                    .first()
+                                    .ok_or_else(|| error(i, "missing width"))?
+                            }
+                            "BBX" => {
+                                bbx = numbers(i, rest)?
+                                    .try_into()
+                                    .map_err(|_| error(i, "BBX needs 4 values"))?;
+
+                                let [_, height, _, y] = bbx;
+                                top = (y.checked_add(height))
+                                    .and_then(i32::checked_neg)
+                                    .ok_or_else(|| error(i, "BBX is out of range"))?;
+                            }
+                            "BITMAP" => {
+                                for (i, line) in lines.by_ref() {
+                                    let line = line.trim();
+
+                                    if line == "ENDCHAR" {
+                                        break;
+                                    }
+
+                                    if !line.is_ascii() {
+                                        return Err(error(i, "invalid bitmap data"));
+                                    }
+
+                                    let row = (0..line.len() / 2)
+                                        .map(|j| u8::from_str_radix(&line[j * 2..j * 2 + 2], 16))
+                                        .collect::<Result<Vec<_>, _>>()
+                                        .map_err(|_| error(i, "invalid bitmap data"))?;
+
+                                    rows.push(row);
+                                }
+
+                                break;
+                            }
+                            "ENDCHAR" => break,
+                            _ => {}
+                        }
+                    }
+
+                    let Some(c) = u32::try_from(encoding).ok().and_then(char::from_u32) else {
+                        continue;
+                    };
+
+                    let [width, height, x, _] = bbx;
+                    let rows = rows.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+                    glyphs.push(BitmapGlyph {
+                        c,
+                        image: unpack_bits(
+                            &rows[..rows.len().min(height.max(0) as usize)],
+                            width.max(0) as u32,
+                        ),
+                        position: [x as f32, top as f32],
+                        advance: advance as f32,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        let property = |name: &str| properties.get(name).and_then(|v| v.parse::<f32>().ok());
+
+        let ascent = property("FONT_ASCENT").unwrap_or((bounding_box[1] + bounding_box[3]) as f32);
+        let descent = property("FONT_DESCENT").unwrap_or(-bounding_box[3] as f32);
+
+        Ok(Self {
+            glyphs,
+            px_size: property("PIXEL_SIZE").unwrap_or(ascent + descent),
+            metrics: FontMetrics {
+                ascent,
+                descent: -descent,
+                line_gap: 0.,
+            },
+            kerning: HashMap::new(),
+            filter: wgpu::FilterMode::Nearest,
+        })
+    }
+
+    /// Loads a font from the contents of a PCF (Portable Compiled Format) file.
+    ///
+    /// PCF files are often distributed compressed (as `.pcf.gz`); they need to be decompressed
+    /// before being passed here. Glyph encodings are assumed to be unicode code points, like with
+    /// [BitmapFont::from_bdf].
+    pub fn from_pcf(data: &[u8]) -> Result<Self, BitmapFontError> {
+        pcf::parse(data)
+    }
+
+    /// Loads a font from an AngelCode BMFont atlas, including its kerning pairs.
+    ///
+    /// `descriptor` is the contents of the text format .fnt file, and `pages` are the page images
+    /// in the order of their ids. The glyphs' coverage is taken from the alpha channel of the
+    /// pages if they have one, and from their brightness otherwise; any colour in the pages is
+    /// ignored, since the colour is set per [crate::Text].
+    ///
+    /// For BMFont atlases of distance fields, use [crate::SdfAtlas::from_bmfont] instead.
+    pub fn from_bmfont(descriptor: &str, pages: &[DynamicImage]) -> Result<Self, BitmapFontError> {
+        let font = BmFont::parse(descriptor)?;
+
+        let glyphs = font
+            .chars
+            .iter()
+            .filter_map(|ch| Some((char::from_u32(ch.id)?, ch)))
+            .map(|(c, ch)| {
+                let page = pages
+                    .get(ch.page)
+                    .ok_or(BitmapFontError::MissingPage(ch.page))?;
+
+                // The rect can be anything the file says, so it mustn't overflow
+                let right = ch.x.checked_add(ch.width);
+                let bottom = ch.y.checked_add(ch.height);
+                if !(right.is_some_and(|right| right <= page.width())
+                    && bottom.is_some_and(|bottom| bottom <= page.height()))
+                {
+                    return Err(BitmapFontError::GlyphOutOfBounds(c));
+                }
+
+                let has_alpha = page.color().has_alpha();
+                let image = (ch.width > 0 && ch.height > 0).then(|| {
+                    GrayImage::from_fn(ch.width, ch.height, |x, y| {
+                        let pixel = page.get_pixel(ch.x + x, ch.y + y);
+
+                        match has_alpha {
+                            true => Luma([pixel.0[3]]),
+                            false => image::Pixel::to_luma(&pixel),
+                        }
+                    })
+                });
+
+                Ok(BitmapGlyph {
+                    c,
+                    image,
+                    position: [ch.xoffset, ch.yoffset - font.base],
+                    advance: ch.xadvance,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let kerning = font
+            .kernings
+            .iter()
+            .filter_map(|(&(first, second), &amount)| {
+                Some(((char::from_u32(first)?, char::from_u32(second)?), amount))
+            })
+            .collect();
+
+        Ok(Self {
+            glyphs,
+            px_size: font.size,
+            metrics: FontMetrics {
+                ascent: font.base,
+                descent: font.base - font.line_height,
+                line_gap: 0.,
+            },
+            kerning,
+            filter: wgpu::FilterMode::Linear,
+        })
+    }
+}
+
+mod pcf {
+    //! The PCF format is a binary version of BDF, made of a table of contents followed by
+    //! tables that each start with their own format flags.
+    //!
+    //! See <https://fontforge.org/docs/techref/pcf-format.html> for a description of the format.
+
+    use super::{unpack_bits, BitmapFont, BitmapFontError, BitmapGlyph};
+    use crate::FontMetrics;
+
+    const PROPERTIES: u32 = 1 << 0;
+    const ACCELERATORS: u32 = 1 << 1;
+    const METRICS: u32 = 1 << 2;
+    const BITMAPS: u32 = 1 << 3;
+    const BDF_ENCODINGS: u32 = 1 << 5;
+    const BDF_ACCELERATORS: u32 = 1 << 8;
+
+    const COMPRESSED_METRICS: u32 = 0x100;
+    const BYTE_MSB_FIRST: u32 = 1 << 2;
+    const BIT_MSB_FIRST: u32 = 1 << 3;
+
+    fn error(message: &str) -> BitmapFontError {
+        BitmapFontError::Pcf(message.to_string())
+    }
+
+    /// Reads the values in a table, in the byte order given by the table's format.
+    struct Reader<'a> {
+        data: &'a [u8],
+        pos: usize,
+        big_endian: bool,
+    }
+
+    impl<'a> Reader<'a> {
+        /// Starts reading a table, reading its format first.
+        fn table(data: &'a [u8], offset: usize) -> Result<(Self, u32), BitmapFontError> {
+            let mut reader = Reader {
+                data,
+                pos: offset,
+                big_endian: false,
+            };
+
+            // The format itself is always little endian
+            let format = reader.u32()?;
+            reader.big_endian = format & BYTE_MSB_FIRST != 0;
+            Ok((reader, format))
+        }
+
+        fn bytes(&mut self, len: usize) -> Result<&'a [u8], BitmapFontError> {
+            let bytes = self
+                .data
+                .get(self.pos..self.pos + len)
+                .ok_or_else(|| error("unexpected end of file"))?;
+
+            self.pos += len;
+            Ok(bytes)
+        }
+
+        fn u8(&mut self) -> Result<u8, BitmapFontError> {
+            Ok(self.bytes(1)?[0])
+        }
+
+        fn u16(&mut self) -> Result<u16, BitmapFontError> {
+            let bytes = self.bytes(2)?.try_into().unwrap();
+
+            Ok(match self.big_endian {
+                true => u16::from_be_bytes(bytes),
+                false => u16::from_le_bytes(bytes),
+            })
+        }
+
+        fn i16(&mut self) -> Result<i16, BitmapFontError> {
+            Ok(self.u16()? as i16)
+        }
+
+        fn u32(&mut self) -> Result<u32, BitmapFontError> {
+            let bytes = self.bytes(4)?.try_into().unwrap();
+
+            Ok(match self.big_endian {
+                true => u32::from_be_bytes(bytes),
+                false => u32::from_le_bytes(bytes),
+            })
+        }
+
+        fn i32(&mut self) -> Result<i32, BitmapFontError> {
+            Ok(self.u32()? as i32)
+        }
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    struct Metrics {
+        left: i32,
+        right: i32,
+        width: i32,
+        ascent: i32,
+        descent: i32,
+    }
+
+    fn read_metrics(data: &[u8], offset: usize) -> Result<Vec<Metrics>, BitmapFontError> {
+        let (mut reader, format) = Reader::table(data, offset)?;
+
+        if format & COMPRESSED_METRICS != 0 {
+            let count = reader.u16()? as usize;
+
+            (0..count)
+                .map(|_| {
+                    let mut next = || Ok::<_, BitmapFontError>(reader.u8()? as i32 - 0x80);
+
+                    Ok(Metrics {
+                        left: next()?,
+                        right: next()?,
+                        width: next()?,
+                        ascent: next()?,
+                        descent: next()?,
+                    })
+                })
+                .collect()
+        } else {
+            let count = reader.u32()? as usize;
+
+            (0..count)
+                .map(|_| {
+                    let metrics = Metrics {
+                        left: reader.i16()? as i32,
+                        right: reader.i16()? as i32,
+                        width: reader.i16()? as i32,
+                        ascent: reader.i16()? as i32,
+                        descent: reader.i16()? as i32,
+                    };
+
+                    // Attributes, which we don't need
+                    reader.u16()?;
+                    Ok(metrics)
+                })
+                .collect()
+        }
+    }
+
+    /// Reads the bitmap of every glyph, converted to rows padded to a whole byte with the most
+    /// significant bit first.
+    fn read_bitmaps(
+        data: &[u8],
+        offset: usize,
+        metrics: &[Metrics],
+    ) -> Result<Vec<Option<image::GrayImage>>, BitmapFontError> {
+        let (mut reader, format) = Reader::table(data, offset)?;
+
+        let count = reader.u32()? as usize;
+        let offsets = (0..count)
+            .map(|_| reader.u32().map(|o| o as usize))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut sizes = [0; 4];
+        for size in &mut sizes {
+            *size = reader.u32()? as usize;
+        }
+
+        let row_pad = 1 << (format & 3);
+        let scan_unit = 1 << ((format >> 4) & 3);
+        let mut bitmap_data = reader.bytes(sizes[(format & 3) as usize])?.to_vec();
+
+        // Convert the data so that bits and bytes are in the most significant first order
+        if format & BIT_MSB_FIRST == 0 {
+            for byte in &mut bitmap_data {
+                *byte = byte.reverse_bits();
+            }
+        }
+
+        if (format & BIT_MSB_FIRST == 0) != (format & BYTE_MSB_FIRST == 0) && scan_unit > 1 {
+            for unit in bitmap_data.chunks_exact_mut(scan_unit) {
+                unit.reverse();
+            }
+        }
+
+        metrics
+            .iter()
+            .zip(offsets)
+            .map(|(m, offset)| {
+                let width = (m.right - m.left).max(0) as usize;
+                let height = (m.ascent + m.descent).max(0) as usize;
+                let stride = width.div_ceil(8).div_ceil(row_pad) * row_pad;
+
+                let rows = (0..height)
+                    .map(|y| {
+                        let start = offset + y * stride;
+                        bitmap_data
+                            .get(start..start + stride)
+                            .ok_or_else(|| error("glyph bitmap out of bounds"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(unpack_bits(&rows, width as u32))
+            })
+            .collect()
+    }
+
+    /// Reads the map from character codes to glyph indices.
+    fn read_encodings(data: &[u8], offset: usize) -> Result<Vec<(u32, usize)>, BitmapFontError> {
+        let (mut reader, _) = Reader::table(data, offset)?;
+
+        let min_byte2 = reader.i16()? as u32;
+        let max_byte2 = reader.i16()? as u32;
+        let min_byte1 = reader.i16()? as u32;
+        let max_byte1 = reader.i16()? as u32;
+        let _default_char = reader.i16()?;
+
+        let mut encodings = Vec::new();
+
+        for byte1 in min_byte1..=max_byte1 {
+            for byte2 in min_byte2..=max_byte2 {
+                let index = reader.u16()?;
+
+                if index != 0xFFFF {
+                    encodings.push(((byte1 << 8) | byte2, index as usize));
+                }
+            }
+        }
+
+        Ok(encodings)
+    }
+
+    /// Reads the font's ascent and descent from an accelerator table.
+    fn read_accelerators(data: &[u8], offset: usize) -> Result<(i32, i32), BitmapFontError> {
+        let (mut reader, _) = Reader::table(data, offset)?;
+
+        // Flags about the font that we don't need
+        reader.bytes(8)?;
+
+        Ok((reader.i32()?, reader.i32()?))
+    }
+
+    /// Reads the PIXEL_SIZE property, if the font has one.
+    fn read_pixel_size(data: &[u8], offset: usize) -> Result<Option<f32>, BitmapFontError> {
+        let (mut reader, _) = Reader::table(data, offset)?;
+
+        let count = reader.u32()? as usize;
+        let mut properties = Vec::new();
+
+        for _ in 0..count {
+            let name = reader.u32()? as usize;
+            let is_string = reader.u8()? != 0;
+            let value = reader.i32()?;
+            properties.push((name, is_string, value));
+        }
+
+        // The property list is padded to a multiple of 4 bytes
+        if !count.is_multiple_of(4) {
+            reader.bytes(4 - count % 4)?;
+        }
+
+        let strings_len = reader.u32()? as usize;
+        let strings = reader.bytes(strings_len)?;
+
+        let name = |offset: usize| {
+            let bytes = strings.get(offset..)?;
+            let end = bytes.iter().position(|b| *b == 0)?;
+            std::str::from_utf8(&bytes[..end]).ok()
+        };
+
+        Ok(properties
+            .iter()
+            .find(|(n, is_string, _)| !is_string && name(*n) == Some("PIXEL_SIZE"))
+            .map(|(_, _, value)| *value as f32))
+    }
+
+    pub(super) fn parse(data: &[u8]) -> Result<BitmapFont, BitmapFontError> {
+        if data.get(..4) != Some(b"\x01fcp") {
+            return Err(error("not a PCF file"));
+        }
+
+        let mut reader = Reader {
+            data,
+            pos: 4,
+            big_endian: false,
+        };
+
+        let table_count = reader.u32()?;
+        let mut tables = Vec::new();
+
+        for _ in 0..table_count {
+            let ty = reader.u32()?;
+            let _format = reader.u32()?;
+            let _size = reader.u32()?;
+            let offset = reader.u32()? as usize;
+            tables.push((ty, offset));
+        }
+
+        let table = |ty: u32| tables.iter().find(|(t, _)| *t == ty).map(|(_, o)| *o);
+
+        let metrics = read_metrics(data, table(METRICS).ok_or_else(|| error("no metrics"))?)?;
+        let bitmaps = read_bitmaps(
+            data,
+            table(BITMAPS).ok_or_else(|| error("no bitmaps"))?,
+            &metrics,
+        )?;
+        let encodings = read_encodings(
+            data,
+            table(BDF_ENCODINGS).ok_or_else(|| error("no encodings"))?,
+        )?;
+
+        let (ascent, descent) = match table(BDF_ACCELERATORS).or(table(ACCELERATORS)) {
+            Some(offset) => read_accelerators(data, offset)?,
+            None => (
+                metrics.iter().map(|m| m.ascent).max().unwrap_or(0),
+                metrics.iter().map(|m| m.descent).max().unwrap_or(0),
+            ),
+        };
+
+        let pixel_size = match table(PROPERTIES) {
+            Some(offset) => read_pixel_size(data, offset)?,
+            None => None,
+        };
+
+        let glyphs = encodings
+            .into_iter()
+            .filter_map(|(code, index)| {
+                let c = char::from_u32(code)?;
+                let m = metrics.get(index)?;
+
+                Some(BitmapGlyph {
+                    c,
+                    image: bitmaps.get(index)?.clone(),
+                    position: [m.left as f32, -m.ascent as f32],
+                    advance: m.width as f32,
+                })
+            })
+            .collect();
+
+        Ok(BitmapFont {
+            glyphs,
+            px_size: pixel_size.unwrap_or((ascent + descent) as f32),
+            metrics: FontMetrics {
+                ascent: ascent as f32,
+                descent: -descent as f32,
+                line_gap: 0.,
+            },
+            kerning: Default::default(),
+            filter: wgpu::FilterMode::Nearest,
+        })
+    }
+}
@@ -0,0 +1,183 @@
+//! The gaussian blur of blurred baked texts (see
+//! [TextRenderer::bake_blurred](crate::TextRenderer::bake_blurred)).
+
+use std::num::NonZeroU64;
+
+use wgpu::util::DeviceExt;
+
+/// How far the blur reaches, in standard deviations. Past this the weights are too small to
+/// change an 8 bit colour.
+pub(crate) const BLUR_REACH: f32 = 3.;
+
+/// The settings of one pass of the blur for the shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct BlurUniform {
+    direction: [i32; 2],
+    sigma: f32,
+    reach: i32,
+}
+
+/// The pipeline that blurs textures in one direction, for textures in one format. The blur is
+/// separable, so it's drawn twice: across and then down.
+#[derive(Debug)]
+pub(crate) struct BlurPipeline {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl BlurPipeline {
+    /// Creates the pipeline for blurring textures in a format. `label` makes the labels of the
+    /// GPU objects.
+    pub(crate) fn new(
+        format: wgpu::TextureFormat,
+        label: impl Fn(&str) -> String,
+        device: &wgpu::Device,
+    ) -> Self {
+        // The shader loads texels itself, so the texture doesn't have to be filterable
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(&label("blur bind group layout")),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(std::mem::size_of::<BlurUniform>() as _),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&label("blur pipeline layout")),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&label("blur shader")),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/blur_shader.wgsl").into()),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&label("blur render pipeline")),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+            // Added in wgpu 22
+            #[cfg(not(feature = "wgpu-0_20"))]
+            cache: None,
+        });
+
+        Self {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+
+    /// Blurs a texture with a standard deviation in pixels, through a texture of the same size
+    /// that's created for it. The texture has to be one that can be drawn to and bound.
+    pub(crate) fn blur(
+        &self,
+        texture: &wgpu::Texture,
+        sigma: f32,
+        encoder: &mut wgpu::CommandEncoder,
+        label: impl Fn(&str) -> String,
+        device: &wgpu::Device,
+    ) {
+        let scratch = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(&label("blur texture")),
+            size: texture.size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: texture.format(),
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&Default::default());
+        let scratch_view = scratch.create_view(&Default::default());
+
+        let passes = [
+            (&view, &scratch_view, [1, 0], "horizontal"),
+            (&scratch_view, &view, [0, 1], "vertical"),
+        ];
+
+        for (source, destination, direction, name) in passes {
+            let settings = BlurUniform {
+                direction,
+                sigma,
+                reach: (sigma * BLUR_REACH).ceil() as i32,
+            };
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&label(&format!("{name} blur uniform buffer"))),
+                contents: bytemuck::bytes_of(&settings),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&label(&format!("{name} blur bind group"))),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(&label(&format!("{name} blur render pass"))),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: destination,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
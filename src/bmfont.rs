@@ -0,0 +1,189 @@
+//! A parser for the text format of AngelCode BMFont descriptor (.fnt) files.
+//!
+//! See <https://www.angelcode.com/products/bmfont/doc/file_format.html> for the format.
+
+use std::fmt;
+
+use crate::HashMap;
+
+/// An error from parsing a BMFont descriptor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BmFontError {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for BmFontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for BmFontError {}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct BmChar {
+    pub(crate) id: u32,
+    pub(crate) x: u32,
+    pub(crate) y: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) xoffset: f32,
+    pub(crate) yoffset: f32,
+    pub(crate) xadvance: f32,
+    pub(crate) page: usize,
+    /// The channels the character is in, as a bitmask of blue (1), green (2), red (4) and alpha
+    /// (8).
+    pub(crate) channels: Option<u32>,
+}
+
+/// What a channel of the pages holds, from the `alphaChnl`, `redChnl` etc. of the "common" line.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ChannelContents {
+    Glyph,
+    Outline,
+    GlyphAndOutline,
+    Zero,
+    One,
+}
+
+impl ChannelContents {
+    fn from_value(value: f32) -> Option<Self> {
+        match value as u32 {
+            0 => Some(Self::Glyph),
+            1 => Some(Self::Outline),
+            2 => Some(Self::GlyphAndOutline),
+            3 => Some(Self::Zero),
+            4 => Some(Self::One),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct BmFont {
+    /// The size of the font in pixels. BMFont uses negative sizes to mean the size of the
+    /// characters rather than the cell, but we don't care about the difference.
+    pub(crate) size: f32,
+    pub(crate) line_height: f32,
+    /// The distance from the top of a line to the baseline.
+    pub(crate) base: f32,
+    /// What the alpha channel of the pages holds, if the descriptor says.
+    pub(crate) alpha_channel: Option<ChannelContents>,
+    /// What the red channel of the pages holds, if the descriptor says.
+    pub(crate) red_channel: Option<ChannelContents>,
+    pub(crate) chars: Vec<BmChar>,
+    /// Kerning amounts, keyed by the ids of the first and second character.
+    pub(crate) kernings: HashMap<(u32, u32), f32>,
+}
+
+/// Splits a line of a descriptor into its tag and key=value pairs, respecting quoted values.
+fn parse_line(line: &str) -> (&str, Vec<(&str, &str)>) {
+    let line = line.trim();
+    let (tag, mut rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let mut pairs = Vec::new();
+
+    loop {
+        rest = rest.trim_start();
+
+        let Some((key, after)) = rest.split_once('=') else {
+            break;
+        };
+
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((value, after)) => (value, after),
+                None => (quoted, ""),
+            },
+            None => after.split_once(char::is_whitespace).unwrap_or((after, "")),
+        };
+
+        pairs.push((key.trim(), value));
+        rest = after;
+    }
+
+    (tag, pairs)
+}
+
+impl BmFont {
+    pub(crate) fn parse(descriptor: &str) -> Result<Self, BmFontError> {
+        let mut font = BmFont::default();
+
+        for (i, line) in descriptor.lines().enumerate() {
+            let error = |message: String| BmFontError {
+                line: i + 1,
+                message,
+            };
+
+            let (tag, pairs) = parse_line(line);
+
+            let number = |key: &str| -> Result<f32, BmFontError> {
+                let value = pairs
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| *v)
+                    .ok_or_else(|| error(format!("missing \"{key}\" in \"{tag}\"")))?;
+
+                value
+                    .parse()
+                    .map_err(|_| error(format!("invalid value for \"{key}\": {value:?}")))
+            };
+
+            let optional = |key: &str| -> Result<Option<f32>, BmFontError> {
+                if pairs.iter().any(|(k, _)| *k == key) {
+                    number(key).map(Some)
+                } else {
+                    Ok(None)
+                }
+            };
+
+            let contents = |key: &str| -> Result<Option<ChannelContents>, BmFontError> {
+                optional(key)?
+                    .map(|value| {
+                        ChannelContents::from_value(value)
+                            .ok_or_else(|| error(format!("invalid value for \"{key}\": {value}")))
+                    })
+                    .transpose()
+            };
+
+            match tag {
+                "info" => font.size = number("size")?.abs(),
+                "common" => {
+                    font.line_height = number("lineHeight")?;
+                    font.base = number("base")?;
+                    font.alpha_channel = contents("alphaChnl")?;
+                    font.red_channel = contents("redChnl")?;
+                }
+                // The pages are given with the descriptor in order of their ids, so their file
+                // names aren't needed
+                "char" => font.chars.push(BmChar {
+                    id: number("id")? as u32,
+                    x: number("x")? as u32,
+                    y: number("y")? as u32,
+                    width: number("width")? as u32,
+                    height: number("height")? as u32,
+                    xoffset: number("xoffset")?,
+                    yoffset: number("yoffset")?,
+                    xadvance: number("xadvance")?,
+                    page: number("page")? as usize,
+                    channels: optional("chnl")?.map(|value| value as u32),
+                }),
+                "kerning" => {
+                    let first = number("first")? as u32;
+                    let second = number("second")? as u32;
+                    font.kernings.insert((first, second), number("amount")?);
+                }
+                _ => {}
+            }
+        }
+
+        if font.line_height <= 0. {
+            return Err(BmFontError {
+                line: 0,
+                message: "missing \"common\" line".to_string(),
+            });
+        }
+
+        Ok(font)
+    }
+}
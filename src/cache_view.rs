@@ -0,0 +1,175 @@
+//! A grid of a font's cached glyph textures for debugging the cache (see
+//! [TextRenderer::cache_view]).
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    bake::BakedSettingsUniform, CharacterInstance, Error, FontId, GlyphKey, Rect, TextRenderer,
+};
+
+/// The space between the cells of the grid, in physical pixels.
+const CELL_GAP: f32 = 2.;
+
+/// The textures of a font's cached glyphs laid out in a grid, made with
+/// [TextRenderer::cache_view] and drawn with [TextRenderer::draw_cache_view].
+///
+/// This is a snapshot of what was in the cache when it was made. Glyphs that have been evicted
+/// since are left out when it's drawn, and new ones aren't added, so make a new one to see them.
+#[derive(Debug)]
+pub struct CacheView {
+    font: FontId,
+    /// The glyph in each cell, in the order of the instances.
+    glyphs: Vec<GlyphKey>,
+    instance_buffer: wgpu::Buffer,
+    settings_bind_group: wgpu::BindGroup,
+    /// The grid in logical pixels.
+    bounds: Rect,
+}
+
+impl CacheView {
+    /// The number of glyphs in the grid.
+    pub fn glyph_count(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    /// The rectangle the grid covers, in logical pixels.
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+}
+
+impl TextRenderer {
+    /// Lays out the textures of every glyph that's cached for a font in a grid, e.g. to check
+    /// which glyphs are resident, how the sdf radius looks around them, or whether
+    /// [TextRenderer::clear_cache] is working. Draw it with [TextRenderer::draw_cache_view].
+    ///
+    /// Each texture is drawn at its own size (one texel to a physical pixel), in grey: coverage
+    /// for raster fonts, which is white on black and includes any subpixel phases stacked from top
+    /// to bottom, and the distance field for sdf fonts, which is dark inside the glyph and gets
+    /// brighter out to the radius. The cells start at `position` and wrap at
+    /// `max_width`, both in logical pixels.
+    ///
+    /// Returns an error if the font isn't loaded in this renderer.
+    pub fn cache_view(
+        &self,
+        font: FontId,
+        position: [f32; 2],
+        max_width: f32,
+        device: &wgpu::Device,
+    ) -> Result<CacheView, Error> {
+        let font_data = self.fonts.get(font)?;
+        let max_width = max_width * self.scale_factor;
+
+        let mut textures = (font_data.char_cache.iter())
+            .map(|(&c, character)| (GlyphKey::Char(c), character))
+            .chain(
+                (font_data.glyph_cache.iter())
+                    .map(|(&id, character)| (GlyphKey::Glyph(id), character)),
+            )
+            .chain(
+                (font_data.fallback_cache.iter())
+                    .map(|(&(font, id), character)| (GlyphKey::Fallback(font, id), character)),
+            )
+            .filter_map(|(key, character)| Some((key, character.texture.as_ref()?)))
+            .collect::<Vec<_>>();
+        textures.sort_by_key(|(key, _)| *key);
+
+        // The cells are placed left to right, and start a new row when they'd go past the width
+        let mut pen = [0.; 2];
+        let mut row_height = 0f32;
+        let mut extent = [0f32; 2];
+        let mut instances = Vec::with_capacity(textures.len());
+
+        for (_, texture) in &textures {
+            let size = [
+                texture.texture.width() as f32,
+                texture.texture.height() as f32,
+            ];
+            if pen[0] > 0. && pen[0] + size[0] > max_width {
+                pen = [0., pen[1] + row_height + CELL_GAP];
+                row_height = 0.;
+            }
+
+            instances.push(CharacterInstance {
+                position: pen,
+                size,
+                index: 0,
+            });
+            extent = [
+                extent[0].max(pen[0] + size[0]),
+                extent[1].max(pen[1] + size[1]),
+            ];
+            row_height = row_height.max(size[1]);
+            pen[0] += size[0] + CELL_GAP;
+        }
+
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&self.label("cache view instance buffer")),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        // The texels line up with the pixels, so they're drawn as they are
+        let origin = position.map(|p| (p * self.scale_factor).round());
+        let settings = BakedSettingsUniform {
+            text_position: origin,
+            relative_position: [0.; 2],
+        };
+        let settings_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&self.label("cache view settings uniform buffer")),
+            contents: bytemuck::bytes_of(&settings),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let settings_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&self.label("cache view settings uniform bind group")),
+            layout: &self.baked_settings_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: settings_buffer.as_entire_binding(),
+            }],
+        });
+
+        let bounds = Rect {
+            min: origin.map(|p| p / self.scale_factor),
+            max: [0, 1].map(|i| (origin[i] + extent[i]) / self.scale_factor),
+        };
+
+        Ok(CacheView {
+            font,
+            glyphs: textures.into_iter().map(|(key, _)| key).collect(),
+            instance_buffer,
+            settings_bind_group,
+            bounds,
+        })
+    }
+
+    /// Draws a [CacheView] to a render pass. Glyphs that aren't cached any more are skipped.
+    ///
+    /// Returns an error if the view's font isn't loaded in this renderer, in which case nothing is
+    /// drawn.
+    pub fn draw_cache_view<'pass>(
+        &'pass self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        view: &'pass CacheView,
+    ) -> Result<(), Error> {
+        let font_data = self.fonts.get(view.font)?;
+        if view.glyphs.is_empty() {
+            return Ok(());
+        }
+
+        let target = &self.targets[0];
+        render_pass.set_pipeline(&target.pipelines.cache_view);
+        render_pass.set_bind_group(0, &target.screen_bind_group, &[]);
+        render_pass.set_bind_group(2, &view.settings_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, view.instance_buffer.slice(..));
+
+        for (cell, &key) in (0..).zip(&view.glyphs) {
+            let Some(texture) = font_data.character(key).and_then(|c| c.texture.as_ref()) else {
+                continue;
+            };
+            render_pass.set_bind_group(1, &texture.bind_group, &[]);
+            render_pass.draw(0..4, cell..cell + 1);
+        }
+        Ok(())
+    }
+}
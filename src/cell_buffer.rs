@@ -0,0 +1,390 @@
+//! Grids of character cells that each have their own colours, for drawing terminals and TUIs (see
+//! [TextRenderer::cell_buffer]).
+
+use std::ops::Range;
+
+use itertools::Itertools;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    text::glyph_runs, uploads::Uploads, CharacterInstance, Error, FontId, FrameStats, GlyphKey,
+    Rect, TextRenderer,
+};
+
+/// The settings of a cell buffer for the shaders.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct CellSettingsUniform {
+    position: [f32; 2],
+    subpixel_phases: f32,
+    /// The font's sdf radius, or 0 if it doesn't use sdf.
+    sdf_radius: f32,
+    image_scale: f32,
+    _padding: [f32; 3],
+}
+
+/// What a cell shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cell {
+    character: char,
+    fg: [f32; 4],
+    bg: [f32; 4],
+}
+
+/// The cells a buffer starts with: spaces with a transparent background.
+const BLANK: Cell = Cell {
+    character: ' ',
+    fg: [0., 0., 0., 1.],
+    bg: [0.; 4],
+};
+
+/// A grid of character cells, each with its own foreground and background colour, made with
+/// [TextRenderer::cell_buffer] and drawn with [TextRenderer::draw_cell_buffer], e.g. for a
+/// terminal or a TUI.
+///
+/// Cells are changed with [CellBuffer::set_cell], which marks their row as damaged. Only the
+/// damaged rows are laid out and uploaded again by [TextRenderer::prepare_cell_buffer], so a
+/// buffer where a few cells change each frame is cheap to keep up to date.
+///
+/// The cells all have the same size, from the advance of 'M' and the line height of the font, so
+/// the grid lines up best with monospace fonts. Glyphs wider than their cell reach into the next
+/// one.
+#[derive(Debug)]
+pub struct CellBuffer {
+    font: FontId,
+    columns: usize,
+    rows: usize,
+    /// The cells of each row in turn.
+    cells: Vec<Cell>,
+    /// Whether each row has changed since it was last uploaded.
+    damaged: Vec<bool>,
+    /// Each row's glyph draws: the range of instances of each glyph in the row.
+    row_draws: Vec<Vec<(GlyphKey, Range<u32>)>>,
+    /// Every row's glyph draws, sorted by glyph so each texture is only bound once.
+    draws: Vec<(GlyphKey, Range<u32>)>,
+    /// The size of a cell in physical pixels.
+    cell_size: [f32; 2],
+    /// How far below the top of a cell its baseline is, in physical pixels.
+    baseline: f32,
+    /// The number of physical pixels per logical pixel when the buffer was made.
+    scale_factor: f32,
+    /// A background instance for each cell, then room for a glyph instance for each cell, a row at
+    /// a time.
+    instance_buffer: wgpu::Buffer,
+    settings_bind_group: wgpu::BindGroup,
+    /// The grid in logical pixels.
+    bounds: Rect,
+}
+
+impl CellBuffer {
+    /// The number of cells in each row.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// The number of rows of cells.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The size of each cell, in logical pixels.
+    pub fn cell_size(&self) -> [f32; 2] {
+        self.cell_size.map(|s| s / self.scale_factor)
+    }
+
+    /// The rectangle the grid covers, in logical pixels.
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    /// Sets the character and the foreground and background colours of the cell in column `x` of
+    /// row `y`. Its row is marked as damaged if the cell changes.
+    ///
+    /// The change is drawn once the buffer has been prepared with
+    /// [TextRenderer::prepare_cell_buffer].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell is outside the grid.
+    pub fn set_cell(&mut self, x: usize, y: usize, ch: char, fg: [f32; 4], bg: [f32; 4]) {
+        assert!(
+            x < self.columns && y < self.rows,
+            "cell ({x}, {y}) is outside the {}x{} grid",
+            self.columns,
+            self.rows
+        );
+
+        let cell = Cell {
+            character: ch,
+            fg,
+            bg,
+        };
+        let old = &mut self.cells[y * self.columns + x];
+        if *old != cell {
+            *old = cell;
+            self.damaged[y] = true;
+        }
+    }
+
+    /// Returns the character and the foreground and background colours of the cell in column `x`
+    /// of row `y`, or None if it's outside the grid.
+    pub fn cell(&self, x: usize, y: usize) -> Option<(char, [f32; 4], [f32; 4])> {
+        if x >= self.columns || y >= self.rows {
+            return None;
+        }
+        let cell = self.cells[y * self.columns + x];
+        Some((cell.character, cell.fg, cell.bg))
+    }
+
+    /// The rows that have changed since the buffer was last prepared, from top to bottom. Every
+    /// row is damaged when the buffer is made.
+    pub fn damaged_rows(&self) -> impl Iterator<Item = usize> + '_ {
+        (self.damaged.iter()).positions(|&damaged| damaged)
+    }
+
+    /// The index of the first glyph instance of a row.
+    fn glyph_start(&self, row: usize) -> u32 {
+        ((self.rows + row) * self.columns) as u32
+    }
+}
+
+/// Packs a colour into 8 bits a channel, which the cell shaders unpack from the instance index.
+fn pack_color(color: [f32; 4]) -> u32 {
+    u32::from_le_bytes(color.map(|c| (c.clamp(0., 1.) * 255.).round() as u8))
+}
+
+impl TextRenderer {
+    /// Makes a grid of `columns` by `rows` character cells, for drawing text where every cell has
+    /// its own colours, like a terminal. Change the cells with [CellBuffer::set_cell], then upload
+    /// the changes with [TextRenderer::prepare_cell_buffer] and draw it with
+    /// [TextRenderer::draw_cell_buffer].
+    ///
+    /// The grid's top left corner is at `position`, in logical pixels, and its cells are laid out
+    /// at the renderer's current scale factor, rounded to whole physical pixels so neighbouring
+    /// backgrounds meet without gaps. The cells start out blank, and nothing is drawn until the
+    /// buffer is prepared.
+    ///
+    /// Returns an error if the font isn't loaded in this renderer.
+    pub fn cell_buffer(
+        &mut self,
+        font: FontId,
+        columns: usize,
+        rows: usize,
+        position: [f32; 2],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<CellBuffer, Error> {
+        // The cells are as wide as the font's 'M'
+        self.generate_chars(['M'].into_iter(), font, device, &Uploads::Queue(queue))?;
+        let font_data = self.fonts.get(font)?;
+
+        let scale = self.scale_factor / font_data.glyph_scale_factor;
+        let advance = font_data.char_cache.get(&font_data.substitute('M'));
+        let width = advance.map_or(0., |m| m.advance * scale);
+        let height = font_data.line_spacing(1.) * self.scale_factor;
+        let cell_size = [width.round().max(1.), height.round().max(1.)];
+        let baseline = (font_data.metrics.ascent * self.scale_factor).round();
+
+        let cells = columns * rows;
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&self.label("cell buffer instance buffer")),
+            size: (2 * cells * std::mem::size_of::<CharacterInstance>()) as _,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // The cells line up with the pixels, so the grid is moved to a whole pixel
+        let origin = position.map(|p| (p * self.scale_factor).round());
+        let settings = CellSettingsUniform {
+            position: origin,
+            subpixel_phases: font_data.subpixel_phases as f32,
+            sdf_radius: font_data.sdf_settings.as_ref().map_or(0., |sdf| sdf.radius),
+            image_scale: self.scale_factor,
+            _padding: [0.; 3],
+        };
+        let settings_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&self.label("cell buffer settings uniform buffer")),
+            contents: bytemuck::bytes_of(&settings),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let settings_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&self.label("cell buffer settings uniform bind group")),
+            layout: &self.cell_settings_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: settings_buffer.as_entire_binding(),
+            }],
+        });
+
+        let extent = [columns as f32 * cell_size[0], rows as f32 * cell_size[1]];
+        let bounds = Rect {
+            min: origin.map(|p| p / self.scale_factor),
+            max: [0, 1].map(|i| (origin[i] + extent[i]) / self.scale_factor),
+        };
+
+        Ok(CellBuffer {
+            font,
+            columns,
+            rows,
+            cells: vec![BLANK; cells],
+            damaged: vec![true; rows],
+            row_draws: vec![Vec::new(); rows],
+            draws: Vec::new(),
+            cell_size,
+            baseline,
+            scale_factor: self.scale_factor,
+            instance_buffer,
+            settings_bind_group,
+            bounds,
+        })
+    }
+
+    /// Uploads the rows of a [CellBuffer] that have changed since it was last prepared, generating
+    /// any of their characters that aren't cached. Rows that haven't changed aren't touched,
+    /// except to generate their characters again if they've been evicted (e.g. by
+    /// [TextRenderer::clear_cache]).
+    ///
+    /// Returns an error if the buffer's font isn't loaded in this renderer.
+    pub fn prepare_cell_buffer(
+        &mut self,
+        buffer: &mut CellBuffer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), Error> {
+        let evicted = {
+            let font_data = self.fonts.get(buffer.font)?;
+            (buffer.draws.iter())
+                .filter(|(key, _)| font_data.character(*key).is_none())
+                .filter_map(|(key, _)| match key {
+                    GlyphKey::Char(c) => Some(*c),
+                    GlyphKey::Glyph(_) | GlyphKey::Fallback(..) => None,
+                })
+                .collect_vec()
+        };
+        let damaged = buffer.damaged_rows().collect_vec();
+        if damaged.is_empty() && evicted.is_empty() {
+            return Ok(());
+        }
+
+        let chars = (damaged.iter())
+            .flat_map(|&row| &buffer.cells[row * buffer.columns..(row + 1) * buffer.columns])
+            .map(|cell| cell.character)
+            .chain(evicted);
+        self.generate_chars(chars, buffer.font, device, &Uploads::Queue(queue))?;
+
+        let font_data = self.fonts.get(buffer.font)?;
+        let scale = buffer.scale_factor / font_data.glyph_scale_factor;
+        let instance_size = std::mem::size_of::<CharacterInstance>();
+
+        for row in damaged {
+            let cells = &buffer.cells[row * buffer.columns..(row + 1) * buffer.columns];
+            let [width, height] = buffer.cell_size;
+            let y = row as f32 * height;
+
+            let backgrounds = (0..).zip(cells).map(|(x, cell)| CharacterInstance {
+                position: [x as f32 * width, y],
+                size: buffer.cell_size,
+                index: pack_color(cell.bg),
+            });
+            let backgrounds = backgrounds.collect_vec();
+
+            // A row's glyphs are sorted so that each glyph's instances are next to each other
+            let mut glyphs = (0..)
+                .zip(cells)
+                .filter_map(|(x, cell)| {
+                    let key = GlyphKey::Char(font_data.substitute(cell.character));
+                    let texture = font_data.character(key)?.texture.as_ref()?;
+                    let [left, top] = texture.position.map(|p| p * scale);
+                    let instance = CharacterInstance {
+                        position: [x as f32 * width + left, y + buffer.baseline + top],
+                        size: texture.size.map(|s| s * scale),
+                        index: pack_color(cell.fg),
+                    };
+                    Some((key, instance))
+                })
+                .collect_vec();
+            glyphs.sort_by_key(|(key, _)| *key);
+
+            let start = buffer.glyph_start(row);
+            buffer.row_draws[row] = glyph_runs(glyphs.iter().map(|(key, _)| *key))
+                .into_iter()
+                .map(|(key, range)| (key, range.start + start..range.end + start))
+                .collect();
+            let glyphs = glyphs
+                .into_iter()
+                .map(|(_, instance)| instance)
+                .collect_vec();
+
+            for (first, instances) in [
+                (row * buffer.columns, backgrounds),
+                (start as usize, glyphs),
+            ] {
+                let data = bytemuck::cast_slice(&instances);
+                let offset = (first * instance_size) as u64;
+                queue.write_buffer(&buffer.instance_buffer, offset, data);
+                self.frame_counters.add_upload(data.len());
+            }
+            buffer.damaged[row] = false;
+        }
+
+        // Sorting is stable, so each glyph's draws are still from top to bottom
+        buffer.draws = buffer.row_draws.concat();
+        buffer.draws.sort_by_key(|(key, _)| *key);
+        Ok(())
+    }
+
+    /// Draws a [CellBuffer] to a render pass as it was when it was last prepared (see
+    /// [TextRenderer::prepare_cell_buffer]). All of the backgrounds are drawn first in one draw,
+    /// then the glyphs, with one draw for each run of the same glyph in a row. Glyphs that aren't
+    /// cached any more are skipped.
+    ///
+    /// Returns an error if the buffer's font isn't loaded in this renderer, in which case nothing
+    /// is drawn.
+    pub fn draw_cell_buffer<'pass>(
+        &'pass self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        buffer: &'pass CellBuffer,
+    ) -> Result<(), Error> {
+        let font_data = self.fonts.get(buffer.font)?;
+        if buffer.cells.is_empty() {
+            return Ok(());
+        }
+
+        let target = &self.targets[0];
+        let cells = buffer.cells.len() as u32;
+        render_pass.set_pipeline(&target.pipelines.cell_background);
+        render_pass.set_bind_group(0, &target.screen_bind_group, &[]);
+        render_pass.set_bind_group(1, &buffer.settings_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, buffer.instance_buffer.slice(..));
+        render_pass.draw(0..4, 0..cells);
+
+        let mut stats = FrameStats {
+            instances: cells as u64,
+            draw_calls: 1,
+            bind_group_switches: 2,
+            ..Default::default()
+        };
+
+        render_pass.set_pipeline(&target.pipelines.cell);
+        render_pass.set_bind_group(2, &buffer.settings_bind_group, &[]);
+        stats.bind_group_switches += 1;
+
+        let mut bound = None;
+        for (key, instances) in &buffer.draws {
+            let Some(texture) = font_data.character(*key).and_then(|c| c.texture.as_ref()) else {
+                continue;
+            };
+            if bound != Some(key) {
+                render_pass.set_bind_group(1, &texture.bind_group, &[]);
+                bound = Some(key);
+                stats.bind_group_switches += 1;
+            }
+            render_pass.draw(0..4, instances.clone());
+            stats.instances += instances.len() as u64;
+            stats.draw_calls += 1;
+        }
+
+        self.frame_counters.add(&stats);
+        Ok(())
+    }
+}
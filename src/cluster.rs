@@ -0,0 +1,215 @@
+//! Characters that are drawn together as one glyph, like emoji ZWJ sequences.
+//!
+//! Kaku doesn't shape text, but some sequences of characters are meant to be drawn as a single
+//! glyph. "👨‍👩‍👧‍👦" is four people joined by zero width joiners (ZWJs), and emoji fonts draw it as
+//! one family with a ligature. These sequences are grouped into clusters, and if the font has a
+//! ligature for a whole cluster it's drawn with that glyph (by id, like text laid out by another
+//! library). Otherwise its characters are drawn one by one, without the joiners.
+//!
+//! Variation selectors are part of the cluster before them too. U+FE0E and U+FE0F ask for the
+//! text or emoji style of a character like "☀", and others pick variants of CJK ideographs. If the
+//! font has the variant (in its cmap format 14 table) that glyph is drawn, otherwise the selector
+//! is ignored and the character is drawn as usual.
+
+use std::ops::Range;
+
+use ab_glyph::{Font, FontArc, GlyphId};
+use itertools::Itertools;
+use ttf_parser::{
+    gsub::{LigatureSubstitution, SubstitutionSubtable},
+    Face, Tag,
+};
+
+/// The zero width joiner, which asks for the characters either side of it to be joined.
+const ZWJ: char = '\u{200D}';
+
+/// The features whose ligatures are looked up. Emoji fonts keep their sequences in these.
+const FEATURES: [Tag; 3] = [
+    Tag::from_bytes(b"ccmp"),
+    Tag::from_bytes(b"liga"),
+    Tag::from_bytes(b"rlig"),
+];
+
+/// Returns whether a character is a variation selector, which picks a variant of the character
+/// before it. These are never drawn themselves.
+pub(crate) fn is_variation_selector(c: char) -> bool {
+    matches!(c, '\u{FE00}'..='\u{FE0F}' | '\u{E0100}'..='\u{E01EF}')
+}
+
+/// Returns whether a character belongs to the cluster of the character before it even without a
+/// joiner: emoji skin tone modifiers, the keycap mark and the tags of subdivision flags.
+fn extends(c: char) -> bool {
+    matches!(
+        c,
+        '\u{20E3}' | '\u{1F3FB}'..='\u{1F3FF}' | '\u{E0020}'..='\u{E007F}'
+    )
+}
+
+/// The byte index just after the character of a text at a byte index.
+fn char_end(text: &str, index: usize) -> usize {
+    index + text[index..].chars().next().map_or(0, char::len_utf8)
+}
+
+/// Splits the characters of a line into clusters, as ranges of indices into `chars`. Most
+/// clusters are a single character.
+///
+/// `chars` are the characters that are drawn with their byte indices in `text` (see
+/// [TextData::indexed_lines](crate::text::TextData::indexed_lines)), which still has the joiners
+/// that were taken out of them.
+pub(crate) fn clusters<'a>(
+    text: &'a str,
+    chars: &'a [(usize, char)],
+) -> impl Iterator<Item = Range<usize>> + 'a {
+    let joined = |pair: &[(usize, char)]| {
+        let [(before, _), (after, c)] = *pair else {
+            return false;
+        };
+
+        // Tabs that become several spaces have the same index for each one
+        let end = char_end(text, before);
+        after >= end && (extends(c) || text[end..after].contains(ZWJ))
+    };
+
+    let mut start = 0;
+    std::iter::from_fn(move || {
+        if start == chars.len() {
+            return None;
+        }
+
+        let len = 1 + chars[start..]
+            .windows(2)
+            .take_while(|&pair| joined(pair))
+            .count();
+        let cluster = start..start + len;
+        start = cluster.end;
+        Some(cluster)
+    })
+}
+
+/// The part of a text a cluster of its characters came from, including its joiners and any
+/// variation selectors after it. Returns None if that's only one character, since then there's
+/// nothing to look up (see [cluster_glyph]).
+pub(crate) fn cluster_text<'a>(text: &'a str, cluster: &[(usize, char)]) -> Option<&'a str> {
+    let start = cluster.first().map_or(0, |&(index, _)| index);
+    let last = cluster
+        .last()
+        .map_or(0, |&(index, _)| char_end(text, index));
+    let selectors = (text[last..].chars())
+        .take_while(|&c| is_variation_selector(c))
+        .map(char::len_utf8)
+        .sum::<usize>();
+
+    let cluster = &text[start..last + selectors];
+    cluster.chars().nth(1).is_some().then_some(cluster)
+}
+
+/// The glyph a font draws a cluster of characters with, if it has a ligature for all of them or a
+/// variant of a character picked by a variation selector. Returns None if the characters are drawn
+/// as themselves.
+///
+/// The ligatures of the font's [FEATURES] are applied in order, like a shaper would, so ligatures
+/// made in more than one step work too. Joiners are skipped when a ligature doesn't include them.
+pub(crate) fn cluster_glyph(font: &FontArc, cluster: &str) -> Option<GlyphId> {
+    // Glyph 0 is the .notdef glyph, which fonts don't make ligatures of
+    let drawn = |c: char| c != ZWJ && !is_variation_selector(c);
+    if (cluster.chars()).any(|c| drawn(c) && font.glyph_id(c).0 == 0) {
+        return None;
+    }
+
+    let face = face(font, cluster)?;
+    let zwj = ttf_parser::GlyphId(font.glyph_id(ZWJ).0);
+    let mut glyphs = Vec::new();
+    let mut previous = None;
+    let mut changed = false;
+
+    for c in cluster.chars() {
+        if !is_variation_selector(c) {
+            glyphs.push(ttf_parser::GlyphId(font.glyph_id(c).0));
+            previous = Some(c);
+            continue;
+        }
+
+        // Selectors the font has no variant for are left out, like shapers do
+        let variant = previous.and_then(|base| face.glyph_variation_index(base, c));
+        if let (Some(variant), Some(glyph)) = (variant, glyphs.last_mut()) {
+            changed |= variant != *glyph;
+            *glyph = variant;
+        }
+    }
+
+    if glyphs.len() == 1 {
+        return changed.then(|| GlyphId(glyphs[0].0));
+    }
+
+    let gsub = face.tables().gsub?;
+
+    let lookups = (gsub.features.into_iter())
+        .filter(|feature| FEATURES.contains(&feature.tag))
+        .flat_map(|feature| feature.lookup_indices)
+        .sorted()
+        .dedup();
+
+    for lookup in lookups.filter_map(|index| gsub.lookups.get(index)) {
+        let subtables = (lookup.subtables.into_iter::<SubstitutionSubtable>())
+            .filter_map(|subtable| match subtable {
+                SubstitutionSubtable::Ligature(ligatures) => Some(ligatures),
+                _ => None,
+            })
+            .collect_vec();
+
+        let mut i = 0;
+        while i < glyphs.len() {
+            let found = (subtables.iter()).find_map(|ligatures| find(ligatures, &glyphs[i..], zwj));
+            if let Some((glyph, len)) = found {
+                glyphs.splice(i..i + len, [glyph]);
+            }
+            i += 1;
+        }
+    }
+
+    match glyphs[..] {
+        [glyph] => Some(GlyphId(glyph.0)),
+        _ => None,
+    }
+}
+
+/// Finds a ligature in a subtable for the glyphs at the start of a sequence, returning it and how
+/// many glyphs it replaces.
+fn find(
+    ligatures: &LigatureSubstitution,
+    glyphs: &[ttf_parser::GlyphId],
+    zwj: ttf_parser::GlyphId,
+) -> Option<(ttf_parser::GlyphId, usize)> {
+    let set = ligatures
+        .ligature_sets
+        .get(ligatures.coverage.get(*glyphs.first()?)?)?;
+
+    set.into_iter().find_map(|ligature| {
+        let mut len = 1;
+        for component in ligature.components {
+            while glyphs.get(len) == Some(&zwj) && component != zwj {
+                len += 1;
+            }
+            if glyphs.get(len) != Some(&component) {
+                return None;
+            }
+            len += 1;
+        }
+
+        Some((ligature.glyph, len))
+    })
+}
+
+/// Parses the face a font was made from. The data of a font from a font collection is the whole
+/// collection, so this finds the face whose glyphs for the cluster's characters match the font's.
+fn face<'a>(font: &'a FontArc, cluster: &str) -> Option<Face<'a>> {
+    let data = font.font_data();
+    let faces = ttf_parser::fonts_in_collection(data).unwrap_or(1);
+
+    (0..faces)
+        .filter_map(|index| Face::parse(data, index).ok())
+        .find(|face| {
+            (cluster.chars())
+                .all(|c| face.glyph_index(c).map_or(0, |id| id.0) == font.glyph_id(c).0)
+        })
+}
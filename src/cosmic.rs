@@ -0,0 +1,296 @@
+//! Drawing text that has been laid out by cosmic-text.
+
+use ab_glyph::{FontVec, GlyphId};
+use cosmic_text::{fontdb, Buffer, FontSystem};
+use log::warn;
+
+use crate::{
+    layout::PlacedGlyph,
+    text::{SdfTextData, TextData},
+    uploads::Uploads,
+    CharacterInstance, Error, FontId, FontOptions, FontSize, GlyphKey, HashMap, Preload, Text,
+    TextRenderer,
+};
+
+/// The glyphs of a buffer that are drawn with the same font, size and color, which become one
+/// [Text].
+struct GlyphGroup {
+    /// The font, the font size's bits and the color's bits.
+    key: (FontId, u32, Option<u32>),
+    text: String,
+    /// Each glyph along with its pen position and the byte index of its text in `text`.
+    glyphs: Vec<(GlyphId, [f32; 2], usize)>,
+}
+
+/// Turns the layout of a [cosmic_text::Buffer] into kaku [Text], so text can be shaped, wrapped and
+/// laid out bidirectionally by cosmic-text and then drawn by kaku (e.g. with sdf outlines).
+///
+/// cosmic-text picks glyphs from the fonts in its [FontSystem], including fallback fonts, so the
+/// adapter loads each of those fonts into the [TextRenderer] the first time one of its glyphs is
+/// drawn, using the same [FontOptions] for all of them (except for [FontOptions::with_preload],
+/// since the glyphs are cached by id rather than by character).
+///
+/// ```rust,no_run
+/// # use cosmic_text::{Attrs, Buffer, FontSystem, Metrics, Shaping};
+/// # use kaku::*;
+/// # fn example(
+/// #     sdf: SdfSettings,
+/// #     mut font_system: FontSystem,
+/// #     device: &wgpu::Device,
+/// #     queue: &wgpu::Queue,
+/// #     mut text_renderer: TextRenderer,
+/// # ) -> Result<(), Error> {
+/// let mut adapter = CosmicTextAdapter::new(FontOptions::new(FontSize::Px(40.)).with_sdf(sdf));
+///
+/// let mut buffer = Buffer::new(&mut font_system, Metrics::new(32., 40.));
+/// buffer.set_size(&mut font_system, Some(400.), None);
+/// buffer.set_text(&mut font_system, "Hello, world!", Attrs::new(), Shaping::Advanced);
+/// buffer.shape_until_scroll(&mut font_system, false);
+///
+/// let texts = adapter.build(
+///     &buffer,
+///     &mut font_system,
+///     [10., 10.],
+///     [0., 0., 0., 1.],
+///     &device,
+///     &queue,
+///     &mut text_renderer,
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CosmicTextAdapter {
+    options: FontOptions,
+    /// The fonts loaded for each cosmic-text face. Fonts without sdf are loaded once for each size
+    /// they're used at, so they're always rasterised at the size they're drawn.
+    fonts: HashMap<(fontdb::ID, Option<u32>), Option<FontId>>,
+}
+
+impl CosmicTextAdapter {
+    /// Creates an adapter that loads cosmic-text's fonts with the given options.
+    ///
+    /// The options' font size is only used by sdf fonts, which are generated once at that size and
+    /// scaled to whatever size cosmic-text uses. Other fonts are loaded at the size they're drawn.
+    pub fn new(options: FontOptions) -> Self {
+        Self {
+            options,
+            fonts: Default::default(),
+        }
+    }
+
+    /// Creates [Text] objects from the layout of a cosmic-text buffer. The text is drawn with the
+    /// top left of the buffer at `position`, in `color` unless cosmic-text gives the glyphs a color
+    /// of their own.
+    ///
+    /// The buffer has to be shaped already (e.g. with [Buffer::shape_until_scroll]), and only the
+    /// lines cosmic-text has laid out are drawn. One Text is made for each combination of font,
+    /// size and color in the buffer.
+    ///
+    /// The Texts can be moved, recolored and outlined like any other, but [Text::set_text] and
+    /// [Text::set_pixel_snap] lay them out again with kaku's own layout. To change the text, change
+    /// the buffer and build them again.
+    ///
+    /// Glyphs from fonts that can't be loaded are skipped, and a warning is logged. Returns an
+    /// error if a font can't be scaled.
+    #[allow(clippy::too_many_arguments)]
+    pub fn build(
+        &mut self,
+        buffer: &Buffer,
+        font_system: &mut FontSystem,
+        position: [f32; 2],
+        color: [f32; 4],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_renderer: &mut TextRenderer,
+    ) -> Result<Vec<Text>, Error> {
+        let mut groups: Vec<GlyphGroup> = Vec::new();
+
+        for run in buffer.layout_runs() {
+            for glyph in run.glyphs {
+                let Some(font) = self.font(
+                    glyph.font_id,
+                    glyph.font_size,
+                    font_system,
+                    device,
+                    queue,
+                    text_renderer,
+                )?
+                else {
+                    continue;
+                };
+
+                let key = (
+                    font,
+                    glyph.font_size.to_bits(),
+                    glyph.color_opt.map(|c| c.0),
+                );
+                let group = match groups.iter().position(|group| group.key == key) {
+                    Some(i) => &mut groups[i],
+                    None => {
+                        groups.push(GlyphGroup {
+                            key,
+                            text: String::new(),
+                            glyphs: Vec::new(),
+                        });
+                        groups.last_mut().unwrap()
+                    }
+                };
+
+                let pen = [
+                    glyph.x + glyph.font_size * glyph.x_offset,
+                    run.line_y + glyph.y - glyph.font_size * glyph.y_offset,
+                ];
+                let index = group.text.len();
+                group.text.push_str(&run.text[glyph.start..glyph.end]);
+                group.glyphs.push((GlyphId(glyph.glyph_id), pen, index));
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|group| {
+                let GlyphGroup {
+                    key: (font, font_size, glyph_color),
+                    text,
+                    glyphs,
+                } = group;
+
+                let color = glyph_color
+                    .map(|c| cosmic_text::Color(c).as_rgba().map(|c| c as f32 / 255.))
+                    .unwrap_or(color);
+
+                text_renderer.generate_glyph_textures(
+                    glyphs.iter().map(|(id, _, _)| GlyphKey::Glyph(*id)),
+                    font,
+                    device,
+                    &Uploads::Queue(queue),
+                )?;
+
+                let font_data = text_renderer.fonts.get(font)?;
+                // cosmic-text's font sizes are em sizes, like FontSize::Px
+                let font_size = FontSize::Px(f32::from_bits(font_size));
+                let scale = font_data.px_size_of(font_size)? / font_data.px_size;
+
+                // The buffer is laid out in logical pixels, but kaku lays text out in physical
+                // pixels (see TextRenderer::set_scale_factor)
+                let scale_factor = text_renderer.scale_factor;
+                let glyph_scale = scale * scale_factor / font_data.glyph_scale_factor;
+
+                let (instances, placed): (Vec<_>, Vec<_>) = glyphs
+                    .into_iter()
+                    .filter_map(|(id, pen, index)| {
+                        let texture = font_data.glyph_cache.get(&id)?.texture.as_ref()?;
+                        let instance = CharacterInstance {
+                            position: [
+                                pen[0] * scale_factor + texture.position[0] * glyph_scale,
+                                pen[1] * scale_factor + texture.position[1] * glyph_scale,
+                            ],
+                            size: texture.size.map(|s| s * glyph_scale),
+                            index: 0,
+                        };
+                        // A glyph can be a whole cluster, which is named after its first character
+                        let placed = PlacedGlyph {
+                            index,
+                            character: text[index..].chars().next()?,
+                            key: GlyphKey::Glyph(id),
+                            instance,
+                            inset: texture.padding * glyph_scale,
+                        };
+
+                        Some(((GlyphKey::Glyph(id), instance), placed))
+                    })
+                    .unzip();
+
+                let data = TextData {
+                    text,
+                    normalize: false,
+                    font,
+                    position,
+                    relative_position: [0., 0.],
+                    color,
+                    opacity: 1.,
+                    scale,
+                    halign: Default::default(),
+                    valign: Default::default(),
+                    pixel_snap: false,
+                    stem_darkening: 0.,
+                    nearest_filter: font_data.filter == wgpu::FilterMode::Nearest,
+                    subpixel_phases: font_data.subpixel_phases,
+                    scale_factor,
+                    max_width: None,
+                    tabs: Default::default(),
+                    block_align: false,
+                    baseline_offset: 0.,
+                    ignore_trailing_whitespace: false,
+                    line_aligns: Vec::new(),
+                    paragraphs: Default::default(),
+                    animation: Default::default(),
+                    reveal: None,
+                    marquee: None,
+                    marquee_start: 0.,
+                    panel: None,
+                    sdf: font_data.sdf_settings.map(|sdf| SdfTextData {
+                        radius: sdf.radius,
+                        distance_scale: 1.,
+                        outline: None,
+                    }),
+                };
+
+                let mut text = Text::from_instances(data, instances, device, queue, text_renderer);
+                text.glyphs = placed;
+                Ok(text)
+            })
+            .collect()
+    }
+
+    /// Returns the kaku font for a cosmic-text face at a given size, loading it if it hasn't been
+    /// loaded yet. Returns None if the face can't be loaded.
+    fn font(
+        &mut self,
+        face: fontdb::ID,
+        font_size: f32,
+        font_system: &mut FontSystem,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_renderer: &mut TextRenderer,
+    ) -> Result<Option<FontId>, Error> {
+        let size = self.options.sdf.is_none().then_some(font_size.to_bits());
+
+        if let Some(font) = self.fonts.get(&(face, size)) {
+            return Ok(*font);
+        }
+
+        let index = font_system.db().face(face).map_or(0, |face| face.index);
+        let font = font_system
+            .get_font(face)
+            .and_then(|font| FontVec::try_from_vec_and_index(font.data().to_vec(), index).ok());
+
+        let font = match font {
+            Some(font) => {
+                let size = match size {
+                    Some(_) => FontSize::Px(font_size),
+                    None => self.options.size,
+                };
+
+                // Preloading caches characters, but cosmic-text text is drawn by glyph id
+                let options = FontOptions {
+                    size,
+                    preload: Preload::None,
+                    ..self.options.clone()
+                };
+
+                Some(text_renderer.load_font_with_options(font, options, device, queue)?)
+            }
+            None => {
+                warn!(
+                    "Font {face} from cosmic-text couldn't be loaded, so its glyphs won't be drawn"
+                );
+                None
+            }
+        };
+
+        self.fonts.insert((face, size), font);
+        Ok(font)
+    }
+}
@@ -0,0 +1,425 @@
+//! A software renderer that draws [Text] objects into images without using the GPU.
+//!
+//! This does the same maths as kaku's shaders (including sdf outlines) so the output should look
+//! the same as what gets drawn to the screen, give or take rounding. It's meant for testing: you
+//! can render text in a headless CI job and compare it against a known-good "golden" image with
+//! [compare_images](crate::compare_images) or [assert_matches_golden](crate::assert_matches_golden),
+//! to catch regressions in layout or effects.
+//!
+//! This module is only available with the `cpu-render` feature.
+
+use image::{GrayImage, Luma, Rgba, RgbaImage};
+
+use crate::{
+    animation::Reveal, panel::PanelUniform, sdf::SdfImage, text::TextData, CharTexture,
+    CharacterInstance, Error, Rect, SdfFormat, ShaderConstants, ShaderDebugMode, Text,
+    TextRenderer,
+};
+
+/// Converts a raster glyph image into the float image kept for cpu rendering.
+pub(crate) fn coverage_image(image: &GrayImage) -> SdfImage {
+    SdfImage::from_fn(image.width(), image.height(), |x, y| {
+        Luma([image.get_pixel(x, y).0[0] as f32 / 255.])
+    })
+}
+
+/// Rounds a distance field to the precision of the texture format it's uploaded in, so that the
+/// cpu renderer samples the same values as the GPU.
+pub(crate) fn quantized_image(image: &SdfImage, format: SdfFormat) -> SdfImage {
+    let mut image = image.clone();
+
+    for value in image.iter_mut() {
+        *value = match format {
+            SdfFormat::R8Unorm => (*value * 255.) as u8 as f32 / 255.,
+            SdfFormat::R16Unorm => (*value * 65535.) as u16 as f32 / 65535.,
+            SdfFormat::R16Float => half::f16::from_f32(*value).to_f32(),
+        };
+    }
+
+    image
+}
+
+/// Samples an image the way wgpu does with clamp-to-edge addressing.
+fn sample(image: &SdfImage, filter: wgpu::FilterMode, [u, v]: [f32; 2]) -> f32 {
+    let (w, h) = image.dimensions();
+
+    if filter == wgpu::FilterMode::Nearest {
+        let x = ((u * w as f32).max(0.) as u32).min(w - 1);
+        let y = ((v * h as f32).max(0.) as u32).min(h - 1);
+        return image.get_pixel(x, y).0[0];
+    }
+
+    let x = u * w as f32 - 0.5;
+    let y = v * h as f32 - 0.5;
+
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let fx = x - x0;
+    let fy = y - y0;
+
+    let texel = |x: f32, y: f32| {
+        let x = (x.max(0.) as u32).min(w - 1);
+        let y = (y.max(0.) as u32).min(h - 1);
+        image.get_pixel(x, y).0[0]
+    };
+
+    let top = texel(x0, y0) * (1. - fx) + texel(x0 + 1., y0) * fx;
+    let bottom = texel(x0, y0 + 1.) * (1. - fx) + texel(x0 + 1., y0 + 1.) * fx;
+    top * (1. - fy) + bottom * fy
+}
+
+/// The same as wgsl's smoothstep.
+fn smoothstep(low: f32, high: f32, x: f32) -> f32 {
+    let t = ((x - low) / (high - low)).clamp(0., 1.);
+    t * t * (3. - 2. * t)
+}
+
+/// Blends a colour onto a pixel the same way as [wgpu::BlendState::ALPHA_BLENDING].
+fn blend(pixel: &mut Rgba<u8>, src: [f32; 4]) {
+    let dst = pixel.0.map(|c| c as f32 / 255.);
+    let a = src[3];
+
+    let out = [
+        src[0] * a + dst[0] * (1. - a),
+        src[1] * a + dst[1] * (1. - a),
+        src[2] * a + dst[2] * (1. - a),
+        a + dst[3] * (1. - a),
+    ];
+
+    pixel.0 = out.map(|c| (c.clamp(0., 1.) * 255.).round() as u8);
+}
+
+/// Draws one glyph quad, colouring each pixel whose centre is inside it with `shade`, which is
+/// given the sampled texture value.
+///
+/// If the texture has more than one subpixel phase, the quad is moved to a whole pixel and the
+/// phase that makes up the difference is sampled, like in the basic shader.
+fn draw_quad(
+    target: &mut RgbaImage,
+    position: [f32; 2],
+    instance: &CharacterInstance,
+    texture: &CharTexture,
+    phases: u32,
+    clip: [f32; 2],
+    shade: impl Fn(f32) -> [f32; 4],
+) {
+    let mut x0 = position[0] + instance.position[0];
+    let y0 = position[1] + instance.position[1];
+    let [w, h] = instance.size;
+
+    let phases = phases.max(1) as f32;
+    let mut phase = 0.;
+
+    if phases > 1. {
+        // wgsl's round rounds halfway cases to even
+        let steps = (x0 * phases).round_ties_even();
+        x0 = (steps / phases).floor();
+        phase = steps - x0 * phases;
+    }
+
+    if w <= 0. || h <= 0. {
+        return;
+    }
+
+    // Pixels are drawn if their centre is in the quad, like the GPU's rasterisation rules
+    let first_pixel = |start: f32| (start - 0.5).ceil().max(0.) as u32;
+    let last_pixel = |end: f32, max: u32| ((end - 0.5).ceil().max(0.) as u32).min(max);
+
+    for y in first_pixel(y0)..last_pixel(y0 + h, target.height()) {
+        for x in first_pixel(x0)..last_pixel(x0 + w, target.width()) {
+            if x as f32 + 0.5 < clip[0] || x as f32 + 0.5 >= clip[1] {
+                continue;
+            }
+
+            let u = (x as f32 + 0.5 - x0) / w;
+            let v = ((y as f32 + 0.5 - y0) / h + phase) / phases;
+
+            let color = shade(sample(&texture.image, texture.filter, [u, v]));
+            blend(target.get_pixel_mut(x, y), color);
+        }
+    }
+}
+
+/// The same as the shaders' finish_colour: applies the gamma and debug mode to the colour of a
+/// pixel.
+fn finish_color(
+    [r, g, b, a]: [f32; 4],
+    alpha: f32,
+    value: f32,
+    constants: &ShaderConstants,
+) -> [f32; 4] {
+    let alpha = alpha.powf(1. / constants.gamma());
+
+    match constants.debug_mode {
+        ShaderDebugMode::Off => [r, g, b, a * alpha],
+        ShaderDebugMode::GlyphQuads => {
+            let t = a * alpha;
+            let mix = |quad: f32, text: f32| quad + (text - quad) * t;
+            [mix(1., r), mix(0., g), mix(1., b), mix(0.3, 1.)]
+        }
+        ShaderDebugMode::TextureValues => [value, value, value, 1.],
+    }
+}
+
+/// The shaders' PCG hash, for the random offsets of shaking glyphs.
+fn hash(value: u32) -> u32 {
+    let state = value.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+/// The same as the shaders' animation_offset: how far a glyph is moved by the text's animation.
+fn animation_offset(data: &TextData, index: u32, time: f32) -> [f32; 2] {
+    let [mode, amplitude, speed, _] = data.animation.uniform(data.scale_factor);
+    let cycles = time * speed;
+
+    match mode as u32 {
+        1 => [
+            0.,
+            amplitude * (std::f32::consts::TAU * cycles - index as f32 * 0.5).sin(),
+        ],
+        2 => {
+            let random = hash(index ^ hash(cycles.floor() as i32 as u32));
+            let x = (random & 0xffff) as f32 / 65535.;
+            let y = (random >> 16) as f32 / 65535.;
+            [x, y].map(|r| amplitude * (r * 2. - 1.))
+        }
+        _ => [0., 0.],
+    }
+}
+
+/// The same as the shaders' animation_colour: the colour of a glyph, which changes with the
+/// rainbow animation.
+fn animation_color(data: &TextData, index: u32, time: f32) -> [f32; 4] {
+    let [mode, _, speed, _] = data.animation.uniform(data.scale_factor);
+
+    if mode as u32 != 3 {
+        return data.faded(data.color);
+    }
+
+    // wgsl's fract is x - floor(x), even for negative numbers
+    let fract = |x: f32| x - x.floor();
+    let hue = fract(time * speed + index as f32 * 0.1);
+    let [r, g, b] = [0., 2. / 3., 1. / 3.]
+        .map(|offset| ((fract(hue + offset) * 6. - 3.).abs() - 1.).clamp(0., 1.));
+    [r, g, b, data.faded(data.color)[3]]
+}
+
+/// The same as the shaders' reveal_progress: how far through the reveal a glyph is, from 0
+/// (hidden) to 1 (shown).
+fn reveal_progress(data: &TextData, index: u32, time: f32) -> f32 {
+    let [start, duration, stagger, _] = Reveal::uniform(data.reveal);
+
+    if duration <= 0. {
+        return 1.;
+    }
+
+    let start = start + index as f32 * stagger;
+    ((time - start) / duration).clamp(0., 1.)
+}
+
+/// The same as the shaders' marquee_x: where the marquee moves the left of a glyph to, relative to
+/// the text's position.
+fn marquee_x(marquee: [[f32; 4]; 2], x: f32, width: f32, time: f32) -> f32 {
+    let [[start, speed, period, furthest], [left, ..]] = marquee;
+    let scrolled = (time - start).max(0.) * speed;
+
+    if period <= 0. {
+        return x - scrolled.clamp(0., furthest);
+    }
+
+    let right = x + width - scrolled - left;
+    right - period * ((right / period).ceil() - 1.) + left - width
+}
+
+/// Where a text is drawn on the target. Relative positions are resolved against the size of the
+/// target, like in the shaders.
+fn position_on_target(data: &TextData, target: &RgbaImage) -> [f32; 2] {
+    let [x, y] = data.draw_position();
+    let [rel_x, rel_y] = data.relative_position;
+    [
+        x + (rel_x * target.width() as f32).round(),
+        y + (rel_y * target.height() as f32).round(),
+    ]
+}
+
+/// The same as the panel shader's rounded_rect_distance: the signed distance from a rounded
+/// rectangle centred on the origin, negative inside it.
+fn rounded_rect_distance(offset: [f32; 2], half_size: [f32; 2], radius: f32) -> f32 {
+    let corner = [0, 1].map(|i| offset[i].abs() - half_size[i] + radius);
+    let outside = corner.map(|c| c.max(0.));
+    (outside[0] * outside[0] + outside[1] * outside[1]).sqrt() + corner[0].max(corner[1]).min(0.)
+        - radius
+}
+
+/// Draws the panel behind a text, a rect in physical pixels relative to the text's position,
+/// matching the panel shader.
+fn draw_panel(target: &mut RgbaImage, data: &TextData, rect: Rect, settings: &PanelUniform) {
+    let rect = rect.translate(position_on_target(data, target));
+    let half_size = [rect.width() / 2., rect.height() / 2.];
+    let centre = [0, 1].map(|i| rect.min[i] + half_size[i]);
+    let radius = settings.corner_radius.min(half_size[0].min(half_size[1]));
+
+    let premultiplied = |[r, g, b, a]: [f32; 4]| [r * a, g * a, b * a, a];
+    let fill = premultiplied(settings.fill);
+    let border = premultiplied(settings.border_color);
+
+    // Pixels are drawn if their centre is in the quad, like the GPU's rasterisation rules
+    let first_pixel = |start: f32| (start - 0.5).ceil().max(0.) as u32;
+    let last_pixel = |end: f32, max: u32| ((end - 0.5).ceil().max(0.) as u32).min(max);
+
+    for y in first_pixel(rect.min[1])..last_pixel(rect.max[1], target.height()) {
+        for x in first_pixel(rect.min[0])..last_pixel(rect.max[0], target.width()) {
+            let offset = [x as f32 + 0.5 - centre[0], y as f32 + 0.5 - centre[1]];
+            let distance = rounded_rect_distance(offset, half_size, radius);
+
+            let coverage = (0.5 - distance).clamp(0., 1.);
+            let inside = if settings.border_width > 0. {
+                (0.5 - distance - settings.border_width).clamp(0., 1.)
+            } else {
+                1.
+            };
+
+            let [r, g, b, a] =
+                [0, 1, 2, 3].map(|i| (border[i] + (fill[i] - border[i]) * inside) * coverage);
+            if a <= 0. {
+                continue;
+            }
+            blend(target.get_pixel_mut(x, y), [r / a, g / a, b / a, a]);
+        }
+    }
+}
+
+/// Draws the glyphs of a piece of text, matching what the shaders do.
+fn draw_glyphs(
+    target: &mut RgbaImage,
+    data: &TextData,
+    glyphs: &[(&CharacterInstance, &CharTexture)],
+    bounds: Rect,
+    time: f32,
+    constants: &ShaderConstants,
+) {
+    let position = position_on_target(data, target);
+
+    // Nothing outside the marquee's box is drawn, if there is one
+    let marquee = data.marquee_uniform(bounds);
+    let clip = match marquee[1] {
+        [left, right, enabled, _] if enabled > 0. => [position[0] + left, position[0] + right],
+        _ => [f32::NEG_INFINITY, f32::INFINITY],
+    };
+
+    // Revealing glyphs slide up from a quarter of their height below their place and fade in
+    let glyph_position = |instance: &CharacterInstance| {
+        let [x, y] = animation_offset(data, instance.index, time);
+        let [glyph_x, _] = instance.position;
+        let scroll = marquee_x(marquee, glyph_x, instance.size[0], time) - glyph_x;
+        let progress = reveal_progress(data, instance.index, time);
+        let slide = (1. - progress) * (1. - progress) * instance.size[1] * 0.25;
+        [position[0] + scroll + x, position[1] + y + slide]
+    };
+    let glyph_color = |instance: &CharacterInstance, [r, g, b, a]: [f32; 4]| {
+        [r, g, b, a * reveal_progress(data, instance.index, time)]
+    };
+
+    let Some(sdf) = &data.sdf else {
+        for (instance, texture) in glyphs {
+            let color = glyph_color(instance, animation_color(data, instance.index, time));
+            draw_quad(
+                target,
+                glyph_position(instance),
+                instance,
+                texture,
+                data.subpixel_phases,
+                clip,
+                |coverage| {
+                    let alpha = coverage.powf(1. / (1. + data.stem_darkening.max(-0.9)));
+                    finish_color(color, alpha, coverage, constants)
+                },
+            );
+        }
+
+        return;
+    };
+
+    let distance = |value: f32| (value - 0.5) * 2. * sdf.radius;
+    let aa_thresh = constants.edge_smoothing() / data.image_scale();
+
+    // The outline is drawn underneath all the characters, like in TextRenderer::draw_text
+    if let Some(outline) = &sdf.outline {
+        let radius =
+            data.outline_width() * data.scale_factor * sdf.distance_scale / data.image_scale();
+
+        for (instance, texture) in glyphs {
+            let color = glyph_color(instance, data.faded(outline.color));
+            draw_quad(
+                target,
+                glyph_position(instance),
+                instance,
+                texture,
+                1,
+                clip,
+                |value| {
+                    let alpha = smoothstep(radius + aa_thresh, radius - aa_thresh, distance(value));
+                    finish_color(color, alpha, value, constants)
+                },
+            );
+        }
+    }
+
+    for (instance, texture) in glyphs {
+        let color = glyph_color(instance, animation_color(data, instance.index, time));
+        draw_quad(
+            target,
+            glyph_position(instance),
+            instance,
+            texture,
+            1,
+            clip,
+            |value| {
+                let alpha = smoothstep(aa_thresh, -aa_thresh, distance(value));
+                finish_color(color, alpha, value, constants)
+            },
+        );
+    }
+}
+
+impl TextRenderer {
+    /// Draws a [Text] object into an image on the CPU, blending it over what's already there.
+    ///
+    /// This gives (almost exactly) the same result as [TextRenderer::draw_text], but doesn't need
+    /// a render pass, so it can be used for testing or rendering without a GPU. The text is
+    /// positioned in pixel coordinates of the image.
+    ///
+    /// Missing characters are skipped and errors are returned in the same cases as
+    /// [TextRenderer::draw_text].
+    pub fn draw_text_to_image(&self, text: &Text, target: &mut RgbaImage) -> Result<(), Error> {
+        let font_data = self.fonts.get(text.data.font)?;
+
+        let glyphs: Vec<_> = self
+            .instance_textures(font_data, text)
+            .into_iter()
+            .map(|(i, texture)| (&text.instances[i as usize], texture))
+            .collect();
+
+        if let Some(panel) = &text.data.panel {
+            let rect = text.physical_panel_rect(panel);
+            draw_panel(
+                target,
+                &text.data,
+                rect,
+                &PanelUniform::new(panel, &text.data),
+            );
+        }
+
+        let bounds = text.layout_bounds;
+        draw_glyphs(
+            target,
+            &text.data,
+            &glyphs,
+            bounds,
+            self.time,
+            &self.constants,
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+//! Glyphs drawn from images registered by the user (see
+//! [TextRenderer::register_glyph](crate::TextRenderer::register_glyph)).
+
+use image::{imageops, GrayImage};
+
+/// Where a registered glyph's image is drawn and how far it moves the pen (see
+/// [TextRenderer::register_glyph](crate::TextRenderer::register_glyph)). These are in pixels at
+/// the size the font was loaded at.
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub struct GlyphMetrics {
+    /// The position of the image's top left corner relative to the pen position, which is on the
+    /// baseline. Negative y is up, so an image sitting on the baseline has a y of minus its
+    /// height.
+    pub offset: [f32; 2],
+    /// How far the pen moves after the glyph.
+    pub advance: f32,
+}
+
+impl GlyphMetrics {
+    /// Metrics for an image sitting on the baseline, with no space on either side.
+    pub fn on_baseline(image: &GrayImage) -> Self {
+        Self {
+            offset: [0., -(image.height() as f32)],
+            advance: image.width() as f32,
+        }
+    }
+}
+
+/// A glyph registered for a character, kept so it can be generated again whenever the font's
+/// characters are (e.g. when the scale factor changes).
+#[derive(Debug)]
+pub(crate) struct CustomGlyph {
+    pub(crate) image: GrayImage,
+    pub(crate) metrics: GlyphMetrics,
+}
+
+impl CustomGlyph {
+    /// The glyph's image, position and advance at a number of times the loaded size, like the
+    /// generated glyphs in [crate::fallback_glyph].
+    pub(crate) fn scaled(&self, scale: f32) -> (GrayImage, [f32; 2], f32) {
+        let GlyphMetrics { offset, advance } = self.metrics;
+
+        if scale == 1. || self.image.width() == 0 || self.image.height() == 0 {
+            return (self.image.clone(), offset, advance);
+        }
+
+        let [width, height] = [self.image.width(), self.image.height()]
+            .map(|size| ((size as f32 * scale).round() as u32).max(1));
+        let image = imageops::resize(&self.image, width, height, imageops::Triangle);
+        (image, offset.map(|p| p * scale), advance * scale)
+    }
+}
@@ -0,0 +1,123 @@
+//! Wireframes of texts' glyphs and lines for debugging layouts (see [TextRenderer::draw_debug]).
+
+use std::sync::Arc;
+
+use wgpu::util::DeviceExt;
+
+use crate::{bake::BakedSettingsUniform, text::TextData, CharacterInstance, Text, TextRenderer};
+
+/// What each of the overlay's instances is, which picks its colour in the shader.
+#[derive(Clone, Copy, Debug)]
+enum DebugQuad {
+    Glyph = 0,
+    Ascent = 1,
+    Baseline = 2,
+    Descent = 3,
+}
+
+/// The buffers of a text's debug overlay. They're made the first time the overlay is drawn, and
+/// made again after the text is laid out again.
+#[derive(Debug)]
+pub(crate) struct DebugOverlay {
+    /// A quad for each glyph, then the ascent, baseline and descent of each line.
+    instance_buffer: wgpu::Buffer,
+    instance_count: u32,
+    /// Its position is the text's, which is the same as a baked text's settings.
+    pub(crate) settings_buffer: Arc<wgpu::Buffer>,
+    bind_group: wgpu::BindGroup,
+}
+
+impl DebugOverlay {
+    fn new(text: &Text, text_renderer: &TextRenderer, device: &wgpu::Device) -> Self {
+        let quad = |kind: DebugQuad, min: [f32; 2], size: [f32; 2]| CharacterInstance {
+            position: min,
+            size,
+            index: kind as u32,
+        };
+
+        // The lines are a pixel tall, inside the line's box
+        let glyphs = (text.glyphs.iter()).map(|glyph| {
+            let rect = glyph.instance.rect();
+            quad(DebugQuad::Glyph, rect.min, [rect.width(), rect.height()])
+        });
+        let lines = text.lines.iter().flat_map(|line| {
+            let [left, top] = line.rect.min;
+            let [width, bottom] = [line.rect.width(), line.rect.max[1]];
+            [
+                quad(DebugQuad::Ascent, [left, top], [width, 1.]),
+                quad(DebugQuad::Baseline, [left, line.baseline], [width, 1.]),
+                quad(DebugQuad::Descent, [left, bottom - 1.], [width, 1.]),
+            ]
+        });
+        let instances: Vec<_> = glyphs.chain(lines).collect();
+
+        // Empty buffers can't be bound, so there's always room for one instance
+        let contents = if instances.is_empty() {
+            bytemuck::bytes_of(&quad(DebugQuad::Glyph, [0.; 2], [0.; 2])).to_vec()
+        } else {
+            bytemuck::cast_slice(&instances).to_vec()
+        };
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&text_renderer.label("debug overlay instance buffer")),
+            contents: &contents,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let settings_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&text_renderer.label("debug overlay settings uniform buffer")),
+            contents: bytemuck::bytes_of(&Self::settings_uniform(&text.data)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&text_renderer.label("debug overlay settings uniform bind group")),
+            layout: &text_renderer.baked_settings_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: settings_buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            instance_buffer,
+            instance_count: instances.len() as u32,
+            settings_buffer: Arc::new(settings_buffer),
+            bind_group,
+        }
+    }
+
+    /// The overlay's settings for a text, which put it where the text is.
+    pub(crate) fn settings_uniform(data: &TextData) -> BakedSettingsUniform {
+        BakedSettingsUniform {
+            text_position: data.draw_position(),
+            relative_position: data.relative_position,
+        }
+    }
+}
+
+impl TextRenderer {
+    /// Draws wireframes over a text for debugging its layout: the outline of each glyph's quad
+    /// (magenta), and the ascent (green), baseline (red) and descent (blue) of each line. Draw it
+    /// after the text so it's on top.
+    ///
+    /// The quads are where the layout put them, without the text's animation. Texts laid out by
+    /// cosmic-text don't keep their lines, so only their glyphs are drawn.
+    ///
+    /// The overlay's buffers are made with `device` the first time it's drawn, and kept until the
+    /// text is laid out again, so drawing it every frame is cheap.
+    pub fn draw_debug<'pass>(
+        &'pass self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        text: &'pass Text,
+        device: &wgpu::Device,
+    ) {
+        let overlay = (text.debug_overlay).get_or_init(|| DebugOverlay::new(text, self, device));
+        let target = &self.targets[0];
+
+        render_pass.set_pipeline(&target.pipelines.debug);
+        render_pass.set_bind_group(0, &target.screen_bind_group, &[]);
+        render_pass.set_bind_group(1, &overlay.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, overlay.instance_buffer.slice(..));
+        render_pass.draw(0..4, 0..overlay.instance_count);
+    }
+}
@@ -0,0 +1,172 @@
+//! A persistent on-disk cache for signed distance fields.
+//!
+//! Generating a distance field is the slowest part of caching a character, so this cache saves
+//! every generated field to a directory and loads it back the next time the same glyph is needed
+//! (even in a later run of the program). Fields are keyed by a hash of the font data, the glyph
+//! id, the font scale and the sdf settings, so changing any of those will miss the cache instead
+//! of loading stale data.
+//!
+//! The file format is a small header (magic bytes, width, height, padding) followed by the
+//! distance values as little-endian f32s. The keys are hashed with FNV-1a rather than one of std's
+//! hashers, whose algorithms can change between Rust releases, so the files are still found after
+//! a toolchain update.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use ab_glyph::{Font, FontArc, GlyphId, PxScale};
+use log::warn;
+
+use crate::sdf::{SdfImage, SdfSettings, SdfSource};
+
+const MAGIC: &[u8; 8] = b"KAKUSDF1";
+const HEADER_LEN: usize = MAGIC.len() + 3 * 4;
+/// The version of how keys are made, which is part of every key. Changing what goes into a key (or
+/// how distance fields are generated) should bump this, so that old files are never loaded.
+const KEY_VERSION: u32 = 2;
+
+/// A 64 bit FNV-1a hasher, which always gives the same hash for the same bytes.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// Hashes the raw data of a font, to identify it in the disk cache.
+pub(crate) fn font_hash(font: &FontArc) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(font.font_data());
+    hasher.0
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub(crate) struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, font_hash: u64, glyph: GlyphId, scale: PxScale, sdf: &SdfSettings) -> PathBuf {
+        let source: u8 = match sdf.source {
+            SdfSource::Raster => 0,
+            SdfSource::Outline => 1,
+        };
+
+        // Everything is written as little-endian bytes, so keys are the same on every platform
+        let mut hasher = Fnv1a::new();
+        hasher.write(&KEY_VERSION.to_le_bytes());
+        hasher.write(&font_hash.to_le_bytes());
+        hasher.write(&glyph.0.to_le_bytes());
+        hasher.write(&scale.x.to_bits().to_le_bytes());
+        hasher.write(&scale.y.to_bits().to_le_bytes());
+        hasher.write(&sdf.radius.to_bits().to_le_bytes());
+        hasher.write(&[source]);
+
+        self.dir.join(format!("{:016x}.sdf", hasher.0))
+    }
+
+    /// Loads a distance field from the cache, returning it along with its padding.
+    ///
+    /// Returns None if the field isn't cached or the cache file can't be read.
+    pub(crate) fn load(
+        &self,
+        font_hash: u64,
+        glyph: GlyphId,
+        scale: PxScale,
+        sdf: &SdfSettings,
+    ) -> Option<(SdfImage, u32)> {
+        let path = self.path(font_hash, glyph, scale, sdf);
+        let bytes = fs::read(&path).ok()?;
+
+        let result = decode(&bytes);
+
+        if result.is_none() {
+            warn!("Ignoring invalid sdf cache file {}", path.display());
+        }
+
+        result
+    }
+
+    /// Saves a distance field to the cache. Failing to write the file only logs a warning, since
+    /// the cache is just an optimisation.
+    pub(crate) fn store(
+        &self,
+        font_hash: u64,
+        glyph: GlyphId,
+        scale: PxScale,
+        sdf: &SdfSettings,
+        image: &SdfImage,
+        padding: u32,
+    ) {
+        let path = self.path(font_hash, glyph, scale, sdf);
+
+        if let Err(e) = write_file(&self.dir, &path, image, padding) {
+            warn!("Couldn't write sdf cache file {}: {e}", path.display());
+        }
+    }
+}
+
+/// Writes a cache file. It's written to a temporary file in the same directory first and then
+/// renamed into place, so other threads or programs sharing the cache never load a half written
+/// file, and a crash doesn't leave one behind.
+fn write_file(dir: &Path, path: &Path, image: &SdfImage, padding: u32) -> io::Result<()> {
+    // Each write gets its own temporary file, in case several write the same field at once
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+
+    fs::create_dir_all(dir)?;
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + image.len() * 4);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&image.width().to_le_bytes());
+    bytes.extend_from_slice(&image.height().to_le_bytes());
+    bytes.extend_from_slice(&padding.to_le_bytes());
+
+    for value in image.iter() {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let write = WRITES.fetch_add(1, Ordering::Relaxed);
+    let temp_path = path.with_extension(format!("{}-{write}.tmp", std::process::id()));
+    let result = fs::write(&temp_path, bytes).and_then(|()| fs::rename(&temp_path, path));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn decode(bytes: &[u8]) -> Option<(SdfImage, u32)> {
+    let (header, data) = bytes.split_at_checked(HEADER_LEN)?;
+    let (magic, header) = header.split_at(MAGIC.len());
+
+    if magic != MAGIC {
+        return None;
+    }
+
+    let read_u32 = |i: usize| u32::from_le_bytes(header[i * 4..i * 4 + 4].try_into().unwrap());
+    let width = read_u32(0);
+    let height = read_u32(1);
+    let padding = read_u32(2);
+
+    let values = data
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+
+    let image = SdfImage::from_raw(width, height, values)?;
+    Some((image, padding))
+}
@@ -0,0 +1,168 @@
+//! Drawing text inside egui panels, using egui-wgpu paint callbacks.
+
+use std::sync::{Arc, OnceLock};
+
+use egui_wgpu::{CallbackResources, CallbackTrait, ScreenDescriptor};
+use log::warn;
+
+use crate::{profiling::Profiling, ScreenUniform, Text, TextRenderer};
+
+/// An egui-wgpu paint callback that draws kaku text inside an egui panel, e.g. for sdf headings
+/// with outlines that egui can't draw itself.
+///
+/// The callback draws with a [TextRenderer] stored in egui-wgpu's callback resources. Create it
+/// with [TextRendererBuilder::for_egui](crate::TextRendererBuilder::for_egui), so it matches
+/// egui's render pass, and store it when setting up your app:
+///
+/// ```rust,no_run
+/// # use kaku::*;
+/// # struct CreationContext {
+/// #     wgpu_render_state: Option<egui_wgpu::RenderState>,
+/// # }
+/// # fn example(creation_context: &CreationContext) {
+/// let render_state = creation_context.wgpu_render_state.as_ref().unwrap();
+/// let text_renderer = TextRendererBuilder::for_egui(render_state, 1, None)
+///     .build(&render_state.device);
+///
+/// render_state.renderer.write().callback_resources.insert(text_renderer);
+/// # }
+/// ```
+///
+/// Fonts are loaded and text is built with the stored renderer:
+///
+/// ```rust,no_run
+/// # use std::sync::Arc;
+/// # use kaku::*;
+/// # fn example(render_state: &egui_wgpu::RenderState, font: FontId) -> Result<(), Error> {
+/// let mut renderer = render_state.renderer.write();
+/// let text_renderer = renderer.callback_resources.get_mut::<TextRenderer>().unwrap();
+/// let heading = Arc::new(TextBuilder::new("Heading", font, [10., 40.]).build(
+///     &render_state.device,
+///     &render_state.queue,
+///     text_renderer,
+/// )?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Then each frame, add the callback to a panel's painter:
+///
+/// ```rust,no_run
+/// # use std::sync::Arc;
+/// # use kaku::*;
+/// # fn example(ui: &mut egui::Ui, heading: Arc<Text>) {
+/// let (rect, _) = ui.allocate_exact_size(egui::vec2(300., 60.), egui::Sense::hover());
+/// ui.painter().add(EguiTextCallback::paint_callback(rect, [heading.clone()]));
+/// # }
+/// ```
+///
+/// Text positions are relative to the top left corner of the callback's rect. They're in physical
+/// pixels, unless the renderer's scale factor is set to egui's pixels per point (see
+/// [TextRenderer::set_scale_factor]). Text outside the rect (or outside the panel's clip rect) is
+/// clipped.
+pub struct EguiTextCallback {
+    rect: egui::Rect,
+    texts: Vec<Arc<Text>>,
+    /// The screen uniform that moves the text to the rect, which is made when the callback is
+    /// prepared.
+    screen_bind_group: OnceLock<wgpu::BindGroup>,
+}
+
+impl EguiTextCallback {
+    /// Creates a callback that draws the given text in a rect.
+    ///
+    /// The rect has to be the same one the callback is painted in. [EguiTextCallback::paint_callback]
+    /// does both at once.
+    pub fn new(rect: egui::Rect, texts: impl IntoIterator<Item = Arc<Text>>) -> Self {
+        Self {
+            rect,
+            texts: texts.into_iter().collect(),
+            screen_bind_group: OnceLock::new(),
+        }
+    }
+
+    /// Creates an egui paint callback that draws the given text in a rect.
+    pub fn paint_callback(
+        rect: egui::Rect,
+        texts: impl IntoIterator<Item = Arc<Text>>,
+    ) -> egui::PaintCallback {
+        egui_wgpu::Callback::new_paint_callback(rect, Self::new(rect, texts))
+    }
+}
+
+impl CallbackTrait for EguiTextCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        screen_descriptor: &ScreenDescriptor,
+        _egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let Some(text_renderer) = callback_resources.get::<TextRenderer>() else {
+            warn!("No kaku TextRenderer in egui's callback resources, so text can't be drawn");
+            return Vec::new();
+        };
+
+        let [width, height] = screen_descriptor.size_in_pixels;
+        let origin = self.rect.min * screen_descriptor.pixels_per_point;
+        let screen_uniform = ScreenUniform::new((width, height)).translated([origin.x, origin.y]);
+
+        self.screen_bind_group
+            .get_or_init(|| text_renderer.create_screen_bind_group(screen_uniform, device));
+
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        info: egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a CallbackResources,
+    ) {
+        let (Some(text_renderer), Some(screen_bind_group)) = (
+            callback_resources.get::<TextRenderer>(),
+            self.screen_bind_group.get(),
+        ) else {
+            return;
+        };
+
+        // Only draw where the rect and egui's clip rect overlap. Both are already clamped to the
+        // screen
+        let rect = info.viewport_in_pixels();
+        let clip = info.clip_rect_in_pixels();
+        let left = rect.left_px.max(clip.left_px);
+        let top = rect.top_px.max(clip.top_px);
+        let right = (rect.left_px + rect.width_px).min(clip.left_px + clip.width_px);
+        let bottom = (rect.top_px + rect.height_px).min(clip.top_px + clip.height_px);
+
+        if right <= left || bottom <= top {
+            return;
+        }
+
+        render_pass.set_scissor_rect(
+            left as u32,
+            top as u32,
+            (right - left) as u32,
+            (bottom - top) as u32,
+        );
+
+        // egui sets the viewport to the rect, but that gets squashed if the rect is partly off the
+        // screen. The screen uniform already moves the text to the rect, so draw over the whole
+        // screen instead
+        let [width, height] = info.screen_size_px;
+        render_pass.set_viewport(0., 0., width as f32, height as f32, 0., 1.);
+
+        for text in &self.texts {
+            if let Err(e) = text_renderer.draw_text_with_screen(
+                render_pass,
+                text,
+                &text_renderer.targets[0].pipelines,
+                screen_bind_group,
+                Profiling::NONE,
+            ) {
+                warn!("Couldn't draw text in egui: {e}");
+            }
+        }
+    }
+}
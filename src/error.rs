@@ -0,0 +1,92 @@
+//! The error type returned by [TextRenderer](crate::TextRenderer) and [Text](crate::Text)
+//! operations.
+
+use std::fmt;
+
+use crate::FontId;
+#[cfg(has_wgpu)]
+use crate::TargetId;
+
+/// An error from loading a font, or creating or drawing text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The font isn't loaded in this text renderer. This happens if a [FontId] from one renderer
+    /// is used with another.
+    FontNotFound(FontId),
+    /// The font doesn't say how many units are in an em, so it can't be scaled to a font size.
+    UnscalableFont,
+    /// A character of the text doesn't have a cached texture, so the text can't be laid out again
+    /// (e.g. in [Text::set_pixel_snap](crate::Text::set_pixel_snap) after the font's cache was
+    /// cleared). Drawing skips these characters instead.
+    GlyphNotCached(char),
+    /// The target wasn't added to this text renderer (see
+    /// [TextRenderer::add_target](crate::TextRenderer::add_target)). This happens if a [TargetId]
+    /// from one renderer is used with another.
+    #[cfg(has_wgpu)]
+    TargetNotFound(TargetId),
+    /// The number of view projections given for a multiview target doesn't match its number of
+    /// views (see [TextRenderer::set_view_projections](crate::TextRenderer::set_view_projections)).
+    WrongViewCount {
+        /// The number of views the target has.
+        views: u32,
+        /// The number of projections that were given.
+        projections: usize,
+    },
+    /// Text can't be baked for a multiview target (see
+    /// [TextRenderer::bake_for_target](crate::TextRenderer::bake_for_target)). Bake it for a
+    /// single view target in the same format instead, which can then be drawn to any target.
+    #[cfg(has_wgpu)]
+    MultiviewTarget(TargetId),
+    /// A texture would be bigger than the device supports, e.g. when baking a huge text (see
+    /// [TextRenderer::bake](crate::TextRenderer::bake)).
+    TextureTooLarge {
+        /// The size of the texture.
+        size: (u32, u32),
+        /// The device's [max_texture_dimension_2d](wgpu::Limits::max_texture_dimension_2d).
+        max: u32,
+    },
+    /// The device doesn't have the features something needs, e.g.
+    /// [TEXTURE_FORMAT_16BIT_NORM](wgpu::Features::TEXTURE_FORMAT_16BIT_NORM) for
+    /// [SdfFormat::R16Unorm](crate::SdfFormat::R16Unorm) distance fields.
+    #[cfg(has_wgpu)]
+    MissingFeatures(wgpu::Features),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::FontNotFound(font) => {
+                write!(f, "font {} is not loaded in this text renderer", font.0)
+            }
+            Error::UnscalableFont => write!(f, "the font has no units per em, so can't be scaled"),
+            Error::GlyphNotCached(c) => write!(f, "character {c:?} is not in the font's cache"),
+            #[cfg(has_wgpu)]
+            Error::TargetNotFound(target) => {
+                write!(f, "target {} is not in this text renderer", target.0)
+            }
+            Error::WrongViewCount { views, projections } => write!(
+                f,
+                "the target has {views} views, but {projections} view projections were given"
+            ),
+            #[cfg(has_wgpu)]
+            Error::MultiviewTarget(target) => {
+                write!(
+                    f,
+                    "target {} is multiview, so text can't be baked for it",
+                    target.0
+                )
+            }
+            Error::TextureTooLarge { size, max } => write!(
+                f,
+                "a {}x{} texture is bigger than this device's limit of {max} pixels",
+                size.0, size.1
+            ),
+            #[cfg(has_wgpu)]
+            Error::MissingFeatures(features) => {
+                write!(f, "the device doesn't have the features {features:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,101 @@
+//! Glyphs that kaku draws itself for characters a font doesn't have (see [crate::MissingGlyphs]).
+//!
+//! These are drawn as coverage images at the font's loaded size, so they can be turned into
+//! distance fields the same way as raster glyphs.
+
+use image::{GrayImage, Luma};
+
+/// A 3x5 pixel font for the hex digits 0-F. Each row is 3 bits, most significant bit on the left.
+const HEX_DIGITS: [[u8; 5]; 16] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+    [0b111, 0b101, 0b111, 0b101, 0b101],
+    [0b110, 0b101, 0b110, 0b101, 0b110],
+    [0b111, 0b100, 0b100, 0b100, 0b111],
+    [0b110, 0b101, 0b101, 0b101, 0b110],
+    [0b111, 0b100, 0b111, 0b100, 0b111],
+    [0b111, 0b100, 0b111, 0b100, 0b100],
+];
+
+const DIGIT_WIDTH: u32 = 3;
+const DIGIT_HEIGHT: u32 = 5;
+
+/// Draws a hollow box to stand in for a character the font doesn't have, for fonts whose .notdef
+/// glyph is empty or that don't have outlines at all.
+///
+/// The box is sized from the font's pixel size and ascent. Returns the image, the position of its
+/// top left corner relative to the pen position, and the advance.
+pub(crate) fn notdef_box(px_size: f32, ascent: f32) -> (GrayImage, [f32; 2], f32) {
+    let width = (px_size * 0.5).round().max(3.) as u32;
+    let height = (ascent * 0.8).round().max(3.) as u32;
+    let stroke = (px_size / 16.).round().clamp(1., (width / 3) as f32) as u32;
+    let bearing = (px_size * 0.08).round().max(1.);
+
+    let image = GrayImage::from_fn(width, height, |x, y| {
+        let edge = x < stroke || y < stroke || x >= width - stroke || y >= height - stroke;
+        Luma([if edge { 255 } else { 0 }])
+    });
+
+    let advance = width as f32 + 2. * bearing;
+    (image, [bearing, -(height as f32)], advance)
+}
+
+/// Draws a box containing a character's codepoint in hex, in two rows, like Firefox does for
+/// characters that no font can draw.
+///
+/// Characters in the Basic Multilingual Plane get 4 digits and the rest get 6. The digits are
+/// scaled up by a whole number of pixels so they stay crisp. Returns the same things as
+/// [notdef_box].
+pub(crate) fn hex_box(c: char, px_size: f32) -> (GrayImage, [f32; 2], f32) {
+    let digits = match c as u32 {
+        0..=0xFFFF => format!("{:04X}", c as u32),
+        _ => format!("{:06X}", c as u32),
+    };
+    let columns = digits.len() as u32 / 2;
+
+    // Each unit is one pixel of the digit font. The box has a 1 unit border with 1 unit of space
+    // inside it, and 1 unit between digits.
+    let unit = (px_size / 16.).floor().max(1.) as u32;
+    let width = (columns * (DIGIT_WIDTH + 1) + 3) * unit;
+    let height = (2 * (DIGIT_HEIGHT + 1) + 3) * unit;
+    let bearing = unit as f32;
+
+    let mut image = GrayImage::from_fn(width, height, |x, y| {
+        let edge = x < unit || y < unit || x >= width - unit || y >= height - unit;
+        Luma([if edge { 255 } else { 0 }])
+    });
+
+    for (i, digit) in digits.chars().enumerate() {
+        // The digits are always valid hex, since they were just formatted
+        let rows = HEX_DIGITS[digit.to_digit(16).unwrap() as usize];
+        let left = (2 + (i as u32 % columns) * (DIGIT_WIDTH + 1)) * unit;
+        let top = (2 + (i as u32 / columns) * (DIGIT_HEIGHT + 1)) * unit;
+
+        for (row, bits) in rows.iter().enumerate() {
+            for column in 0..DIGIT_WIDTH {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+
+                for dy in 0..unit {
+                    for dx in 0..unit {
+                        let x = left + column * unit + dx;
+                        let y = top + row as u32 * unit + dy;
+                        image.put_pixel(x, y, Luma([255]));
+                    }
+                }
+            }
+        }
+    }
+
+    let advance = width as f32 + 2. * bearing;
+    (image, [bearing, -(height as f32)], advance)
+}
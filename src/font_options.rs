@@ -0,0 +1,202 @@
+//! Options for loading a font into the [crate::TextRenderer].
+
+#[cfg(has_wgpu)]
+use crate::SamplerSettings;
+use crate::{FontSize, SdfSettings};
+
+/// The default padding around raster glyph textures (see [FontOptions::with_glyph_padding]).
+pub(crate) const DEFAULT_GLYPH_PADDING: u32 = 1;
+
+/// Which characters to cache as soon as a font is loaded.
+///
+/// See [FontOptions::with_preload].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Preload {
+    /// Don't cache any characters up front. Characters will be cached as they are needed.
+    #[default]
+    None,
+    /// Cache all the printable ASCII characters (from `' '` to `'~'`).
+    Ascii,
+    /// Cache the given characters.
+    Chars(Vec<char>),
+}
+
+impl Preload {
+    pub(crate) fn chars(&self) -> Vec<char> {
+        match self {
+            Preload::None => Vec::new(),
+            Preload::Ascii => (' '..='~').collect(),
+            Preload::Chars(chars) => chars.clone(),
+        }
+    }
+}
+
+/// How to draw characters that a font doesn't have a glyph for.
+///
+/// See [FontOptions::with_missing_glyphs] and [crate::TextRenderer::set_missing_glyphs].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MissingGlyphs {
+    /// Draw whatever the font draws for them. Most fonts draw their .notdef glyph (usually a box),
+    /// but some fonts' .notdef glyph is empty, and fonts made from pre-rendered glyphs (e.g.
+    /// bitmap fonts) draw nothing.
+    #[default]
+    Font,
+    /// Always draw something visible: the font's .notdef glyph, or a generated box if the .notdef
+    /// glyph is empty or the font doesn't have one. This is useful during development and testing
+    /// to make sure no text is silently lost.
+    Notdef,
+    /// Draw a box containing the character's codepoint in hex, like Firefox does. This makes it
+    /// easy to tell which characters are missing, e.g. when checking translations.
+    HexBox,
+}
+
+/// Options for loading a font, used with [crate::TextRenderer::load_font_with_options].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontOptions {
+    pub(crate) size: FontSize,
+    pub(crate) sdf: Option<SdfSettings>,
+    pub(crate) preload: Preload,
+    pub(crate) supersampling: u32,
+    pub(crate) subpixel_phases: u32,
+    pub(crate) glyph_padding: u32,
+    pub(crate) missing_glyphs: MissingGlyphs,
+    #[cfg(has_wgpu)]
+    pub(crate) texture_usages: wgpu::TextureUsages,
+    #[cfg(has_wgpu)]
+    pub(crate) sampler: Option<SamplerSettings>,
+    #[cfg(feature = "swash")]
+    pub(crate) hinting: bool,
+}
+
+impl FontOptions {
+    /// Creates a new set of font options, for a font of the given size.
+    ///
+    /// By default, the font will not use sdf and no characters will be preloaded.
+    pub fn new(size: FontSize) -> Self {
+        Self {
+            size,
+            sdf: None,
+            preload: Preload::None,
+            supersampling: 1,
+            subpixel_phases: 1,
+            glyph_padding: DEFAULT_GLYPH_PADDING,
+            missing_glyphs: MissingGlyphs::Font,
+            #[cfg(has_wgpu)]
+            texture_usages: wgpu::TextureUsages::empty(),
+            #[cfg(has_wgpu)]
+            sampler: None,
+            #[cfg(feature = "swash")]
+            hinting: false,
+        }
+    }
+
+    /// Enables sdf rendering for the font, with the given settings.
+    ///
+    /// See [crate::TextRenderer::load_font_with_sdf] for details.
+    pub fn with_sdf(mut self, sdf_settings: SdfSettings) -> Self {
+        self.sdf = Some(sdf_settings);
+        self
+    }
+
+    /// Sets which characters should be cached as soon as the font is loaded.
+    ///
+    /// The default is [Preload::None].
+    pub fn with_preload(mut self, preload: Preload) -> Self {
+        self.preload = preload;
+        self
+    }
+
+    /// Sets how many times larger the font's glyphs are rasterised before being shrunk back down
+    /// to size, e.g. 2 or 4. The default is 1 (no supersampling).
+    ///
+    /// Shrinking each glyph with a box filter gives slightly crisper edges than rasterising it at
+    /// its real size, without the cost of generating distance fields. Rasterising takes longer
+    /// with higher values, but this only happens once per character, and drawing is no slower.
+    ///
+    /// This only affects fonts that don't use sdf, and is ignored if the font is hinted. Values of
+    /// 0 are treated as 1.
+    pub fn with_supersampling(mut self, factor: u32) -> Self {
+        self.supersampling = factor.max(1);
+        self
+    }
+
+    /// Sets how many horizontal subpixel offsets each glyph is rasterised at, e.g. 4. The default
+    /// is 1, which rasterises each glyph once.
+    ///
+    /// Raster text at a fractional position is blurred across two pixels, and text that moves
+    /// slowly (e.g. while scrolling) visibly smears as it goes. With more than one phase, each
+    /// glyph is also rasterised at offsets of 1/phases of a pixel, and when it's drawn its quad is
+    /// moved to a whole pixel and the rasterisation closest to its real position is used. Slowly
+    /// moving text then stays sharp and moves in steps too small to see, without having to snap it
+    /// to pixels (see [crate::TextBuilder::pixel_snap]). Each glyph's texture is `phases` times
+    /// bigger, and takes that much longer to generate.
+    ///
+    /// Only horizontal positions are affected. This works best for text drawn at the size it was
+    /// rasterised at, since the offsets are in the texture's pixels. This only affects fonts that
+    /// don't use sdf, and is ignored if the font is hinted. Values of 0 are treated as 1.
+    pub fn with_subpixel_phases(mut self, phases: u32) -> Self {
+        self.subpixel_phases = phases.max(1);
+        self
+    }
+
+    /// Sets how many pixels of transparent padding are added around each glyph's texture. The
+    /// default is 1.
+    ///
+    /// When text is scaled, the texture is sampled with bilinear filtering, which blends each
+    /// pixel with its neighbours. Without padding, the pixels on the edge of a glyph are blended
+    /// with themselves instead of with transparency, which can leave hard edges and streaks.
+    /// If you only ever draw text at its original size you can set this to 0 to save a little
+    /// texture memory.
+    ///
+    /// This only affects fonts that don't use sdf, since distance fields are already padded by
+    /// their radius.
+    pub fn with_glyph_padding(mut self, padding: u32) -> Self {
+        self.glyph_padding = padding;
+        self
+    }
+
+    /// Sets how characters that the font doesn't have a glyph for are drawn. The default is
+    /// [MissingGlyphs::Font].
+    pub fn with_missing_glyphs(mut self, missing_glyphs: MissingGlyphs) -> Self {
+        self.missing_glyphs = missing_glyphs;
+        self
+    }
+
+    /// Adds extra usages to the font's glyph textures, on top of the `TEXTURE_BINDING` and
+    /// `COPY_DST` they always have. This is for running your own passes over kaku's glyph data,
+    /// e.g. copying glyphs out with `COPY_SRC` or writing effects into them from a compute shader
+    /// with `STORAGE_BINDING`. Use [crate::TextRenderer::glyph_texture] to get at the textures.
+    ///
+    /// The usages have to be supported by the texture format, which is `R8Unorm` for fonts that
+    /// don't use sdf and [crate::SdfFormat]'s format for ones that do. For example most devices
+    /// can't use `R8Unorm` as a storage texture, so wgpu will give a validation error when a
+    /// glyph is cached.
+    #[cfg(has_wgpu)]
+    pub fn with_texture_usages(mut self, usages: wgpu::TextureUsages) -> Self {
+        self.texture_usages = usages;
+        self
+    }
+
+    /// Sets how the font's glyph textures are sampled, instead of using the renderer's settings
+    /// (see [crate::TextRendererBuilder::with_sampler]).
+    #[cfg(has_wgpu)]
+    pub fn with_sampler(mut self, settings: SamplerSettings) -> Self {
+        self.sampler = Some(settings);
+        self
+    }
+
+    /// Sets whether the font's glyphs should be hinted. The default is false.
+    ///
+    /// Hinting snaps glyph outlines to the pixel grid, which makes small text (around 12-14px)
+    /// much sharper, at the cost of slightly distorting the shapes of the glyphs. Hinted glyphs are
+    /// rasterised with swash instead of ab_glyph.
+    ///
+    /// This only affects fonts that don't use sdf, since distance fields are meant to be scaled
+    /// and hinting only makes sense at a fixed size. If swash can't read the font, a warning is
+    /// logged and the font is drawn unhinted.
+    #[cfg(feature = "swash")]
+    pub fn with_hinting(mut self, hinting: bool) -> Self {
+        self.hinting = hinting;
+        self
+    }
+}
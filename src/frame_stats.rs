@@ -0,0 +1,92 @@
+//! Counting what the renderer draws and uploads, for
+//! [TextRenderer::frame_stats](crate::TextRenderer::frame_stats).
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// What a [TextRenderer](crate::TextRenderer) has drawn and uploaded since its stats were last
+/// reset, from [TextRenderer::frame_stats](crate::TextRenderer::frame_stats).
+///
+/// Reset them at the start of each frame with
+/// [TextRenderer::reset_frame_stats](crate::TextRenderer::reset_frame_stats) to get per-frame
+/// numbers, e.g. for a performance HUD. A jump in draw calls or bind groups for the same texts
+/// usually means glyphs aren't being batched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// The [Text](crate::Text)s and [BakedText](crate::BakedText)s drawn.
+    pub texts_drawn: u64,
+    /// The glyph instances drawn, including each glyph's outline pass and panels.
+    pub instances: u64,
+    /// The draw calls recorded into render passes.
+    pub draw_calls: u64,
+    /// The bind groups set on render passes, which happens whenever a draw uses a different
+    /// glyph texture. Texts' settings don't count when they're pushed as push constants.
+    pub bind_group_switches: u64,
+    /// The bytes uploaded for glyph textures and for texts' instances and settings, whether
+    /// they're written to the queue, a staging belt or an encoder. The small buffers made along
+    /// with new texts (e.g. their settings) aren't counted.
+    pub bytes_uploaded: u64,
+}
+
+/// The renderer's [FrameStats] so far, shared with the texts it built so they can count their
+/// own uploads.
+#[derive(Debug, Default)]
+pub(crate) struct FrameCounters {
+    texts_drawn: AtomicU64,
+    instances: AtomicU64,
+    draw_calls: AtomicU64,
+    bind_group_switches: AtomicU64,
+    bytes_uploaded: AtomicU64,
+}
+
+impl FrameCounters {
+    /// Adds the stats of some draws.
+    pub(crate) fn add(&self, stats: &FrameStats) {
+        let counters = self.counters();
+        let values = [
+            stats.texts_drawn,
+            stats.instances,
+            stats.draw_calls,
+            stats.bind_group_switches,
+            stats.bytes_uploaded,
+        ];
+
+        for (counter, value) in counters.into_iter().zip(values) {
+            counter.fetch_add(value, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts an upload of `bytes` bytes.
+    pub(crate) fn add_upload(&self, bytes: usize) {
+        (self.bytes_uploaded).fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn load(&self) -> FrameStats {
+        let [texts_drawn, instances, draw_calls, bind_group_switches, bytes_uploaded] = self
+            .counters()
+            .map(|counter| counter.load(Ordering::Relaxed));
+
+        FrameStats {
+            texts_drawn,
+            instances,
+            draw_calls,
+            bind_group_switches,
+            bytes_uploaded,
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        for counter in self.counters() {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn counters(&self) -> [&AtomicU64; 5] {
+        [
+            &self.texts_drawn,
+            &self.instances,
+            &self.draw_calls,
+            &self.bind_group_switches,
+            &self.bytes_uploaded,
+        ]
+    }
+}
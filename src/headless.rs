@@ -0,0 +1,142 @@
+//! Drawing text into an image on the GPU, without a window or surface.
+
+use std::fmt;
+
+use image::RgbaImage;
+
+use crate::{
+    profiling::Profiling, readback::read_texture, target::OffscreenAttachments, Error,
+    ScreenUniform, Text, TextRenderer,
+};
+
+/// An error from [TextRenderer::render_to_image].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenderToImageError {
+    /// The renderer draws to a texture format that can't be read back as an RGBA image. Only the
+    /// 8 bit RGBA and BGRA formats are supported.
+    UnsupportedFormat(wgpu::TextureFormat),
+    /// One of the texts couldn't be drawn.
+    Text(Error),
+    /// The image couldn't be copied back from the GPU.
+    Readback(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for RenderToImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderToImageError::UnsupportedFormat(format) => {
+                write!(f, "can't read back images in the format {format:?}")
+            }
+            RenderToImageError::Text(e) => write!(f, "couldn't draw text: {e}"),
+            RenderToImageError::Readback(e) => write!(f, "couldn't read back the image: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for RenderToImageError {}
+
+impl From<Error> for RenderToImageError {
+    fn from(e: Error) -> Self {
+        RenderToImageError::Text(e)
+    }
+}
+
+impl TextRenderer {
+    /// Draws some text into a new image of the given size on the GPU, and copies it back into an
+    /// [RgbaImage]. This is useful for generating images of text without a window, e.g. thumbnails
+    /// on a server.
+    ///
+    /// The texts are drawn in order over the background color, exactly as
+    /// [TextRenderer::draw_text] would draw them (the renderer's own target size isn't used or
+    /// changed). The renderer has to be built for an 8 bit RGBA or BGRA format, e.g.
+    /// `Rgba8UnormSrgb`; multisampling and depth buffers are handled here.
+    ///
+    /// This blocks until the GPU has finished drawing, so it doesn't work on the web.
+    pub fn render_to_image<'a>(
+        &self,
+        texts: impl IntoIterator<Item = &'a Text>,
+        (width, height): (u32, u32),
+        background: wgpu::Color,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<RgbaImage, RenderToImageError> {
+        use wgpu::TextureFormat as F;
+
+        let target = &self.targets[0];
+        let config = &target.config;
+
+        let bgra = match config.format {
+            F::Rgba8Unorm | F::Rgba8UnormSrgb => false,
+            F::Bgra8Unorm | F::Bgra8UnormSrgb => true,
+            format => return Err(RenderToImageError::UnsupportedFormat(format)),
+        };
+
+        if width == 0 || height == 0 {
+            return Ok(RgbaImage::new(width, height));
+        }
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let target_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(&self.label("headless target texture")),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let target_view = target_texture.create_view(&Default::default());
+
+        // The pipelines only work with render passes that match what the renderer was built for
+        let attachments = OffscreenAttachments::new(
+            config,
+            size,
+            |name| self.label(&format!("headless {name}")),
+            device,
+        );
+
+        let screen_bind_group =
+            self.create_screen_bind_group(ScreenUniform::new((width, height)), device);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some(&self.label("headless command encoder")),
+        });
+
+        {
+            let mut render_pass = attachments.begin_pass(
+                &mut encoder,
+                &target_view,
+                background,
+                &self.label("headless render pass"),
+            );
+
+            for text in texts {
+                self.draw_text_with_screen(
+                    &mut render_pass,
+                    text,
+                    &target.pipelines,
+                    &screen_bind_group,
+                    Profiling::NONE,
+                )?;
+            }
+        }
+
+        let label = self.label("headless readback buffer");
+        let mut data = read_texture(&target_texture, encoder, &label, device, queue)
+            .map_err(RenderToImageError::Readback)?;
+
+        if bgra {
+            for pixel in data.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Ok(RgbaImage::from_raw(width, height, data).expect("the image data is the right size"))
+    }
+}
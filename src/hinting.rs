@@ -0,0 +1,63 @@
+//! Hinted glyph rasterisation using [swash](https://github.com/dfrg/swash).
+//!
+//! ab_glyph doesn't hint glyphs, so small text drawn without sdf can look blurry next to the
+//! native text of the OS. Swash runs the font's hinting instructions, which snap the outlines to
+//! the pixel grid before they're rasterised.
+//!
+//! This module is only available with the `swash` feature.
+
+use std::cell::RefCell;
+
+use ab_glyph::{Font, FontArc, GlyphId, PxScale, ScaleFont};
+use image::GrayImage;
+use swash::{
+    scale::{Render, ScaleContext, Source},
+    zeno::Format,
+};
+
+thread_local! {
+    // Scale contexts keep caches that are worth reusing, but they aren't Sync, so each thread
+    // that generates textures gets its own.
+    static CONTEXT: RefCell<ScaleContext> = RefCell::new(ScaleContext::new());
+}
+
+/// Returns whether swash is able to read a font.
+pub(crate) fn supports_hinting(font: &FontArc) -> bool {
+    swash::FontRef::from_index(font.font_data(), 0).is_some()
+}
+
+/// Rasterises a hinted glyph, returning its coverage image along with the position of its top
+/// left corner relative to the pen position.
+///
+/// Returns None if the glyph has nothing to draw. The font should be checked with
+/// [supports_hinting] first.
+pub(crate) fn rasterize_hinted(
+    font: &FontArc,
+    glyph: GlyphId,
+    scale: PxScale,
+) -> Option<(GrayImage, [f32; 2])> {
+    // ab_glyph doesn't tell us which face of a collection it's using, so this assumes the first
+    let swash_font = swash::FontRef::from_index(font.font_data(), 0)?;
+
+    // Swash sizes fonts in pixels per em, whereas ab_glyph's scale is the height of the font
+    let ppem = font.as_scaled(scale).scale_factor().horizontal * font.units_per_em()?;
+
+    let image = CONTEXT.with_borrow_mut(|context| {
+        let mut scaler = context.builder(swash_font).size(ppem).hint(true).build();
+
+        Render::new(&[Source::Outline])
+            .format(Format::Alpha)
+            .render(&mut scaler, glyph.0)
+    })?;
+
+    let placement = image.placement;
+
+    if placement.width == 0 || placement.height == 0 {
+        return None;
+    }
+
+    let position = [placement.left as f32, -placement.top as f32];
+    let image = GrayImage::from_raw(placement.width, placement.height, image.data)?;
+
+    Some((image, position))
+}
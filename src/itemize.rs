@@ -0,0 +1,51 @@
+//! Splitting lines of text into runs of one script, so each run can be drawn with one font.
+//!
+//! When a font doesn't have some characters, they're drawn with its fallback fonts (see
+//! [TextRenderer::set_fallback_fonts](crate::TextRenderer::set_fallback_fonts)). Choosing a font
+//! for each character on its own would switch fonts in the middle of words whenever the font has
+//! some letters of a script but not others, or draw the digits and punctuation of Japanese text
+//! in a Latin font. Instead each run gets the first font that has all of its characters.
+//!
+//! Characters that are used by every script (spaces, digits, punctuation) and combining marks
+//! belong to the run they're in, and ones at the start of a line belong to the first run. Kaku
+//! lays text out from left to right, so runs only change direction when their script does.
+
+use std::ops::Range;
+
+use unicode_script::{Script, UnicodeScript};
+
+/// The script a character belongs to, or None if it takes the script of the text around it.
+fn script(c: char) -> Option<Script> {
+    match c.script() {
+        Script::Common | Script::Inherited | Script::Unknown => None,
+        script => Some(script),
+    }
+}
+
+/// Splits the characters of a line into runs of one script, as ranges of indices into `chars`.
+pub(crate) fn runs(chars: &[(usize, char)]) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut start = 0;
+
+    std::iter::from_fn(move || {
+        if start == chars.len() {
+            return None;
+        }
+
+        let mut run_script = None;
+        let len = chars[start..]
+            .iter()
+            .take_while(|&&(_, c)| match (run_script, script(c)) {
+                (Some(run_script), Some(script)) => script == run_script,
+                (None, script) => {
+                    run_script = script;
+                    true
+                }
+                (_, None) => true,
+            })
+            .count();
+
+        let run = start..start + len;
+        start = run.end;
+        Some(run)
+    })
+}
@@ -0,0 +1,907 @@
+//! The layout of a text, separate from the GPU buffers it's drawn from (see [TextLayout]).
+
+use std::{ops::Range, sync::Arc};
+
+use crate::{
+    text::{ParagraphStyle, TextData},
+    CharacterInstance, GlyphKey, VerticalAlignment,
+};
+#[cfg(has_wgpu)]
+use crate::{uploads::Uploads, Error, Text, TextRenderer};
+
+/// An axis-aligned rectangle.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    /// The top left corner.
+    pub min: [f32; 2],
+    /// The bottom right corner.
+    pub max: [f32; 2],
+}
+
+impl Rect {
+    /// The width of the rectangle.
+    pub fn width(&self) -> f32 {
+        self.max[0] - self.min[0]
+    }
+
+    /// The height of the rectangle.
+    pub fn height(&self) -> f32 {
+        self.max[1] - self.min[1]
+    }
+
+    /// Whether a point is inside the rectangle (including its edges).
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        (0..2).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+
+    /// The smallest rectangle containing both rectangles.
+    pub fn union(&self, other: Rect) -> Rect {
+        Rect {
+            min: [0, 1].map(|i| self.min[i].min(other.min[i])),
+            max: [0, 1].map(|i| self.max[i].max(other.max[i])),
+        }
+    }
+
+    /// Makes the rectangle smaller by the same amount on every side. It shrinks to its centre
+    /// rather than turning inside out.
+    pub(crate) fn inset(&self, amount: f32) -> Rect {
+        let amount = [0, 1].map(|i| amount.min((self.max[i] - self.min[i]) * 0.5));
+        Rect {
+            min: [0, 1].map(|i| self.min[i] + amount[i]),
+            max: [0, 1].map(|i| self.max[i] - amount[i]),
+        }
+    }
+
+    /// Moves the rectangle by an offset.
+    pub fn translate(&self, offset: [f32; 2]) -> Rect {
+        Rect {
+            min: [0, 1].map(|i| self.min[i] + offset[i]),
+            max: [0, 1].map(|i| self.max[i] + offset[i]),
+        }
+    }
+}
+
+/// A character in a [TextLayout], and the quad its texture is drawn on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LaidOutGlyph {
+    /// The character. For a cluster of characters drawn as one glyph (e.g. an emoji ZWJ
+    /// sequence), this is its first character.
+    pub character: char,
+    /// The byte index of the character in the text. Characters that turn into more than one
+    /// glyph (like tabs, which are drawn as spaces) have the same index for each one.
+    pub index: usize,
+    /// The quad the character's texture is drawn on, relative to the text's position. This
+    /// includes any padding around the glyph in the texture (e.g. for sdf fonts, the sdf radius).
+    pub rect: Rect,
+    /// The part of the quad the glyph itself covers (its ink box), without the padding.
+    pub ink: Rect,
+}
+
+/// A character of a layout that has something to draw, in physical pixels.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PlacedGlyph {
+    /// The byte index of the character in the text.
+    pub(crate) index: usize,
+    pub(crate) character: char,
+    /// The glyph that's drawn, which isn't the character's own for clusters.
+    pub(crate) key: GlyphKey,
+    pub(crate) instance: CharacterInstance,
+    /// How far the glyph's ink is from each edge of its quad.
+    pub(crate) inset: f32,
+}
+
+impl PlacedGlyph {
+    /// The box around the glyph's ink.
+    pub(crate) fn ink(&self) -> Rect {
+        self.instance.rect().inset(self.inset)
+    }
+
+    /// The public version of the glyph, with its boxes moved into other coordinates.
+    pub(crate) fn laid_out(&self, convert: impl Fn(Rect) -> Rect) -> LaidOutGlyph {
+        LaidOutGlyph {
+            character: self.character,
+            index: self.index,
+            rect: convert(self.instance.rect()),
+            ink: convert(self.ink()),
+        }
+    }
+}
+
+/// The size and spacing of a character, in physical pixels. These don't depend on where the
+/// character ends up, so they're kept to lay the text out again (e.g. wrapped to another width)
+/// without looking the characters up again.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MeasuredChar {
+    /// The byte index of the character in the text.
+    pub(crate) index: usize,
+    pub(crate) character: char,
+    /// The glyph the character is drawn with. A cluster of characters drawn as one glyph is
+    /// measured as one character, the first of the cluster (see [crate::cluster]).
+    pub(crate) key: GlyphKey,
+    /// The kerning between the character and the one before it, if there is any.
+    pub(crate) kern: Option<f32>,
+    pub(crate) advance: f32,
+    /// The character's quad, if it has something to draw.
+    pub(crate) quad: Option<MeasuredQuad>,
+}
+
+/// The quad a character's texture is drawn on, in physical pixels.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MeasuredQuad {
+    /// The position of the top left corner relative to the pen position.
+    pub(crate) offset: [f32; 2],
+    pub(crate) size: [f32; 2],
+    /// How far the glyph's ink is from each edge of the quad.
+    pub(crate) inset: f32,
+}
+
+/// The characters of a line of the text, before it's wrapped.
+#[derive(Clone, Debug)]
+pub(crate) struct MeasuredLine {
+    /// The byte range of the line in the text.
+    pub(crate) text: Range<usize>,
+    pub(crate) chars: Vec<MeasuredChar>,
+}
+
+/// Every character of a text measured, and the font's vertical metrics at the text's scale, in
+/// physical pixels.
+#[derive(Clone, Debug)]
+pub(crate) struct MeasuredText {
+    pub(crate) lines: Vec<MeasuredLine>,
+    pub(crate) ascent: f32,
+    pub(crate) descent: f32,
+    pub(crate) line_gap: f32,
+    /// The distance between tab stops, if tabs move to the next one (see
+    /// [Tabs::Columns](crate::Tabs::Columns)).
+    pub(crate) tab_stop: Option<f32>,
+}
+
+/// A line of a [TextLayout].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineBox {
+    /// The line's box, relative to the text's position. It goes from the font's ascent to its
+    /// descent, and is as wide as the advances of the line's characters.
+    pub rect: Rect,
+    /// The height of the line's baseline, relative to the text's position.
+    pub baseline: f32,
+    /// The line's glyphs, as indices into [TextLayout::glyphs] (or [Text::glyphs]).
+    pub glyphs: Range<usize>,
+    /// The byte range of the line in the text, without the line break at the end. Lines that were
+    /// wrapped (see [TextBuilder::max_width](crate::TextBuilder::max_width)) end where the next
+    /// line starts.
+    pub text: Range<usize>,
+}
+
+impl LineBox {
+    /// The width of the line, from the advances of its characters.
+    pub fn width(&self) -> f32 {
+        self.rect.width()
+    }
+
+    /// How far the line reaches above its baseline.
+    pub fn ascent(&self) -> f32 {
+        self.baseline - self.rect.min[1]
+    }
+
+    /// How far the line reaches below its baseline, as a positive number (unlike ab_glyph's
+    /// descent).
+    pub fn descent(&self) -> f32 {
+        self.rect.max[1] - self.baseline
+    }
+}
+
+/// A text that has been laid out but doesn't have any GPU buffers yet.
+///
+/// Make one with [TextBuilder::layout](crate::TextBuilder::layout), look at where its characters
+/// and lines ended up, and turn it into a [Text] with [TextLayout::build]. Layouts can be kept and
+/// built as many times as needed, so text that's shown over and over only has to be laid out once.
+///
+/// Everything is measured in the same units as the text's position (logical pixels, see
+/// [TextRenderer::set_scale_factor]), relative to the text's position, and includes the text's
+/// scale and alignment.
+#[derive(Clone, Debug)]
+pub struct TextLayout {
+    pub(crate) data: TextData,
+    pub(crate) double_buffered: bool,
+    /// Whether the layout was made by a [TextMeasurer](crate::TextMeasurer), without the glyphs'
+    /// textures.
+    pub(crate) from_measurer: bool,
+    /// The size and spacing of every character, which the layout can be made again from with a
+    /// different wrap width.
+    pub(crate) measured: Arc<MeasuredText>,
+    /// Each character that has something to draw, in the order of the text.
+    pub(crate) glyphs: Vec<PlacedGlyph>,
+    /// The lines, in physical pixels.
+    pub(crate) lines: Vec<LineBox>,
+    /// Where a caret goes before each character and at the end of each line, as byte indices and
+    /// x positions in physical pixels, in the order of the text.
+    pub(crate) carets: Vec<(usize, f32)>,
+}
+
+impl TextLayout {
+    /// The string that was laid out.
+    pub fn text(&self) -> &str {
+        &self.data.text
+    }
+
+    /// The characters that have something to draw, in the order of the text. Characters without
+    /// textures (like spaces) aren't included.
+    pub fn glyphs(&self) -> impl ExactSizeIterator<Item = LaidOutGlyph> + '_ {
+        (self.glyphs.iter()).map(|glyph| glyph.laid_out(|rect| self.to_logical(rect)))
+    }
+
+    /// The text's lines, from top to bottom.
+    pub fn lines(&self) -> impl ExactSizeIterator<Item = LineBox> + '_ {
+        self.lines.iter().map(|line| LineBox {
+            rect: self.to_logical(line.rect),
+            baseline: line.baseline / self.data.scale_factor,
+            glyphs: line.glyphs.clone(),
+            text: line.text.clone(),
+        })
+    }
+
+    /// The box containing all of the text's lines, relative to the text's position.
+    ///
+    /// These are the text's logical bounds, which come from the characters' advances and the
+    /// font's metrics. Glyphs that reach past the font's ascent or descent (or the quads of sdf
+    /// glyphs, which include the sdf radius) can go outside it. Use [TextLayout::ink_bounds] for
+    /// the box around what's actually drawn.
+    pub fn bounds(&self) -> Rect {
+        self.to_logical(self.physical_bounds())
+    }
+
+    /// The box around the ink of all of the text's glyphs (see [LaidOutGlyph::ink]), relative to
+    /// the text's position. This is what should be centred to visually centre something like
+    /// "•" or "▶", whose ink is much smaller than its line.
+    ///
+    /// Returns None if the text has nothing to draw (e.g. it's empty or only spaces). Layouts made
+    /// by a [TextMeasurer](crate::TextMeasurer) have no glyphs, so they always return None.
+    pub fn ink_bounds(&self) -> Option<Rect> {
+        let ink = union(self.glyphs.iter().map(PlacedGlyph::ink))?;
+        Some(self.to_logical(ink))
+    }
+
+    /// The rectangle of a text cursor (caret) at a byte index of the text, relative to the text's
+    /// position. It has no width, and goes from the top of the caret's line to the bottom.
+    ///
+    /// A caret at a line break goes at the end of the line before it, a caret where a line was
+    /// wrapped goes at the start of the line after it, and indices past the end of the text go at
+    /// the end of the last line. Indices in the middle of a character (or at a
+    /// character that isn't drawn, like a zero width space) go before the next character.
+    pub fn caret_rect(&self, byte_index: usize) -> Rect {
+        self.to_logical(caret_rect(&self.lines, &self.carets, byte_index))
+    }
+
+    /// The rectangle to highlight a line with (e.g. the current line in a code editor), relative
+    /// to the text's position. It's as tall as the line, and as wide as the whole text (its
+    /// widest line), so the highlights of every line line up no matter how long each one is.
+    ///
+    /// Returns None if there's no line with that index.
+    pub fn line_highlight_rect(&self, line: usize) -> Option<Rect> {
+        line_highlight_rect(&self.lines, line).map(|rect| self.to_logical(rect))
+    }
+
+    /// The rectangles covering a byte range of the text, one for each line it's on, relative to
+    /// the text's position. They're as tall as their lines, e.g. for highlighting a selection.
+    ///
+    /// Indices in the middle of a character go before the next character, like with
+    /// [TextLayout::caret_rect]. Empty ranges don't cover anything.
+    pub fn range_rects(&self, range: Range<usize>) -> Vec<Rect> {
+        range_rects(&self.lines, &self.carets, range)
+            .map(|(_, rect)| self.to_logical(rect))
+            .collect()
+    }
+
+    /// Creates a [Text] with this layout, uploading it to the GPU.
+    ///
+    /// Any characters that aren't cached any more (e.g. after [TextRenderer::clear_cache]) are
+    /// generated again. If the renderer's scale factor has changed since the text was laid out,
+    /// or the layout was made by a [TextMeasurer](crate::TextMeasurer), it's laid out again first.
+    ///
+    /// Returns an error if the text's font isn't loaded in the text renderer.
+    #[cfg(has_wgpu)]
+    pub fn build(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_renderer: &mut TextRenderer,
+    ) -> Result<Text, Error> {
+        let bytes = 0..self.data.text.len();
+        text_renderer.generate_text(&self.data, bytes, device, &Uploads::Queue(queue))?;
+
+        let layout = if self.data.scale_factor == text_renderer.scale_factor && !self.from_measurer
+        {
+            self.clone()
+        } else {
+            let data = TextData {
+                scale_factor: text_renderer.scale_factor,
+                ..self.data.clone()
+            };
+            let mut layout = text_renderer.create_text_layout(data)?;
+            layout.double_buffered = self.double_buffered;
+            layout
+        };
+
+        Ok(Text::from_layout(layout, device, queue, text_renderer))
+    }
+
+    /// Lays out measured characters, wrapping and aligning them as the text's settings say.
+    pub(crate) fn flow(data: TextData, measured: Arc<MeasuredText>) -> TextLayout {
+        let mut flow = Flow::new(&data, &measured);
+        // Texts aligned by their own lines are moved once they're all placed
+        if !data.block_align {
+            flow.y = vertical_offset(&data, measured.ascent, measured.descent);
+        }
+        let max_width = data.max_width.map(|width| width * data.scale_factor);
+
+        for line in &measured.lines {
+            flow.line(line, max_width);
+        }
+
+        let Flow {
+            mut glyphs,
+            mut lines,
+            mut carets,
+            line_carets,
+            ..
+        } = flow;
+
+        if !data.line_aligns.is_empty() {
+            align_lines(&data, &mut glyphs, &mut lines, &mut carets, &line_carets);
+        }
+
+        // The block's top and bottom are where the first line's would be if it were that tall
+        if let (true, Some(first), Some(last)) = (data.block_align, lines.first(), lines.last()) {
+            let v_offset = vertical_offset(&data, -first.rect.min[1], -last.rect.max[1]);
+
+            for glyph in &mut glyphs {
+                glyph.instance.position[1] += v_offset;
+            }
+            for line in &mut lines {
+                line.rect = line.rect.translate([0., v_offset]);
+                line.baseline += v_offset;
+            }
+        }
+
+        if data.snaps_to_pixels() {
+            for glyph in &mut glyphs {
+                glyph.instance.position = glyph.instance.position.map(f32::round);
+            }
+        }
+
+        TextLayout {
+            data,
+            double_buffered: false,
+            from_measurer: false,
+            measured,
+            glyphs,
+            lines,
+            carets,
+        }
+    }
+
+    /// Lays a text out again after an edit, when only some of its lines were measured again:
+    /// `lines` of `measured` replaced the lines `old_lines` of `old`'s measurements.
+    ///
+    /// The lines before the edit are kept as they are. The lines after it are laid out again until
+    /// one starts a line the same way as before, and from there on they're only moved.
+    ///
+    /// Returns None if the text has to be laid out in full, which is when the position of each
+    /// line depends on the others (see [TextBuilder::block_align](crate::TextBuilder::block_align)
+    /// and [TextBuilder::line_align](crate::TextBuilder::line_align)).
+    #[cfg(has_wgpu)]
+    pub(crate) fn reflow(
+        data: TextData,
+        measured: Arc<MeasuredText>,
+        old: &Text,
+        lines: Range<usize>,
+        old_lines: Range<usize>,
+    ) -> Option<Reflow> {
+        let old_measured = old.measured.as_deref()?;
+        if data.block_align || !data.line_aligns.is_empty() {
+            return None;
+        }
+
+        // Where the old layout of a measured line starts, as indices into its lines, glyphs and
+        // carets
+        let old_start = |line: usize| {
+            let start = old_measured.lines[line].text.start;
+            let i = old.lines.partition_point(|line| line.text.start < start);
+            let glyph = old
+                .lines
+                .get(i)
+                .map_or(old.glyphs.len(), |line| line.glyphs.start);
+            let caret = old.carets.partition_point(|&(index, _)| index < start);
+            (i, glyph, caret)
+        };
+
+        let (kept_lines, kept_glyphs, kept_carets) = old_start(lines.start);
+        let MeasuredText {
+            ascent,
+            descent,
+            line_gap,
+            ..
+        } = *measured;
+        // The baseline the next line would have had, like in Flow::end_line
+        let y_after = |line: &LineBox| line.baseline + (ascent - descent + line_gap);
+
+        let mut flow = Flow::new(&data, &measured);
+        flow.glyphs = old.glyphs[..kept_glyphs].to_vec();
+        flow.lines = old.lines[..kept_lines].to_vec();
+        flow.carets = old.carets[..kept_carets].to_vec();
+        flow.paragraph = lines.start;
+        flow.paragraph_state = paragraph_state(&measured.lines[..lines.start]);
+        flow.y = match kept_lines.checked_sub(1) {
+            Some(i) => y_after(&old.lines[i]),
+            None => vertical_offset(&data, ascent, descent),
+        };
+        let max_width = data.max_width.map(|width| width * data.scale_factor);
+
+        for line in &measured.lines[lines.clone()] {
+            flow.line(line, max_width);
+        }
+
+        // The lines after the edit are moved once they'd start out the same as before. Glyphs
+        // snapped to pixels can only be moved by whole pixels.
+        let snap = data.snaps_to_pixels();
+        let mut next = lines.end;
+        let moved = loop {
+            let Some(line) = measured.lines.get(next) else {
+                break None;
+            };
+
+            let old_line = next - lines.end + old_lines.end;
+            let (i, glyph, caret) = old_start(old_line);
+            let dy = flow.y - y_after(&old.lines[i - 1]);
+            if flow.paragraph_state == paragraph_state(&old_measured.lines[..old_line])
+                && (!snap || dy.fract() == 0.)
+            {
+                break Some((i, glyph, caret, dy));
+            }
+
+            flow.line(line, max_width);
+            next += 1;
+        };
+
+        let Flow {
+            mut glyphs,
+            mut lines,
+            mut carets,
+            ..
+        } = flow;
+
+        let new_glyphs = kept_glyphs..glyphs.len();
+        if snap {
+            for glyph in &mut glyphs[new_glyphs.clone()] {
+                glyph.instance.position = glyph.instance.position.map(f32::round);
+            }
+        }
+
+        let old_glyphs = match moved {
+            Some((old_line, old_glyph, old_caret, dy)) => {
+                let byte_shift = data.text.len() as isize - old.data.text.len() as isize;
+                let shift = |index: usize| index.wrapping_add_signed(byte_shift);
+                let glyph_shift = |index: usize| index - old_glyph + new_glyphs.end;
+
+                lines.extend(old.lines[old_line..].iter().map(|line| LineBox {
+                    rect: line.rect.translate([0., dy]),
+                    baseline: line.baseline + dy,
+                    glyphs: glyph_shift(line.glyphs.start)..glyph_shift(line.glyphs.end),
+                    text: shift(line.text.start)..shift(line.text.end),
+                }));
+                glyphs.extend(old.glyphs[old_glyph..].iter().map(|glyph| {
+                    let mut glyph = *glyph;
+                    glyph.index = shift(glyph.index);
+                    glyph.instance.position[1] += dy;
+                    glyph
+                }));
+                carets
+                    .extend((old.carets[old_caret..].iter()).map(|&(index, x)| (shift(index), x)));
+
+                kept_glyphs..old_glyph
+            }
+            None => kept_glyphs..old.glyphs.len(),
+        };
+
+        let layout = TextLayout {
+            data,
+            double_buffered: false,
+            from_measurer: false,
+            measured,
+            glyphs,
+            lines,
+            carets,
+        };
+        Some(Reflow {
+            layout,
+            old_glyphs,
+            new_glyphs,
+        })
+    }
+
+    /// The instances to draw the text with.
+    pub(crate) fn instances(&self) -> Vec<(GlyphKey, CharacterInstance)> {
+        glyph_instances(&self.glyphs)
+    }
+
+    /// The box containing all of the lines, in physical pixels.
+    pub(crate) fn physical_bounds(&self) -> Rect {
+        union(self.lines.iter().map(|line| line.rect)).unwrap_or_default()
+    }
+
+    fn to_logical(&self, rect: Rect) -> Rect {
+        let scale = self.data.scale_factor;
+        Rect {
+            min: rect.min.map(|p| p / scale),
+            max: rect.max.map(|p| p / scale),
+        }
+    }
+}
+
+/// The instances to draw some glyphs with.
+pub(crate) fn glyph_instances(glyphs: &[PlacedGlyph]) -> Vec<(GlyphKey, CharacterInstance)> {
+    (glyphs.iter())
+        .map(|glyph| (glyph.key, glyph.instance))
+        .collect()
+}
+
+/// The box around some rectangles, or None if there aren't any.
+pub(crate) fn union(rects: impl IntoIterator<Item = Rect>) -> Option<Rect> {
+    rects.into_iter().reduce(|a, b| a.union(b))
+}
+
+/// The rectangle of a caret at a byte index, in the same coordinates as the lines and carets.
+pub(crate) fn caret_rect(lines: &[LineBox], carets: &[(usize, f32)], byte_index: usize) -> Rect {
+    // Wrapped lines start where the line before them ends, and the caret goes on the later one
+    let Some(line) = (lines.iter().rev())
+        .find(|line| line.text.start <= byte_index)
+        .or(lines.first())
+    else {
+        return Rect::default();
+    };
+
+    // The carets are in the order of the text, and every line has one at its end
+    let byte_index = byte_index.min(line.text.end);
+    let i = carets.partition_point(|&(index, _)| index < byte_index);
+    let x = carets[i].1;
+
+    Rect {
+        min: [x, line.rect.min[1]],
+        max: [x, line.rect.max[1]],
+    }
+}
+
+/// The rectangles covering a byte range, one for each line it's on, with the lines they're on.
+/// Lines the range only touches the edge of are skipped.
+pub(crate) fn range_rects<'a>(
+    lines: &'a [LineBox],
+    carets: &'a [(usize, f32)],
+    range: Range<usize>,
+) -> impl Iterator<Item = (&'a LineBox, Rect)> + 'a {
+    lines.iter().filter_map(move |line| {
+        let start = range.start.max(line.text.start);
+        let end = range.end.min(line.text.end);
+        if start >= end {
+            return None;
+        }
+
+        // Carets before the line are before its start, so this finds one in the line. Wrapped
+        // lines don't have a caret at their end, but the line's box ends there anyway.
+        let x = |b| carets[carets.partition_point(|&(index, _)| index < b)].1;
+        let max_x = if end == line.text.end {
+            line.rect.max[0]
+        } else {
+            x(end)
+        };
+
+        let rect = Rect {
+            min: [x(start), line.rect.min[1]],
+            max: [max_x, line.rect.max[1]],
+        };
+        Some((line, rect))
+    })
+}
+
+/// The rectangle of a line, stretched to the width of the widest line.
+pub(crate) fn line_highlight_rect(lines: &[LineBox], line: usize) -> Option<Rect> {
+    let rect = lines.get(line)?.rect;
+    let bounds = union(lines.iter().map(|line| line.rect))?;
+
+    Some(Rect {
+        min: [bounds.min[0], rect.min[1]],
+        max: [bounds.max[0], rect.max[1]],
+    })
+}
+
+/// A text laid out again after an edit by [TextLayout::reflow].
+pub(crate) struct Reflow {
+    pub(crate) layout: TextLayout,
+    /// The old glyphs that were laid out again, which were replaced by the new glyphs in
+    /// `new_glyphs`. The glyphs after them are the same as before, only moved.
+    pub(crate) old_glyphs: Range<usize>,
+    pub(crate) new_glyphs: Range<usize>,
+}
+
+/// The state of [TextLayout::flow] as it places characters line by line.
+struct Flow<'a> {
+    /// The horizontal alignment's proportion.
+    halign: f32,
+    snap: bool,
+    /// Whether lines are aligned by where their last character that isn't a space ends.
+    ignore_trailing_whitespace: bool,
+    measured: &'a MeasuredText,
+    glyphs: Vec<PlacedGlyph>,
+    lines: Vec<LineBox>,
+    carets: Vec<(usize, f32)>,
+    /// The paragraph (line of the text before wrapping) of each line, and its carets as indices
+    /// into `carets`.
+    line_carets: Vec<(usize, Range<usize>)>,
+    /// The paragraph being placed.
+    paragraph: usize,
+    /// The indents and spacing of paragraphs, in physical pixels. These paragraphs are separated
+    /// by blank lines, unlike the ones `paragraph` counts.
+    paragraphs: ParagraphStyle,
+    paragraph_state: ParagraphState,
+    /// The baseline of the next line.
+    y: f32,
+}
+
+impl<'a> Flow<'a> {
+    /// Starts laying out a text from its first line, at a baseline of 0.
+    fn new(data: &TextData, measured: &'a MeasuredText) -> Self {
+        Flow {
+            halign: data.halign.proportion(),
+            snap: data.snaps_to_pixels(),
+            ignore_trailing_whitespace: data.ignore_trailing_whitespace,
+            measured,
+            glyphs: Vec::new(),
+            lines: Vec::new(),
+            carets: Vec::new(),
+            line_carets: Vec::new(),
+            paragraph: 0,
+            paragraphs: data.paragraphs.map(|p| p * data.scale_factor),
+            paragraph_state: ParagraphState::Before,
+            y: 0.,
+        }
+    }
+
+    /// Places the characters of a line, wrapping it onto more lines if it's wider than
+    /// `max_width`.
+    ///
+    /// Lines are only wrapped at spaces (and tabs), and the spaces stay at the end of the line
+    /// before the wrap. Words that are wider than `max_width` on their own get a line to
+    /// themselves.
+    fn line(&mut self, line: &MeasuredLine, max_width: Option<f32>) {
+        let chars = &line.chars;
+        let ParagraphStyle {
+            first_line_indent,
+            hanging_indent,
+            space_before,
+            space_after,
+        } = self.paragraphs;
+
+        // Blank lines end paragraphs, and the next line that isn't blank starts a new one
+        let mut x = if chars.is_empty() {
+            if self.paragraph_state == ParagraphState::Inside {
+                self.paragraph_state = ParagraphState::Between;
+            }
+            0.
+        } else if self.paragraph_state == ParagraphState::Inside {
+            hanging_indent
+        } else {
+            if self.paragraph_state == ParagraphState::Between {
+                self.y += space_after + space_before;
+            }
+            self.paragraph_state = ParagraphState::Inside;
+            first_line_indent
+        };
+        // Where the line ends if its trailing spaces are left out
+        let mut content_end = x;
+        let mut start = line.text.start;
+        let mut first_glyph = self.glyphs.len();
+        let mut first_caret = self.carets.len();
+        let mut line_empty = true;
+
+        for (i, c) in chars.iter().enumerate() {
+            if let Some(max_width) = max_width {
+                let starts_word = !is_space(c) && (i == 0 || is_space(&chars[i - 1]));
+
+                if starts_word && !line_empty {
+                    let word_end = (chars[i..].iter())
+                        .position(is_space)
+                        .map_or(chars.len(), |end| i + end);
+                    let width = (chars[i + 1..word_end].iter())
+                        .fold(c.kern.unwrap_or(0.) + c.advance, |width, c| {
+                            width + c.kern.unwrap_or(0.) + c.advance
+                        });
+
+                    if x + width > max_width {
+                        let end = (x, content_end);
+                        self.end_line(start..c.index, end, false, first_glyph, first_caret);
+                        x = hanging_indent;
+                        content_end = x;
+                        start = c.index;
+                        first_glyph = self.glyphs.len();
+                        first_caret = self.carets.len();
+                        line_empty = true;
+                    }
+                }
+            }
+
+            // Kerning is between characters on the same line
+            if let Some(kern) = c.kern.filter(|_| !line_empty) {
+                x += kern;
+            }
+            line_empty = false;
+
+            // Tabs become several spaces, and the caret goes before the first one
+            if self
+                .carets
+                .last()
+                .is_none_or(|&(index, _)| index != c.index)
+            {
+                self.carets.push((c.index, x));
+            }
+
+            if let Some(quad) = c.quad {
+                let pen_x = if self.snap { x.round() } else { x };
+
+                self.glyphs.push(PlacedGlyph {
+                    index: c.index,
+                    character: c.character,
+                    key: c.key,
+                    instance: CharacterInstance {
+                        position: [pen_x + quad.offset[0], self.y + quad.offset[1]],
+                        size: quad.size,
+                        index: 0,
+                    },
+                    inset: quad.inset,
+                });
+            }
+
+            x += match self.measured.tab_stop {
+                Some(stop) if c.character == '\t' && stop > 0. => next_tab_stop(x, stop) - x,
+                _ => c.advance,
+            };
+
+            if !is_space(c) {
+                content_end = x;
+            }
+        }
+
+        let end = (x, content_end);
+        self.end_line(start..line.text.end, end, true, first_glyph, first_caret);
+        self.paragraph += 1;
+    }
+
+    /// Aligns the line that's been placed since `first_glyph` and `first_caret`, and moves down
+    /// to the next one. Lines that end at a line break get a caret at their end; wrapped lines
+    /// don't, since that's the same place as the start of the next line.
+    ///
+    /// `end` is where the line ends, and where it ends without its trailing spaces.
+    fn end_line(
+        &mut self,
+        text: Range<usize>,
+        (end, content_end): (f32, f32),
+        line_break: bool,
+        first_glyph: usize,
+        first_caret: usize,
+    ) {
+        let MeasuredText {
+            ascent,
+            descent,
+            line_gap,
+            ..
+        } = *self.measured;
+
+        if line_break {
+            self.carets.push((text.end, end));
+        }
+
+        let width = if self.ignore_trailing_whitespace {
+            content_end
+        } else {
+            end
+        };
+
+        // Apply horizontal alignment line by line
+        let h_offset = -width * self.halign;
+
+        for glyph in &mut self.glyphs[first_glyph..] {
+            glyph.instance.position[0] += h_offset;
+        }
+        for (_, x) in &mut self.carets[first_caret..] {
+            *x += h_offset;
+        }
+
+        self.lines.push(LineBox {
+            rect: Rect {
+                min: [h_offset, self.y - ascent],
+                max: [h_offset + width, self.y - descent],
+            },
+            baseline: self.y,
+            glyphs: first_glyph..self.glyphs.len(),
+            text,
+        });
+        (self.line_carets).push((self.paragraph, first_caret..self.carets.len()));
+
+        self.y += ascent - descent + line_gap;
+    }
+}
+
+/// Where [Flow] is in the text's blank line separated paragraphs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ParagraphState {
+    /// Before the first paragraph.
+    Before,
+    Inside,
+    /// After a paragraph, in the blank lines before the next.
+    Between,
+}
+
+/// Where [Flow] is in the paragraphs after placing some lines, which only depends on which of
+/// them are blank.
+fn paragraph_state(lines: &[MeasuredLine]) -> ParagraphState {
+    match lines.iter().rposition(|line| !line.chars.is_empty()) {
+        None => ParagraphState::Before,
+        Some(i) if i + 1 == lines.len() => ParagraphState::Inside,
+        Some(_) => ParagraphState::Between,
+    }
+}
+
+/// How far down the text is moved by its vertical alignment and baseline offset, where its top
+/// and bottom are `ascent` and `descent` from the first and last baselines.
+fn vertical_offset(data: &TextData, ascent: f32, descent: f32) -> f32 {
+    let v_offset = match data.valign {
+        VerticalAlignment::Baseline => 0.,
+        VerticalAlignment::Top => ascent,
+        VerticalAlignment::Middle => ascent - (ascent - descent) * 0.5,
+        VerticalAlignment::Bottom => descent,
+        VerticalAlignment::Ratio(r) => ascent - (ascent - descent) * r.clamp(0., 1.),
+    };
+    v_offset + data.baseline_offset * data.scale_factor
+}
+
+/// Moves the lines of paragraphs with their own horizontal alignment (see
+/// [TextBuilder::line_align](crate::TextBuilder::line_align)), so they're aligned inside the box
+/// of the widest line instead of around the text's position. The widest line stays where the
+/// text's own alignment put it.
+fn align_lines(
+    data: &TextData,
+    glyphs: &mut [PlacedGlyph],
+    lines: &mut [LineBox],
+    carets: &mut [(usize, f32)],
+    line_carets: &[(usize, Range<usize>)],
+) {
+    let halign = data.halign.proportion();
+    let block_width = lines.iter().map(LineBox::width).fold(0., f32::max);
+
+    for (line, (paragraph, line_carets)) in lines.iter_mut().zip(line_carets) {
+        let Some(align) = data.line_align(*paragraph) else {
+            continue;
+        };
+
+        let width = line.width();
+        let aligned = -block_width * halign + (block_width - width) * align;
+        let shift = aligned - line.rect.min[0];
+
+        line.rect = line.rect.translate([shift, 0.]);
+        for glyph in &mut glyphs[line.glyphs.clone()] {
+            glyph.instance.position[0] += shift;
+        }
+        for (_, x) in &mut carets[line_carets.clone()] {
+            *x += shift;
+        }
+    }
+}
+
+/// Whether a character is somewhere lines can be wrapped.
+fn is_space(c: &MeasuredChar) -> bool {
+    c.character == ' ' || c.character == '\t'
+}
+
+/// The x position of the first tab stop after `x`. A tab that's right at a stop (or a rounding
+/// error away from one) moves to the next one, like it does in code editors.
+fn next_tab_stop(x: f32, stop: f32) -> f32 {
+    ((x / stop + 0.001).floor() + 1.) * stop
+}
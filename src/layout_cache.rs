@@ -0,0 +1,151 @@
+//! Reusing the layouts of strings that are laid out over and over, for
+//! [TextRendererBuilder::with_layout_cache](crate::TextRendererBuilder::with_layout_cache).
+//!
+//! A layout is relative to the text's position and doesn't include its colour or outline, so
+//! layouts are keyed by only the things that move characters around. The renderer clears the
+//! layouts of a font whenever its cached characters change, since the layouts were made from
+//! them.
+
+use std::sync::Mutex;
+
+use crate::{layout::TextLayout, text::TextData, FontId, HashMap, Tabs, VerticalAlignment};
+
+/// The settings of a text that change where its characters go.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct LayoutKey {
+    text: String,
+    font: FontId,
+    /// The bits of the floats, so the key can be hashed.
+    scale: u32,
+    scale_factor: u32,
+    max_width: Option<u32>,
+    halign: u32,
+    valign: (u8, u32),
+    pixel_snap: bool,
+    tabs: Tabs,
+    block_align: bool,
+    baseline_offset: u32,
+    ignore_trailing_whitespace: bool,
+    line_aligns: Vec<(usize, u32)>,
+    paragraphs: [u32; 4],
+}
+
+impl LayoutKey {
+    fn new(data: &TextData) -> Self {
+        let valign = match data.valign {
+            VerticalAlignment::Baseline => (0, 0),
+            VerticalAlignment::Top => (1, 0),
+            VerticalAlignment::Middle => (2, 0),
+            VerticalAlignment::Bottom => (3, 0),
+            VerticalAlignment::Ratio(r) => (4, r.to_bits()),
+        };
+
+        Self {
+            text: data.text.clone(),
+            font: data.font,
+            scale: data.scale.to_bits(),
+            scale_factor: data.scale_factor.to_bits(),
+            max_width: data.max_width.map(f32::to_bits),
+            halign: data.halign.proportion().to_bits(),
+            valign,
+            pixel_snap: data.pixel_snap,
+            tabs: data.tabs,
+            block_align: data.block_align,
+            baseline_offset: data.baseline_offset.to_bits(),
+            ignore_trailing_whitespace: data.ignore_trailing_whitespace,
+            line_aligns: (data.line_aligns.iter())
+                .map(|(line, halign)| (*line, halign.proportion().to_bits()))
+                .collect(),
+            paragraphs: [
+                data.paragraphs.first_line_indent,
+                data.paragraphs.hanging_indent,
+                data.paragraphs.space_before,
+                data.paragraphs.space_after,
+            ]
+            .map(f32::to_bits),
+        }
+    }
+}
+
+/// The most recently used layouts, up to a maximum number.
+#[derive(Debug)]
+pub(crate) struct LayoutCache {
+    capacity: usize,
+    // Texts are laid out by `&self` methods of the renderer
+    inner: Mutex<Layouts>,
+}
+
+#[derive(Debug, Default)]
+struct Layouts {
+    /// Each layout, and the time it was last used.
+    layouts: HashMap<LayoutKey, (TextLayout, u64)>,
+    /// Counts up every time a layout is used.
+    clock: u64,
+}
+
+impl LayoutCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Default::default(),
+        }
+    }
+
+    /// Returns the cached layout of a text, or lays it out with `layout` and caches it.
+    ///
+    /// The cached layout is given the text's data, so its position, colour etc. are the ones
+    /// that were asked for.
+    pub(crate) fn get_or_insert<E>(
+        &self,
+        data: TextData,
+        layout: impl FnOnce(TextData) -> Result<TextLayout, E>,
+    ) -> Result<TextLayout, E> {
+        if self.capacity == 0 {
+            return layout(data);
+        }
+
+        let key = LayoutKey::new(&data);
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let now = inner.clock;
+
+        if let Some((cached, last_used)) = inner.layouts.get_mut(&key) {
+            *last_used = now;
+            return Ok(TextLayout {
+                data,
+                ..cached.clone()
+            });
+        }
+
+        let new_layout = layout(data)?;
+        let layouts = &mut inner.layouts;
+
+        if layouts.len() >= self.capacity {
+            let oldest = (layouts.iter())
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+
+            if let Some(oldest) = oldest {
+                layouts.remove(&oldest);
+            }
+        }
+
+        layouts.insert(key, (new_layout.clone(), now));
+        Ok(new_layout)
+    }
+
+    /// Forgets the layouts of every text with a font.
+    pub(crate) fn clear_font(&self, font: FontId) {
+        (self.inner.lock().unwrap().layouts).retain(|key, _| key.font != font);
+    }
+
+    /// Forgets every layout.
+    pub(crate) fn clear(&self) {
+        self.inner.lock().unwrap().layouts.clear();
+    }
+
+    /// The number of layouts that are cached.
+    pub(crate) fn len(&self) -> usize {
+        self.inner.lock().unwrap().layouts.len()
+    }
+}
@@ -9,24 +9,50 @@
 //!
 //! Here is an example of how to use the crate. You first need to create a [TextRenderer] struct,
 //! then load a font using [ab_glyph], then you can create a [Text] object, which is the thing that
-//! can be drawn.
+//! can be drawn. Most of these steps return a [Result] with kaku's [Error] type, e.g. if a
+//! [FontId] is used with a renderer it didn't come from.
 //!
-//! ```rust
+//! ```rust,no_run
+//! # use kaku::*;
+//! # macro_rules! include_bytes {
+//! #     ($file:literal) => { std::include_bytes!(concat!("../examples/fonts/", $file)) };
+//! # }
+//! # #[cfg(has_wgpu)]
+//! # fn example(
+//! #     device: &wgpu::Device,
+//! #     queue: &wgpu::Queue,
+//! #     target_format: wgpu::TextureFormat,
+//! #     target_size: (u32, u32),
+//! # ) -> Result<(), Box<dyn std::error::Error>> {
 //! let mut text_renderer =
 //!     TextRendererBuilder::new(target_format, target_size).build(&device);
 //!     
 //! let font = ab_glyph::FontRef::try_from_slice(include_bytes!("FiraSans-Regular.ttf"))?;
-//! let font = text_renderer.load_font_with_sdf(font, 45., SdfSettings { radius: 15. });
+//! let font = text_renderer.load_font_with_sdf(
+//!     font,
+//!     FontSize::Pt(45.),
+//!     SdfSettings { radius: 15., ..Default::default() },
+//! )?;
 //!
 //! let text = TextBuilder::new("Hello, world!", font, [100., 100.])
 //!     .outlined([1.; 4], 10.)
-//!     .build(&device, &queue, &mut text_renderer);
+//!     .build(&device, &queue, &mut text_renderer)?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! You can then draw this text object during a render pass like so:
 //!
-//! ```rust
-//! text_renderer.draw(&mut render_pass, &text);
+//! ```rust,no_run
+//! # #[cfg(has_wgpu)]
+//! # fn example<'pass>(
+//! #     text_renderer: &'pass kaku::TextRenderer,
+//! #     mut render_pass: wgpu::RenderPass<'pass>,
+//! #     text: &'pass kaku::Text,
+//! # ) -> Result<(), kaku::Error> {
+//! text_renderer.draw_text(&mut render_pass, &text)?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! # Performance
@@ -36,37 +62,227 @@
 //! of time using [TextRenderer::generate_char_textures], but is still a cost. If you don't need
 //! the features provided by sdf rendering, you should use non-sdf rendering instead.
 
+// Without wgpu only the measurer is built, which leaves the glyph generation code unused
+#![cfg_attr(not(has_wgpu), allow(dead_code))]
+
+// build.rs checks this first so the error isn't buried under everything that uses wgpu, but
+// builds that don't run build scripts still get it here. build.rs also sets the `has_wgpu` cfg
+// when there is a version of wgpu, which everything that needs a device is behind
+#[cfg(not(any(feature = "wgpu-0_20", feature = "wgpu-22", feature = "measure")))]
+compile_error!(
+    "kaku needs a version of wgpu to build against: enable one of the wgpu-* features, or the \
+    measure feature to only lay text out without one"
+);
+
+// The wgpu-* features pick which version of wgpu the rest of the crate sees as `wgpu`. The oldest
+// one wins, so that `--all-features` builds (and egui-wgpu, which is on 0.20) still work
+//
+// Either way it's re-exported, so users can use the same version kaku was built with
+#[cfg(feature = "wgpu-0_20")]
+pub use wgpu;
+#[cfg(all(feature = "wgpu-22", not(feature = "wgpu-0_20")))]
+pub extern crate wgpu_22 as wgpu;
+
+mod animation;
+#[cfg(has_wgpu)]
+mod atlas;
+#[cfg(has_wgpu)]
+mod bake;
+#[cfg(has_wgpu)]
+mod bitmap_font;
+#[cfg(has_wgpu)]
+mod blur;
+#[cfg(has_wgpu)]
+mod bmfont;
+#[cfg(has_wgpu)]
+mod cache_view;
+#[cfg(has_wgpu)]
+mod cell_buffer;
+mod cluster;
+#[cfg(feature = "cosmic-text")]
+mod cosmic;
+#[cfg(feature = "cpu-render")]
+mod cpu_render;
+mod custom_glyph;
+#[cfg(has_wgpu)]
+mod debug_overlay;
+#[cfg(feature = "disk-cache")]
+mod disk_cache;
+#[cfg(feature = "egui")]
+mod egui_callback;
+mod error;
+mod fallback_glyph;
+mod font_options;
+#[cfg(has_wgpu)]
+mod frame_stats;
+#[cfg(feature = "headless")]
+mod headless;
+#[cfg(feature = "swash")]
+mod hinting;
+mod itemize;
+mod layout;
+mod layout_cache;
+#[cfg(feature = "measure")]
+mod measure;
+#[cfg(has_wgpu)]
+mod memory;
+mod panel;
+mod preedit;
+#[cfg(has_wgpu)]
+mod profiling;
+mod raster;
+#[cfg(has_wgpu)]
+mod readback;
+#[cfg(has_wgpu)]
+mod sampler;
 mod sdf;
+#[cfg(has_wgpu)]
+mod settings_pool;
+#[cfg(has_wgpu)]
+mod staging;
+mod style;
+#[cfg(has_wgpu)]
+mod target;
+#[cfg(feature = "test-utils")]
+mod test_utils;
 mod text;
-
-pub use text::{FontSize, HorizontalAlignment, Text, TextBuilder, VerticalAlignment};
-
-use image::GrayImage;
+mod unicode_block;
+#[cfg(has_wgpu)]
+mod uploads;
+#[cfg(has_wgpu)]
+mod windowed;
+
+pub use animation::{GlyphAnimation, Marquee};
+#[cfg(has_wgpu)]
+use atlas::AtlasGlyph;
+#[cfg(has_wgpu)]
+pub use atlas::{AtlasError, SdfAtlas};
+#[cfg(has_wgpu)]
+use bake::BakedSettingsUniform;
+#[cfg(has_wgpu)]
+pub use bake::BakedText;
+#[cfg(has_wgpu)]
+pub use bitmap_font::{BitmapFont, BitmapFontError};
+#[cfg(has_wgpu)]
+pub use bmfont::BmFontError;
+#[cfg(has_wgpu)]
+pub use cache_view::CacheView;
+#[cfg(has_wgpu)]
+pub use cell_buffer::CellBuffer;
+#[cfg(has_wgpu)]
+use cell_buffer::CellSettingsUniform;
+#[cfg(feature = "cosmic-text")]
+pub use cosmic::CosmicTextAdapter;
+#[cfg(feature = "cosmic-text")]
+pub use cosmic_text;
+use custom_glyph::CustomGlyph;
+pub use custom_glyph::GlyphMetrics;
+#[cfg(feature = "egui")]
+pub use egui_callback::EguiTextCallback;
+pub use error::Error;
+pub use font_options::{FontOptions, MissingGlyphs, Preload};
+#[cfg(has_wgpu)]
+use frame_stats::FrameCounters;
+#[cfg(has_wgpu)]
+pub use frame_stats::FrameStats;
+#[cfg(feature = "headless")]
+pub use headless::RenderToImageError;
+pub use layout::{LaidOutGlyph, LineBox, Rect, TextLayout};
+#[cfg(feature = "measure")]
+pub use measure::TextMeasurer;
+#[cfg(has_wgpu)]
+pub use memory::MemoryReport;
+pub use panel::Panel;
+#[cfg(has_wgpu)]
+use panel::PanelUniform;
+pub use preedit::{PreeditSegment, Underline, UnderlineStyle};
+#[cfg(has_wgpu)]
+use profiling::Profiling;
+// Re-exported so that pools for TextRenderer::set_thread_pool come from the same version of rayon
+#[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+pub use rayon;
+#[cfg(has_wgpu)]
+pub use readback::ReadGlyphError;
+#[cfg(has_wgpu)]
+pub use sampler::SamplerSettings;
+pub use style::TextStyle;
+#[cfg(has_wgpu)]
+pub use target::{TargetConfig, TargetId};
+#[cfg(feature = "test-utils")]
+pub use test_utils::{assert_matches_golden, compare_images, ImageDiff, UPDATE_GOLDEN_VAR};
+#[cfg(has_wgpu)]
+pub use text::Text;
+pub use text::{Anchor, FontSize, HorizontalAlignment, Tabs, TextBuilder, VerticalAlignment};
+pub use unicode_block::UnicodeBlock;
+#[cfg(feature = "wgpu-profiler")]
+pub use wgpu_profiler;
+#[cfg(has_wgpu)]
+pub use windowed::WindowedText;
+
+#[cfg(has_wgpu)]
+use image::{GrayImage, ImageBuffer};
+use layout::{MeasuredChar, MeasuredLine, MeasuredQuad, MeasuredText};
+#[cfg(has_wgpu)]
+use layout_cache::LayoutCache;
+#[cfg(all(has_wgpu, feature = "rayon", not(target_arch = "wasm32")))]
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use text::TextData;
 
-use std::num::NonZeroU64;
+#[cfg(has_wgpu)]
+use std::{borrow::Cow, num::NonZeroU64, ops::RangeInclusive, sync::atomic::Ordering};
+use std::{ops::Range, sync::Arc};
 
 pub use ab_glyph;
-use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+use ab_glyph::{Font, FontArc, GlyphId, PxScale, ScaleFont};
 use ahash::AHashMap;
+#[cfg(feature = "disk-cache")]
+use disk_cache::DiskCache;
 use itertools::Itertools;
+#[cfg(all(has_wgpu, not(feature = "tracing")))]
 use log::info;
-use sdf::create_sdf_texture;
+use log::warn;
+#[cfg(has_wgpu)]
+use memory::MemoryCounters;
+#[cfg(has_wgpu)]
+use sdf::{create_sdf_texture, create_sdf_texture_from_outline, SdfImage};
+#[cfg(has_wgpu)]
+use settings_pool::SettingsPool;
+#[cfg(has_wgpu)]
+use staging::Staging;
+#[cfg(has_wgpu)]
+use target::{RenderTarget, ShaderVariant, TargetPipelines, TextShaders, SCRGB_NITS};
+#[cfg(has_wgpu)]
 use text::{SdfSettingsUniform, SettingsUniform};
-use wgpu::{
-    include_wgsl, util::DeviceExt, DepthStencilState, TextureFormat, TextureViewDescriptor,
-};
+#[cfg(has_wgpu)]
+use uploads::Uploads;
+#[cfg(has_wgpu)]
+use wgpu::{DepthStencilState, TextureFormat, TextureViewDescriptor};
 
 type HashMap<K, V> = AHashMap<K, V>;
 
-pub use sdf::SdfSettings;
+pub use sdf::{SdfFormat, SdfSettings, SdfSource};
 
 #[derive(Debug)]
 struct CharTexture {
+    #[cfg(has_wgpu)]
     bind_group: wgpu::BindGroup,
+    /// The size of the texture on the GPU, in bytes.
+    texture_bytes: u64,
+    /// The texture itself, e.g. for reading it back (see [TextRenderer::read_glyph]).
+    #[cfg(has_wgpu)]
+    texture: Arc<wgpu::Texture>,
     position: [f32; 2],
     size: [f32; 2],
+    /// How many pixels of empty space (or distance field) there are around the glyph on each side
+    /// of the texture.
+    padding: f32,
+    /// A copy of the texture's data, for drawing on the CPU. This is the coverage for raster
+    /// fonts and the distance field for sdf fonts.
+    #[cfg(feature = "cpu-render")]
+    image: SdfImage,
+    /// How the texture is sampled, for drawing on the CPU.
+    #[cfg(feature = "cpu-render")]
+    filter: wgpu::FilterMode,
 }
 
 #[derive(Debug)]
@@ -76,10 +292,94 @@ struct Character {
     texture: Option<CharTexture>,
     /// The amount of space to leave after this character
     advance: f32,
+    /// Whether the font doesn't have a glyph for this character.
+    missing: bool,
 }
 
 type CharacterCache = HashMap<char, Character>;
 
+/// What a character texture is made from. Text laid out by kaku looks its characters up in the
+/// font, but layout engines that shape text themselves (like cosmic-text) choose glyphs by id, and
+/// so do clusters of characters drawn as one ligature (see [cluster]). Characters the font doesn't
+/// have can be drawn with glyphs of one of its fallback fonts, by index.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) enum GlyphKey {
+    Char(char),
+    Glyph(GlyphId),
+    Fallback(usize, GlyphId),
+}
+
+impl GlyphKey {
+    fn glyph_id(self, font: &FontArc) -> GlyphId {
+        match self {
+            GlyphKey::Char(c) => font.glyph_id(c),
+            GlyphKey::Glyph(id) | GlyphKey::Fallback(_, id) => id,
+        }
+    }
+}
+
+impl From<char> for GlyphKey {
+    fn from(c: char) -> Self {
+        GlyphKey::Char(c)
+    }
+}
+
+impl std::fmt::Display for GlyphKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GlyphKey::Char(c) => write!(f, "{c:?}"),
+            GlyphKey::Glyph(id) => write!(f, "glyph {}", id.0),
+            GlyphKey::Fallback(font, id) => write!(f, "glyph {} of fallback font {font}", id.0),
+        }
+    }
+}
+
+/// Filters out images with no pixels, which can't be made into textures. Pre-rendered glyphs with
+/// empty images are drawn as nothing, like spaces.
+#[cfg(has_wgpu)]
+fn non_empty<I: image::GenericImageView>(image: &Option<I>) -> Option<&I> {
+    image
+        .as_ref()
+        .filter(|image| image.width() > 0 && image.height() > 0)
+}
+
+/// Shrinks a glyph image that is too big to be a texture on this device, keeping its aspect ratio.
+///
+/// The glyph's quad keeps its size, so an oversized glyph (e.g. a huge font size on an adapter
+/// with low limits) is just drawn at a lower resolution instead of making wgpu reject the texture.
+#[cfg(has_wgpu)]
+fn fit_to_device<'a, P>(
+    key: GlyphKey,
+    image: &'a ImageBuffer<P, Vec<P::Subpixel>>,
+    device: &wgpu::Device,
+) -> Cow<'a, ImageBuffer<P, Vec<P::Subpixel>>>
+where
+    P: image::Pixel + 'static,
+{
+    let max = device.limits().max_texture_dimension_2d;
+    let (width, height) = image.dimensions();
+
+    if width <= max && height <= max {
+        return Cow::Borrowed(image);
+    }
+
+    let shrink = max as f32 / width.max(height) as f32;
+    let new_width = ((width as f32 * shrink) as u32).clamp(1, max);
+    let new_height = ((height as f32 * shrink) as u32).clamp(1, max);
+
+    warn!(
+        "The texture for {key} is {width}x{height}, but this device only supports textures up to \
+        {max} pixels wide, so it will be drawn at {new_width}x{new_height}"
+    );
+
+    Cow::Owned(image::imageops::resize(
+        image,
+        new_width,
+        new_height,
+        image::imageops::FilterType::Triangle,
+    ))
+}
+
 /// A handle to a font stored in the [TextRenderer].
 ///
 /// When you load a font into the text renderer using [TextRenderer::load_font], it will give you
@@ -87,40 +387,537 @@ type CharacterCache = HashMap<char, Character>;
 #[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Ord, PartialOrd)]
 pub struct FontId(usize);
 
+/// The vertical metrics of a font at the size it was loaded with, in pixels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct FontMetrics {
+    ascent: f32,
+    descent: f32,
+    line_gap: f32,
+}
+
+impl FontMetrics {
+    fn new(font: &FontArc, scale: PxScale) -> Self {
+        let scaled_font = font.as_scaled(scale);
+
+        Self {
+            ascent: scaled_font.ascent(),
+            descent: scaled_font.descent(),
+            line_gap: scaled_font.line_gap(),
+        }
+    }
+}
+
+/// A font that draws the characters another font doesn't have (see
+/// [TextRenderer::set_fallback_fonts]).
 #[derive(Debug)]
-struct FontData {
+struct FallbackFont {
     font: FontArc,
+    /// The scale that makes the font's em the same size as the main font's.
+    scale: PxScale,
+    /// The glyph each cluster of characters drawn with this font is drawn as (see
+    /// [FontData::clusters]).
+    clusters: HashMap<String, Option<GlyphId>>,
+    #[cfg(feature = "disk-cache")]
+    hash: u64,
+}
+
+#[derive(Debug)]
+struct FontData {
+    /// The font's outlines, used to create the textures for new characters.
+    ///
+    /// Fonts made from pre-rendered glyphs (e.g. an imported atlas) don't have one, so they can
+    /// only draw the characters they were created with.
+    font: Option<FontArc>,
     px_size: f32,
     scale: PxScale,
+    metrics: FontMetrics,
     char_cache: CharacterCache,
+    /// Glyphs chosen by id rather than by character, for text laid out by another library and
+    /// for clusters.
+    glyph_cache: HashMap<GlyphId, Character>,
+    /// The glyph each cluster of characters that has been looked up is drawn as, if the font has
+    /// a ligature or variant for it (see [cluster]).
+    clusters: HashMap<String, Option<GlyphId>>,
+    /// The fonts that draw runs of text this font doesn't have, in order of preference.
+    fallbacks: Vec<FallbackFont>,
+    /// Glyphs of the fallback fonts, which are generated with this font's settings.
+    fallback_cache: HashMap<(usize, GlyphId), Character>,
     sdf_settings: Option<SdfSettings>,
+    /// Extra space between pairs of characters, in pixels at the loaded size. This is only used by
+    /// fonts made from pre-rendered glyphs, since outline fonts aren't kerned.
+    kerning: HashMap<(char, char), f32>,
+    /// How many times larger glyphs are rasterised before being shrunk down, for non-sdf fonts.
+    supersampling: u32,
+    /// How many horizontal subpixel offsets raster glyphs are rasterised at. Each glyph's texture
+    /// has them stacked from top to bottom.
+    subpixel_phases: u32,
+    /// The transparent border added around raster glyph textures, in pixels.
+    glyph_padding: u32,
+    /// How characters the font doesn't have are drawn.
+    missing_glyphs: MissingGlyphs,
+    /// Characters that are drawn as other characters (see [TextRenderer::set_substitutions]).
+    substitutions: HashMap<char, char>,
+    /// Characters drawn with images instead of the font (see [TextRenderer::register_glyph]).
+    custom_glyphs: HashMap<char, CustomGlyph>,
+    /// How the glyph textures are filtered. This is only nearest for some bitmap fonts.
+    #[cfg(has_wgpu)]
+    filter: wgpu::FilterMode,
+    /// Extra usages for the glyph textures (see [FontOptions::with_texture_usages]).
+    #[cfg(has_wgpu)]
+    texture_usages: wgpu::TextureUsages,
+    /// The sampler the glyph textures use instead of the renderer's (see
+    /// [FontOptions::with_sampler]).
+    #[cfg(has_wgpu)]
+    sampler: Option<wgpu::Sampler>,
+    /// How many times larger than the loaded size the glyphs are rasterised, so they're drawn at
+    /// the screen's resolution (see [TextRenderer::set_scale_factor]). This is always 1 for sdf
+    /// fonts, which scale smoothly anyway, and for fonts made from pre-rendered glyphs.
+    glyph_scale_factor: f32,
+    /// Whether glyphs are rasterised with hinting (see [FontOptions::with_hinting]).
+    #[cfg(feature = "swash")]
+    hinting: bool,
+    #[cfg(feature = "disk-cache")]
+    hash: u64,
 }
 
 impl FontData {
-    fn new(font: FontArc, size: FontSize) -> Self {
-        let scale = size.scale(&font);
-        let px_size = size.px_size(&font);
-
-        Self {
-            font,
+    fn new(font: FontArc, size: FontSize) -> Result<Self, Error> {
+        let scale = size.scale(&font)?;
+        let px_size = scale.y;
+
+        Ok(Self {
+            #[cfg(feature = "disk-cache")]
+            hash: disk_cache::font_hash(&font),
+            metrics: FontMetrics::new(&font, scale),
+            font: Some(font),
             scale,
             px_size,
             sdf_settings: None,
             char_cache: Default::default(),
-        }
+            glyph_cache: Default::default(),
+            clusters: Default::default(),
+            fallbacks: Vec::new(),
+            fallback_cache: Default::default(),
+            kerning: Default::default(),
+            supersampling: 1,
+            subpixel_phases: 1,
+            glyph_padding: font_options::DEFAULT_GLYPH_PADDING,
+            missing_glyphs: MissingGlyphs::Font,
+            substitutions: Default::default(),
+            custom_glyphs: Default::default(),
+            #[cfg(has_wgpu)]
+            filter: wgpu::FilterMode::Linear,
+            #[cfg(has_wgpu)]
+            texture_usages: wgpu::TextureUsages::empty(),
+            #[cfg(has_wgpu)]
+            sampler: None,
+            glyph_scale_factor: 1.,
+            #[cfg(feature = "swash")]
+            hinting: false,
+        })
     }
 
-    fn new_with_sdf(font: FontArc, size: FontSize, sdf_settings: SdfSettings) -> Self {
-        let scale = size.scale(&font);
-        let px_size = size.px_size(&font);
+    fn new_with_sdf(
+        font: FontArc,
+        size: FontSize,
+        sdf_settings: SdfSettings,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            sdf_settings: Some(sdf_settings),
+            ..Self::new(font, size)?
+        })
+    }
 
+    /// Creates a font from characters that have already been rendered.
+    fn new_prerendered(
+        px_size: f32,
+        metrics: FontMetrics,
+        sdf_settings: Option<SdfSettings>,
+        char_cache: CharacterCache,
+    ) -> Self {
         Self {
-            font,
-            scale,
+            font: None,
             px_size,
-            sdf_settings: Some(sdf_settings),
-            char_cache: Default::default(),
+            scale: PxScale::from(px_size),
+            metrics,
+            char_cache,
+            glyph_cache: Default::default(),
+            clusters: Default::default(),
+            fallbacks: Vec::new(),
+            fallback_cache: Default::default(),
+            sdf_settings,
+            kerning: Default::default(),
+            supersampling: 1,
+            subpixel_phases: 1,
+            glyph_padding: font_options::DEFAULT_GLYPH_PADDING,
+            missing_glyphs: MissingGlyphs::Font,
+            substitutions: Default::default(),
+            custom_glyphs: Default::default(),
+            #[cfg(has_wgpu)]
+            filter: wgpu::FilterMode::Linear,
+            #[cfg(has_wgpu)]
+            texture_usages: wgpu::TextureUsages::empty(),
+            #[cfg(has_wgpu)]
+            sampler: None,
+            glyph_scale_factor: 1.,
+            #[cfg(feature = "swash")]
+            hinting: false,
+            #[cfg(feature = "disk-cache")]
+            hash: 0,
+        }
+    }
+
+    /// The size in pixels that a given font size corresponds to for this font.
+    fn px_size_of(&self, size: FontSize) -> Result<f32, Error> {
+        match (&self.font, size) {
+            // Em sizes are relative to the size the font was loaded with
+            (_, FontSize::Em(em)) => Ok(em * self.px_size),
+            (Some(font), _) => size.px_size(font),
+            (None, FontSize::Px(px)) => Ok(px),
+            (None, FontSize::Pt(pt)) => Ok(pt * (96. / 72.)),
+        }
+    }
+
+    /// Lays out a piece of text, finding the instance for each character that has something to
+    /// draw and the box of each line. The characters have to be cached.
+    fn layout(&self, text: TextData) -> Result<TextLayout, Error> {
+        let measured = self.measure_text(&text)?;
+        Ok(TextLayout::flow(text, Arc::new(measured)))
+    }
+
+    /// Finds the size and spacing of each of a text's characters, which don't change when the text
+    /// is wrapped or aligned differently. The characters have to be cached.
+    fn measure_text(&self, text: &TextData) -> Result<MeasuredText, Error> {
+        // Text is laid out in physical pixels, and the glyphs might already be rasterised at the
+        // scale factor
+        let metrics_scale = text.scale * text.scale_factor;
+        let scale = metrics_scale / self.glyph_scale_factor;
+        let lines = self.measure_lines(text, 0..text.text.len())?;
+
+        let tab_stop = match text.tabs {
+            Tabs::Spaces(_) => None,
+            Tabs::Columns(columns) => {
+                let space = self.char_cache.get(&' ');
+                space.map(|space| space.advance * scale * columns as f32)
+            }
+        };
+
+        Ok(MeasuredText {
+            lines,
+            tab_stop,
+            ascent: self.metrics.ascent * metrics_scale,
+            descent: self.metrics.descent * metrics_scale,
+            line_gap: self.metrics.line_gap * text.scale_factor,
+        })
+    }
+
+    /// Measures the lines of a byte range of a text, which has to start at the start of a line and
+    /// end at the end of one. The characters have to be cached.
+    fn measure_lines(
+        &self,
+        text: &TextData,
+        bytes: Range<usize>,
+    ) -> Result<Vec<MeasuredLine>, Error> {
+        let metrics_scale = text.scale * text.scale_factor;
+        let scale = metrics_scale / self.glyph_scale_factor;
+
+        let measure = |index, character, key, kern, char_data: &Character| MeasuredChar {
+            index,
+            character,
+            key,
+            kern,
+            advance: char_data.advance * scale,
+            quad: char_data.texture.as_ref().map(|texture| MeasuredQuad {
+                offset: texture.position.map(|p| p * scale),
+                size: texture.size.map(|s| s * scale),
+                inset: texture.padding * scale,
+            }),
+        };
+
+        text.indexed_lines(bytes)
+            .map(|(range, line)| {
+                let line = line.collect_vec();
+                let fonts = self.line_fonts(&line);
+                let mut chars = Vec::with_capacity(line.len());
+                let mut previous = None;
+
+                for indices in cluster::clusters(&text.text, &line) {
+                    let cluster = &line[indices.clone()];
+                    let (index, first) = cluster[0];
+
+                    if let Some(source) = cluster::cluster_text(&text.text, cluster) {
+                        let font = fonts[indices.start];
+                        let glyph = (self.cached_cluster(font, source))
+                            .ok_or(Error::GlyphNotCached(first))?;
+
+                        if let Some(id) = glyph {
+                            let key = cluster_key(font, id);
+                            let char_data =
+                                (self.character(key)).ok_or(Error::GlyphNotCached(first))?;
+                            chars.push(measure(index, first, key, None, char_data));
+                            previous = None;
+                            continue;
+                        }
+                    }
+
+                    for (&(index, c), &font) in cluster.iter().zip(&fonts[indices]) {
+                        let c = self.substitute(c);
+                        let key = self.char_key(font, c);
+                        let char_data = (self.character(key)).ok_or(Error::GlyphNotCached(c))?;
+
+                        // Only this font's own characters are kerned
+                        let kern = previous
+                            .filter(|_| font.is_none())
+                            .and_then(|p| self.kerning.get(&(p, c)))
+                            .map(|kern| kern * metrics_scale);
+                        previous = font.is_none().then_some(c);
+
+                        chars.push(measure(index, c, key, kern, char_data));
+                    }
+                }
+
+                Ok(MeasuredLine { text: range, chars })
+            })
+            .collect()
+    }
+
+    /// The distance between the baselines of two lines of text with a scale, in logical pixels.
+    fn line_spacing(&self, scale: f32) -> f32 {
+        (self.metrics.ascent - self.metrics.descent) * scale + self.metrics.line_gap
+    }
+
+    /// The scale a glyph is loaded at. Glyphs of fallback fonts have their font's own scale.
+    fn key_scale(&self, key: GlyphKey) -> PxScale {
+        match key {
+            GlyphKey::Fallback(font, _) => {
+                (self.fallbacks.get(font)).map_or(self.scale, |f| f.scale)
+            }
+            GlyphKey::Char(_) | GlyphKey::Glyph(_) => self.scale,
+        }
+    }
+
+    /// The scale a glyph is rasterised at, which includes the glyph scale factor.
+    fn glyph_scale(&self, key: GlyphKey) -> PxScale {
+        let scale = self.key_scale(key);
+
+        PxScale {
+            x: scale.x * self.glyph_scale_factor,
+            y: scale.y * self.glyph_scale_factor,
+        }
+    }
+
+    /// How far the pen moves after a glyph, in pixels at the scale the glyphs are rasterised at.
+    fn glyph_advance(&self, font: &FontArc, key: GlyphKey) -> f32 {
+        font.as_scaled(self.glyph_scale(key))
+            .h_advance(key.glyph_id(font))
+    }
+
+    /// The outlines a glyph is made from: its fallback font's, or the font's own.
+    fn key_font(&self, key: GlyphKey) -> Option<&FontArc> {
+        match key {
+            GlyphKey::Fallback(font, _) => self.fallbacks.get(font).map(|f| &f.font),
+            GlyphKey::Char(_) | GlyphKey::Glyph(_) => self.font.as_ref(),
+        }
+    }
+
+    /// The hash of the font a glyph is made from, which its distance fields are saved under.
+    #[cfg(feature = "disk-cache")]
+    fn key_hash(&self, key: GlyphKey) -> u64 {
+        match key {
+            GlyphKey::Fallback(font, _) => self.fallbacks.get(font).map_or(self.hash, |f| f.hash),
+            GlyphKey::Char(_) | GlyphKey::Glyph(_) => self.hash,
+        }
+    }
+
+    /// The character that's drawn for a character of text (see
+    /// [TextRenderer::set_substitutions]).
+    fn substitute(&self, c: char) -> char {
+        self.substitutions.get(&c).copied().unwrap_or(c)
+    }
+
+    /// Replaces the font's fallback fonts (see [TextRenderer::set_fallback_fonts]), forgetting the
+    /// glyphs of the old ones.
+    fn set_fallbacks(&mut self, fonts: Vec<FontArc>) -> Result<(), Error> {
+        // Fonts of the same size can have very different heights, so the fallbacks are scaled to
+        // have the same em size as this font instead
+        let em = match &self.font {
+            Some(font) => (font.units_per_em()).map_or(self.px_size, |units| {
+                self.scale.y * units / font.height_unscaled()
+            }),
+            None => self.px_size,
+        };
+
+        self.fallbacks = fonts
+            .into_iter()
+            .map(|font| {
+                Ok(FallbackFont {
+                    scale: FontSize::Px(em).scale(&font)?,
+                    clusters: Default::default(),
+                    #[cfg(feature = "disk-cache")]
+                    hash: disk_cache::font_hash(&font),
+                    font,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        self.fallback_cache.clear();
+        Ok(())
+    }
+
+    /// Whether a font draws a character: this font for None, or one of its fallback fonts.
+    fn has_char(&self, font: Option<usize>, c: char) -> bool {
+        // Glyph 0 is always the .notdef glyph
+        match (font, &self.font) {
+            (Some(font), _) => self.fallbacks[font].font.glyph_id(c).0 != 0,
+            (None, _) if self.custom_glyphs.contains_key(&c) => true,
+            (None, Some(font)) => font.glyph_id(c).0 != 0,
+            (None, None) => (self.char_cache.get(&c)).is_some_and(|char_data| !char_data.missing),
+        }
+    }
+
+    /// Chooses the font each character of a line is drawn with (see [itemize]): None for this
+    /// font, or the index of one of its fallback fonts.
+    ///
+    /// Each run of one script is drawn with the first font that has all of its characters. If none
+    /// of them do, each character is drawn with the first font that has it, and characters no font
+    /// has are drawn as this font's missing characters.
+    fn line_fonts(&self, line: &[(usize, char)]) -> Vec<Option<usize>> {
+        let mut fonts = vec![None; line.len()];
+
+        if self.fallbacks.is_empty() {
+            return fonts;
+        }
+
+        // Spaces and controls have nothing to draw, so they stay in this font (and tabs are
+        // measured as its spaces)
+        let drawn = |&i: &usize| {
+            let c = self.substitute(line[i].1);
+            !c.is_whitespace() && !c.is_control()
+        };
+        let candidates = || std::iter::once(None).chain((0..self.fallbacks.len()).map(Some));
+
+        for run in itemize::runs(line) {
+            let run_font = candidates().find(|&font| {
+                (run.clone().filter(drawn)).all(|i| self.has_char(font, self.substitute(line[i].1)))
+            });
+
+            for i in run.filter(drawn) {
+                let c = self.substitute(line[i].1);
+                fonts[i] = run_font
+                    .unwrap_or_else(|| candidates().find(|&font| self.has_char(font, c)).flatten());
+            }
+        }
+
+        fonts
+    }
+
+    /// The key a (substituted) character drawn with a font is cached under. Tabs that move to the
+    /// next column are measured as spaces.
+    fn char_key(&self, font: Option<usize>, c: char) -> GlyphKey {
+        match font {
+            Some(font) => GlyphKey::Fallback(font, self.fallbacks[font].font.glyph_id(c)),
+            None if c == '\t' => GlyphKey::Char(' '),
+            None => GlyphKey::Char(c),
+        }
+    }
+
+    /// The glyph a cluster drawn with a font is drawn as, if it has been looked up (see
+    /// [FontData::cluster_glyph]).
+    fn cached_cluster(&self, font: Option<usize>, text: &str) -> Option<Option<GlyphId>> {
+        let clusters = match font {
+            Some(font) => &self.fallbacks[font].clusters,
+            None => &self.clusters,
+        };
+
+        clusters.get(text).copied()
+    }
+
+    /// The glyph a cluster of characters drawn with a font is drawn as, if the font has a ligature
+    /// or variant for it (see [cluster]). Each cluster is only looked up once.
+    fn cluster_glyph(&mut self, font: Option<usize>, text: &str) -> Option<GlyphId> {
+        let (outlines, clusters) = match font {
+            Some(font) => {
+                let fallback = &mut self.fallbacks[font];
+                (Some(&fallback.font), &mut fallback.clusters)
+            }
+            None => (self.font.as_ref(), &mut self.clusters),
+        };
+
+        if let Some(glyph) = clusters.get(text) {
+            return *glyph;
         }
+
+        let glyph = outlines.and_then(|font| cluster::cluster_glyph(font, text));
+        clusters.insert(text.to_string(), glyph);
+        glyph
+    }
+
+    /// Chooses the fonts of the lines of a byte range of a text and looks up its clusters,
+    /// returning the characters and glyphs that have to be cached to lay them out.
+    ///
+    /// The characters are the text's own (before substitutions), with tabs as spaces.
+    fn text_glyphs(&mut self, text: &TextData, bytes: Range<usize>) -> (Vec<char>, Vec<GlyphKey>) {
+        let mut chars = Vec::new();
+        let mut glyphs = Vec::new();
+
+        for (_, line) in text.indexed_lines(bytes) {
+            let line = line.collect_vec();
+            let fonts = self.line_fonts(&line);
+
+            for indices in cluster::clusters(&text.text, &line) {
+                let cluster = &line[indices.clone()];
+
+                if let Some(source) = cluster::cluster_text(&text.text, cluster) {
+                    let font = fonts[indices.start];
+
+                    if let Some(id) = self.cluster_glyph(font, source) {
+                        glyphs.push(cluster_key(font, id));
+                        continue;
+                    }
+                }
+
+                for (&(_, c), &font) in cluster.iter().zip(&fonts[indices]) {
+                    match font {
+                        Some(_) => glyphs.push(self.char_key(font, self.substitute(c))),
+                        None => chars.push(if c == '\t' { ' ' } else { c }),
+                    }
+                }
+            }
+        }
+
+        (chars, glyphs)
+    }
+
+    /// The cached character or glyph for a key, if there is one.
+    fn character(&self, key: GlyphKey) -> Option<&Character> {
+        match key {
+            GlyphKey::Char(c) => self.char_cache.get(&c),
+            GlyphKey::Glyph(id) => self.glyph_cache.get(&id),
+            GlyphKey::Fallback(font, id) => self.fallback_cache.get(&(font, id)),
+        }
+    }
+
+    /// Caches the character or glyph for a key.
+    fn cache(&mut self, key: GlyphKey, character: Character) {
+        match key {
+            GlyphKey::Char(c) => self.char_cache.insert(c, character),
+            GlyphKey::Glyph(id) => self.glyph_cache.insert(id, character),
+            GlyphKey::Fallback(font, id) => self.fallback_cache.insert((font, id), character),
+        };
+    }
+
+    /// Forgets all of the font's cached characters and glyphs.
+    fn clear_cache(&mut self) {
+        self.char_cache.clear();
+        self.glyph_cache.clear();
+        self.fallback_cache.clear();
+    }
+}
+
+/// The key a cluster's glyph in a font is cached under (see [FontData::line_fonts]).
+fn cluster_key(font: Option<usize>, id: GlyphId) -> GlyphKey {
+    match font {
+        Some(font) => GlyphKey::Fallback(font, id),
+        None => GlyphKey::Glyph(id),
     }
 }
 
@@ -131,10 +928,8 @@ struct FontMap {
 
 impl FontMap {
     /// Load a font into the map
-    fn load(&mut self, font: FontArc, size: FontSize) -> FontId {
-        let id = self.fonts.len();
-        self.fonts.push(FontData::new(font, size));
-        FontId(id)
+    fn load(&mut self, font: FontArc, size: FontSize) -> Result<FontId, Error> {
+        Ok(self.insert(FontData::new(font, size)?))
     }
 
     /// Load a font into the map with sdf rendering enabled
@@ -143,30 +938,33 @@ impl FontMap {
         font: FontArc,
         size: FontSize,
         sdf_settings: SdfSettings,
-    ) -> FontId {
+    ) -> Result<FontId, Error> {
+        Ok(self.insert(FontData::new_with_sdf(font, size, sdf_settings)?))
+    }
+
+    fn insert(&mut self, font_data: FontData) -> FontId {
         let id = self.fonts.len();
-        self.fonts
-            .push(FontData::new_with_sdf(font, size, sdf_settings));
+        self.fonts.push(font_data);
         FontId(id)
     }
 
-    fn get(&self, font: FontId) -> &FontData {
-        self.fonts.get(font.0).expect("Font not found in renderer!")
+    fn get(&self, font: FontId) -> Result<&FontData, Error> {
+        self.fonts.get(font.0).ok_or(Error::FontNotFound(font))
     }
 
-    fn get_mut(&mut self, font: FontId) -> &mut FontData {
-        self.fonts
-            .get_mut(font.0)
-            .expect("Font not found in renderer!")
+    fn get_mut(&mut self, font: FontId) -> Result<&mut FontData, Error> {
+        self.fonts.get_mut(font.0).ok_or(Error::FontNotFound(font))
     }
 }
 
+#[cfg(has_wgpu)]
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Zeroable, bytemuck::Pod)]
 struct ScreenUniform {
     projection: [[f32; 4]; 4],
 }
 
+#[cfg(has_wgpu)]
 impl ScreenUniform {
     fn new(target_size: (u32, u32)) -> Self {
         let width = target_size.0 as f32;
@@ -186,39 +984,13 @@ impl ScreenUniform {
             ],
         }
     }
-}
-
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Zeroable, bytemuck::Pod)]
-struct TextureVertex {
-    tex_coord: [f32; 2],
-}
 
-/// Creates vertex data to draw a quad with given position and size
-const TEXTURE_VERTICES: [TextureVertex; 4] = [
-    TextureVertex {
-        tex_coord: [0.0, 0.0],
-    },
-    TextureVertex {
-        tex_coord: [0.0, 1.0],
-    },
-    TextureVertex {
-        tex_coord: [1.0, 0.0],
-    },
-    TextureVertex {
-        tex_coord: [1.0, 1.0],
-    },
-];
-
-fn texture_vertex_layout() -> wgpu::VertexBufferLayout<'static> {
-    wgpu::VertexBufferLayout {
-        array_stride: std::mem::size_of::<TextureVertex>() as _,
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &const {
-            wgpu::vertex_attr_array![
-                0 => Float32x2,
-            ]
-        },
+    /// Moves the origin of the pixel coordinates to the given position on the target.
+    fn translated(mut self, [x, y]: [f32; 2]) -> Self {
+        let [sx, sy] = [self.projection[0][0], self.projection[1][1]];
+        self.projection[3][0] += x * sx;
+        self.projection[3][1] += y * sy;
+        self
     }
 }
 
@@ -229,30 +1001,115 @@ struct CharacterInstance {
     position: [f32; 2],
     /// The width and height of the box
     size: [f32; 2],
+    /// The glyph's place in the text, which animations use to tell glyphs apart (see
+    /// [GlyphAnimation])
+    index: u32,
+}
+
+impl CharacterInstance {
+    /// The quad the instance is drawn on.
+    fn rect(&self) -> Rect {
+        Rect {
+            min: self.position,
+            max: [0, 1].map(|i| self.position[i] + self.size[i]),
+        }
+    }
 }
 
+#[cfg(has_wgpu)]
 fn character_instance_layout() -> wgpu::VertexBufferLayout<'static> {
     wgpu::VertexBufferLayout {
         array_stride: std::mem::size_of::<CharacterInstance>() as _,
         step_mode: wgpu::VertexStepMode::Instance,
         attributes: &const {
             wgpu::vertex_attr_array![
+                0 => Float32x2,
                 1 => Float32x2,
-                2 => Float32x2,
+                2 => Uint32,
             ]
         },
     }
 }
 
+/// What the shaders draw instead of text, for debugging (see
+/// [TextRendererBuilder::with_debug_mode]).
+#[cfg(has_wgpu)]
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+pub enum ShaderDebugMode {
+    /// Text is drawn normally.
+    #[default]
+    Off,
+    /// Each glyph's quad is drawn in translucent magenta behind the glyph, to check the size and
+    /// position of the quads.
+    GlyphQuads,
+    /// The raw values of each glyph's texture are drawn in grey, e.g. to see how far a distance
+    /// field spreads.
+    TextureValues,
+}
+
+/// The values of the shaders' pipeline-overridable constants.
+#[cfg(has_wgpu)]
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+struct ShaderConstants {
+    /// The bits of the floats, so the builder can still be hashed.
+    edge_smoothing: u32,
+    gamma: u32,
+    debug_mode: ShaderDebugMode,
+}
+
+#[cfg(has_wgpu)]
+impl Default for ShaderConstants {
+    fn default() -> Self {
+        Self {
+            edge_smoothing: 1f32.to_bits(),
+            gamma: 1f32.to_bits(),
+            debug_mode: ShaderDebugMode::Off,
+        }
+    }
+}
+
+#[cfg(has_wgpu)]
+impl ShaderConstants {
+    fn edge_smoothing(&self) -> f32 {
+        f32::from_bits(self.edge_smoothing)
+    }
+
+    fn gamma(&self) -> f32 {
+        f32::from_bits(self.gamma)
+    }
+
+    /// The constants as wgpu takes them when creating a pipeline.
+    fn to_map(self) -> std::collections::HashMap<String, f64> {
+        [
+            ("edge_smoothing", self.edge_smoothing() as f64),
+            ("gamma", self.gamma() as f64),
+            ("debug_mode", self.debug_mode as u32 as f64),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect()
+    }
+}
+
 /// A builder for a [TextRenderer] struct.
+#[cfg(has_wgpu)]
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub struct TextRendererBuilder {
-    target_format: wgpu::TextureFormat,
-    target_size: (u32, u32),
-    msaa_samples: u32,
-    depth_format: Option<TextureFormat>,
+    target: TargetConfig,
+    compatibility: bool,
+    push_constants: bool,
+    shared_settings: bool,
+    staging_belt: Option<wgpu::BufferAddress>,
+    glyph_readback: bool,
+    sampler_settings: SamplerSettings,
+    constants: ShaderConstants,
+    label_prefix: String,
+    layout_cache: Option<usize>,
+    #[cfg(feature = "disk-cache")]
+    disk_cache: Option<std::path::PathBuf>,
 }
 
+#[cfg(has_wgpu)]
 impl TextRendererBuilder {
     /// Creates a new TextRendererBuilder.
     ///
@@ -260,10 +1117,18 @@ impl TextRendererBuilder {
     /// to, and the size of the target surface.
     pub fn new(target_format: wgpu::TextureFormat, target_size: (u32, u32)) -> Self {
         Self {
-            target_format,
-            target_size,
-            msaa_samples: 1,
-            depth_format: None,
+            target: TargetConfig::new(target_format, target_size),
+            compatibility: false,
+            push_constants: false,
+            shared_settings: false,
+            staging_belt: None,
+            glyph_readback: false,
+            sampler_settings: Default::default(),
+            constants: Default::default(),
+            label_prefix: "kaku".into(),
+            layout_cache: None,
+            #[cfg(feature = "disk-cache")]
+            disk_cache: None,
         }
     }
 
@@ -273,7 +1138,7 @@ impl TextRendererBuilder {
     /// text look any better. Instead, this option is used if you want to draw on a render pass
     /// that already uses multisampling.
     pub fn with_msaa_sample_count(mut self, samples: u32) -> Self {
-        self.msaa_samples = samples;
+        self.target = self.target.with_msaa_sample_count(samples);
         self
     }
 
@@ -282,47 +1147,264 @@ impl TextRendererBuilder {
     /// By default the renderer will only be compatible with render passes that don't use a depth
     /// buffer. If yours does use a depth buffer, you will want to set this option.
     pub fn with_depth(mut self, depth_format: TextureFormat) -> Self {
-        self.depth_format = Some(depth_format);
+        self.target = self.target.with_depth(depth_format);
+        self
+    }
+
+    /// Makes the renderer draw for an HDR target, where white text is as bright as the given paper
+    /// white brightness in nits. See [TargetConfig::with_paper_white].
+    pub fn with_paper_white(mut self, paper_white: f32) -> Self {
+        self.target = self.target.with_paper_white(paper_white);
+        self
+    }
+
+    /// Enables compatibility mode, for downlevel adapters (e.g. WebGL2) that may not support
+    /// everything kaku normally uses.
+    ///
+    /// In this mode, character textures are bound as non-filterable and the shaders filter them by
+    /// hand, and distance fields that would use [SdfFormat::R16Unorm] use
+    /// [SdfFormat::R8Unorm] instead. The renderer then works with a device created with no
+    /// features and [wgpu::Limits::downlevel_webgl2_defaults]. It looks the same, but
+    /// drawing is a little slower, so only use it if you need to.
+    pub fn with_compatibility_mode(mut self, enabled: bool) -> Self {
+        self.compatibility = enabled;
+        self
+    }
+
+    /// Makes text pass its settings (color, position, outline etc.) to the shaders with push
+    /// constants, instead of each [Text] having its own uniform buffer and bind group. This saves
+    /// creating hundreds of tiny buffers in UI-heavy scenes.
+    ///
+    /// Push constants are only available on native backends, and the device needs
+    /// [wgpu::Features::PUSH_CONSTANTS] and a [wgpu::Limits::max_push_constant_size] of at least
+    /// 128 bytes. Avoid them on the GL backend, where wgpu can read them misaligned.
+    pub fn with_push_constants(mut self, enabled: bool) -> Self {
+        self.push_constants = enabled;
+        self
+    }
+
+    /// Makes texts share uniform buffers for their settings (color, position, outline etc.),
+    /// instead of each [Text] having its own uniform buffer and bind group.
+    ///
+    /// The settings of up to 256 texts are kept in one buffer, and texts are drawn by changing the
+    /// offset of a single bind group. Unlike [TextRendererBuilder::with_push_constants] this works
+    /// on every backend. If push constants are enabled too, they are used instead.
+    pub fn with_shared_settings_buffer(mut self, enabled: bool) -> Self {
+        self.shared_settings = enabled;
+        self
+    }
+
+    /// Makes texts upload their changes through a [wgpu::util::StagingBelt] with chunks of the
+    /// given size, instead of writing to the queue every time they change.
+    ///
+    /// Changes to texts built by the renderer (e.g. [Text::set_text] or [Text::set_position]) are
+    /// collected until [TextRenderer::finish_uploads] records them into a command encoder, which
+    /// has to be submitted before the texts are drawn. If a text changes the same thing several
+    /// times in between, only the last change is uploaded. After submitting, call
+    /// [TextRenderer::recall_uploads] so the belt's buffers can be reused.
+    ///
+    /// This is worth it when hundreds of texts change every frame. The chunk size should be a bit
+    /// more than the amount of text data that changes in a frame, e.g. 64KiB.
+    pub fn with_staging_belt(mut self, chunk_size: wgpu::BufferAddress) -> Self {
+        self.staging_belt = Some(chunk_size);
+        self
+    }
+
+    /// Creates glyph textures so they can be copied back to the CPU with
+    /// [TextRenderer::read_glyph], e.g. to look at the distance fields while tuning
+    /// [SdfSettings]. This is off by default, since it's only needed for debugging.
+    pub fn with_glyph_readback(mut self, enabled: bool) -> Self {
+        self.glyph_readback = enabled;
+        self
+    }
+
+    /// Sets how glyph textures are sampled, e.g. to repeat them or to use anisotropic filtering
+    /// (see [SamplerSettings]). Fonts can override this with [FontOptions::with_sampler].
+    pub fn with_sampler(mut self, settings: SamplerSettings) -> Self {
+        self.sampler_settings = settings;
+        self
+    }
+
+    /// Sets how wide the anti-aliased edges of sdf text are, as a multiple of the usual width of
+    /// about a pixel. The default is 1.
+    ///
+    /// Larger values make text softer, which can look better when it's moving or heavily
+    /// downscaled, and smaller values make it sharper. Text without sdf isn't affected.
+    pub fn with_edge_smoothing(mut self, edge_smoothing: f32) -> Self {
+        self.constants.edge_smoothing = edge_smoothing.to_bits();
+        self
+    }
+
+    /// Sets the gamma the alpha of text is adjusted with: alpha is raised to the power of
+    /// `1 / gamma`. The default is 1, which leaves it as it is.
+    ///
+    /// Values above 1 make the edges of text heavier, which can help light text on a dark
+    /// background, and values below 1 make them lighter.
+    pub fn with_gamma(mut self, gamma: f32) -> Self {
+        self.constants.gamma = gamma.to_bits();
+        self
+    }
+
+    /// Makes the shaders draw debugging information instead of text (see [ShaderDebugMode]).
+    pub fn with_debug_mode(mut self, debug_mode: ShaderDebugMode) -> Self {
+        self.constants.debug_mode = debug_mode;
+        self
+    }
+
+    /// Sets the prefix of the labels of all the GPU objects the renderer creates (buffers,
+    /// textures, pipelines etc.), and of the profiler scopes text is drawn in. The default is "kaku".
+    ///
+    /// This is useful when an app has several renderers, e.g. "ui text" and "world text", so you
+    /// can tell which one created what in a GPU capture.
+    pub fn with_label_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.label_prefix = prefix.into();
+        self
+    }
+
+    /// Makes the renderer remember the layouts of the last `capacity` texts it laid out, so
+    /// laying out the same string with the same font, scale, alignment and wrap width again
+    /// reuses the layout instead of measuring every character again.
+    ///
+    /// This is for immediate mode UIs that build the same texts every frame. Texts that only
+    /// differ in their position or colour share a layout. When the cache is full, the layout that
+    /// was used the longest time ago is forgotten. The cache is off by default.
+    pub fn with_layout_cache(mut self, capacity: usize) -> Self {
+        self.layout_cache = Some(capacity);
         self
     }
 
+    /// Sets a directory to save generated signed distance fields to.
+    ///
+    /// Whenever a distance field is generated it will be saved in this directory, and the next time
+    /// the same character is needed with the same font, size and sdf settings it will be loaded
+    /// from there instead of being generated again, even in later runs of the program. The
+    /// directory will be created if it doesn't exist.
+    #[cfg(feature = "disk-cache")]
+    pub fn with_disk_cache(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.disk_cache = Some(dir.into());
+        self
+    }
+
+    /// Creates a builder for a text renderer that draws inside egui panels with
+    /// [EguiTextCallback].
+    ///
+    /// The renderer draws in egui-wgpu's render pass, so its pipelines have to match it.
+    /// `msaa_samples` and `depth_format` need to be what egui-wgpu's renderer was created with (in
+    /// eframe, `NativeOptions::multisampling` and `NativeOptions::depth_buffer`). Compatibility mode
+    /// is enabled if egui is running on WebGL or another GL backend.
+    #[cfg(feature = "egui")]
+    pub fn for_egui(
+        render_state: &egui_wgpu::RenderState,
+        msaa_samples: u32,
+        depth_format: Option<TextureFormat>,
+    ) -> Self {
+        let mut builder = Self::new(render_state.target_format, (1, 1))
+            .with_msaa_sample_count(msaa_samples)
+            .with_compatibility_mode(render_state.adapter.get_info().backend == wgpu::Backend::Gl);
+        builder.target.depth_format = depth_format;
+        builder
+    }
+
     /// Creates a new TextRenderer from the current configuration.
     pub fn build(self, device: &wgpu::Device) -> TextRenderer {
-        TextRenderer::new(
-            device,
-            self.target_format,
-            self.target_size,
-            self.msaa_samples,
-            self.depth_format,
-        )
+        TextRenderer::new(device, self)
     }
 }
 
-fn create_text_pipeline(
+/// Creates one of the text shaders from its source, changed for the given variant.
+///
+/// In compatibility mode, `nearest` is a WGSL expression saying whether the character texture
+/// should use nearest filtering, and the shader's texture sample is replaced with one that filters
+/// by hand (see [TextRendererBuilder::with_compatibility_mode]).
+///
+/// For multiview targets, the screen uniform is replaced with an array of one projection per view
+/// (see [TargetConfig::with_multiview]). With push constants, the text settings are read from
+/// push constants instead of a uniform buffer (see [TextRendererBuilder::with_push_constants]).
+#[cfg(has_wgpu)]
+fn create_text_shader(
     label: &str,
-    layout: &wgpu::PipelineLayout,
-    render_format: wgpu::TextureFormat,
-    samples: u32,
-    shader: &wgpu::ShaderModule,
-    depth_format: Option<TextureFormat>,
+    source: &str,
+    nearest: &str,
+    variant: ShaderVariant,
+    device: &wgpu::Device,
+) -> wgpu::ShaderModule {
+    const SAMPLE: &str = "textureSample(texture, texture_sampler, input.tex_coord)";
+    const SCREEN: &str = "var<uniform> screen: mat4x4<f32>;";
+    const VS_MAIN: &str =
+        "fn vs_main(@builtin(vertex_index) vertex_index: u32, instance: CharacterInstance)";
+    const PROJECT: &str = "let projection = screen;";
+    const SETTINGS: &str = "@group(2) @binding(0)\nvar<uniform> settings:";
+
+    let mut source = Cow::Borrowed(source);
+
+    if let Some(views) = variant.views {
+        debug_assert!([SCREEN, VS_MAIN, PROJECT]
+            .iter()
+            .all(|s| source.contains(s)));
+        let screens = format!("var<uniform> screens: array<mat4x4<f32>, {views}>;");
+        let vs_main = "fn vs_main(@builtin(vertex_index) vertex_index: u32, \
+            instance: CharacterInstance, \
+            @builtin(view_index) view_index: i32)";
+        source = Cow::Owned(
+            source
+                .replace(SCREEN, &screens)
+                .replace(VS_MAIN, vs_main)
+                .replace(PROJECT, "let projection = screens[view_index];"),
+        );
+    }
+
+    if variant.push_constants {
+        debug_assert!(source.contains(SETTINGS));
+        source = Cow::Owned(source.replace(SETTINGS, "var<push_constant> settings:"));
+    }
+
+    if variant.compatibility {
+        debug_assert!(source.contains(SAMPLE));
+        let manual = format!("sample_manual(texture, input.tex_coord, {nearest})");
+        let filtering = include_str!("shaders/manual_filtering.wgsl");
+        source = Cow::Owned(source.replace(SAMPLE, &manual) + filtering);
+    }
+
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source),
+    })
+}
+
+/// Creates a render pipeline for drawing text, with the shaders' overridable constants set if
+/// they have them.
+#[cfg(has_wgpu)]
+fn create_text_pipeline(
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    target: &TargetConfig,
+    constants: Option<ShaderConstants>,
+    blend: wgpu::BlendState,
     device: &wgpu::Device,
 ) -> wgpu::RenderPipeline {
+    let constants = constants.map(ShaderConstants::to_map).unwrap_or_default();
+    let compilation_options = || wgpu::PipelineCompilationOptions {
+        constants: &constants,
+        ..Default::default()
+    };
+
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some(label),
         layout: Some(layout),
         vertex: wgpu::VertexState {
             module: shader,
             entry_point: "vs_main",
-            buffers: &[texture_vertex_layout(), character_instance_layout()],
-            compilation_options: Default::default(),
+            buffers: &[character_instance_layout()],
+            compilation_options: compilation_options(),
         },
         fragment: Some(wgpu::FragmentState {
             module: shader,
             entry_point: "fs_main",
-            compilation_options: Default::default(),
+            compilation_options: compilation_options(),
             targets: &[Some(wgpu::ColorTargetState {
-                format: render_format,
-                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                format: target.format,
+                blend: Some(blend),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }),
@@ -330,7 +1412,7 @@ fn create_text_pipeline(
             topology: wgpu::PrimitiveTopology::TriangleStrip,
             ..Default::default()
         },
-        depth_stencil: depth_format.map(|format| DepthStencilState {
+        depth_stencil: target.depth_format.map(|format| DepthStencilState {
             format,
             depth_write_enabled: false,
             depth_compare: wgpu::CompareFunction::Always,
@@ -338,14 +1420,18 @@ fn create_text_pipeline(
             bias: wgpu::DepthBiasState::default(),
         }),
         multisample: wgpu::MultisampleState {
-            count: samples,
+            count: target.msaa_samples,
             mask: !0,
             alpha_to_coverage_enabled: false,
         },
-        multiview: None,
+        multiview: target.multiview,
+        // Added in wgpu 22
+        #[cfg(not(feature = "wgpu-0_20"))]
+        cache: None,
     })
 }
 
+#[cfg(has_wgpu)]
 #[derive(Debug)]
 /// The main struct that handles text rendering to the screen. Use this struct to load fonts and
 /// draw text during a render pass.
@@ -354,32 +1440,90 @@ fn create_text_pipeline(
 pub struct TextRenderer {
     fonts: FontMap,
     char_bind_group_layout: wgpu::BindGroupLayout,
-
-    screen_bind_group: wgpu::BindGroup,
-    screen_buffer: wgpu::Buffer,
+    /// The samplers character bind groups use, depending on how their texture is filtered, unless
+    /// their font has its own (see [FontOptions::with_sampler]).
+    linear_sampler: wgpu::Sampler,
+    nearest_sampler: wgpu::Sampler,
 
     pub(crate) settings_layout: wgpu::BindGroupLayout,
     pub(crate) sdf_settings_layout: wgpu::BindGroupLayout,
-
-    vertex_buffer: wgpu::Buffer,
-
-    basic_pipeline: wgpu::RenderPipeline,
-    sdf_pipeline: wgpu::RenderPipeline,
-    outline_pipeline: wgpu::RenderPipeline,
+    /// The layout of the settings of baked texts (see [TextRenderer::bake]).
+    baked_settings_layout: wgpu::BindGroupLayout,
+    /// The layout of the settings of texts' panels (see [Panel]).
+    pub(crate) panel_settings_layout: wgpu::BindGroupLayout,
+    /// The layout of the settings of cell buffers (see [CellBuffer]).
+    cell_settings_layout: wgpu::BindGroupLayout,
+
+    /// The shaders of single view targets, kept for making the pipelines of targets added later.
+    shaders: TextShaders,
+    /// The targets text can be drawn to, indexed by [TargetId]. The first one is the target the
+    /// renderer was built for.
+    targets: Vec<RenderTarget>,
+
+    /// Whether the renderer was built in compatibility mode (see
+    /// [TextRendererBuilder::with_compatibility_mode]).
+    compatibility: bool,
+    /// The features of the device the renderer was built with, which fonts' texture formats are
+    /// checked against.
+    device_features: wgpu::Features,
+    /// Whether text settings are passed with push constants (see
+    /// [TextRendererBuilder::with_push_constants]).
+    pub(crate) push_constants: bool,
+    /// The buffers text settings are kept in, if texts share them (see
+    /// [TextRendererBuilder::with_shared_settings_buffer]).
+    pub(crate) settings_pool: Option<SettingsPool>,
+    /// The staging belt texts upload their changes through, if they don't write to the queue
+    /// (see [TextRendererBuilder::with_staging_belt]).
+    pub(crate) staging: Option<Staging>,
+    /// Whether glyph textures can be read back (see [TextRendererBuilder::with_glyph_readback]).
+    glyph_readback: bool,
+    /// How many bytes the buffers of the texts built by this renderer use.
+    pub(crate) memory: Arc<MemoryCounters>,
+    /// What has been drawn and uploaded since the stats were reset (see
+    /// [TextRenderer::frame_stats]).
+    pub(crate) frame_counters: Arc<FrameCounters>,
+    /// The values of the shaders' overridable constants, for the pipelines of targets added later
+    /// and for the cpu renderer.
+    constants: ShaderConstants,
+    /// The number of physical pixels per logical pixel (see [TextRenderer::set_scale_factor]).
+    scale_factor: f32,
+    /// The time animations are drawn at, in seconds (see [TextRenderer::set_time]).
+    pub(crate) time: f32,
+    /// What the labels of GPU objects start with (see [TextRendererBuilder::with_label_prefix]).
+    label_prefix: String,
+    /// The layouts of recently laid out texts (see [TextRendererBuilder::with_layout_cache]).
+    layout_cache: Option<LayoutCache>,
+
+    #[cfg(feature = "disk-cache")]
+    disk_cache: Option<DiskCache>,
+    #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+    thread_pool: Option<std::sync::Arc<rayon::ThreadPool>>,
 }
 
+#[cfg(has_wgpu)]
 impl TextRenderer {
-    fn new(
-        device: &wgpu::Device,
-        target_format: wgpu::TextureFormat,
-        target_size: (u32, u32),
-        msaa_samples: u32,
-        depth_stencil_state: Option<TextureFormat>,
-    ) -> Self {
+    fn new(device: &wgpu::Device, config: TextRendererBuilder) -> Self {
+        let TextRendererBuilder {
+            target,
+            compatibility,
+            push_constants,
+            shared_settings,
+            staging_belt,
+            glyph_readback,
+            sampler_settings,
+            constants,
+            label_prefix,
+            layout_cache,
+            #[cfg(feature = "disk-cache")]
+            disk_cache,
+        } = config;
+
+        let label = |name: &str| format!("{label_prefix} {name}");
+
         // Texture bind group layout to use when creating cached char textures
         let char_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("kaku character texture bind group layout"),
+                label: Some(&label("character texture bind group layout")),
                 entries: &[
                     wgpu::BindGroupLayoutEntry {
                         binding: 0,
@@ -387,64 +1531,44 @@ impl TextRenderer {
                         ty: wgpu::BindingType::Texture {
                             multisampled: false,
                             view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            sample_type: wgpu::TextureSampleType::Float {
+                                filterable: !compatibility,
+                            },
                         },
                         count: None,
                     },
                     wgpu::BindGroupLayoutEntry {
                         binding: 1,
                         visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        ty: wgpu::BindingType::Sampler(if compatibility {
+                            wgpu::SamplerBindingType::NonFiltering
+                        } else {
+                            wgpu::SamplerBindingType::Filtering
+                        }),
                         count: None,
                     },
                 ],
             });
 
-        // The screen uniform is a matrix that transforms pixel coords into screen coords
-        let screen_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("kaku screen uniform bind group layout"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: NonZeroU64::new(std::mem::size_of::<ScreenUniform>() as _),
-                        },
-                        count: None,
-                    }
-                ]
-            });
-
-        let screen_uniform = ScreenUniform::new(target_size);
-
-        let screen_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("kaku screen uniform buffer"),
-            contents: bytemuck::cast_slice(&[screen_uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let screen_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("kaku screen uniform bind group"),
-            layout: &screen_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: screen_buffer.as_entire_binding(),
-            }],
-        });
+        // Character textures are all sampled one of two ways unless their font has its own
+        // sampler settings, so most bind groups share these
+        let sampler = |name: &str, filter| {
+            sampler_settings.create_sampler(&label(name), filter, compatibility, device)
+        };
+        let linear_sampler = sampler("linear character sampler", wgpu::FilterMode::Linear);
+        let nearest_sampler = sampler("nearest character sampler", wgpu::FilterMode::Nearest);
 
         // The settings bind group for a piece of text details how it should be drawn in the
-        // fragment stage
+        // fragment stage. Texts sharing a settings buffer are told apart by a dynamic offset
+        let shared_settings = shared_settings && !push_constants;
         let settings_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("kaku text settings uniform bind group layout"),
+            label: Some(&label("text settings uniform bind group layout")),
             entries: &[wgpu::BindGroupLayoutEntry {
                 binding: 0,
                 visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
+                    has_dynamic_offset: shared_settings,
                     min_binding_size: NonZeroU64::new(std::mem::size_of::<SettingsUniform>() as _),
                 },
                 count: None,
@@ -453,13 +1577,13 @@ impl TextRenderer {
 
         let sdf_settings_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("kaku sdf text settings uniform bind group layout"),
+                label: Some(&label("sdf text settings uniform bind group layout")),
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
                     visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
+                        has_dynamic_offset: shared_settings,
                         min_binding_size: NonZeroU64::new(
                             std::mem::size_of::<SdfSettingsUniform>() as _,
                         ),
@@ -468,105 +1592,323 @@ impl TextRenderer {
                 }],
             });
 
-        // The render pipeline to use to render the text with no sdf
-        let basic_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("kaku text rendering pipeline layout"),
-                bind_group_layouts: &[
-                    &screen_bind_group_layout,
-                    &char_bind_group_layout,
-                    &settings_layout,
-                ],
-                push_constant_ranges: &[],
+        let baked_settings_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(&label("baked text settings uniform bind group layout")),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(
+                            std::mem::size_of::<BakedSettingsUniform>() as _,
+                        ),
+                    },
+                    count: None,
+                }],
             });
 
-        let basic_shader = device.create_shader_module(include_wgsl!("shaders/text_shader.wgsl"));
-
-        let basic_pipeline = create_text_pipeline(
-            "kaku basic text render pipeline",
-            &basic_pipeline_layout,
-            target_format,
-            msaa_samples,
-            &basic_shader,
-            depth_stencil_state,
-            device,
-        );
-
-        // The render pipeline to use to render the text with no sdf
-        let sdf_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("kaku sdf text rendering pipeline layout"),
-            bind_group_layouts: &[
-                &screen_bind_group_layout,
-                &char_bind_group_layout,
-                &sdf_settings_layout,
-            ],
-            push_constant_ranges: &[],
-        });
-
-        let sdf_shader = device.create_shader_module(include_wgsl!("shaders/sdf_text_shader.wgsl"));
-
-        let sdf_pipeline = create_text_pipeline(
-            "kaku sdf text render pipeline",
-            &sdf_pipeline_layout,
-            target_format,
-            msaa_samples,
-            &sdf_shader,
-            depth_stencil_state,
-            device,
-        );
+        let panel_settings_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(&label("panel settings uniform bind group layout")),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(std::mem::size_of::<PanelUniform>() as _),
+                    },
+                    count: None,
+                }],
+            });
 
-        let outline_shader =
-            device.create_shader_module(include_wgsl!("shaders/sdf_outline_shader.wgsl"));
+        let cell_settings_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(&label("cell buffer settings uniform bind group layout")),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(
+                            std::mem::size_of::<CellSettingsUniform>() as _,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
 
-        let outline_pipeline = create_text_pipeline(
-            "kaku sdf text outline render pipeline",
-            &sdf_pipeline_layout,
-            target_format,
-            msaa_samples,
-            &outline_shader,
-            depth_stencil_state,
+        let variant = ShaderVariant {
+            views: None,
+            compatibility,
+            push_constants,
+        };
+        let shaders = TextShaders::new(
+            variant,
+            &char_bind_group_layout,
+            &settings_layout,
+            &sdf_settings_layout,
+            &baked_settings_layout,
+            &panel_settings_layout,
+            &cell_settings_layout,
+            label,
             device,
         );
-
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("kaku character vertex buffer"),
-            contents: bytemuck::cast_slice(&TEXTURE_VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+        let main_target = RenderTarget::new(target, &shaders, constants, 0., label, device);
 
         Self {
             fonts: Default::default(),
             char_bind_group_layout,
+            linear_sampler,
+            nearest_sampler,
             settings_layout,
-            basic_pipeline,
-            screen_bind_group,
-            screen_buffer,
-            vertex_buffer,
             sdf_settings_layout,
-            sdf_pipeline,
-            outline_pipeline,
+            baked_settings_layout,
+            panel_settings_layout,
+            cell_settings_layout,
+            shaders,
+            targets: vec![main_target],
+            compatibility,
+            device_features: device.features(),
+            push_constants,
+            settings_pool: shared_settings.then(|| SettingsPool::new(device)),
+            staging: staging_belt.map(Staging::new),
+            glyph_readback,
+            memory: Default::default(),
+            frame_counters: Default::default(),
+            constants,
+            scale_factor: 1.,
+            time: 0.,
+            label_prefix,
+            layout_cache: layout_cache.map(LayoutCache::new),
+            #[cfg(feature = "disk-cache")]
+            disk_cache: disk_cache.map(DiskCache::new),
+            #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+            thread_pool: None,
         }
     }
 
+    /// Makes a label for a GPU object, starting with the renderer's label prefix.
+    pub(crate) fn label(&self, name: &str) -> String {
+        format!("{} {name}", self.label_prefix)
+    }
+
     /// Configure the text renderer to draw to a surface with the given dimensions.
     ///
     /// You want to use this when the window resizes. You might also want to use it before drawing
     /// to a texture which is smaller than the screen, if you so choose.
     pub fn resize(&self, new_size: (u32, u32), queue: &wgpu::Queue) {
-        let screen_uniform = ScreenUniform::new(new_size);
-        queue.write_buffer(
-            &self.screen_buffer,
-            0,
-            bytemuck::cast_slice(&[screen_uniform]),
-        );
+        self.targets[0].resize(new_size, queue);
+    }
+
+    /// Registers another kind of render pass to draw text in, e.g. an `Rgba16Float` offscreen
+    /// texture as well as the sRGB swapchain the renderer was built for, and returns its id.
+    ///
+    /// This creates a set of pipelines for the target. Draw to it with
+    /// [TextRenderer::draw_text_for_target], and resize it with [TextRenderer::resize_target].
+    /// Fonts and [Text] objects are shared between all of the renderer's targets.
+    pub fn add_target(&mut self, config: TargetConfig, device: &wgpu::Device) -> TargetId {
+        let id = TargetId(self.targets.len());
+        let label = |name: &str| self.label(&format!("{name} (target {})", id.0));
+
+        // The shaders of multiview targets depend on their number of views
+        let multiview_shaders = config.multiview.map(|views| {
+            let variant = ShaderVariant {
+                views: Some(views),
+                compatibility: self.compatibility,
+                push_constants: self.push_constants,
+            };
+            TextShaders::new(
+                variant,
+                &self.char_bind_group_layout,
+                &self.settings_layout,
+                &self.sdf_settings_layout,
+                &self.baked_settings_layout,
+                &self.panel_settings_layout,
+                &self.cell_settings_layout,
+                label,
+                device,
+            )
+        });
+        let shaders = multiview_shaders.as_ref().unwrap_or(&self.shaders);
+
+        let target = RenderTarget::new(config, shaders, self.constants, self.time, label, device);
+
+        self.targets.push(target);
+        id
+    }
+
+    /// Returns the id of the target the renderer was built for, which is the one
+    /// [TextRenderer::draw_text] and [TextRenderer::resize] use.
+    pub fn main_target(&self) -> TargetId {
+        TargetId(0)
+    }
+
+    /// Configures one of the renderer's targets to draw to a surface with the given dimensions,
+    /// like [TextRenderer::resize].
+    ///
+    /// Returns an error if the target wasn't added to this renderer.
+    pub fn resize_target(
+        &self,
+        target: TargetId,
+        new_size: (u32, u32),
+        queue: &wgpu::Queue,
+    ) -> Result<(), Error> {
+        self.target(target)?.resize(new_size, queue);
+        Ok(())
+    }
+
+    /// Sets the projection of each view of a multiview target (see [TargetConfig::with_multiview]),
+    /// e.g. for rendering text to both eyes of a VR headset.
+    ///
+    /// Each projection is a column-major matrix that transforms the pixel coordinates text is laid
+    /// out in to clip space, e.g. the eye's view-projection matrix multiplied by a transform that
+    /// places the text in the world. Resizing the target resets every view to the usual screen
+    /// projection.
+    ///
+    /// Returns an error if the target wasn't added to this renderer, or if there isn't exactly one
+    /// projection for each of its views.
+    pub fn set_view_projections(
+        &self,
+        target: TargetId,
+        projections: &[[[f32; 4]; 4]],
+        queue: &wgpu::Queue,
+    ) -> Result<(), Error> {
+        self.target(target)?
+            .set_view_projections(projections, queue)
+    }
+
+    /// Sets the paper white brightness of a target in nits, e.g. when the user changes it in the
+    /// settings of a game (see [TargetConfig::with_paper_white]).
+    ///
+    /// Returns an error if the target wasn't added to this renderer.
+    pub fn set_paper_white(
+        &self,
+        target: TargetId,
+        paper_white: f32,
+        queue: &wgpu::Queue,
+    ) -> Result<(), Error> {
+        self.set_output_brightness(target, paper_white / SCRGB_NITS, queue)
+    }
+
+    /// Sets the multiplier for the color of everything drawn to a target (see
+    /// [TargetConfig::with_output_brightness]).
+    ///
+    /// Returns an error if the target wasn't added to this renderer.
+    pub fn set_output_brightness(
+        &self,
+        target: TargetId,
+        brightness: f32,
+        queue: &wgpu::Queue,
+    ) -> Result<(), Error> {
+        self.target(target)?
+            .set_output_brightness(brightness, queue);
+        Ok(())
+    }
+
+    /// Sets the time that [GlyphAnimation]s are drawn at, in seconds. Call this every frame (e.g.
+    /// with the time since the app started) to play them.
+    ///
+    /// The time is shared by every target and every text, and starts at 0. Floats lose precision
+    /// as they grow, so wrap it around (e.g. every hour) if your app runs for days.
+    pub fn set_time(&mut self, seconds: f32, queue: &wgpu::Queue) {
+        self.time = seconds;
+
+        for target in &self.targets {
+            target.set_time(seconds, queue);
+        }
+    }
+
+    /// The time animations are drawn at (see [TextRenderer::set_time]).
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    fn target(&self, target: TargetId) -> Result<&RenderTarget, Error> {
+        self.targets
+            .get(target.0)
+            .ok_or(Error::TargetNotFound(target))
+    }
+
+    /// Sets the number of physical pixels per logical pixel of the screen, e.g. from winit's
+    /// `Window::scale_factor`. The default is 1.
+    ///
+    /// Text positions, sizes and outline widths are all in logical pixels, and are multiplied by
+    /// the scale factor when the text is laid out. Fonts that don't use sdf are rasterised at the
+    /// scaled size so they stay crisp on HiDPI displays, which means their cached characters are
+    /// cleared when the scale factor changes. Sdf fonts scale smoothly, so they're left as they
+    /// are.
+    ///
+    /// [Text] objects created before the scale factor changed have to be rebuilt (or have their
+    /// text set again with [Text::set_text]) to be drawn at the new scale.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        let scale_factor = scale_factor as f32;
+
+        if scale_factor == self.scale_factor {
+            return;
+        }
+
+        self.scale_factor = scale_factor;
+        self.clear_layout_cache();
+
+        for font_data in &mut self.fonts.fonts {
+            if font_data.font.is_some() && font_data.sdf_settings.is_none() {
+                font_data.glyph_scale_factor = scale_factor;
+                font_data.clear_cache();
+            }
+        }
+    }
+
+    /// Returns the scale factor set with [TextRenderer::set_scale_factor].
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor as f64
+    }
+
+    /// Creates a bind group for a screen uniform, for drawing with
+    /// [TextRenderer::draw_text_with_screen].
+    fn create_screen_bind_group(
+        &self,
+        screen_uniform: ScreenUniform,
+        device: &wgpu::Device,
+    ) -> wgpu::BindGroup {
+        use wgpu::util::DeviceExt;
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&self.label("screen uniform buffer")),
+            contents: bytemuck::cast_slice(&[screen_uniform]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&self.label("screen uniform bind group")),
+            layout: &self.shaders.screen_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                // The output settings are shared with the main target
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.targets[0].output_buffer.as_entire_binding(),
+                },
+            ],
+        })
     }
 
     /// Loads a font for use in the text renderer.
-    pub fn load_font<F>(&mut self, font: F, size: FontSize) -> FontId
+    ///
+    /// Returns an error if the font can't be scaled to the given size.
+    pub fn load_font<F>(&mut self, font: F, size: FontSize) -> Result<FontId, Error>
     where
         F: Font + Send + Sync + 'static,
     {
-        self.fonts.load(FontArc::new(font), size)
+        let font = self.fonts.load(FontArc::new(font), size)?;
+        self.fonts.get_mut(font)?.glyph_scale_factor = self.scale_factor;
+        Ok(font)
     }
 
     /// Loads a font for use in the text renderer with sdf rendering.
@@ -575,323 +1917,1560 @@ impl TextRenderer {
     /// pixellation, and can have effects applied to it. However, creating the textures for each
     /// character will take longer and the textures will take up more space on the GPU. So if you
     /// don't need any of these effects, use [TextRenderer::load_font] instead.
+    ///
+    /// Returns an error if the font can't be scaled to the given size, or if the device the
+    /// renderer was built with doesn't have the features needed for the settings'
+    /// [format](SdfSettings::format).
     pub fn load_font_with_sdf<F>(
         &mut self,
         font: F,
         size: FontSize,
         sdf_settings: SdfSettings,
-    ) -> FontId
+    ) -> Result<FontId, Error>
     where
         F: Font + Send + Sync + 'static,
     {
-        self.fonts
-            .load_with_sdf(FontArc::new(font), size, sdf_settings)
-    }
-
-    /// Draws a [Text] object to the given render pass.
-    pub fn draw_text<'pass>(
-        &'pass self,
-        render_pass: &mut wgpu::RenderPass<'pass>,
-        text: &'pass Text,
-    ) {
-        // Set the pipeline depending on if the font uses sdf
-        let use_sdf = self.font_uses_sdf(text.data.font);
-        let use_outline = text.data.sdf.is_some_and(|sdf| sdf.outline.is_some());
-
-        if use_sdf {
-            render_pass.set_pipeline(&self.sdf_pipeline);
-        } else {
-            render_pass.set_pipeline(&self.basic_pipeline);
+        let mut sdf_settings = sdf_settings;
+
+        // 16 bit normalised textures need a feature that downlevel adapters don't have
+        if self.compatibility && sdf_settings.format == SdfFormat::R16Unorm {
+            warn!(
+                "R16Unorm distance fields aren't used in compatibility mode, using R8Unorm instead"
+            );
+            sdf_settings.format = SdfFormat::R8Unorm;
         }
 
-        let font_data = self.fonts.get(text.data.font);
-
-        render_pass.set_bind_group(0, &self.screen_bind_group, &[]);
-        render_pass.set_bind_group(2, &text.settings_bind_group, &[]);
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.set_vertex_buffer(1, text.instance_buffer.slice(..));
-
-        if use_outline {
-            render_pass.set_pipeline(&self.outline_pipeline);
-
-            let mut i = 0;
-            for c in text.data.text.lines().flat_map(|s| s.chars()) {
-                let char_data = font_data.char_cache.get(&c).unwrap();
-
-                if let Some(texture) = &char_data.texture {
-                    render_pass.set_bind_group(1, &texture.bind_group, &[]);
-                    render_pass.draw(0..4, i as u32..i as u32 + 1);
-                    i += 1;
-                }
-            }
-
-            render_pass.set_pipeline(&self.sdf_pipeline);
+        let features = sdf_settings.format.required_features();
+        if !self.device_features.contains(features) {
+            return Err(Error::MissingFeatures(features - self.device_features));
         }
 
-        let mut i = 0;
-        for c in text.data.text.lines().flat_map(|s| s.chars()) {
-            let char_data = font_data.char_cache.get(&c).unwrap();
-
-            if let Some(texture) = &char_data.texture {
-                render_pass.set_bind_group(1, &texture.bind_group, &[]);
-                render_pass.draw(0..4, i as u32..i as u32 + 1);
-                i += 1;
-            }
-        }
+        self.fonts
+            .load_with_sdf(FontArc::new(font), size, sdf_settings)
     }
 
-    /// Returns whether a given font was loaded with sdf enabled.
-    pub fn font_uses_sdf(&self, font: FontId) -> bool {
-        self.fonts.get(font).sdf_settings.is_some()
-    }
-
-    fn create_text_instances(&self, text: &TextData) -> Vec<CharacterInstance> {
-        let mut position = [0., 0.];
-        let scale = text.scale;
-        let font = self.fonts.get(text.font);
-        let char_cache = &font.char_cache;
-        let scaled_font = font.font.as_scaled(font.scale);
-        let ascent = scaled_font.ascent() * scale;
-        let descent = scaled_font.descent() * scale;
-        let line_gap = scaled_font.line_gap();
-
-        let mut instances: Vec<CharacterInstance> = text
-            .text
-            .lines()
-            .flat_map(|line| {
-                let mut instances = Vec::new();
-                for c in line.chars() {
-                    let char_data = char_cache.get(&c).unwrap();
-
-                    if let Some(texture) = char_data.texture.as_ref() {
-                        let x = position[0] + texture.position[0] * scale;
-                        let y = position[1] + texture.position[1] * scale;
-
-                        let w = texture.size[0] * scale;
-                        let h = texture.size[1] * scale;
-
-                        instances.push(CharacterInstance {
-                            position: [x, y],
-                            size: [w, h],
-                        });
-                    }
-
-                    position[0] += char_data.advance * scale;
-                }
-
-                // Apply horizontal alignment line by line
-                let text_width = position[0];
-                let h_offset = -text_width * text.halign.proportion();
-
-                for instance in &mut instances {
-                    instance.position[0] += h_offset;
-                }
-
-                // Reset position for the next line
-                position[0] = 0.;
-                position[1] += ascent - descent + line_gap;
+    /// Loads a font for use in the text renderer, with the given options.
+    ///
+    /// Unlike [TextRenderer::load_font] and [TextRenderer::load_font_with_sdf], this can also
+    /// cache characters as soon as the font is loaded (see [FontOptions::with_preload]), which is
+    /// why it needs access to the device and queue.
+    pub fn load_font_with_options<F>(
+        &mut self,
+        font: F,
+        options: FontOptions,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<FontId, Error>
+    where
+        F: Font + Send + Sync + 'static,
+    {
+        let font = match options.sdf {
+            Some(sdf_settings) => self.load_font_with_sdf(font, options.size, sdf_settings)?,
+            None => self.load_font(font, options.size)?,
+        };
 
-                instances
-            })
-            .collect_vec();
+        let sampler = options.sampler.map(|settings| {
+            let label = self.label("font character sampler");
+            settings.create_sampler(&label, wgpu::FilterMode::Linear, self.compatibility, device)
+        });
 
-        // Apply vertical alignment to the whole text
+        let font_data = self.fonts.get_mut(font)?;
+        font_data.sampler = sampler;
+        font_data.supersampling = options.supersampling;
+        font_data.glyph_padding = options.glyph_padding;
+        font_data.missing_glyphs = options.missing_glyphs;
+        font_data.texture_usages = options.texture_usages;
 
-        let v_offset = match text.valign {
-            VerticalAlignment::Baseline => 0.,
-            VerticalAlignment::Top => ascent,
-            VerticalAlignment::Middle => ascent - (ascent - descent) * 0.5,
-            VerticalAlignment::Bottom => descent,
-            VerticalAlignment::Ratio(r) => ascent - (ascent - descent) * r.clamp(0., 1.),
-        };
+        if options.sdf.is_none() {
+            font_data.subpixel_phases = options.subpixel_phases;
+        }
 
-        for instance in &mut instances {
-            instance.position[1] += v_offset;
+        #[cfg(feature = "swash")]
+        if options.hinting && options.sdf.is_none() {
+            let font_data = self.fonts.get_mut(font)?;
+
+            if font_data
+                .font
+                .as_ref()
+                .is_some_and(hinting::supports_hinting)
+            {
+                font_data.hinting = true;
+                // Hinted outlines are snapped to the pixel grid, so they can't be offset
+                font_data.subpixel_phases = 1;
+            } else {
+                warn!("The font couldn't be read by swash, so it won't be hinted");
+            }
         }
 
-        instances
+        self.generate_char_textures(options.preload.chars().into_iter(), font, device, queue)?;
+        Ok(font)
     }
 
-    /// Creates and caches the character textures necessary to draw a certain string with a given
-    /// font.
-    ///
-    /// This is called every time a new [Text] is created, but you might also want to call
-    /// it yourself if you know you're going to be displaying some text in the future and want to
-    /// generate the character textures in advance.
+    /// Loads a font made of pre-generated signed distance fields, such as an atlas made by an
+    /// external tool (see [SdfAtlas]).
     ///
-    /// For example, if you are making a game with a score display that might change every frame,
-    /// you might want to cache all the characters from '0' to '9' beforehand to save this from
-    /// happening between frames.
-    pub fn generate_char_textures(
+    /// All the glyphs in the atlas are uploaded to the GPU straight away, so no distance fields
+    /// will have to be generated when drawing with this font. However, only the characters in the
+    /// atlas can be drawn; any others will be drawn as nothing (or as boxes, see
+    /// [TextRenderer::set_missing_glyphs]).
+    pub fn load_sdf_atlas(
         &mut self,
-        chars: impl Iterator<Item = char>,
-        font: FontId,
+        atlas: &SdfAtlas,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-    ) {
-        let char_data = {
-            let font_data = self.fonts.get(font);
-            let new_characters = chars
-                .filter(|c| !font_data.char_cache.contains_key(c))
-                .unique()
-                .collect_vec();
-
-            let font = &font_data.font;
-            let scale = font_data.scale;
-            let sdf = font_data.sdf_settings.as_ref();
-
-            new_characters
-                .into_par_iter()
-                .map(|c| {
-                    let data = match sdf {
-                        None => self.create_char_texture(c, font, scale, device, queue),
-                        Some(sdf) => {
-                            self.create_char_texture_sdf(c, font, scale, sdf, device, queue)
-                        }
-                    };
-                    (c, data)
-                })
-                .collect::<Vec<_>>()
+    ) -> FontId {
+        let sdf_settings = SdfSettings {
+            radius: atlas.radius,
+            ..Default::default()
         };
 
-        self.fonts.get_mut(font).char_cache.extend(char_data);
+        let char_cache = atlas
+            .glyphs
+            .iter()
+            .map(|glyph| {
+                let texture = non_empty(&glyph.image).map(|image| {
+                    let image = fit_to_device(glyph.c.into(), image, device);
+
+                    let (texture, bind_group, texture_bytes) = self.create_char_bind_group(
+                        glyph.c.into(),
+                        &sdf_settings.format.encode(&image),
+                        image.dimensions(),
+                        sdf_settings.format.texture_format(),
+                        wgpu::FilterMode::Linear,
+                        wgpu::TextureUsages::empty(),
+                        None,
+                        device,
+                        &Uploads::Queue(queue),
+                    );
+
+                    CharTexture {
+                        bind_group,
+                        texture_bytes,
+                        texture,
+                        position: glyph.position,
+                        size: glyph.size,
+                        padding: atlas.radius,
+                        #[cfg(feature = "cpu-render")]
+                        image: cpu_render::quantized_image(&image, sdf_settings.format),
+                        #[cfg(feature = "cpu-render")]
+                        filter: wgpu::FilterMode::Linear,
+                    }
+                });
+
+                let data = Character {
+                    texture,
+                    advance: glyph.advance,
+                    missing: false,
+                };
+                (glyph.c, data)
+            })
+            .collect();
+
+        self.fonts.insert(FontData::new_prerendered(
+            atlas.px_size,
+            atlas.metrics,
+            Some(sdf_settings),
+            char_cache,
+        ))
     }
 
-    fn create_char_texture_sdf(
-        &self,
-        c: char,
-        font: &FontArc,
-        scale: PxScale,
-        sdf: &SdfSettings,
+    /// Loads a bitmap font (see [BitmapFont]).
+    ///
+    /// All the glyphs in the font are uploaded to the GPU straight away. Glyphs from BDF and PCF
+    /// fonts are sampled with nearest neighbour filtering so they stay crisp when scaled up, which
+    /// works best with whole number scales. Only the characters in the font can be drawn; any
+    /// others will be drawn as nothing (or as boxes, see [TextRenderer::set_missing_glyphs]).
+    pub fn load_bitmap_font(
+        &mut self,
+        font: &BitmapFont,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-    ) -> Character {
-        info!("Creating sdf character texture for {c}");
-        // Calculate metrics
-        let scaled = font.as_scaled(scale);
-        let glyph = font.glyph_id(c).with_scale(scale);
+    ) -> FontId {
+        let char_cache = font
+            .glyphs
+            .iter()
+            .map(|glyph| {
+                let texture = non_empty(&glyph.image).map(|image| {
+                    self.create_raster_char_texture(
+                        glyph.c.into(),
+                        &[(image, glyph.position)],
+                        1,
+                        font.filter,
+                        font_options::DEFAULT_GLYPH_PADDING,
+                        wgpu::TextureUsages::empty(),
+                        None,
+                        device,
+                        &Uploads::Queue(queue),
+                    )
+                });
+
+                let data = Character {
+                    texture,
+                    advance: glyph.advance,
+                    missing: false,
+                };
+                (glyph.c, data)
+            })
+            .collect();
 
-        let advance = scaled.h_advance(glyph.id);
+        self.fonts.insert(FontData {
+            kerning: font.kerning.clone(),
+            filter: font.filter,
+            ..FontData::new_prerendered(font.px_size, font.metrics, None, char_cache)
+        })
+    }
 
-        let texture = scaled.outline_glyph(glyph).map(|outlined| {
-            let px_bounds = outlined.px_bounds();
-            let width = px_bounds.width().ceil() as u32;
-            let height = px_bounds.height().ceil() as u32;
-            let mut x = px_bounds.min.x;
-            let mut y = px_bounds.min.y;
+    /// Returns the signed distance field that kaku generates for a character, as an image.
+    ///
+    /// The image uses the same convention as most external sdf tools: the inside of the glyph is
+    /// bright, the outside is dark, and the edge of the glyph is at a value of 128. This can be
+    /// used to bake kaku's distance fields into an offline atlas (see also
+    /// [TextRenderer::export_sdf_atlas]).
+    ///
+    /// Returns None if the font doesn't use sdf, the character has nothing to draw (e.g. a space),
+    /// or the font was made from pre-generated glyphs (e.g. with [TextRenderer::load_sdf_atlas]).
+    /// Also returns None if the font isn't loaded in this renderer.
+    pub fn export_sdf(&self, font: FontId, c: char) -> Option<GrayImage> {
+        let font_data = self.fonts.get(font).ok()?;
+        let sdf = font_data.sdf_settings.as_ref()?;
+        let outlines = font_data.font.as_ref()?;
+
+        let (image, _) = self.create_sdf_image(c.into(), outlines, font_data, sdf)?;
+        Some(atlas::export_image(&image))
+    }
 
-            let mut image = image::GrayImage::new(width, height);
-            outlined.draw(|x, y, val| image.put_pixel(x, y, image::Luma([(val * 255.) as u8])));
+    /// Exports the signed distance fields for every character currently cached for a font, as an
+    /// [SdfAtlas].
+    ///
+    /// The atlas can be saved with [SdfAtlas::to_bmfont] and loaded again later with
+    /// [SdfAtlas::from_bmfont] and [TextRenderer::load_sdf_atlas], so that the distance fields
+    /// never have to be generated at runtime.
+    ///
+    /// Glyphs registered with [TextRenderer::register_glyph] aren't included. Returns None if the
+    /// font doesn't use sdf, was made from pre-generated glyphs, or isn't loaded in this renderer.
+    pub fn export_sdf_atlas(&self, font: FontId) -> Option<SdfAtlas> {
+        let font_data = self.fonts.get(font).ok()?;
+        let sdf = font_data.sdf_settings.as_ref()?;
+        let outlines = font_data.font.as_ref()?;
+
+        // Registered glyphs aren't the font's, so they're left for the user to register again
+        let glyphs = font_data
+            .char_cache
+            .iter()
+            .filter(|(c, _)| !font_data.custom_glyphs.contains_key(c))
+            .sorted_by_key(|(c, _)| **c)
+            .map(|(&c, character)| {
+                let (image, position) = self
+                    .create_sdf_image(c.into(), outlines, font_data, sdf)
+                    .map(|(image, position)| (Some(image), position))
+                    .unwrap_or((None, [0.; 2]));
+
+                AtlasGlyph {
+                    c,
+                    size: image
+                        .as_ref()
+                        .map(|image| [image.width() as f32, image.height() as f32])
+                        .unwrap_or([0.; 2]),
+                    image,
+                    position,
+                    advance: character.advance,
+                }
+            })
+            .collect();
+
+        Some(SdfAtlas {
+            glyphs,
+            px_size: font_data.px_size,
+            radius: sdf.radius,
+            metrics: font_data.metrics,
+        })
+    }
 
-            let (sdf_image, padding) = create_sdf_texture(&image, (width, height), sdf);
+    /// Records the changes texts have made since the last call into a command encoder, through
+    /// the renderer's staging belt (see [TextRendererBuilder::with_staging_belt]).
+    ///
+    /// The encoder has to be submitted before (or be the same encoder as) any render pass that
+    /// draws the changed texts. This does nothing if the renderer doesn't have a staging belt.
+    pub fn finish_uploads(&mut self, encoder: &mut wgpu::CommandEncoder, device: &wgpu::Device) {
+        if let Some(staging) = &mut self.staging {
+            staging.finish(encoder, device);
+        }
+    }
+
+    /// Lets the staging belt reuse the buffers of uploads that have been submitted. Call this
+    /// after submitting the encoder passed to [TextRenderer::finish_uploads].
+    pub fn recall_uploads(&mut self) {
+        if let Some(staging) = &mut self.staging {
+            staging.recall();
+        }
+    }
+
+    /// Draws a [Text] object to the given render pass.
+    ///
+    /// If some of the text's characters aren't cached any more (e.g. after
+    /// [TextRenderer::clear_cache]), they are skipped and a warning is logged. Use
+    /// [TextRenderer::prepare_text] to generate them again.
+    ///
+    /// To time the draws with wgpu-profiler, use [TextRenderer::draw_text_profiled] (with the
+    /// `wgpu-profiler` feature) instead.
+    ///
+    /// Returns an error if the text's font isn't loaded in this renderer, in which case nothing is
+    /// drawn.
+    pub fn draw_text<'pass>(
+        &'pass self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        text: &'pass Text,
+    ) -> Result<(), Error> {
+        let target = &self.targets[0];
+        self.draw_text_with_screen(
+            render_pass,
+            text,
+            &target.pipelines,
+            &target.screen_bind_group,
+            Profiling::NONE,
+        )
+    }
+
+    /// Draws a [Text] object to the given render pass like [TextRenderer::draw_text], timing it in
+    /// a scope of `profiler` labelled with the start of the text and its font. If the text has an
+    /// outline, its pass gets a scope of its own inside that one.
+    ///
+    /// The scopes are only timed if the device has
+    /// [wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES]; without it they're skipped, as in the rest
+    /// of wgpu-profiler.
+    #[cfg(feature = "wgpu-profiler")]
+    pub fn draw_text_profiled<'pass>(
+        &'pass self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        text: &'pass Text,
+        profiler: &wgpu_profiler::GpuProfiler,
+        device: &wgpu::Device,
+    ) -> Result<(), Error> {
+        let target = &self.targets[0];
+        self.draw_text_with_screen(
+            render_pass,
+            text,
+            &target.pipelines,
+            &target.screen_bind_group,
+            Profiling::new(profiler, device),
+        )
+    }
 
-            image = sdf_image;
-            x -= padding as f32;
-            y -= padding as f32;
+    /// Draws a [Text] object to a render pass for one of the renderer's other targets (see
+    /// [TextRenderer::add_target]). The render pass has to match the target's [TargetConfig].
+    ///
+    /// Returns an error if the target wasn't added to this renderer or the text's font isn't
+    /// loaded in it, in which case nothing is drawn. Otherwise this works like
+    /// [TextRenderer::draw_text].
+    pub fn draw_text_for_target<'pass>(
+        &'pass self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        target: TargetId,
+        text: &'pass Text,
+    ) -> Result<(), Error> {
+        let target = self.target(target)?;
+        self.draw_text_with_screen(
+            render_pass,
+            text,
+            &target.pipelines,
+            &target.screen_bind_group,
+            Profiling::NONE,
+        )
+    }
 
-            let bind_group = self.create_char_bind_group(c, &image, device, queue);
+    /// Draws text with a target's pipelines and a screen uniform, which can be different to the
+    /// target's own, e.g. one that has been moved to the corner of an egui panel.
+    pub(crate) fn draw_text_with_screen<'pass>(
+        &'pass self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        text: &'pass Text,
+        pipelines: &'pass TargetPipelines,
+        screen_bind_group: &'pass wgpu::BindGroup,
+        profiling: Profiling<'_>,
+    ) -> Result<(), Error> {
+        let font_data = self.fonts.get(text.data.font)?;
+        let draws = self.text_draws(font_data, text);
+        let mut stats = FrameStats {
+            texts_drawn: 1,
+            ..Default::default()
+        };
+
+        // The text's scope is named after it and its font, so it can be found in the profiler's
+        // results
+        profiling.scope(
+            || text.data.debug_label(self),
+            render_pass,
+            |render_pass, profiling| {
+                // The panel goes behind everything else
+                if let Some(panel) = &text.panel_buffers {
+                    render_pass.set_pipeline(&pipelines.panel);
+                    render_pass.set_bind_group(0, screen_bind_group, &[]);
+                    render_pass.set_bind_group(1, &panel.bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, panel.instance_buffer.slice(..));
+                    render_pass.draw(0..4, 0..1);
+                    stats.instances += 1;
+                    stats.draw_calls += 1;
+                    stats.bind_group_switches += 2;
+                }
+
+                // Set the pipeline depending on if the font uses sdf
+                let use_sdf = font_data.sdf_settings.is_some();
+                let use_outline = text.data.sdf.is_some_and(|sdf| sdf.outline.is_some());
+
+                if use_sdf {
+                    render_pass.set_pipeline(&pipelines.sdf);
+                } else {
+                    render_pass.set_pipeline(&pipelines.basic);
+                }
 
-            CharTexture {
-                bind_group,
-                size: [image.width() as f32, image.height() as f32],
-                position: [x, y],
+                render_pass.set_bind_group(0, screen_bind_group, &[]);
+                stats.bind_group_switches += 1 + text.bind_settings(render_pass) as u64;
+                // The quads' corners come from the vertex index, so the instances are the only
+                // vertex buffer
+                render_pass.set_vertex_buffer(0, text.instance_buffer.slice(..));
+                text.instances_drawn
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
+
+                if use_outline {
+                    profiling.scope(
+                        || self.label("outline"),
+                        render_pass,
+                        |render_pass, _| {
+                            render_pass.set_pipeline(&pipelines.outline);
+                            // Push constants don't survive pipeline changes on every backend
+                            // (e.g. GL)
+                            stats.bind_group_switches += text.bind_settings(render_pass) as u64;
+                            self.draw_instances(render_pass, &draws, &mut stats);
+
+                            render_pass.set_pipeline(&pipelines.sdf);
+                            stats.bind_group_switches += text.bind_settings(render_pass) as u64;
+                        },
+                    );
+                }
+
+                self.draw_instances(render_pass, &draws, &mut stats);
+            },
+        );
+
+        self.frame_counters.add(&stats);
+        Ok(())
+    }
+
+    /// Draws the instances of a text with the pipeline that's already set, one draw for each
+    /// glyph, counting them in `stats`.
+    fn draw_instances<'pass>(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        draws: &[(Range<u32>, &'pass CharTexture)],
+        stats: &mut FrameStats,
+    ) {
+        stats.bind_group_switches += draws.len() as u64;
+        stats.draw_calls += draws.len() as u64;
+        stats.instances += draws
+            .iter()
+            .map(|(range, _)| range.len() as u64)
+            .sum::<u64>();
+
+        for (instances, texture) in draws {
+            render_pass.set_bind_group(1, &texture.bind_group, &[]);
+            render_pass.draw(0..4, instances.clone());
+        }
+    }
+
+    /// Returns the draws needed for a piece of text, each with the texture it draws. Each one is a
+    /// range of instances that all draw the same glyph.
+    ///
+    /// Instances whose characters aren't cached any more are left out, so the rest of the text can
+    /// still be drawn.
+    fn text_draws<'a>(
+        &self,
+        font_data: &'a FontData,
+        text: &Text,
+    ) -> Vec<(Range<u32>, &'a CharTexture)> {
+        let mut missing = 0;
+
+        let draws = text::glyph_runs(text.instance_glyphs.iter().copied());
+
+        // Only the glyphs of the visible characters are drawn (see Text::set_visible_chars). The
+        // instances are sorted by glyph, but each glyph's instances are still in text order
+        let visible = text.visible_glyphs;
+        let glyph_of_draw = |instance: u32| text.instance_order[instance as usize];
+
+        let draws = draws.into_iter().filter_map(|(key, range)| {
+            if visible == u32::MAX {
+                return Some((key, range));
             }
+
+            let shown = range
+                .clone()
+                .take_while(|&draw| glyph_of_draw(draw) < visible);
+            let range = range.start..range.start + shown.count() as u32;
+            (!range.is_empty()).then_some((key, range))
         });
 
-        Character { texture, advance }
+        let textures = draws
+            .filter_map(|(key, range)| match font_data.character(key) {
+                Some(char_data) => Some((range, char_data.texture.as_ref()?)),
+                None => {
+                    missing += range.len();
+                    None
+                }
+            })
+            .collect();
+
+        if missing > 0 {
+            warn!(
+                "{missing} characters of the text {:?} aren't cached, so they won't be drawn. \
+                Use TextRenderer::prepare_text to generate them again",
+                text.data.text
+            );
+        }
+
+        textures
     }
 
-    fn create_char_texture(
+    /// Returns the texture for each instance of a piece of text that has one, along with the
+    /// index of the instance, in the order the GPU draws them.
+    #[cfg(feature = "cpu-render")]
+    fn instance_textures<'a>(
         &self,
+        font_data: &'a FontData,
+        text: &Text,
+    ) -> Vec<(u32, &'a CharTexture)> {
+        let draws = self.text_draws(font_data, text);
+        draws
+            .into_iter()
+            .flat_map(|(range, texture)| range.map(move |instance| (instance, texture)))
+            .collect()
+    }
+
+    /// Generates any characters of a [Text] that aren't cached any more (e.g. after
+    /// [TextRenderer::clear_cache]), so that it's drawn in full again.
+    ///
+    /// Characters are generated the same way as when the text was created, so the text's layout
+    /// doesn't change. Returns an error if the text's font isn't loaded in this renderer.
+    pub fn prepare_text(
+        &mut self,
+        text: &Text,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), Error> {
+        let uploads = Uploads::Queue(queue);
+
+        // Text laid out by another library only draws glyphs by id, so its characters are never
+        // needed
+        if text.measured.is_some() {
+            let bytes = 0..text.data.text.len();
+            self.generate_text(&text.data, bytes, device, &uploads)
+        } else {
+            let glyphs = text
+                .instance_glyphs
+                .iter()
+                .filter(|key| !matches!(key, GlyphKey::Char(_)))
+                .copied()
+                .collect_vec();
+
+            self.generate_glyph_textures(glyphs.into_iter(), text.data.font, device, &uploads)
+        }
+    }
+
+    /// Returns whether a given font was loaded with sdf enabled.
+    ///
+    /// Returns false if the font isn't loaded in this renderer.
+    pub fn font_uses_sdf(&self, font: FontId) -> bool {
+        self.fonts
+            .get(font)
+            .is_ok_and(|font_data| font_data.sdf_settings.is_some())
+    }
+
+    /// Returns the sdf radius a font was loaded with, or None if the font doesn't use sdf (or isn't
+    /// loaded in this renderer).
+    pub fn sdf_radius(&self, font: FontId) -> Option<f32> {
+        self.fonts
+            .get(font)
+            .ok()?
+            .sdf_settings
+            .map(|sdf| sdf.radius)
+    }
+
+    /// Returns whether a given font has a glyph for a character.
+    ///
+    /// Characters that the font doesn't define will be drawn as its .notdef glyph or as nothing
+    /// (see [MissingGlyphs]), so this can be used to decide whether to filter the text or use a
+    /// fallback font before creating a [Text]. Substituted characters (see
+    /// [TextRenderer::set_substitutions]) are looked up as the characters they're drawn as.
+    /// Returns false if the font isn't loaded in this renderer.
+    pub fn has_glyph(&self, font: FontId, c: char) -> bool {
+        let Ok(font_data) = self.fonts.get(font) else {
+            return false;
+        };
+        let c = font_data.substitute(c);
+
+        if font_data.custom_glyphs.contains_key(&c) {
+            return true;
+        }
+
+        match &font_data.font {
+            // Glyph 0 is always the .notdef glyph, which is what fonts give back for unknown chars
+            Some(font) => font.glyph_id(c).0 != 0,
+            None => font_data
+                .char_cache
+                .get(&c)
+                .is_some_and(|char_data| !char_data.missing),
+        }
+    }
+
+    /// Changes how characters that a font doesn't have a glyph for are drawn (see
+    /// [MissingGlyphs]).
+    ///
+    /// This can be used with fonts that aren't loaded with [FontOptions], such as bitmap fonts.
+    /// Any missing characters that were already cached are generated again the next time they're
+    /// needed, so [Text] objects created before the change should be rebuilt (or prepared again
+    /// with [TextRenderer::prepare_text]).
+    pub fn set_missing_glyphs(
+        &mut self,
+        font: FontId,
+        missing_glyphs: MissingGlyphs,
+    ) -> Result<(), Error> {
+        let font_data = self.fonts.get_mut(font)?;
+        font_data.missing_glyphs = missing_glyphs;
+        font_data
+            .char_cache
+            .retain(|_, char_data| !char_data.missing);
+
+        if let Some(cache) = &self.layout_cache {
+            cache.clear_font(font);
+        }
+        Ok(())
+    }
+
+    /// Makes a font draw some characters as others, e.g. `'"'` as `'”'` for curly quotes, or `'|'`
+    /// as a separator glyph the font keeps in a private use character. This replaces any
+    /// substitutions the font had before.
+    ///
+    /// The substitutions are made when text is laid out and its characters are generated, so the
+    /// positions of a text's characters are still byte indices into the text it was built with,
+    /// but its [glyphs](Text::glyphs) are the characters that are drawn. [Text] objects created
+    /// before the change keep their old characters until they're rebuilt (or their text is set
+    /// again). Text laid out by cosmic-text chooses its own glyphs, so it isn't affected.
+    ///
+    /// Returns an error if the font isn't loaded in this renderer.
+    pub fn set_substitutions(
+        &mut self,
+        font: FontId,
+        substitutions: std::collections::HashMap<char, char>,
+    ) -> Result<(), Error> {
+        self.fonts.get_mut(font)?.substitutions = substitutions.into_iter().collect();
+
+        if let Some(cache) = &self.layout_cache {
+            cache.clear_font(font);
+        }
+        Ok(())
+    }
+
+    /// Sets the fonts that draw text a font doesn't have glyphs for, in order of preference, e.g.
+    /// a CJK font for Japanese text mixed into English. This replaces any fallback fonts the font
+    /// had before.
+    ///
+    /// Text is split into runs of one script (Latin, Greek, Han etc.), and each run is drawn with
+    /// the first font that has all of its characters, starting with the font itself. Words are
+    /// never split between fonts this way, and spaces, digits and punctuation are drawn in the
+    /// same font as the text around them. If no font has a whole run, each of its characters is
+    /// drawn with the first font that has it.
+    ///
+    /// The fallback fonts are scaled to the same em size as the font, and their glyphs are
+    /// generated with the font's settings (sdf, hinting etc.). The lines are still spaced by the
+    /// font's own metrics. [Text] objects created before the change should be rebuilt.
+    ///
+    /// Returns an error if the font isn't loaded in this renderer, or if one of the fallback fonts
+    /// can't be scaled.
+    pub fn set_fallback_fonts(
+        &mut self,
+        font: FontId,
+        fallbacks: Vec<FontArc>,
+    ) -> Result<(), Error> {
+        self.fonts.get_mut(font)?.set_fallbacks(fallbacks)?;
+
+        if let Some(cache) = &self.layout_cache {
+            cache.clear_font(font);
+        }
+        Ok(())
+    }
+
+    /// Draws a character of a font with an image instead of its glyph, e.g. to put icons in a
+    /// private use character like `'\u{E000}'` and draw them in the middle of text. The image is
+    /// the glyph's coverage at the size the font was loaded at, and is drawn in the text's colour.
+    /// The metrics say where it goes and how far it moves the pen (see [GlyphMetrics]).
+    ///
+    /// The glyph is generated like any other character the next time it's needed, so it's scaled
+    /// up for the screen's scale factor and turned into a distance field for sdf fonts. [Text]
+    /// objects created before it was registered keep their old character until they're rebuilt.
+    ///
+    /// Returns an error if the font isn't loaded in this renderer.
+    pub fn register_glyph(
+        &mut self,
+        font: FontId,
         c: char,
-        font: &FontArc,
-        scale: PxScale,
+        image: GrayImage,
+        metrics: GlyphMetrics,
+    ) -> Result<(), Error> {
+        let font_data = self.fonts.get_mut(font)?;
+        font_data.char_cache.remove(&c);
+        font_data
+            .custom_glyphs
+            .insert(c, CustomGlyph { image, metrics });
+
+        if let Some(cache) = &self.layout_cache {
+            cache.clear_font(font);
+        }
+        Ok(())
+    }
+
+    /// Returns an iterator over all the characters that currently have cached textures for a
+    /// given font.
+    ///
+    /// The characters are returned in no particular order. If the font isn't loaded in this
+    /// renderer, the iterator is empty.
+    pub fn cached_chars(&self, font: FontId) -> impl Iterator<Item = char> + '_ {
+        self.fonts
+            .get(font)
+            .into_iter()
+            .flat_map(|font_data| font_data.char_cache.keys().copied())
+    }
+
+    /// Returns the cached texture of a character, for running your own GPU passes over it (see
+    /// [FontOptions::with_texture_usages]).
+    ///
+    /// Raster fonts' textures hold coverage (with any subpixel phases stacked from top to bottom),
+    /// and sdf fonts' hold the distance field, where 0.5 is on the edge of the glyph. Anything
+    /// written to the texture shows up the next time the character is drawn, until the character
+    /// is generated again (e.g. after [TextRenderer::clear_cache]).
+    ///
+    /// Returns None if the character isn't cached, or doesn't have a texture (like a space), and
+    /// an error if the font isn't loaded in this renderer.
+    pub fn glyph_texture(
+        &self,
+        font: FontId,
+        c: char,
+    ) -> Result<Option<Arc<wgpu::Texture>>, Error> {
+        let texture = (self.fonts.get(font)?.character(GlyphKey::Char(c)))
+            .and_then(|character| character.texture.as_ref())
+            .map(|texture| texture.texture.clone());
+
+        Ok(texture)
+    }
+
+    /// Returns how much GPU memory this renderer is using for glyph textures, and how much the
+    /// texts it built are using for their buffers.
+    ///
+    /// Texts stop being counted when they're dropped, but glyph textures are kept until the cache
+    /// is cleared (see [TextRenderer::clear_cache]), so a steadily growing `glyph_textures` means
+    /// characters are being cached and never evicted.
+    pub fn memory_usage(&self) -> MemoryReport {
+        let glyph_textures = (self.fonts.fonts.iter())
+            .flat_map(|font_data| {
+                font_data
+                    .char_cache
+                    .values()
+                    .chain(font_data.glyph_cache.values())
+                    .chain(font_data.fallback_cache.values())
+            })
+            .filter_map(|character| character.texture.as_ref())
+            .map(|texture| texture.texture_bytes)
+            .sum();
+
+        let target_buffers: u64 = (self.targets.iter())
+            .map(|target| target.screen_buffer.size() + target.output_buffer.size())
+            .sum();
+        let shared_settings = self.settings_pool.as_ref().map_or(0, SettingsPool::size);
+
+        MemoryReport {
+            glyph_textures,
+            instance_buffers: self.memory.instance_buffers.load(Ordering::Relaxed),
+            uniform_buffers: self.memory.uniform_buffers.load(Ordering::Relaxed)
+                + target_buffers
+                + shared_settings,
+        }
+    }
+
+    /// Returns what the renderer has drawn and uploaded since [TextRenderer::reset_frame_stats]
+    /// was last called (or since it was built), e.g. for a performance HUD.
+    ///
+    /// Draws of [Text]s (including in egui) and [BakedText]s are counted when they're recorded
+    /// into a render pass, and uploads when they're written. Debug overlays and cache views aren't
+    /// counted.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_counters.load()
+    }
+
+    /// Sets all of the renderer's [frame stats](TextRenderer::frame_stats) back to 0, e.g. at the
+    /// start of each frame.
+    pub fn reset_frame_stats(&self) {
+        self.frame_counters.reset();
+    }
+
+    /// Clears all cached character textures for a given font, freeing their memory on the GPU.
+    ///
+    /// This is useful for long-running apps that want to drop characters they no longer need,
+    /// e.g. when switching between scenes. Any [Text] objects that use this font will need their
+    /// characters to be generated again (e.g. with [TextRenderer::prepare_text]) before they are
+    /// drawn in full; until then the missing characters are skipped.
+    pub fn clear_cache(&mut self, font: FontId) -> Result<(), Error> {
+        self.fonts.get_mut(font)?.clear_cache();
+
+        if let Some(cache) = &self.layout_cache {
+            cache.clear_font(font);
+        }
+        Ok(())
+    }
+
+    /// Lays out a piece of text, finding the instance for each character that has something to
+    /// draw and the box of each line. The characters have to be cached.
+    pub(crate) fn create_text_layout(&self, text: TextData) -> Result<TextLayout, Error> {
+        let font_data = self.fonts.get(text.font)?;
+
+        match &self.layout_cache {
+            Some(cache) => cache.get_or_insert(text, |text| font_data.layout(text)),
+            None => font_data.layout(text),
+        }
+    }
+
+    /// The number of layouts in the renderer's layout cache (see
+    /// [TextRendererBuilder::with_layout_cache]).
+    pub fn cached_layout_count(&self) -> usize {
+        self.layout_cache.as_ref().map_or(0, LayoutCache::len)
+    }
+
+    /// Forgets every layout in the renderer's layout cache (see
+    /// [TextRendererBuilder::with_layout_cache]).
+    ///
+    /// This doesn't need to be called when fonts change, since the layouts of a font are forgotten
+    /// whenever its cached characters are cleared.
+    pub fn clear_layout_cache(&self) {
+        if let Some(cache) = &self.layout_cache {
+            cache.clear();
+        }
+    }
+
+    /// Creates and caches the character textures necessary to draw a certain string with a given
+    /// font.
+    ///
+    /// This is called every time a new [Text] is created, but you might also want to call
+    /// it yourself if you know you're going to be displaying some text in the future and want to
+    /// generate the character textures in advance.
+    ///
+    /// For example, if you are making a game with a score display that might change every frame,
+    /// you might want to cache all the characters from '0' to '9' beforehand to save this from
+    /// happening between frames.
+    ///
+    /// Returns an error if the font isn't loaded in this renderer.
+    pub fn generate_char_textures(
+        &mut self,
+        chars: impl Iterator<Item = char>,
+        font: FontId,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-    ) -> Character {
-        info!("Creating character texture for {c}");
-        // Calculate metrics
-        let scaled = font.as_scaled(scale);
-        let glyph = font.glyph_id(c).with_scale(scale);
+    ) -> Result<(), Error> {
+        self.generate_chars(chars, font, device, &Uploads::Queue(queue))
+    }
 
-        let advance = scaled.h_advance(glyph.id);
+    /// Like [TextRenderer::generate_char_textures], but the textures are uploaded by copies
+    /// recorded into `encoder` instead of being written to the queue straight away.
+    ///
+    /// This is for engines that schedule all their GPU work themselves. The characters are cached
+    /// as soon as this returns, so the encoder has to be submitted before any text using them is
+    /// drawn, or they'll be drawn as garbage.
+    pub fn generate_char_textures_with_encoder(
+        &mut self,
+        chars: impl Iterator<Item = char>,
+        font: FontId,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> Result<(), Error> {
+        let uploads = Uploads::deferred();
+        let result = self.generate_chars(chars, font, device, &uploads);
+        uploads.record(encoder);
+        result
+    }
+
+    /// Creates and caches the character textures for some characters, uploading them to wherever
+    /// `uploads` says.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(font = font.0))
+    )]
+    pub(crate) fn generate_chars(
+        &mut self,
+        chars: impl Iterator<Item = char>,
+        font: FontId,
+        device: &wgpu::Device,
+        uploads: &Uploads,
+    ) -> Result<(), Error> {
+        let char_data = {
+            let font_data = self.fonts.get(font)?;
+            let new_characters = chars
+                .map(|c| font_data.substitute(c))
+                .filter(|c| !font_data.char_cache.contains_key(c))
+                .unique()
+                .collect_vec();
+
+            let create_char = |c: char| {
+                if let Some(glyph) = font_data.custom_glyphs.get(&c) {
+                    return (
+                        c,
+                        self.create_custom_char(c, glyph, font_data, device, uploads),
+                    );
+                }
+
+                let missing = match &font_data.font {
+                    // Glyph 0 is always the .notdef glyph
+                    Some(font) => font.glyph_id(c).0 == 0,
+                    None => true,
+                };
+
+                let data = match &font_data.font {
+                    _ if missing && !c.is_control() => {
+                        self.create_missing_char(c, font_data, device, uploads)
+                    }
+                    Some(font) => self.create_char(c.into(), font, font_data, device, uploads),
+                    None => Character {
+                        texture: None,
+                        advance: 0.,
+                        missing,
+                    },
+                };
+
+                (c, Character { missing, ..data })
+            };
 
-        let texture = scaled.outline_glyph(glyph).map(|outlined| {
-            let px_bounds = outlined.px_bounds();
-            let width = px_bounds.width().ceil() as u32;
-            let height = px_bounds.height().ceil() as u32;
-            let x = px_bounds.min.x;
-            let y = px_bounds.min.y;
+            self.map_chars(new_characters, create_char)
+        };
+
+        self.fonts.get_mut(font)?.char_cache.extend(char_data);
+        Ok(())
+    }
 
-            let mut image = image::GrayImage::new(width, height);
-            outlined.draw(|x, y, val| image.put_pixel(x, y, image::Luma([(val * 255.) as u8])));
+    /// Creates and caches everything the lines of a byte range of a text need to be laid out: its
+    /// characters, the glyphs its clusters are drawn as (see [cluster]), and the glyphs of the
+    /// fallback fonts that draw the runs the font doesn't have (see [itemize]).
+    pub(crate) fn generate_text(
+        &mut self,
+        data: &TextData,
+        bytes: Range<usize>,
+        device: &wgpu::Device,
+        uploads: &Uploads,
+    ) -> Result<(), Error> {
+        let (chars, glyphs) = self.fonts.get_mut(data.font)?.text_glyphs(data, bytes);
+        self.generate_chars(chars.into_iter(), data.font, device, uploads)?;
+        self.generate_glyph_textures(glyphs.into_iter(), data.font, device, uploads)
+    }
 
-            let bind_group = self.create_char_bind_group(c, &image, device, queue);
+    /// Creates and caches the textures for glyphs chosen by id, for text that was laid out by
+    /// another library rather than by kaku, for clusters drawn as ligatures, and for glyphs of
+    /// fallback fonts.
+    ///
+    /// Fonts made from pre-rendered glyphs don't know their glyph ids, so their glyphs are drawn as
+    /// nothing.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(font = font.0))
+    )]
+    pub(crate) fn generate_glyph_textures(
+        &mut self,
+        glyphs: impl Iterator<Item = GlyphKey>,
+        font: FontId,
+        device: &wgpu::Device,
+        uploads: &Uploads,
+    ) -> Result<(), Error> {
+        let glyph_data = {
+            let font_data = self.fonts.get(font)?;
+            let new_glyphs = glyphs
+                .filter(|&key| font_data.character(key).is_none())
+                .unique()
+                .collect_vec();
 
-            CharTexture {
-                bind_group,
-                size: [image.width() as f32, image.height() as f32],
-                position: [x, y],
+            let create_glyph = |key: GlyphKey| {
+                let data = match font_data.key_font(key) {
+                    Some(font) => self.create_char(key, font, font_data, device, uploads),
+                    None => Character {
+                        texture: None,
+                        advance: 0.,
+                        missing: true,
+                    },
+                };
+
+                (key, data)
+            };
+
+            self.map_chars(new_glyphs, create_glyph)
+        };
+
+        let font_data = self.fonts.get_mut(font)?;
+        for (key, data) in glyph_data {
+            font_data.cache(key, data);
+        }
+        Ok(())
+    }
+
+    /// Runs a function over a list of characters, in parallel if the `rayon` feature is enabled.
+    #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+    fn map_chars<C: Send, T: Send>(
+        &self,
+        chars: Vec<C>,
+        f: impl Fn(C) -> T + Sync + Send,
+    ) -> Vec<T> {
+        match &self.thread_pool {
+            Some(pool) => pool.install(|| chars.into_par_iter().map(f).collect()),
+            None => chars.into_par_iter().map(f).collect(),
+        }
+    }
+
+    /// Runs a function over a list of characters, in parallel if the `rayon` feature is enabled.
+    #[cfg(any(not(feature = "rayon"), target_arch = "wasm32"))]
+    fn map_chars<C, T>(&self, chars: Vec<C>, f: impl Fn(C) -> T) -> Vec<T> {
+        chars.into_iter().map(f).collect()
+    }
+
+    /// Sets the thread pool that character textures are generated on.
+    ///
+    /// By default, kaku generates textures on rayon's global thread pool. If your program manages
+    /// its own pool, you can pass it here so kaku's work is scheduled alongside the rest of your
+    /// program's. Passing None goes back to using the global pool.
+    ///
+    /// The pool has to come from the same version of rayon as kaku's, which is re-exported as
+    /// [kaku::rayon](crate::rayon).
+    #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+    pub fn set_thread_pool(&mut self, pool: Option<std::sync::Arc<rayon::ThreadPool>>) {
+        self.thread_pool = pool;
+    }
+
+    /// Creates and caches the character textures for every character in a range, e.g.
+    /// `'a'..='z'` or a [UnicodeBlock] such as [UnicodeBlock::Hiragana].
+    ///
+    /// Control characters in the range are skipped, since they are never drawn.
+    ///
+    /// See [TextRenderer::generate_char_textures] for more details.
+    pub fn generate_char_range(
+        &mut self,
+        range: impl Into<RangeInclusive<char>>,
+        font: FontId,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), Error> {
+        let chars = range.into().filter(|c| !c.is_control());
+        self.generate_char_textures(chars, font, device, queue)
+    }
+
+    /// Creates the texture for a character from the font's outlines.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(glyph = %key, sdf = font_data.sdf_settings.is_some())
+        )
+    )]
+    fn create_char(
+        &self,
+        key: GlyphKey,
+        font: &FontArc,
+        font_data: &FontData,
+        device: &wgpu::Device,
+        uploads: &Uploads,
+    ) -> Character {
+        match &font_data.sdf_settings {
+            None => self.create_char_texture(key, font, font_data, device, uploads),
+            Some(sdf) => self.create_char_texture_sdf(key, font, font_data, sdf, device, uploads),
+        }
+    }
+
+    /// Creates a character that the font doesn't have a glyph for, drawing it as set by the font's
+    /// [MissingGlyphs] setting.
+    fn create_missing_char(
+        &self,
+        c: char,
+        font_data: &FontData,
+        device: &wgpu::Device,
+        uploads: &Uploads,
+    ) -> Character {
+        match (font_data.missing_glyphs, &font_data.font) {
+            (MissingGlyphs::Font, Some(font)) => {
+                self.create_char(c.into(), font, font_data, device, uploads)
+            }
+            // Fonts without outlines can't create new characters, so anything that wasn't in the
+            // font to begin with is drawn as nothing
+            (MissingGlyphs::Font, None) => {
+                warn!("Character {c:?} is not in the font and will not be drawn");
+                Character {
+                    texture: None,
+                    advance: 0.,
+                    missing: true,
+                }
             }
+            (MissingGlyphs::Notdef, _) => self.create_notdef_char(c, font_data, device, uploads),
+            (MissingGlyphs::HexBox, _) => {
+                let px_size = font_data.px_size * font_data.glyph_scale_factor;
+                let (image, position, advance) = fallback_glyph::hex_box(c, px_size);
+                let texture = self.create_generated_char_texture(
+                    c.into(),
+                    &image,
+                    position,
+                    font_data,
+                    device,
+                    uploads,
+                );
+
+                Character {
+                    texture: Some(texture),
+                    advance,
+                    missing: true,
+                }
+            }
+        }
+    }
+
+    /// Creates a character from an image registered with [TextRenderer::register_glyph].
+    fn create_custom_char(
+        &self,
+        c: char,
+        glyph: &CustomGlyph,
+        font_data: &FontData,
+        device: &wgpu::Device,
+        uploads: &Uploads,
+    ) -> Character {
+        let (image, position, advance) = glyph.scaled(font_data.glyph_scale_factor);
+        let texture = (image.width() > 0 && image.height() > 0).then(|| {
+            self.create_generated_char_texture(
+                c.into(),
+                &image,
+                position,
+                font_data,
+                device,
+                uploads,
+            )
         });
 
-        Character { texture, advance }
+        Character {
+            texture,
+            advance,
+            missing: false,
+        }
     }
 
-    fn create_char_bind_group(
+    /// Creates a character that is drawn with the font's .notdef glyph, or with a generated box if
+    /// the .notdef glyph is empty or the font doesn't have outlines.
+    fn create_notdef_char(
         &self,
         c: char,
+        font_data: &FontData,
+        device: &wgpu::Device,
+        uploads: &Uploads,
+    ) -> Character {
+        if let Some(font) = &font_data.font {
+            let character = self.create_char(c.into(), font, font_data, device, uploads);
+
+            if character.texture.is_some() {
+                return character;
+            }
+        }
+
+        let (image, position, advance) = fallback_glyph::notdef_box(
+            font_data.px_size * font_data.glyph_scale_factor,
+            font_data.metrics.ascent * font_data.glyph_scale_factor,
+        );
+        let texture = self.create_generated_char_texture(
+            c.into(),
+            &image,
+            position,
+            font_data,
+            device,
+            uploads,
+        );
+
+        Character {
+            texture: Some(texture),
+            advance,
+            missing: true,
+        }
+    }
+
+    /// Uploads a coverage image made by kaku rather than the font (e.g. a fallback box), turning
+    /// it into a distance field first if the font uses sdf.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(glyph = %key))
+    )]
+    fn create_generated_char_texture(
+        &self,
+        key: GlyphKey,
         image: &GrayImage,
+        position: [f32; 2],
+        font_data: &FontData,
         device: &wgpu::Device,
-        queue: &wgpu::Queue,
-    ) -> wgpu::BindGroup {
+        uploads: &Uploads,
+    ) -> CharTexture {
+        let Some(sdf) = &font_data.sdf_settings else {
+            return self.create_raster_char_texture(
+                key,
+                &[(image, position)],
+                font_data.subpixel_phases,
+                wgpu::FilterMode::Linear,
+                font_data.glyph_padding,
+                font_data.texture_usages,
+                font_data.sampler.as_ref(),
+                device,
+                uploads,
+            );
+        };
+
+        let (sdf_image, padding) = create_sdf_texture(image, image.dimensions(), sdf);
+        let size = [sdf_image.width() as f32, sdf_image.height() as f32];
+        let sdf_image = fit_to_device(key, &sdf_image, device);
+
+        let (texture, bind_group, texture_bytes) = self.create_char_bind_group(
+            key,
+            &sdf.format.encode(&sdf_image),
+            sdf_image.dimensions(),
+            sdf.format.texture_format(),
+            wgpu::FilterMode::Linear,
+            font_data.texture_usages,
+            font_data.sampler.as_ref(),
+            device,
+            uploads,
+        );
+
+        CharTexture {
+            bind_group,
+            texture_bytes,
+            texture,
+            size,
+            position: position.map(|p| p - padding as f32),
+            padding: padding as f32,
+            #[cfg(feature = "cpu-render")]
+            image: cpu_render::quantized_image(&sdf_image, sdf.format),
+            #[cfg(feature = "cpu-render")]
+            filter: wgpu::FilterMode::Linear,
+        }
+    }
+
+    /// Generates the signed distance field for a character, returning it along with the position
+    /// of its top left corner relative to the pen position.
+    ///
+    /// Returns None if the character has nothing to draw (e.g. a space, or an outline with no
+    /// area).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(glyph = %key))
+    )]
+    fn create_sdf_image(
+        &self,
+        key: GlyphKey,
+        font: &FontArc,
+        font_data: &FontData,
+        sdf: &SdfSettings,
+    ) -> Option<(SdfImage, [f32; 2])> {
+        let scale = font_data.key_scale(key);
+        let scaled = font.as_scaled(scale);
+        let glyph = key.glyph_id(font).with_scale(scale);
+
+        let outlined = scaled.outline_glyph(glyph.clone())?;
+        let px_bounds = outlined.px_bounds();
+        let width = px_bounds.width().ceil() as u32;
+        let height = px_bounds.height().ceil() as u32;
+
+        // Broken or extremely thin outlines can have no area. These are drawn as nothing, like
+        // spaces, rather than making a distance field of an empty image
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let create_sdf_image = || -> (SdfImage, u32) {
+            match sdf.source {
+                SdfSource::Raster => {
+                    let mut image = image::GrayImage::new(width, height);
+                    outlined
+                        .draw(|x, y, val| image.put_pixel(x, y, image::Luma([(val * 255.) as u8])));
+
+                    create_sdf_texture(&image, (width, height), sdf)
+                }
+                SdfSource::Outline => {
+                    // The outline is always there if ab_glyph managed to outline the glyph
+                    let outline = font.outline(glyph.id).unwrap();
+                    create_sdf_texture_from_outline(&outline, scaled.scale_factor(), px_bounds, sdf)
+                }
+            }
+        };
+
+        #[cfg(feature = "disk-cache")]
+        let (image, padding) = match &self.disk_cache {
+            Some(cache) => cache
+                .load(font_data.key_hash(key), glyph.id, scale, sdf)
+                .unwrap_or_else(|| {
+                    let (image, padding) = create_sdf_image();
+                    cache.store(
+                        font_data.key_hash(key),
+                        glyph.id,
+                        scale,
+                        sdf,
+                        &image,
+                        padding,
+                    );
+                    (image, padding)
+                }),
+            None => create_sdf_image(),
+        };
+
+        #[cfg(not(feature = "disk-cache"))]
+        let (image, padding) = create_sdf_image();
+
+        let x = px_bounds.min.x - padding as f32;
+        let y = px_bounds.min.y - padding as f32;
+
+        Some((image, [x, y]))
+    }
+
+    fn create_char_texture_sdf(
+        &self,
+        key: GlyphKey,
+        font: &FontArc,
+        font_data: &FontData,
+        sdf: &SdfSettings,
+        device: &wgpu::Device,
+        uploads: &Uploads,
+    ) -> Character {
+        #[cfg(not(feature = "tracing"))]
+        info!("Creating sdf character texture for {key}");
+
+        let advance = font_data.glyph_advance(font, key);
+
+        let texture = self
+            .create_sdf_image(key, font, font_data, sdf)
+            .map(|(image, position)| {
+                let size = [image.width() as f32, image.height() as f32];
+                let image = fit_to_device(key, &image, device);
+
+                let (texture, bind_group, texture_bytes) = self.create_char_bind_group(
+                    key,
+                    &sdf.format.encode(&image),
+                    image.dimensions(),
+                    sdf.format.texture_format(),
+                    wgpu::FilterMode::Linear,
+                    font_data.texture_usages,
+                    font_data.sampler.as_ref(),
+                    device,
+                    uploads,
+                );
+
+                CharTexture {
+                    bind_group,
+                    texture_bytes,
+                    texture,
+                    size,
+                    position,
+                    // The distance field always extends as far as the radius past the outline
+                    padding: sdf.radius.ceil(),
+                    #[cfg(feature = "cpu-render")]
+                    image: cpu_render::quantized_image(&image, sdf.format),
+                    #[cfg(feature = "cpu-render")]
+                    filter: wgpu::FilterMode::Linear,
+                }
+            });
+
+        Character {
+            texture,
+            advance,
+            missing: false,
+        }
+    }
+
+    fn create_char_texture(
+        &self,
+        key: GlyphKey,
+        font: &FontArc,
+        font_data: &FontData,
+        device: &wgpu::Device,
+        uploads: &Uploads,
+    ) -> Character {
+        #[cfg(not(feature = "tracing"))]
+        info!("Creating character texture for {key}");
+        // Calculate metrics
+        let scale = font_data.glyph_scale(key);
+        let glyph = key.glyph_id(font).with_scale(scale);
+
+        let advance = font_data.glyph_advance(font, key);
+
+        // Fallback fonts are hinted too, as long as swash can read them
+        #[cfg(feature = "swash")]
+        if font_data.hinting && hinting::supports_hinting(font) {
+            let texture =
+                hinting::rasterize_hinted(font, glyph.id, scale).map(|(image, position)| {
+                    self.create_raster_char_texture(
+                        key,
+                        &[(&image, position)],
+                        1,
+                        wgpu::FilterMode::Linear,
+                        font_data.glyph_padding,
+                        font_data.texture_usages,
+                        font_data.sampler.as_ref(),
+                        device,
+                        uploads,
+                    )
+                });
+
+            return Character {
+                texture,
+                advance,
+                missing: false,
+            };
+        }
+
+        let phases = font_data.subpixel_phases;
+        let texture =
+            raster::rasterize_phases(font, glyph.id, scale, font_data.supersampling, phases).map(
+                |images| {
+                    let images = images.iter().map(|(image, position)| (image, *position));
+                    self.create_raster_char_texture(
+                        key,
+                        &images.collect_vec(),
+                        phases,
+                        wgpu::FilterMode::Linear,
+                        font_data.glyph_padding,
+                        font_data.texture_usages,
+                        font_data.sampler.as_ref(),
+                        device,
+                        uploads,
+                    )
+                },
+            );
+
+        Character {
+            texture,
+            advance,
+            missing: false,
+        }
+    }
+
+    /// Uploads a character's coverage images, given the position of each one's top left corner
+    /// relative to the pen position.
+    ///
+    /// The texture has `phase_count` images stacked from top to bottom (see
+    /// [FontOptions::with_subpixel_phases]), repeating the given ones if there aren't that many.
+    /// Each image is surrounded by `padding` transparent pixels, so that when the texture is
+    /// scaled the edges of the glyph are blended with transparency instead of the clamped edge
+    /// pixels.
+    #[allow(clippy::too_many_arguments)]
+    fn create_raster_char_texture(
+        &self,
+        key: GlyphKey,
+        images: &[(&GrayImage, [f32; 2])],
+        phase_count: u32,
+        filter: wgpu::FilterMode,
+        padding: u32,
+        usages: wgpu::TextureUsages,
+        sampler: Option<&wgpu::Sampler>,
+        device: &wgpu::Device,
+        uploads: &Uploads,
+    ) -> CharTexture {
+        let (image, position) = raster::stack_phases(images, phase_count, padding);
+        let size = [image.width() as f32, (image.height() / phase_count) as f32];
+        let image = fit_to_device(key, &image, device);
+        let image = image.as_ref();
+
+        let (texture, bind_group, texture_bytes) = self.create_char_bind_group(
+            key,
+            image,
+            image.dimensions(),
+            wgpu::TextureFormat::R8Unorm,
+            filter,
+            usages,
+            sampler,
+            device,
+            uploads,
+        );
+
+        CharTexture {
+            bind_group,
+            texture_bytes,
+            texture,
+            size,
+            position,
+            padding: padding as f32,
+            #[cfg(feature = "cpu-render")]
+            image: cpu_render::coverage_image(image),
+            #[cfg(feature = "cpu-render")]
+            filter,
+        }
+    }
+
+    /// The usages glyph textures are created with, including the font's extra ones.
+    fn glyph_texture_usages(&self, extra: wgpu::TextureUsages) -> wgpu::TextureUsages {
+        let usages = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | extra;
+
+        if self.glyph_readback {
+            usages | wgpu::TextureUsages::COPY_SRC
+        } else {
+            usages
+        }
+    }
+
+    /// Uploads the texture data for a character and creates a bind group for it, returning the
+    /// texture and the bind group along with the size of the texture in bytes.
+    ///
+    /// `data` has to be tightly packed texels in the given format, with the given dimensions.
+    #[allow(clippy::too_many_arguments)]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(glyph = %key, width = width, height = height))
+    )]
+    fn create_char_bind_group(
+        &self,
+        key: GlyphKey,
+        data: &[u8],
+        (width, height): (u32, u32),
+        format: wgpu::TextureFormat,
+        filter: wgpu::FilterMode,
+        usages: wgpu::TextureUsages,
+        sampler: Option<&wgpu::Sampler>,
+        device: &wgpu::Device,
+        uploads: &Uploads,
+    ) -> (Arc<wgpu::Texture>, wgpu::BindGroup, u64) {
         let texture_size = wgpu::Extent3d {
-            width: image.width(),
-            height: image.height(),
+            width,
+            height,
             depth_or_array_layers: 1,
         };
 
         let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some(&format!("kaku texture for character: '{c}'")),
+            label: Some(&self.label(&format!("texture for {key}"))),
             size: texture_size,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::R8Unorm,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            format,
+            usage: self.glyph_texture_usages(usages),
             view_formats: &[],
             mip_level_count: 1,
             // TODO: multisampling
             sample_count: 1,
         });
+        let texture = Arc::new(texture);
 
         let view = texture.create_view(&TextureViewDescriptor {
-            label: Some(&format!("kaku texture view for character: '{c}'")),
+            label: Some(&self.label(&format!("texture view for {key}"))),
             ..Default::default()
         });
 
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            image,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(image.width()),
-                rows_per_image: Some(image.height()),
-            },
-            texture_size,
-        );
+        // The formats we use are never compressed so they always have a block size
+        let bytes_per_row = width * format.block_copy_size(None).unwrap();
+        let staging_label = self.label(&format!("staging buffer for {key}"));
+        self.frame_counters.add_upload(data.len());
+        uploads.write_texture(texture.clone(), data, bytes_per_row, &staging_label, device);
 
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
+        let sampler = sampler.unwrap_or(match filter {
+            wgpu::FilterMode::Linear => &self.linear_sampler,
+            wgpu::FilterMode::Nearest => &self.nearest_sampler,
         });
 
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some(&format!("kaku bind group for character '{c}'")),
+            label: Some(&self.label(&format!("bind group for {key}"))),
             layout: &self.char_bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
@@ -900,11 +3479,11 @@ impl TextRenderer {
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
+                    resource: wgpu::BindingResource::Sampler(sampler),
                 },
             ],
         });
 
-        bind_group
+        (texture, bind_group, bytes_per_row as u64 * height as u64)
     }
 }
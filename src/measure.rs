@@ -0,0 +1,262 @@
+//! Laying out and measuring text without a GPU, for [TextMeasurer].
+//!
+//! The measurer keeps the same font data as a [TextRenderer](crate::TextRenderer) and lays text out
+//! with the same code, but only works out each character's advance instead of generating its
+//! texture.
+
+use ab_glyph::{Font, FontArc, ScaleFont};
+use image::GrayImage;
+
+use crate::{
+    custom_glyph::CustomGlyph, fallback_glyph, Character, Error, FontData, FontId, FontMap,
+    FontOptions, FontSize, GlyphKey, GlyphMetrics, MissingGlyphs, SdfSettings, TextBuilder,
+    TextLayout,
+};
+
+/// Lays out and measures text without a `wgpu::Device`, e.g. on a server that needs to know how
+/// big a piece of text will be when a client draws it.
+///
+/// Fonts are loaded like they are with a [TextRenderer](crate::TextRenderer), and text is laid out
+/// with the same metrics, so a [TextLayout] from a measurer has the same lines and bounds as one
+/// from a renderer with the same fonts, options and scale factor. Font ids are given out in the
+/// order fonts are loaded, so if the fonts are loaded in the same order they'll match the
+/// renderer's ids too.
+///
+/// Where glyphs are drawn depends on their textures, which a measurer doesn't make, so its layouts
+/// have no glyphs (see [TextLayout::glyphs]). They can still be built into a [Text](crate::Text)
+/// with [TextLayout::build], which lays them out again with the renderer.
+#[derive(Debug)]
+pub struct TextMeasurer {
+    fonts: FontMap,
+    scale_factor: f32,
+}
+
+impl Default for TextMeasurer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextMeasurer {
+    /// Creates a measurer with no fonts and a scale factor of 1.
+    pub fn new() -> Self {
+        Self {
+            fonts: Default::default(),
+            scale_factor: 1.,
+        }
+    }
+
+    /// Loads a font, like [TextRenderer::load_font](crate::TextRenderer::load_font).
+    pub fn load_font<F>(&mut self, font: F, size: FontSize) -> Result<FontId, Error>
+    where
+        F: Font + Send + Sync + 'static,
+    {
+        let font = self.fonts.load(FontArc::new(font), size)?;
+        self.fonts.get_mut(font)?.glyph_scale_factor = self.scale_factor;
+        Ok(font)
+    }
+
+    /// Loads a font with sdf rendering, like
+    /// [TextRenderer::load_font_with_sdf](crate::TextRenderer::load_font_with_sdf).
+    pub fn load_font_with_sdf<F>(
+        &mut self,
+        font: F,
+        size: FontSize,
+        sdf_settings: SdfSettings,
+    ) -> Result<FontId, Error>
+    where
+        F: Font + Send + Sync + 'static,
+    {
+        self.fonts
+            .load_with_sdf(FontArc::new(font), size, sdf_settings)
+    }
+
+    /// Loads a font with the given options, like
+    /// [TextRenderer::load_font_with_options](crate::TextRenderer::load_font_with_options).
+    ///
+    /// Only the options that change the layout matter here. Nothing is preloaded.
+    pub fn load_font_with_options<F>(
+        &mut self,
+        font: F,
+        options: FontOptions,
+    ) -> Result<FontId, Error>
+    where
+        F: Font + Send + Sync + 'static,
+    {
+        let font = match options.sdf {
+            Some(sdf_settings) => self.load_font_with_sdf(font, options.size, sdf_settings)?,
+            None => self.load_font(font, options.size)?,
+        };
+
+        let font_data = self.fonts.get_mut(font)?;
+        font_data.supersampling = options.supersampling;
+        font_data.glyph_padding = options.glyph_padding;
+        font_data.missing_glyphs = options.missing_glyphs;
+        Ok(font)
+    }
+
+    /// Sets the number of physical pixels per logical pixel, like
+    /// [TextRenderer::set_scale_factor](crate::TextRenderer::set_scale_factor). This should match
+    /// the scale factor of the renderer the text will be drawn with.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        let scale_factor = scale_factor as f32;
+
+        if scale_factor == self.scale_factor {
+            return;
+        }
+
+        self.scale_factor = scale_factor;
+
+        // Raster fonts' advances are measured at the scaled size, like the renderer does
+        for font_data in &mut self.fonts.fonts {
+            if font_data.sdf_settings.is_none() {
+                font_data.glyph_scale_factor = scale_factor;
+                font_data.clear_cache();
+            }
+        }
+    }
+
+    /// Makes a font draw some characters as others, like
+    /// [TextRenderer::set_substitutions](crate::TextRenderer::set_substitutions), so that the
+    /// measurer's layouts match the renderer's.
+    ///
+    /// Returns an error if the font isn't loaded in the measurer.
+    pub fn set_substitutions(
+        &mut self,
+        font: FontId,
+        substitutions: std::collections::HashMap<char, char>,
+    ) -> Result<(), Error> {
+        self.fonts.get_mut(font)?.substitutions = substitutions.into_iter().collect();
+        Ok(())
+    }
+
+    /// Sets the fonts that draw text a font doesn't have glyphs for, like
+    /// [TextRenderer::set_fallback_fonts](crate::TextRenderer::set_fallback_fonts), so that the
+    /// measurer's layouts match the renderer's.
+    ///
+    /// Returns an error if the font isn't loaded in the measurer, or if one of the fallback fonts
+    /// can't be scaled.
+    pub fn set_fallback_fonts(
+        &mut self,
+        font: FontId,
+        fallbacks: Vec<FontArc>,
+    ) -> Result<(), Error> {
+        self.fonts.get_mut(font)?.set_fallbacks(fallbacks)
+    }
+
+    /// Draws a character of a font with an image instead of its glyph, like
+    /// [TextRenderer::register_glyph](crate::TextRenderer::register_glyph), so that the
+    /// measurer's layouts match the renderer's. Only the metrics are used.
+    ///
+    /// Returns an error if the font isn't loaded in the measurer.
+    pub fn register_glyph(
+        &mut self,
+        font: FontId,
+        c: char,
+        image: GrayImage,
+        metrics: GlyphMetrics,
+    ) -> Result<(), Error> {
+        let font_data = self.fonts.get_mut(font)?;
+        font_data.char_cache.remove(&c);
+        font_data
+            .custom_glyphs
+            .insert(c, CustomGlyph { image, metrics });
+        Ok(())
+    }
+
+    /// Returns the scale factor set with [TextMeasurer::set_scale_factor].
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor as f64
+    }
+
+    /// Lays out a text, as [TextBuilder::layout] would with a renderer.
+    ///
+    /// Returns an error if the font isn't loaded in the measurer, or can't be scaled to the
+    /// text's custom font size.
+    pub fn layout(&mut self, text: &TextBuilder) -> Result<TextLayout, Error> {
+        let font_data = self.fonts.get_mut(text.font)?;
+        let data = text.text_data(font_data, self.scale_factor)?;
+
+        let (chars, glyphs) = font_data.text_glyphs(&data, 0..data.text.len());
+        let new_chars: Vec<_> = (chars.into_iter())
+            .map(|c| font_data.substitute(c))
+            .filter(|c| !font_data.char_cache.contains_key(c))
+            .collect();
+        for c in new_chars {
+            let character = measure_char(c, font_data);
+            font_data.char_cache.insert(c, character);
+        }
+
+        // Only fonts with outlines have ligatures and fallback fonts
+        for key in glyphs {
+            let advance = (font_data.key_font(key)).map(|font| font_data.glyph_advance(font, key));
+            if let Some(advance) = advance {
+                let character = Character {
+                    texture: None,
+                    advance,
+                    missing: false,
+                };
+                font_data.cache(key, character);
+            }
+        }
+
+        let mut layout = font_data.layout(data)?;
+        layout.from_measurer = true;
+        Ok(layout)
+    }
+}
+
+/// Works out how a character is drawn without making its texture, mirroring how the renderer
+/// generates characters.
+fn measure_char(c: char, font_data: &FontData) -> Character {
+    if let Some(glyph) = font_data.custom_glyphs.get(&c) {
+        return Character {
+            texture: None,
+            advance: glyph.metrics.advance * font_data.glyph_scale_factor,
+            missing: false,
+        };
+    }
+
+    let Some(font) = &font_data.font else {
+        return Character {
+            texture: None,
+            advance: 0.,
+            missing: true,
+        };
+    };
+
+    // Glyph 0 is always the .notdef glyph
+    let missing = font.glyph_id(c).0 == 0;
+    let fallback = missing && !c.is_control();
+    let key = GlyphKey::Char(c);
+    let px_size = font_data.px_size * font_data.glyph_scale_factor;
+
+    let advance = match font_data.missing_glyphs {
+        // The renderer only draws a box if the font's .notdef glyph is empty
+        MissingGlyphs::Notdef if fallback && !has_outline(font, key, font_data) => {
+            let ascent = font_data.metrics.ascent * font_data.glyph_scale_factor;
+            fallback_glyph::notdef_box(px_size, ascent).2
+        }
+        MissingGlyphs::HexBox if fallback => fallback_glyph::hex_box(c, px_size).2,
+        _ => font_data.glyph_advance(font, key),
+    };
+
+    Character {
+        texture: None,
+        advance,
+        missing,
+    }
+}
+
+/// Whether a glyph has an outline with some area, which is when the renderer gives it a texture.
+fn has_outline(font: &FontArc, key: GlyphKey, font_data: &FontData) -> bool {
+    let scale = font_data.glyph_scale(key);
+    let glyph = key.glyph_id(font).with_scale(scale);
+
+    font.as_scaled(scale)
+        .outline_glyph(glyph)
+        .is_some_and(|outlined| {
+            let bounds = outlined.px_bounds();
+            bounds.width().ceil() > 0. && bounds.height().ceil() > 0.
+        })
+}
@@ -0,0 +1,87 @@
+//! Tracking how much GPU memory a renderer's resources use, for
+//! [TextRenderer::memory_usage](crate::TextRenderer::memory_usage).
+//!
+//! Glyph textures and the renderer's own buffers are owned by the renderer, so they're counted
+//! when a report is made. Texts own their buffers and can be dropped at any time, so each one adds
+//! its buffers to counters shared with its renderer, and takes them away again when it's dropped.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/// How many bytes of GPU memory a [TextRenderer](crate::TextRenderer) and the texts it built are
+/// using, from [TextRenderer::memory_usage](crate::TextRenderer::memory_usage).
+///
+/// These are the sizes wgpu was asked for, so the driver may use a bit more (e.g. for alignment).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// The textures of every cached character and glyph, in every font.
+    pub glyph_textures: u64,
+    /// The instance buffers of every text, including the spare buffers of double buffered texts.
+    pub instance_buffers: u64,
+    /// The settings buffers of every text (or the shared buffers they're kept in), and the screen
+    /// uniforms of every target.
+    pub uniform_buffers: u64,
+}
+
+impl MemoryReport {
+    /// The total number of bytes used.
+    pub fn total(&self) -> u64 {
+        self.glyph_textures + self.instance_buffers + self.uniform_buffers
+    }
+}
+
+/// The bytes used by the buffers of every living text, shared between a renderer and its texts.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryCounters {
+    pub(crate) instance_buffers: AtomicU64,
+    pub(crate) uniform_buffers: AtomicU64,
+}
+
+/// The bytes a text's buffers add to its renderer's [MemoryCounters]. They're taken away again
+/// when this is dropped.
+#[derive(Debug)]
+pub(crate) struct TextMemory {
+    counters: Arc<MemoryCounters>,
+    instance_buffers: u64,
+    uniform_buffers: u64,
+}
+
+impl TextMemory {
+    pub(crate) fn new(counters: Arc<MemoryCounters>) -> Self {
+        Self {
+            counters,
+            instance_buffers: 0,
+            uniform_buffers: 0,
+        }
+    }
+
+    /// Sets how many bytes the text's buffers use, e.g. after they've been replaced with bigger
+    /// ones.
+    pub(crate) fn set(&mut self, instance_buffers: u64, uniform_buffers: u64) {
+        update(
+            &self.counters.instance_buffers,
+            &mut self.instance_buffers,
+            instance_buffers,
+        );
+        update(
+            &self.counters.uniform_buffers,
+            &mut self.uniform_buffers,
+            uniform_buffers,
+        );
+    }
+}
+
+impl Drop for TextMemory {
+    fn drop(&mut self) {
+        self.set(0, 0);
+    }
+}
+
+/// Replaces a text's contribution to a counter.
+fn update(counter: &AtomicU64, bytes: &mut u64, new_bytes: u64) {
+    counter.fetch_sub(*bytes, Ordering::Relaxed);
+    counter.fetch_add(new_bytes, Ordering::Relaxed);
+    *bytes = new_bytes;
+}
@@ -0,0 +1,131 @@
+//! Backgrounds drawn behind texts (see [Panel]).
+
+#[cfg(has_wgpu)]
+use std::sync::Arc;
+
+#[cfg(has_wgpu)]
+use bytemuck::Zeroable;
+#[cfg(has_wgpu)]
+use wgpu::util::DeviceExt;
+
+#[cfg(has_wgpu)]
+use crate::TextRenderer;
+use crate::{layout::Rect, text::TextData};
+
+/// A rounded rectangle drawn behind a [Text](crate::Text), e.g. for tooltips, labels and speech
+/// bubbles. Set it with [TextBuilder::panel](crate::TextBuilder::panel) or
+/// [Text::set_panel](crate::Text::set_panel).
+///
+/// The panel is the text's [bounds](crate::Text::bounds) made bigger by the padding, so it follows
+/// the text as it's moved or changed. [TextRenderer::draw_text](crate::TextRenderer::draw_text)
+/// draws it right before the glyphs, faded by the text's opacity. Sizes are in logical pixels.
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub struct Panel {
+    /// The space between the text's bounds and the edges of the panel, across and down.
+    pub padding: [f32; 2],
+    /// The radius of the panel's corners. It's clamped to half of the panel's shorter side, so a
+    /// big radius makes a pill shape.
+    pub corner_radius: f32,
+    /// The colour inside the border.
+    pub fill: [f32; 4],
+    /// The colour of the border.
+    pub border_color: [f32; 4],
+    /// The width of the border, which is inside the panel's edges. 0 means there isn't one.
+    pub border_width: f32,
+}
+
+impl Panel {
+    /// A panel filled with a colour, with 8 pixels of padding across and 4 down, corners with a
+    /// radius of 4 pixels and no border.
+    pub fn new(fill: [f32; 4]) -> Self {
+        Self {
+            padding: [8., 4.],
+            corner_radius: 4.,
+            fill,
+            border_color: [0.; 4],
+            border_width: 0.,
+        }
+    }
+
+    /// The panel around the bounds of a text (including its outline) in physical pixels.
+    pub(crate) fn rect(&self, bounds: Rect, scale_factor: f32) -> Rect {
+        let padding = self.padding.map(|p| p.max(0.) * scale_factor);
+
+        Rect {
+            min: [0, 1].map(|i| bounds.min[i] - padding[i]),
+            max: [0, 1].map(|i| bounds.max[i] + padding[i]),
+        }
+    }
+}
+
+/// A text's panel settings for the shader. The panel's rectangle is its instance.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct PanelUniform {
+    text_position: [f32; 2],
+    relative_position: [f32; 2],
+    pub(crate) fill: [f32; 4],
+    pub(crate) border_color: [f32; 4],
+    pub(crate) corner_radius: f32,
+    pub(crate) border_width: f32,
+    _padding: [f32; 2],
+}
+
+impl PanelUniform {
+    /// The settings of a text's panel, in physical pixels and with the text's opacity applied.
+    pub(crate) fn new(panel: &Panel, data: &TextData) -> Self {
+        Self {
+            text_position: data.draw_position(),
+            relative_position: data.relative_position,
+            fill: data.faded(panel.fill),
+            border_color: data.faded(panel.border_color),
+            corner_radius: panel.corner_radius.max(0.) * data.scale_factor,
+            border_width: panel.border_width.max(0.) * data.scale_factor,
+            _padding: [0.; 2],
+        }
+    }
+}
+
+/// The buffers of a text's panel.
+#[cfg(has_wgpu)]
+#[derive(Debug)]
+pub(crate) struct PanelBuffers {
+    /// The panel's quad, as one instance.
+    pub(crate) instance_buffer: Arc<wgpu::Buffer>,
+    pub(crate) settings_buffer: Arc<wgpu::Buffer>,
+    pub(crate) bind_group: wgpu::BindGroup,
+}
+
+#[cfg(has_wgpu)]
+impl PanelBuffers {
+    /// Creates the buffers for a panel, which are filled in when the text's settings are
+    /// uploaded.
+    pub(crate) fn new(text_renderer: &TextRenderer, device: &wgpu::Device) -> Self {
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&text_renderer.label("panel instance buffer")),
+            contents: bytemuck::bytes_of(&crate::CharacterInstance::zeroed()),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let settings_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&text_renderer.label("panel settings uniform buffer")),
+            contents: bytemuck::bytes_of(&PanelUniform::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&text_renderer.label("panel settings uniform bind group")),
+            layout: &text_renderer.panel_settings_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: settings_buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            instance_buffer: Arc::new(instance_buffer),
+            settings_buffer: Arc::new(settings_buffer),
+            bind_group,
+        }
+    }
+}
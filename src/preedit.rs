@@ -0,0 +1,107 @@
+//! Underlining the text an input method is composing (see [PreeditSegment]).
+
+use std::ops::Range;
+
+use crate::layout::{self, LineBox, Rect};
+
+/// How a [PreeditSegment] is underlined.
+///
+/// Input methods usually underline the segment being converted differently from the rest, e.g.
+/// with a thick line for the selected clause and a dashed or thin one for the others.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UnderlineStyle {
+    /// A thin, solid line.
+    #[default]
+    Solid,
+    /// A thin line broken into dashes.
+    Dashed,
+    /// A solid line twice as thick as the others.
+    Thick,
+}
+
+/// Part of the text that an input method (IME) is still composing (its preedit text), e.g. a
+/// clause of Japanese that hasn't been converted yet.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PreeditSegment {
+    /// The byte range of the segment in the text.
+    pub range: Range<usize>,
+    /// How the segment is underlined.
+    pub style: UnderlineStyle,
+}
+
+impl PreeditSegment {
+    /// Creates a segment of a byte range of the text.
+    pub fn new(range: Range<usize>, style: UnderlineStyle) -> Self {
+        Self { range, style }
+    }
+}
+
+/// A rectangle to fill to underline part of a [PreeditSegment], from
+/// [Text::preedit_underlines](crate::Text::preedit_underlines).
+///
+/// Segments that go over several lines have an underline on each of them, and dashed segments
+/// have one for each dash.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Underline {
+    /// The index of the segment the underline is part of.
+    pub segment: usize,
+    /// The style of the segment.
+    pub style: UnderlineStyle,
+    /// The rectangle to fill.
+    pub rect: Rect,
+}
+
+/// The underlines of some segments, in the same coordinates as the lines and carets.
+pub(crate) fn underlines(
+    lines: &[LineBox],
+    carets: &[(usize, f32)],
+    segments: &[PreeditSegment],
+) -> Vec<Underline> {
+    let mut underlines = Vec::new();
+
+    for (segment, PreeditSegment { range, style }) in segments.iter().enumerate() {
+        for (line, rect) in layout::range_rects(lines, carets, range.clone()) {
+            // Underlines are a twentieth of the line's height, but at least a pixel
+            let thin = (line.rect.height() / 20.).max(1.);
+            let thickness = match style {
+                UnderlineStyle::Thick => thin * 2.,
+                _ => thin,
+            };
+
+            // Neighbouring segments have a gap between them, so they can be told apart
+            let top = line.baseline + line.descent() * 0.3;
+            let rect = Rect {
+                min: [rect.min[0] + thin, top],
+                max: [
+                    (rect.max[0] - thin).max(rect.min[0] + thin),
+                    top + thickness,
+                ],
+            };
+
+            let mut push = |rect| {
+                underlines.push(Underline {
+                    segment,
+                    style: *style,
+                    rect,
+                })
+            };
+
+            if *style == UnderlineStyle::Dashed {
+                let (dash, gap) = (thin * 3., thin * 2.);
+                let mut x = rect.min[0];
+
+                while x < rect.max[0] {
+                    push(Rect {
+                        min: [x, rect.min[1]],
+                        max: [(x + dash).min(rect.max[0]), rect.max[1]],
+                    });
+                    x += dash + gap;
+                }
+            } else {
+                push(rect);
+            }
+        }
+    }
+
+    underlines
+}
@@ -0,0 +1,81 @@
+//! Timing text draws with wgpu-profiler, for
+//! [TextRenderer::draw_text_profiled](crate::TextRenderer::draw_text_profiled).
+//!
+//! Without the `wgpu-profiler` feature there's never a profiler, so the scopes do nothing and
+//! their labels are never made.
+
+use std::marker::PhantomData;
+
+/// The profiler a text's draws are timed with, if there is one.
+#[derive(Clone, Copy)]
+pub(crate) struct Profiling<'a> {
+    #[cfg(feature = "wgpu-profiler")]
+    profiler: Option<Profiler<'a>>,
+    lifetime: PhantomData<&'a ()>,
+}
+
+#[cfg(feature = "wgpu-profiler")]
+#[derive(Clone, Copy)]
+struct Profiler<'a> {
+    profiler: &'a wgpu_profiler::GpuProfiler,
+    device: &'a wgpu::Device,
+    /// The scope new scopes are nested in.
+    parent: Option<&'a wgpu_profiler::GpuProfilerQuery>,
+}
+
+impl<'a> Profiling<'a> {
+    /// Draws without any profiler scopes.
+    pub(crate) const NONE: Self = Self {
+        #[cfg(feature = "wgpu-profiler")]
+        profiler: None,
+        lifetime: PhantomData,
+    };
+
+    #[cfg(feature = "wgpu-profiler")]
+    pub(crate) fn new(profiler: &'a wgpu_profiler::GpuProfiler, device: &'a wgpu::Device) -> Self {
+        Self {
+            profiler: Some(Profiler {
+                profiler,
+                device,
+                parent: None,
+            }),
+            lifetime: PhantomData,
+        }
+    }
+
+    /// Records `draw` into a render pass inside a profiler scope, which is only labelled if there
+    /// is a profiler. Scopes opened with the [Profiling] given to `draw` are nested in this one.
+    pub(crate) fn scope<'pass, T>(
+        &self,
+        label: impl FnOnce() -> String,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        draw: impl FnOnce(&mut wgpu::RenderPass<'pass>, Profiling<'_>) -> T,
+    ) -> T {
+        #[cfg(feature = "wgpu-profiler")]
+        if let Some(Profiler {
+            profiler,
+            device,
+            parent,
+        }) = self.profiler
+        {
+            let query = (profiler.begin_query(label(), render_pass, device)).with_parent(parent);
+            let nested = Profiling {
+                profiler: Some(Profiler {
+                    profiler,
+                    device,
+                    parent: Some(&query),
+                }),
+                lifetime: PhantomData,
+            };
+
+            let result = draw(render_pass, nested);
+            profiler.end_query(render_pass, query);
+            return result;
+        }
+
+        #[cfg(not(feature = "wgpu-profiler"))]
+        let _ = label;
+
+        draw(render_pass, Profiling::NONE)
+    }
+}
@@ -0,0 +1,142 @@
+//! Rasterising glyphs into coverage images for fonts that don't use sdf.
+
+use ab_glyph::{point, Font, FontArc, GlyphId, PxScale, ScaleFont};
+use image::{GenericImage, GrayImage, Luma};
+
+/// Rasterises a glyph, returning its coverage image along with the position of its top left
+/// corner relative to the pen position.
+///
+/// The glyph is drawn `offset_x` pixels to the right of the pen, which is only used for subpixel
+/// phases (see [rasterize_phases]).
+///
+/// If `supersampling` is more than 1, the glyph is drawn that many times larger in each direction
+/// and then shrunk back down by averaging each block of pixels (see
+/// [crate::FontOptions::with_supersampling]).
+///
+/// Returns None if the glyph has nothing to draw (e.g. a space, or an outline with no area).
+pub(crate) fn rasterize_glyph(
+    font: &FontArc,
+    glyph: GlyphId,
+    scale: PxScale,
+    supersampling: u32,
+    offset_x: f32,
+) -> Option<(GrayImage, [f32; 2])> {
+    let factor = supersampling.max(1);
+    let big_scale = PxScale {
+        x: scale.x * factor as f32,
+        y: scale.y * factor as f32,
+    };
+
+    let position = point(offset_x * factor as f32, 0.);
+    let outlined = font
+        .as_scaled(big_scale)
+        .outline_glyph(glyph.with_scale_and_position(big_scale, position))?;
+
+    let px_bounds = outlined.px_bounds();
+    let width = px_bounds.width().ceil() as u32;
+    let height = px_bounds.height().ceil() as u32;
+
+    // Broken or extremely thin outlines can have no area, and wgpu can't make empty textures
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    if factor == 1 {
+        let mut image = GrayImage::new(width, height);
+        outlined.draw(|x, y, val| image.put_pixel(x, y, Luma([(val * 255.) as u8])));
+
+        return Some((image, [px_bounds.min.x, px_bounds.min.y]));
+    }
+
+    // Line the big image up so each block of pixels covers exactly one pixel of the final image
+    let min_x = px_bounds.min.x as i32;
+    let min_y = px_bounds.min.y as i32;
+    let origin_x = min_x.div_euclid(factor as i32);
+    let origin_y = min_y.div_euclid(factor as i32);
+    let offset_x = (min_x - origin_x * factor as i32) as u32;
+    let offset_y = (min_y - origin_y * factor as i32) as u32;
+
+    let small_width = (offset_x + width).div_ceil(factor);
+    let small_height = (offset_y + height).div_ceil(factor);
+
+    let mut sums = vec![0.; (small_width * small_height) as usize];
+
+    outlined.draw(|x, y, val| {
+        let sx = (x + offset_x) / factor;
+        let sy = (y + offset_y) / factor;
+        sums[(sy * small_width + sx) as usize] += val;
+    });
+
+    let samples = (factor * factor) as f32;
+    let image = GrayImage::from_fn(small_width, small_height, |x, y| {
+        let coverage = sums[(y * small_width + x) as usize] / samples;
+        Luma([(coverage.min(1.) * 255.) as u8])
+    });
+
+    Some((image, [origin_x as f32, origin_y as f32]))
+}
+
+/// Rasterises a glyph once for each subpixel phase, offset by `1 / phases` of a pixel more each
+/// time (see [crate::FontOptions::with_subpixel_phases]).
+///
+/// Returns None if the glyph has nothing to draw.
+pub(crate) fn rasterize_phases(
+    font: &FontArc,
+    glyph: GlyphId,
+    scale: PxScale,
+    supersampling: u32,
+    phases: u32,
+) -> Option<Vec<(GrayImage, [f32; 2])>> {
+    (0..phases.max(1))
+        .map(|phase| {
+            rasterize_glyph(
+                font,
+                glyph,
+                scale,
+                supersampling,
+                phase as f32 / phases as f32,
+            )
+        })
+        .collect()
+}
+
+/// Puts the images of a glyph's subpixel phases into one image, stacked from top to bottom, and
+/// returns it with the position of each phase's top left corner relative to the pen position.
+///
+/// Every phase gets the same box, big enough for all the images, with a border of `padding`
+/// transparent pixels. If there are fewer images than phases, they're repeated.
+pub(crate) fn stack_phases(
+    images: &[(&GrayImage, [f32; 2])],
+    phases: u32,
+    padding: u32,
+) -> (GrayImage, [f32; 2]) {
+    let min = |axis: usize| {
+        images
+            .iter()
+            .map(|(_, pos)| pos[axis])
+            .fold(f32::MAX, f32::min)
+    };
+    let max = |axis: usize, size: fn(&GrayImage) -> u32| {
+        (images.iter())
+            .map(|(image, pos)| pos[axis] + size(image) as f32)
+            .fold(f32::MIN, f32::max)
+    };
+
+    let [min_x, min_y] = [min(0), min(1)];
+    // Positions are whole pixels apart, but generated glyphs can be at fractional ones
+    let width = (max(0, GrayImage::width) - min_x).round() as u32 + 2 * padding;
+    let height = (max(1, GrayImage::height) - min_y).round() as u32 + 2 * padding;
+
+    let mut stacked = GrayImage::new(width, height * phases);
+
+    for (phase, (image, [x, y])) in (0..phases).zip(images.iter().cycle()) {
+        let x = (x - min_x).round() as u32 + padding;
+        let y = (y - min_y).round() as u32 + padding + phase * height;
+
+        // The box is always big enough
+        stacked.copy_from(*image, x, y).unwrap();
+    }
+
+    let position = [min_x, min_y].map(|p| p - padding as f32);
+    (stacked, position)
+}
@@ -0,0 +1,169 @@
+//! Copying textures back from the GPU, for
+//! [TextRenderer::read_glyph](crate::TextRenderer::read_glyph) and
+//! [TextRenderer::render_to_image](crate::TextRenderer::render_to_image).
+
+use std::fmt;
+
+use image::{DynamicImage, GrayImage, ImageBuffer, Luma};
+
+use crate::{Error, FontId, GlyphKey, TextRenderer};
+
+/// An error from [TextRenderer::read_glyph].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReadGlyphError {
+    /// The renderer wasn't built with
+    /// [TextRendererBuilder::with_glyph_readback](crate::TextRendererBuilder::with_glyph_readback),
+    /// so its glyph textures can't be copied from.
+    ReadbackDisabled,
+    /// The font isn't loaded in this renderer.
+    Font(Error),
+    /// The texture couldn't be copied back from the GPU.
+    Readback(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for ReadGlyphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadGlyphError::ReadbackDisabled => {
+                write!(f, "the renderer wasn't built with glyph readback enabled")
+            }
+            ReadGlyphError::Font(e) => write!(f, "couldn't find the glyph: {e}"),
+            ReadGlyphError::Readback(e) => write!(f, "couldn't read back the glyph: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ReadGlyphError {}
+
+impl From<Error> for ReadGlyphError {
+    fn from(e: Error) -> Self {
+        ReadGlyphError::Font(e)
+    }
+}
+
+impl TextRenderer {
+    /// Copies the cached texture of a character back from the GPU, e.g. to save it and look at
+    /// how the distance field turned out while tuning [SdfSettings](crate::SdfSettings). The
+    /// renderer has to be built with
+    /// [TextRendererBuilder::with_glyph_readback](crate::TextRendererBuilder::with_glyph_readback).
+    ///
+    /// The image is the texture as it is: coverage for raster fonts (with any subpixel phases
+    /// stacked from top to bottom), and the distance field for sdf fonts, where 0.5 is on the edge
+    /// of the glyph. 8 bit textures come back as [DynamicImage::ImageLuma8] and 16 bit ones as
+    /// [DynamicImage::ImageLuma16].
+    ///
+    /// Returns None if the character isn't cached, or doesn't have a texture (like a space). This
+    /// blocks until the GPU has finished copying, so it doesn't work on the web.
+    pub fn read_glyph(
+        &self,
+        font: FontId,
+        c: char,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Option<DynamicImage>, ReadGlyphError> {
+        if !self.glyph_readback {
+            return Err(ReadGlyphError::ReadbackDisabled);
+        }
+
+        let font_data = self.fonts.get(font)?;
+        let Some(texture) = (font_data.character(GlyphKey::Char(c)))
+            .and_then(|character| character.texture.as_ref())
+        else {
+            return Ok(None);
+        };
+
+        let texture = &texture.texture;
+        let encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some(&self.label("glyph readback command encoder")),
+        });
+        let label = self.label("glyph readback buffer");
+        let data = read_texture(texture, encoder, &label, device, queue)
+            .map_err(ReadGlyphError::Readback)?;
+
+        let (width, height) = (texture.width(), texture.height());
+        let values = data.chunks_exact(2).map(|bytes| [bytes[0], bytes[1]]);
+        let image = match texture.format() {
+            wgpu::TextureFormat::R16Unorm => {
+                let values = values.map(u16::from_ne_bytes).collect();
+                DynamicImage::ImageLuma16(luma16(width, height, values))
+            }
+            wgpu::TextureFormat::R16Float => {
+                let values = values
+                    .map(|bytes| half::f16::from_ne_bytes(bytes).to_f32())
+                    .map(|value| (value.clamp(0., 1.) * 65535.).round() as u16)
+                    .collect();
+                DynamicImage::ImageLuma16(luma16(width, height, values))
+            }
+            _ => DynamicImage::ImageLuma8(
+                GrayImage::from_raw(width, height, data).expect("the image data is the right size"),
+            ),
+        };
+
+        Ok(Some(image))
+    }
+}
+
+fn luma16(width: u32, height: u32, values: Vec<u16>) -> ImageBuffer<Luma<u16>, Vec<u16>> {
+    ImageBuffer::from_raw(width, height, values).expect("the image data is the right size")
+}
+
+/// Copies a whole texture back from the GPU after the commands in `encoder`, returning its texels
+/// tightly packed. The texture has to have been created with [wgpu::TextureUsages::COPY_SRC].
+///
+/// This submits the encoder and blocks until the copy has finished.
+pub(crate) fn read_texture(
+    texture: &wgpu::Texture,
+    mut encoder: wgpu::CommandEncoder,
+    label: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> Result<Vec<u8>, wgpu::BufferAsyncError> {
+    let size = texture.size();
+    // The formats we use are never compressed so they always have a block size
+    let texel_bytes = texture.format().block_copy_size(None).unwrap();
+
+    // Each row of a copy has to start on a multiple of 256 bytes, so the rows are padded and the
+    // padding is removed again once the texture has been read back
+    let row_bytes = size.width * texel_bytes;
+    let padded_row_bytes = row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: (padded_row_bytes * size.height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_bytes),
+                rows_per_image: Some(size.height),
+            },
+        },
+        size,
+    );
+    queue.submit([encoder.finish()]);
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+    device.poll(wgpu::Maintain::Wait);
+
+    receiver.recv().unwrap_or(Err(wgpu::BufferAsyncError))?;
+
+    let mut data = Vec::with_capacity((row_bytes * size.height) as usize);
+    {
+        let mapped = buffer.slice(..).get_mapped_range();
+        for row in mapped.chunks_exact(padded_row_bytes as usize) {
+            data.extend_from_slice(&row[..row_bytes as usize]);
+        }
+    }
+    Ok(data)
+}
@@ -0,0 +1,73 @@
+//! Settings for the samplers that glyph textures are drawn with.
+
+/// How glyph textures are sampled, apart from their min and mag filters, which kaku chooses (see
+/// [crate::TextRendererBuilder::with_sampler] and [crate::FontOptions::with_sampler]).
+///
+/// Comparison samplers aren't supported, since they only work with depth textures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SamplerSettings {
+    /// How texture coordinates outside the texture are handled horizontally. The default is
+    /// [wgpu::AddressMode::ClampToEdge].
+    pub address_mode_u: wgpu::AddressMode,
+    /// How texture coordinates outside the texture are handled vertically. The default is
+    /// [wgpu::AddressMode::ClampToEdge].
+    pub address_mode_v: wgpu::AddressMode,
+    /// How to blend between mip levels. kaku's own glyph textures only have one level, so this is
+    /// for textures you've made yourself, e.g. with [crate::TextRenderer::glyph_texture]. The
+    /// default is [wgpu::FilterMode::Nearest].
+    pub mipmap_filter: wgpu::FilterMode,
+    /// The maximum anisotropy, from 1 (off, the default) to 16. This is only used if every
+    /// filter is linear, including the mipmap filter, and is ignored otherwise (e.g. for bitmap
+    /// fonts drawn with nearest filtering, or in compatibility mode).
+    pub anisotropy_clamp: u16,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            anisotropy_clamp: 1,
+        }
+    }
+}
+
+impl SamplerSettings {
+    /// Creates a sampler with these settings and the given min and mag filter.
+    ///
+    /// In compatibility mode the shaders do the filtering, so every filter is nearest.
+    pub(crate) fn create_sampler(
+        &self,
+        label: &str,
+        filter: wgpu::FilterMode,
+        compatibility: bool,
+        device: &wgpu::Device,
+    ) -> wgpu::Sampler {
+        let (filter, mipmap_filter) = if compatibility {
+            (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest)
+        } else {
+            (filter, self.mipmap_filter)
+        };
+
+        // wgpu rejects anisotropic samplers unless all their filters are linear
+        let all_linear =
+            filter == wgpu::FilterMode::Linear && mipmap_filter == wgpu::FilterMode::Linear;
+        let anisotropy_clamp = if all_linear {
+            self.anisotropy_clamp.clamp(1, 16)
+        } else {
+            1
+        };
+
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: self.address_mode_u,
+            address_mode_v: self.address_mode_v,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter,
+            anisotropy_clamp,
+            ..Default::default()
+        })
+    }
+}
@@ -1,12 +1,8 @@
-use std::cmp::Reverse;
-
-use ahash::{HashSet, HashSetExt};
-use image::{GrayImage, Luma};
-use ordered_float::OrderedFloat;
-use priority_queue::PriorityQueue;
+use ab_glyph::{Outline, OutlineCurve, Point, PxScaleFactor, Rect};
+use image::{GrayImage, ImageBuffer, Luma};
 
 /// Settings for how the signed distance field calculation should work for a font.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct SdfSettings {
     /// The sdf spread radius.
     ///
@@ -14,6 +10,10 @@ pub struct SdfSettings {
     /// size of effects such as outlines, glow, shadows etc. A higher radius means you can create
     /// larger outlines, but will use more memory on the GPU.
     pub radius: f32,
+    /// What the distance field is calculated from. The default is [SdfSource::Raster].
+    pub source: SdfSource,
+    /// The texture format the distance field is stored in. The default is [SdfFormat::R8Unorm].
+    pub format: SdfFormat,
     // Stuff to do in the future:
 
     // How much to scale up the texture when generating the sdf texture
@@ -21,25 +21,97 @@ pub struct SdfSettings {
     // pub prescale: f32,
 }
 
-fn add_coords_checked(
-    (w, h): (u32, u32),
-    (x, y): (u32, u32),
-    (dx, dy): (i64, i64),
-) -> Option<(u32, u32)> {
-    let x = x as i64 + dx;
-    let y = y as i64 + dy;
+impl Default for SdfSettings {
+    fn default() -> Self {
+        Self {
+            radius: 10.,
+            source: SdfSource::default(),
+            format: SdfFormat::default(),
+        }
+    }
+}
+
+/// What a signed distance field is calculated from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SdfSource {
+    /// Calculate the distance field from the anti-aliased raster image of the glyph.
+    ///
+    /// This is fast, but the distances are only approximate, which can cause small artefacts
+    /// when the text is scaled up a lot.
+    #[default]
+    Raster,
+    /// Calculate the distance field directly from the curves of the glyph's outline.
+    ///
+    /// The distance to each line and quadratic curve (all that TrueType outlines are made of) is
+    /// calculated exactly, and to cubic curves it's found numerically, so text stays clean even at
+    /// very large scales. This takes longer to calculate for glyphs with complicated outlines.
+    Outline,
+}
+
+/// The texture format used to store a signed distance field on the GPU.
+///
+/// A distance field is stored as values from 0 to 1 that cover the whole sdf radius, so with a
+/// wide radius each step in an 8 bit texture covers a larger distance. For large glows and
+/// outlines this can show up as banding, which the 16 bit formats avoid at the cost of using twice
+/// as much memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SdfFormat {
+    /// 8 bit normalised values ([wgpu::TextureFormat::R8Unorm]).
+    #[default]
+    R8Unorm,
+    /// 16 bit normalised values ([wgpu::TextureFormat::R16Unorm]).
+    ///
+    /// This needs the [wgpu::Features::TEXTURE_FORMAT_16BIT_NORM] feature to be enabled on the
+    /// device, or loading the font returns [Error::MissingFeatures](crate::Error::MissingFeatures).
+    R16Unorm,
+    /// 16 bit floating point values ([wgpu::TextureFormat::R16Float]).
+    R16Float,
+}
 
-    if x < 0 || y < 0 || x >= w as i64 || y >= h as i64 {
-        return None;
+#[cfg(has_wgpu)]
+impl SdfFormat {
+    pub(crate) fn texture_format(&self) -> wgpu::TextureFormat {
+        match self {
+            SdfFormat::R8Unorm => wgpu::TextureFormat::R8Unorm,
+            SdfFormat::R16Unorm => wgpu::TextureFormat::R16Unorm,
+            SdfFormat::R16Float => wgpu::TextureFormat::R16Float,
+        }
     }
 
-    // We've checked x and y are >0 so this is fine
-    let x = x as u32;
-    let y = y as u32;
+    /// The device features textures in this format need.
+    pub(crate) fn required_features(&self) -> wgpu::Features {
+        match self {
+            SdfFormat::R16Unorm => wgpu::Features::TEXTURE_FORMAT_16BIT_NORM,
+            SdfFormat::R8Unorm | SdfFormat::R16Float => wgpu::Features::empty(),
+        }
+    }
 
-    Some((x, y))
+    /// Converts a distance field into the bytes to upload to a texture of this format.
+    pub(crate) fn encode(&self, image: &SdfImage) -> Vec<u8> {
+        match self {
+            SdfFormat::R8Unorm => image.iter().map(|v| (v * 255.) as u8).collect(),
+            SdfFormat::R16Unorm => image
+                .iter()
+                .flat_map(|v| ((v * 65535.) as u16).to_ne_bytes())
+                .collect(),
+            SdfFormat::R16Float => image
+                .iter()
+                .flat_map(|v| half::f16::from_f32(*v).to_ne_bytes())
+                .collect(),
+        }
+    }
 }
 
+/// A signed distance field, where each value is in the range 0-1.
+///
+/// A value of 0.5 is on the edge of the glyph, and 0 and 1 are the sdf radius away from the edge
+/// on the inside and outside respectively.
+pub(crate) type SdfImage = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+/// A stand-in for infinity in the distance transform. Using a large finite value instead of
+/// f32::INFINITY keeps the parabola intersections from turning into NaNs.
+const INF: f32 = 1e20;
+
 /// Converts a coverage value from u8 to f32.
 /// I'm using u8s to avoid floating point errors, but when I need to convert to float I will use
 /// this.
@@ -56,83 +128,259 @@ fn is_empty(value: u8) -> bool {
     value == 0
 }
 
-/// Calculates whether a point is on the boundary or not.
-/// A point is a boundary point if the boundary crosses through it (i.e., 0 < coverage < 1), or it
-/// is a fully covered pixel that borders a fully uncovered pixel.
-fn is_boundary_point(image: &GrayImage, (w, h): (u32, u32), (x, y): (u32, u32)) -> bool {
-    let value = image.get_pixel(x, y).0[0];
-
-    if is_empty(value) {
-        false
-    } else if !is_filled(value) {
-        true
+/// The starting values of the two grids fed into the distance transform for a pixel with the
+/// given coverage, as (outer, inner).
+///
+/// The outer grid is 0 on the glyph and infinite outside it, so transforming it gives the squared
+/// distance to the glyph. The inner grid is the opposite. Anti-aliased pixels are partway between,
+/// so their coverage is used as a sub-pixel offset to the edge.
+fn initial_values(value: u8) -> (f32, f32) {
+    if is_filled(value) {
+        (0., INF)
+    } else if is_empty(value) {
+        (INF, 0.)
     } else {
-        // Check the surrounding vertices to see if there are any empty pixels
-        for dx in -1..=1 {
-            for dy in -1..=1 {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
+        let a = value_u8_to_f32(value);
+        let outer = (0.5 - a).max(0.);
+        let inner = (a - 0.5).max(0.);
+        (outer * outer, inner * inner)
+    }
+}
 
-                let Some((x, y)) = add_coords_checked((w, h), (x, y), (dx, dy)) else {
-                    // This means we are on the boundary of the texture
-                    // which is the boundary of the glyph, too!
-                    return true;
-                };
-                let neighbouring_value = image.get_pixel(x, y).0[0];
+/// Reusable buffers for the distance transform, so they aren't reallocated for every row.
+struct EdtBuffers {
+    f: Vec<f32>,
+    v: Vec<usize>,
+    z: Vec<f32>,
+}
 
-                if is_empty(neighbouring_value) {
-                    return true;
-                }
-            }
+impl EdtBuffers {
+    fn new(len: usize) -> Self {
+        Self {
+            f: vec![0.; len],
+            v: vec![0; len],
+            z: vec![0.; len + 1],
+        }
+    }
+}
+
+/// Finds the lower envelope of the parabolas rooted at each of the `n` values of `f`, from
+/// Felzenszwalb & Huttenlocher's distance transform. Returns the root of the envelope at each
+/// position.
+fn lower_envelope<'a>(
+    f: impl Fn(usize) -> f32,
+    n: usize,
+    v: &'a mut [usize],
+    z: &'a mut [f32],
+) -> impl Iterator<Item = usize> + 'a {
+    let intersect = |q: usize, r: usize| {
+        ((f(q) + (q * q) as f32) - (f(r) + (r * r) as f32)) / (2 * q - 2 * r) as f32
+    };
+
+    let mut k = 0;
+    v[0] = 0;
+    z[0] = -INF;
+    z[1] = INF;
+
+    for q in 1..n {
+        let mut s = intersect(q, v[k]);
+
+        while s <= z[k] {
+            k -= 1;
+            s = intersect(q, v[k]);
+        }
+
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = INF;
+    }
+
+    let mut k = 0;
+
+    (0..n).map(move |q| {
+        while z[k + 1] < q as f32 {
+            k += 1;
         }
 
-        false
+        v[k]
+    })
+}
+
+/// The one dimensional squared euclidean distance transform, done in place on `row`.
+fn edt_1d(row: &mut [f32], buffers: &mut EdtBuffers) {
+    let n = row.len();
+    let EdtBuffers { f, v, z } = buffers;
+    f[..n].copy_from_slice(row);
+
+    for (q, (out, root)) in row
+        .iter_mut()
+        .zip(lower_envelope(|q| f[q], n, v, z))
+        .enumerate()
+    {
+        let d = q as f32 - root as f32;
+        *out = d * d + f[root];
+    }
+}
+
+/// Runs the distance transform along one axis of a `w` by `h` grid, in place.
+///
+/// This transforms each row, which are contiguous in memory.
+fn edt_pass(grid: &mut [f32], (w, _): (usize, usize), buffers: &mut EdtBuffers) {
+    for row in grid.chunks_exact_mut(w) {
+        edt_1d(row, buffers);
     }
 }
 
-/// This struct is private and used only for the function [create_sdf_textre].
-/// it is a priority queue key used for a modified version of Dijkstra's algorithm.
-struct PQKey {
-    // The vector distance to the closest boundary point
-    vector: [f32; 2],
-    // The distance modifier of the closest boundary point (based on coverage)
-    dist: f32,
-    interior: bool,
+/// Runs the 2D distance transform on a grid for a field with the given radius, using `scratch`
+/// for the values in between its passes.
+///
+/// With the `simd` feature, fields with small radii are transformed with [edt_2d_windowed],
+/// which is faster than finding the lower envelope for them.
+fn edt_2d(grid: &mut [f32], scratch: &mut [f32], (w, h): (usize, usize), radius: f32) {
+    // Look a pixel further than the radius, so that everything that isn't clamped is exact
+    #[cfg(feature = "simd")]
+    let reach = radius.ceil() as usize + 1;
+    #[cfg(feature = "simd")]
+    if reach <= MAX_WINDOW_REACH {
+        return edt_2d_windowed(grid, scratch, (w, h), reach);
+    }
+
+    #[cfg(not(feature = "simd"))]
+    let _ = radius;
+
+    edt_2d_exact(grid, scratch, (w, h));
+}
+
+/// Runs the 2D distance transform on a grid. The transform is separable, so this is one pass
+/// along each axis, with the grid transposed in between so both passes walk memory the same way.
+fn edt_2d_exact(grid: &mut [f32], scratch: &mut [f32], (w, h): (usize, usize)) {
+    let mut buffers = EdtBuffers::new(w.max(h));
+
+    edt_pass(grid, (w, h), &mut buffers);
+    transpose(grid, scratch, (w, h));
+    edt_pass(scratch, (h, w), &mut buffers);
+    transpose(scratch, grid, (h, w));
 }
 
-impl PartialEq for PQKey {
-    fn eq(&self, other: &Self) -> bool {
-        OrderedFloat(self.vector[0]) == OrderedFloat(other.vector[0])
-            && OrderedFloat(self.vector[1]) == OrderedFloat(other.vector[1])
-            && OrderedFloat(self.dist) == OrderedFloat(other.dist)
+/// Transposes a `w` by `h` grid into `out`, which becomes `h` by `w`.
+fn transpose(grid: &[f32], out: &mut [f32], (w, h): (usize, usize)) {
+    for (y, row) in grid.chunks_exact(w).enumerate() {
+        for (x, value) in row.iter().enumerate() {
+            out[x * h + y] = *value;
+        }
     }
 }
 
-impl Eq for PQKey {}
+/// The furthest [edt_2d_windowed] looks from each pixel before it's quicker to find the lower
+/// envelope, which takes the same time whatever the radius. Measured on x86_64 with SSE2.
+#[cfg(feature = "simd")]
+const MAX_WINDOW_REACH: usize = 16;
+
+/// Runs the 2D distance transform on a grid, with each pass taking the smallest of the values
+/// within `reach` pixels of each pixel, plus the squared distance to them.
+///
+/// Unlike finding the lower envelope, this works on whole rows at once, 4 pixels at a time, and
+/// needs no transposing. It's only exact up to `reach`, but everything further away than the
+/// radius of the field is clamped to its edge anyway.
+#[cfg(feature = "simd")]
+fn edt_2d_windowed(grid: &mut [f32], scratch: &mut [f32], (w, h): (usize, usize), reach: usize) {
+    // Down the columns, taking rows above and below each row
+    for (y, out) in scratch.chunks_exact_mut(w).enumerate() {
+        out.copy_from_slice(&grid[y * w..(y + 1) * w]);
+
+        for d in 1..=reach {
+            let cost = (d * d) as f32;
+
+            if let Some(above) = y.checked_sub(d) {
+                min_plus(out, &grid[above * w..(above + 1) * w], cost);
+            }
+            if y + d < h {
+                min_plus(out, &grid[(y + d) * w..(y + d + 1) * w], cost);
+            }
+        }
+    }
 
-impl PQKey {
-    fn distance(&self) -> f32 {
-        let mut vec_dist =
-            (self.vector[0] * self.vector[0] + self.vector[1] * self.vector[1]).sqrt();
+    // Along the rows, taking the row shifted left and right
+    for (row, out) in scratch.chunks_exact(w).zip(grid.chunks_exact_mut(w)) {
+        out.copy_from_slice(row);
 
-        if self.interior {
-            vec_dist *= -1.0;
+        for d in 1..=reach.min(w.saturating_sub(1)) {
+            let cost = (d * d) as f32;
+            min_plus(&mut out[d..], &row[..w - d], cost);
+            min_plus(&mut out[..w - d], &row[d..], cost);
         }
+    }
+}
+
+/// Lowers each value of `out` to the value at the same place in `source` plus `cost`, if that's
+/// smaller, 4 values at a time.
+#[cfg(feature = "simd")]
+fn min_plus(out: &mut [f32], source: &[f32], cost: f32) {
+    use wide::f32x4;
+
+    let cost_x4 = f32x4::splat(cost);
+    let mut out_chunks = out.chunks_exact_mut(4);
+    let mut source_chunks = source.chunks_exact(4);
+
+    for (out, source) in (&mut out_chunks).zip(&mut source_chunks) {
+        let o = f32x4::from(<[f32; 4]>::try_from(&*out).unwrap());
+        let s = f32x4::from(<[f32; 4]>::try_from(source).unwrap());
+        out.copy_from_slice(&o.min(s + cost_x4).to_array());
+    }
 
-        vec_dist + self.dist
+    for (out, source) in out_chunks
+        .into_remainder()
+        .iter_mut()
+        .zip(source_chunks.remainder())
+    {
+        *out = out.min(source + cost);
     }
 }
 
-impl Ord for PQKey {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        OrderedFloat(self.distance().abs()).cmp(&OrderedFloat(other.distance().abs()))
+/// Turns the transformed outer and inner grids into final sdf values.
+///
+/// This is where most of the per-pixel arithmetic is, so with the `simd` feature it works on 4
+/// pixels at a time.
+#[cfg(feature = "simd")]
+fn combine_distances(outer: &[f32], inner: &[f32], out: &mut [f32], radius: f32) {
+    use wide::f32x4;
+
+    let scale = f32x4::splat(1. / (2. * radius));
+    let half = f32x4::splat(0.5);
+    let zero = f32x4::splat(0.);
+    let one = f32x4::splat(1.);
+
+    let mut outer_chunks = outer.chunks_exact(4);
+    let mut inner_chunks = inner.chunks_exact(4);
+    let mut out_chunks = out.chunks_exact_mut(4);
+
+    for ((o, i), out) in (&mut outer_chunks)
+        .zip(&mut inner_chunks)
+        .zip(&mut out_chunks)
+    {
+        let o = f32x4::from(<[f32; 4]>::try_from(o).unwrap());
+        let i = f32x4::from(<[f32; 4]>::try_from(i).unwrap());
+        let value = ((o.sqrt() - i.sqrt()) * scale + half).max(zero).min(one);
+        out.copy_from_slice(&value.to_array());
     }
+
+    combine_distances_scalar(
+        outer_chunks.remainder(),
+        inner_chunks.remainder(),
+        out_chunks.into_remainder(),
+        radius,
+    );
+}
+
+#[cfg(not(feature = "simd"))]
+fn combine_distances(outer: &[f32], inner: &[f32], out: &mut [f32], radius: f32) {
+    combine_distances_scalar(outer, inner, out, radius);
 }
 
-impl PartialOrd for PQKey {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+fn combine_distances_scalar(outer: &[f32], inner: &[f32], out: &mut [f32], radius: f32) {
+    for ((o, i), out) in outer.iter().zip(inner).zip(out) {
+        *out = ((o.sqrt() - i.sqrt()) / (2. * radius) + 0.5).clamp(0., 1.);
     }
 }
 
@@ -140,7 +388,7 @@ pub(crate) fn create_sdf_texture(
     image: &GrayImage,
     dimensions: (u32, u32),
     sdf: &SdfSettings,
-) -> (GrayImage, u32) {
+) -> (SdfImage, u32) {
     // ab_glyph provides us with grayscale, anti-aliased images of glyphs. We can use this to our
     // advantage by using the value of an anti-aliased pixel to inform the distance calculation.
 
@@ -153,116 +401,377 @@ pub(crate) fn create_sdf_texture(
         dimensions.1 + 2 * texture_padding,
     );
 
-    // converts the coordinates from the old texture to the new, expanded texture
-    let convert_to_new_coord =
-        |x: u32, y: u32| -> (u32, u32) { (x + texture_padding, y + texture_padding) };
+    let (w, h) = (new_dimensions.0 as usize, new_dimensions.1 as usize);
+    let padding = texture_padding as usize;
 
-    // converts the new coordinates to the old coordinates.
-    // returns None if this pixel wouldn't have been in the old texture.
-    let convert_to_old_coord = |x: u32, y: u32| -> Option<(u32, u32)> {
-        let x = x.checked_sub(texture_padding)?;
-        let y = y.checked_sub(texture_padding)?;
+    // Run an exact euclidean distance transform twice: once to find the distance from each pixel
+    // to the glyph and once for the distance to the outside of the glyph. Everything outside the
+    // original texture is outside the glyph.
+    let mut outer = vec![INF; w * h];
+    let mut inner = vec![0.; w * h];
 
-        (x < dimensions.0 && y < dimensions.1).then_some((x, y))
-    };
+    for (y, row) in image.rows().enumerate() {
+        let start = (y + padding) * w + padding;
+
+        for (x, pixel) in row.enumerate() {
+            let (o, i) = initial_values(pixel.0[0]);
+            outer[start + x] = o;
+            inner[start + x] = i;
+        }
+    }
 
-    // converts the signed distance from an absolute float value to a scaled byte value for usage
-    // in a texture.
-    let convert_signed_dist =
-        |val: f32| -> Luma<u8> { Luma([((val / (2. * sdf.radius) + 0.5) * 255.) as u8]) };
+    let mut scratch = vec![0.; w * h];
+    edt_2d(&mut outer, &mut scratch, (w, h), sdf.radius);
+    edt_2d(&mut inner, &mut scratch, (w, h), sdf.radius);
 
-    let mut sdf_texture = GrayImage::new(new_dimensions.0, new_dimensions.1);
+    // The signed distance is the distance to the glyph minus the distance to the outside, which
+    // is negative inside the glyph.
+    combine_distances(&outer, &inner, &mut scratch, sdf.radius);
 
-    // Use a modified dijkstra's algorithm, starting at the boundary pixels, to calculate the
-    // distance from each pixel to its closest boundary
+    let sdf_texture = SdfImage::from_raw(new_dimensions.0, new_dimensions.1, scratch)
+        .expect("sdf buffer should match its dimensions");
 
-    let mut frontier = PriorityQueue::new();
-    let mut visited = HashSet::new();
+    (sdf_texture, texture_padding)
+}
 
-    for x in 0..new_dimensions.0 {
-        for y in 0..new_dimensions.1 {
-            sdf_texture.put_pixel(x, y, convert_signed_dist(sdf.radius));
-        }
+/// A point in the pixel coordinates of a glyph's texture (before padding is added). Curves are
+/// measured in f64, since finding the nearest point on them involves cubic equations.
+type Vec2 = [f64; 2];
+
+fn sub(a: Vec2, b: Vec2) -> Vec2 {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn dot(a: Vec2, b: Vec2) -> f64 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+/// One of the curves of a glyph's outline.
+#[derive(Clone, Copy, Debug)]
+enum Curve {
+    Line([Vec2; 2]),
+    Quad([Vec2; 3]),
+    Cubic([Vec2; 4]),
+}
+
+impl Curve {
+    /// The curves of a glyph outline, in the pixel coordinates of the glyph's texture.
+    fn from_outline(outline: &Outline, scale_factor: PxScaleFactor, px_bounds: Rect) -> Vec<Self> {
+        let to_px = |p: &Point| -> Vec2 {
+            [
+                (p.x * scale_factor.horizontal - px_bounds.min.x) as f64,
+                (p.y * -scale_factor.vertical - px_bounds.min.y) as f64,
+            ]
+        };
+
+        (outline.curves.iter())
+            .map(|curve| match curve {
+                OutlineCurve::Line(p0, p1) => Curve::Line([to_px(p0), to_px(p1)]),
+                OutlineCurve::Quad(p0, p1, p2) => Curve::Quad([to_px(p0), to_px(p1), to_px(p2)]),
+                OutlineCurve::Cubic(p0, p1, p2, p3) => {
+                    Curve::Cubic([to_px(p0), to_px(p1), to_px(p2), to_px(p3)])
+                }
+            })
+            .collect()
     }
 
-    for x in 0..dimensions.0 {
-        for y in 0..dimensions.1 {
-            let (xp, yp) = convert_to_new_coord(x, y);
-
-            if is_boundary_point(image, dimensions, (x, y)) {
-                let signed_dist = 0.5 - value_u8_to_f32(image.get_pixel(x, y).0[0]);
-                sdf_texture.put_pixel(xp, yp, convert_signed_dist(signed_dist));
-                frontier.push(
-                    (xp, yp),
-                    Reverse(PQKey {
-                        vector: [0., 0.],
-                        dist: signed_dist,
-                        interior: true,
-                    }),
-                );
-                visited.insert((xp, yp));
-            } else if is_filled(image.get_pixel(x, y).0[0]) {
-                sdf_texture.put_pixel(xp, yp, convert_signed_dist(-sdf.radius));
-            }
+    fn points(&self) -> &[Vec2] {
+        match self {
+            Curve::Line(points) => points,
+            Curve::Quad(points) => points,
+            Curve::Cubic(points) => points,
         }
     }
 
-    while let Some(((x, y), Reverse(priority))) = frontier.pop() {
-        sdf_texture.put_pixel(x, y, convert_signed_dist(priority.distance()));
+    /// The point at `t` along the curve, from 0 to 1.
+    fn eval(&self, t: f64) -> Vec2 {
+        let u = 1. - t;
+        let weights = match self {
+            Curve::Line(_) => [u, t, 0., 0.],
+            Curve::Quad(_) => [u * u, 2. * u * t, t * t, 0.],
+            Curve::Cubic(_) => [u * u * u, 3. * u * u * t, 3. * u * t * t, t * t * t],
+        };
+
+        (self.points().iter().zip(weights)).fold([0., 0.], |sum, (p, w)| {
+            [sum[0] + w * p[0], sum[1] + w * p[1]]
+        })
+    }
 
-        for dx in -1..=1 {
-            for dy in -1..=1 {
-                // Filtering out squares we don't want to visit
-                // (this one, out of bounds, etc)
-                if dx == 0 && dy == 0 {
-                    continue;
+    /// The squared distance from a point to the nearest point on the curve.
+    ///
+    /// For lines and quadratic curves the nearest point is found exactly, as a root of the
+    /// derivative of the squared distance. For cubic curves that derivative is a quintic, so the
+    /// nearest point is found with Newton's method instead, starting from the nearest of a few
+    /// points along the curve.
+    fn distance_squared(&self, p: Vec2) -> f64 {
+        let distance_at = |t: f64| {
+            let d = sub(self.eval(t.clamp(0., 1.)), p);
+            dot(d, d)
+        };
+
+        match *self {
+            Curve::Line([p0, p1]) => {
+                let ab = sub(p1, p0);
+                let length_squared = dot(ab, ab);
+
+                if length_squared > 0. {
+                    distance_at(dot(sub(p, p0), ab) / length_squared)
+                } else {
+                    distance_at(0.)
                 }
+            }
+            Curve::Quad([p0, p1, p2]) => {
+                // With B(t) = p0 + 2ta + t²b, the distance is smallest where (B(t) - p)·B'(t) = 0
+                let a = sub(p1, p0);
+                let b = [p2[0] - 2. * p1[0] + p0[0], p2[1] - 2. * p1[1] + p0[1]];
+                let m = sub(p0, p);
+
+                let (roots, count) = cubic_roots(
+                    dot(b, b),
+                    3. * dot(a, b),
+                    2. * dot(a, a) + dot(m, b),
+                    dot(m, a),
+                );
 
-                let Some((x, y)) = add_coords_checked(new_dimensions, (x, y), (dx, dy)) else {
-                    continue;
+                (roots[..count].iter())
+                    .map(|&t| distance_at(t))
+                    .fold(distance_at(0.).min(distance_at(1.)), f64::min)
+            }
+            Curve::Cubic([p0, p1, p2, p3]) => {
+                const SAMPLES: usize = 16;
+                const STEPS: usize = 8;
+
+                let mut t = (0..=SAMPLES)
+                    .map(|i| i as f64 / SAMPLES as f64)
+                    .min_by(|&a, &b| distance_at(a).total_cmp(&distance_at(b)))
+                    .unwrap_or(0.);
+
+                // The first and second derivatives of the curve
+                let d1 = |t: f64| {
+                    let u = 1. - t;
+                    let w = [3. * u * u, 6. * u * t, 3. * t * t];
+                    let q = [sub(p1, p0), sub(p2, p1), sub(p3, p2)];
+                    [
+                        w[0] * q[0][0] + w[1] * q[1][0] + w[2] * q[2][0],
+                        w[0] * q[0][1] + w[1] * q[1][1] + w[2] * q[2][1],
+                    ]
+                };
+                let d2 = |t: f64| {
+                    let r = [
+                        [p2[0] - 2. * p1[0] + p0[0], p2[1] - 2. * p1[1] + p0[1]],
+                        [p3[0] - 2. * p2[0] + p1[0], p3[1] - 2. * p2[1] + p1[1]],
+                    ];
+                    [
+                        6. * ((1. - t) * r[0][0] + t * r[1][0]),
+                        6. * ((1. - t) * r[0][1] + t * r[1][1]),
+                    ]
                 };
 
-                if visited.contains(&(x, y)) {
-                    continue;
-                }
+                for _ in 0..STEPS {
+                    let d = sub(self.eval(t), p);
+                    let (first, second) = (d1(t), d2(t));
+                    let slope = dot(first, first) + dot(d, second);
 
-                let interior = match convert_to_old_coord(x, y) {
-                    Some((old_x, old_y)) => {
-                        let value = image.get_pixel(old_x, old_y).0[0];
-                        if is_empty(value) {
-                            false
-                        } else if is_filled(value) {
-                            true
-                        } else {
-                            continue;
-                        }
+                    if slope <= 0. {
+                        break;
                     }
-                    // Points that were not in the original texture are in the exterior
-                    None => false,
-                };
 
-                let vector_distance = [dx as f32, dy as f32];
+                    t = (t - dot(d, first) / slope).clamp(0., 1.);
+                }
+
+                distance_at(t).min(distance_at(0.)).min(distance_at(1.))
+            }
+        }
+    }
+
+    /// The contribution of the curve to the winding number of a point, counting the crossings of
+    /// a ray from the point towards +x.
+    fn winding(&self, p: Vec2) -> i32 {
+        let points = self.points();
+
+        // Curves lie inside the hull of their points, so most of them can be skipped
+        if points.iter().all(|q| q[0] <= p[0])
+            || points.iter().all(|q| q[1] > p[1])
+            || points.iter().all(|q| q[1] <= p[1])
+        {
+            return 0;
+        }
+
+        // Split the curve where it turns around in y, so each piece crosses the ray at most once
+        let mut splits = [0.; 4];
+        let mut count = 1;
+        let y = |i: usize| points[i][1];
+
+        let (mut roots, root_count) = match *self {
+            Curve::Line(_) => ([0.; 3], 0),
+            Curve::Quad(_) => cubic_roots(0., 0., y(0) - 2. * y(1) + y(2), y(1) - y(0)),
+            Curve::Cubic(_) => cubic_roots(
+                0.,
+                -y(0) + 3. * y(1) - 3. * y(2) + y(3),
+                2. * (y(0) - 2. * y(1) + y(2)),
+                y(1) - y(0),
+            ),
+        };
+
+        roots[..root_count].sort_by(f64::total_cmp);
+
+        for &t in roots[..root_count].iter().filter(|&&t| t > 0. && t < 1.) {
+            splits[count] = t;
+            count += 1;
+        }
+
+        splits[count] = 1.;
 
-                let vector = [
-                    vector_distance[0] + priority.vector[0],
-                    vector_distance[1] + priority.vector[1],
-                ];
+        (splits[..=count].windows(2))
+            .map(|piece| {
+                let (t0, t1) = (piece[0], piece[1]);
+                let (start, end) = (self.eval(t0), self.eval(t1));
 
-                let new_key = PQKey {
-                    vector,
-                    dist: priority.dist,
-                    interior,
+                // Like segment crossings, pieces include their start but not their end
+                let direction = if start[1] <= p[1] && end[1] > p[1] {
+                    1
+                } else if end[1] <= p[1] && start[1] > p[1] {
+                    -1
+                } else {
+                    return 0;
                 };
 
-                if new_key.distance().abs() >= sdf.radius {
-                    continue;
+                // The piece only goes one way in y, so the crossing can be found by bisection
+                let (mut low, mut high) = (t0, t1);
+
+                for _ in 0..48 {
+                    let mid = 0.5 * (low + high);
+                    if (self.eval(mid)[1] <= p[1]) == (direction == 1) {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+
+                if self.eval(0.5 * (low + high))[0] > p[0] {
+                    direction
+                } else {
+                    0
                 }
+            })
+            .sum()
+    }
+}
+
+/// The real roots of `a t³ + b t² + c t + d`, which can be a quadratic or linear equation if the
+/// first coefficients are zero.
+fn cubic_roots(a: f64, b: f64, c: f64, d: f64) -> ([f64; 3], usize) {
+    let scale = a.abs().max(b.abs()).max(c.abs()).max(d.abs());
+    let negligible = |x: f64| x.abs() <= scale * 1e-12;
+
+    let (mut roots, count) = if scale == 0. {
+        return ([0.; 3], 0);
+    } else if !negligible(a) {
+        // Solve the depressed cubic x³ + px + q = 0, with t = x - b / 3a
+        let (b, c, d) = (b / a, c / a, d / a);
+        let p = c - b * b / 3.;
+        let q = b * (2. * b * b - 9. * c) / 27. + d;
+        let offset = -b / 3.;
+        let discriminant = q * q / 4. + p * p * p / 27.;
+
+        if discriminant >= 0. {
+            let root = discriminant.sqrt();
+            (
+                [
+                    (-q / 2. + root).cbrt() + (-q / 2. - root).cbrt() + offset,
+                    0.,
+                    0.,
+                ],
+                1,
+            )
+        } else {
+            // Three real roots, which needs p < 0
+            let r = 2. * (-p / 3.).sqrt();
+            let phi = ((3. * q / (2. * p)) * (-3. / p).sqrt())
+                .clamp(-1., 1.)
+                .acos()
+                / 3.;
+            let third = std::f64::consts::TAU / 3.;
+            (
+                [
+                    r * phi.cos() + offset,
+                    r * (phi - third).cos() + offset,
+                    r * (phi - 2. * third).cos() + offset,
+                ],
+                3,
+            )
+        }
+    } else if !negligible(b) {
+        let discriminant = c * c - 4. * b * d;
+
+        if discriminant < 0. {
+            return ([0.; 3], 0);
+        }
+
+        let root = discriminant.sqrt();
+        ([(-c + root) / (2. * b), (-c - root) / (2. * b), 0.], 2)
+    } else if !negligible(c) {
+        ([-d / c, 0., 0.], 1)
+    } else {
+        return ([0.; 3], 0);
+    };
+
+    // Closed form roots can be off by a lot more than rounding, so polish them with a couple of
+    // Newton steps on the original equation
+    for t in &mut roots[..count] {
+        for _ in 0..2 {
+            let value = ((a * *t + b) * *t + c) * *t + d;
+            let slope = (3. * a * *t + 2. * b) * *t + c;
 
-                frontier.push_increase((x, y), Reverse(new_key));
+            if slope != 0. {
+                *t -= value / slope;
             }
         }
+    }
+
+    (roots, count)
+}
+
+/// Creates a signed distance field texture directly from a glyph's outline curves.
+///
+/// This returns the same thing as [create_sdf_texture], but the distances are measured to the
+/// outline's curves themselves instead of being estimated from a raster image.
+pub(crate) fn create_sdf_texture_from_outline(
+    outline: &Outline,
+    scale_factor: PxScaleFactor,
+    px_bounds: Rect,
+    sdf: &SdfSettings,
+) -> (SdfImage, u32) {
+    let texture_padding = sdf.radius.ceil() as u32;
+    let width = px_bounds.width().ceil() as u32 + 2 * texture_padding;
+    let height = px_bounds.height().ceil() as u32 + 2 * texture_padding;
+
+    let curves = Curve::from_outline(outline, scale_factor, px_bounds);
+    let mut sdf_texture = SdfImage::new(width, height);
+
+    for (x, y, pixel) in sdf_texture.enumerate_pixels_mut() {
+        // Sample at the centre of the pixel, in the coordinates of the unpadded glyph
+        let p = [
+            x as f64 + 0.5 - texture_padding as f64,
+            y as f64 + 0.5 - texture_padding as f64,
+        ];
+
+        let mut distance_squared = f64::INFINITY;
+        let mut winding = 0;
+
+        for curve in &curves {
+            distance_squared = distance_squared.min(curve.distance_squared(p));
+            winding += curve.winding(p);
+        }
+
+        let mut distance = (distance_squared.sqrt() as f32).min(sdf.radius);
+
+        // Fonts use the non-zero winding rule, and the interior has negative distance
+        if winding != 0 {
+            distance = -distance;
+        }
 
-        visited.insert((x, y));
+        *pixel = Luma([(distance / (2. * sdf.radius) + 0.5).clamp(0., 1.)]);
     }
 
     (sdf_texture, texture_padding)
@@ -0,0 +1,139 @@
+//! Uniform buffers shared by the settings of many texts, for
+//! [TextRendererBuilder::with_shared_settings_buffer](crate::TextRendererBuilder::with_shared_settings_buffer).
+//!
+//! Settings are allocated in pages: one uniform buffer with room for [SLOTS_PER_PAGE] texts, and a
+//! bind group for each settings layout that binds one slot of it. A text is drawn by binding its
+//! page's bind group with the offset of its slot as the dynamic offset, so texts in the same page
+//! never need another bind group. Pages are never moved or resized, so a text's slot stays valid
+//! for as long as the text lives, and is given back to its page when the text is dropped.
+
+use std::{
+    num::NonZeroU64,
+    sync::{Arc, Mutex},
+};
+
+use crate::text::{SdfSettingsUniform, SettingsUniform};
+
+/// How many texts fit in one page of settings.
+const SLOTS_PER_PAGE: u32 = 256;
+
+/// One uniform buffer of text settings, and the bind groups for drawing with it.
+#[derive(Debug)]
+pub(crate) struct SettingsPage {
+    pub(crate) buffer: Arc<wgpu::Buffer>,
+    pub(crate) bind_group: wgpu::BindGroup,
+    pub(crate) sdf_bind_group: wgpu::BindGroup,
+    /// The indices of the slots no text is using.
+    free: Mutex<Vec<u32>>,
+}
+
+/// A text's place in a [SettingsPage]. The slot is freed when this is dropped.
+#[derive(Debug)]
+pub(crate) struct SettingsSlot {
+    pub(crate) page: Arc<SettingsPage>,
+    /// The offset of the slot in the page's buffer, in bytes.
+    pub(crate) offset: u32,
+    index: u32,
+}
+
+impl Drop for SettingsSlot {
+    fn drop(&mut self) {
+        self.page.free.lock().unwrap().push(self.index);
+    }
+}
+
+/// All the settings pages of a renderer.
+#[derive(Debug)]
+pub(crate) struct SettingsPool {
+    pages: Mutex<Vec<Arc<SettingsPage>>>,
+    /// The distance between slots, which has to be a multiple of the device's
+    /// `min_uniform_buffer_offset_alignment`.
+    stride: u32,
+}
+
+impl SettingsPool {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let alignment = device.limits().min_uniform_buffer_offset_alignment;
+        let size = std::mem::size_of::<SdfSettingsUniform>() as u32;
+
+        Self {
+            pages: Default::default(),
+            stride: size.div_ceil(alignment) * alignment,
+        }
+    }
+
+    /// The size of all the pages' buffers, in bytes.
+    pub(crate) fn size(&self) -> u64 {
+        let pages = self.pages.lock().unwrap();
+        pages.iter().map(|page| page.buffer.size()).sum()
+    }
+
+    /// Finds a free slot for a text's settings, creating a new page if every page is full.
+    pub(crate) fn allocate(
+        &self,
+        settings_layout: &wgpu::BindGroupLayout,
+        sdf_settings_layout: &wgpu::BindGroupLayout,
+        label: impl Fn(&str) -> String,
+        device: &wgpu::Device,
+    ) -> SettingsSlot {
+        let mut pages = self.pages.lock().unwrap();
+
+        for page in pages.iter() {
+            let index = page.free.lock().unwrap().pop();
+            if let Some(index) = index {
+                return SettingsSlot {
+                    page: page.clone(),
+                    offset: index * self.stride,
+                    index,
+                };
+            }
+        }
+
+        let buffer = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&label("shared text settings uniform buffer")),
+            size: (SLOTS_PER_PAGE * self.stride) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        }));
+
+        // Each bind group only sees one slot, the dynamic offset picks which
+        let bind_group = |name: &str, layout, size: usize| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&label(name)),
+                layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &buffer,
+                        offset: 0,
+                        size: NonZeroU64::new(size as u64),
+                    }),
+                }],
+            })
+        };
+
+        let page = Arc::new(SettingsPage {
+            bind_group: bind_group(
+                "shared text settings bind group",
+                settings_layout,
+                std::mem::size_of::<SettingsUniform>(),
+            ),
+            sdf_bind_group: bind_group(
+                "shared sdf text settings bind group",
+                sdf_settings_layout,
+                std::mem::size_of::<SdfSettingsUniform>(),
+            ),
+            buffer,
+            // Slot 0 is taken straight away, and popping hands out the lowest free slot first
+            free: Mutex::new((1..SLOTS_PER_PAGE).rev().collect()),
+        });
+
+        pages.push(page.clone());
+
+        SettingsSlot {
+            page,
+            offset: 0,
+            index: 0,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+// Draws a text that was baked into a texture (see TextRenderer::bake) as a single quad. The quad
+// is one instance, placed relative to the text's position like the glyphs of the text shaders
+struct CharacterInstance {
+    @location(0) char_position: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) index: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) vertex_position: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+};
+
+struct BakedSettings {
+    @location(0) text_position: vec2<f32>,
+    @location(1) relative_position: vec2<f32>,
+};
+
+@group(2) @binding(0)
+var<uniform> settings: BakedSettings;
+
+// Projection matrix that allows us to draw in pixel coords
+@group(0) @binding(0)
+var<uniform> screen: mat4x4<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, instance: CharacterInstance) -> VertexOutput {
+    var out: VertexOutput;
+
+    // The corners of the quad, drawn as a triangle strip: (0, 0), (0, 1), (1, 0), (1, 1)
+    let tex_coord = vec2<f32>(f32(vertex_index >> 1u), f32(vertex_index & 1u));
+
+    // Positions relative to the target are resolved against its size, from the projection's scale
+    let projection = screen;
+    let target_size = vec2<f32>(2.0 / projection[0][0], -2.0 / projection[1][1]);
+    let relative = round(settings.relative_position * target_size);
+
+    let origin = settings.text_position + relative;
+    let position = origin + instance.char_position + tex_coord * instance.size;
+
+    out.tex_coord = tex_coord;
+    out.vertex_position = projection * vec4<f32>(position, 0.0, 1.0);
+    return out;
+}
+
+@group(1) @binding(0)
+var texture: texture_2d<f32>;
+@group(1) @binding(1)
+var texture_sampler: sampler;
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    // The text was drawn over transparent black, so its colours are already multiplied by their
+    // alpha, and by the output brightness
+    return textureSample(texture, texture_sampler, input.tex_coord);
+}
@@ -0,0 +1,46 @@
+// One direction of a separable gaussian blur of a baked text (see TextRenderer::bake_blurred).
+// It's drawn over the whole texture with a single triangle, and loads texels from the source
+// without a sampler, so it works whether or not the texture's format can be filtered
+
+struct BlurSettings {
+    // (1, 0) to blur horizontally, and (0, 1) to blur vertically
+    direction: vec2<i32>,
+    // The standard deviation of the blur in pixels
+    sigma: f32,
+    // How many texels the blur reaches on each side
+    reach: i32,
+};
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> settings: BlurSettings;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // (-1, -1), (3, -1) and (-1, 3), which covers the whole target
+    let corner = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(source));
+    let centre = vec2<i32>(floor(position.xy));
+
+    // The colours are premultiplied by their alpha, so they can be averaged as they are
+    var total = vec4<f32>(0.0);
+    var weights = 0.0;
+    for (var i = -settings.reach; i <= settings.reach; i++) {
+        let weight = exp(-f32(i * i) / (2.0 * settings.sigma * settings.sigma));
+        weights += weight;
+
+        // Texels past the edges are transparent
+        let coord = centre + settings.direction * i;
+        if all(coord >= vec2<i32>(0)) && all(coord < size) {
+            total += weight * textureLoad(source, coord, 0);
+        }
+    }
+
+    return total / weights;
+}
@@ -0,0 +1,85 @@
+// Draws the glyphs of a cell buffer's cells (see CellBuffer). Each glyph is an instance, placed
+// relative to the buffer's position, and its index is its colour packed into 8 bits a channel
+struct CharacterInstance {
+    @location(0) char_position: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) index: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) vertex_position: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+    @location(1) @interpolate(flat) colour: vec4<f32>,
+};
+
+struct CellSettings {
+    @location(0) position: vec2<f32>,
+    @location(1) subpixel_phases: f32,
+    // 0 for raster fonts, whose textures hold coverage rather than distance
+    @location(2) sdf_radius: f32,
+    @location(3) image_scale: f32,
+};
+
+@group(2) @binding(0)
+var<uniform> settings: CellSettings;
+
+// Projection matrix that allows us to draw in pixel coords
+@group(0) @binding(0)
+var<uniform> screen: mat4x4<f32>;
+
+struct OutputSettings {
+    // Multiplies the colour, e.g. to make white text as bright as paper white on HDR targets
+    brightness: f32,
+    // The renderer's clock in seconds, which drives animations
+    time: f32,
+};
+
+@group(0) @binding(1)
+var<uniform> output: OutputSettings;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, instance: CharacterInstance) -> VertexOutput {
+    var out: VertexOutput;
+
+    // The corners of the quad, drawn as a triangle strip: (0, 0), (0, 1), (1, 0), (1, 1)
+    let tex_coord = vec2<f32>(f32(vertex_index >> 1u), f32(vertex_index & 1u));
+
+    let projection = screen;
+    var position = settings.position + instance.char_position;
+    out.tex_coord = tex_coord;
+
+    // Glyphs rasterised at several subpixel offsets are stacked vertically in their texture, like
+    // in the text shader
+    if settings.subpixel_phases > 1.0 {
+        let phases = settings.subpixel_phases;
+        let steps = round(position.x * phases);
+        position.x = floor(steps / phases);
+        let phase = steps - position.x * phases;
+        out.tex_coord.y = (tex_coord.y + phase) / phases;
+    }
+
+    position += tex_coord * instance.size;
+    out.colour = unpack4x8unorm(instance.index);
+    out.vertex_position = projection * vec4<f32>(position, 0.0, 1.0);
+    return out;
+}
+
+@group(1) @binding(0)
+var texture: texture_2d<f32>;
+@group(1) @binding(1)
+var texture_sampler: sampler;
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let value = textureSample(texture, texture_sampler, input.tex_coord).r;
+
+    // Distance fields are thresholded at their edge like in the sdf text shader
+    var alpha = value;
+    if settings.sdf_radius > 0.0 {
+        let distance = (value - 0.5) * 2.0 * settings.sdf_radius;
+        let aa_thresh = 1.0 / settings.image_scale;
+        alpha = smoothstep(aa_thresh, -aa_thresh, distance);
+    }
+
+    return vec4<f32>(input.colour.rgb * output.brightness, input.colour.a * alpha);
+}
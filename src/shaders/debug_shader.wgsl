@@ -0,0 +1,71 @@
+// Draws a text's debug overlay (see TextRenderer::draw_debug): the outline of each glyph's quad,
+// and each line's ascent, baseline and descent. Each of them is an instance, placed relative to
+// the text's position like the glyphs of the text shaders, and its index says what it is
+struct CharacterInstance {
+    @location(0) char_position: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) index: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) vertex_position: vec4<f32>,
+    // How far the fragment is from the quad's top left corner, in pixels
+    @location(0) offset: vec2<f32>,
+    @location(1) @interpolate(flat) size: vec2<f32>,
+    @location(2) @interpolate(flat) kind: u32,
+};
+
+struct DebugSettings {
+    @location(0) text_position: vec2<f32>,
+    @location(1) relative_position: vec2<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> settings: DebugSettings;
+
+// Projection matrix that allows us to draw in pixel coords
+@group(0) @binding(0)
+var<uniform> screen: mat4x4<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, instance: CharacterInstance) -> VertexOutput {
+    var out: VertexOutput;
+
+    // The corners of the quad, drawn as a triangle strip: (0, 0), (0, 1), (1, 0), (1, 1)
+    let corner = vec2<f32>(f32(vertex_index >> 1u), f32(vertex_index & 1u));
+
+    // Positions relative to the target are resolved against its size, from the projection's scale
+    let projection = screen;
+    let target_size = vec2<f32>(2.0 / projection[0][0], -2.0 / projection[1][1]);
+    let relative = round(settings.relative_position * target_size);
+
+    let origin = settings.text_position + relative;
+    let position = origin + instance.char_position + corner * instance.size;
+
+    out.offset = corner * instance.size;
+    out.size = instance.size;
+    out.kind = instance.index;
+    out.vertex_position = projection * vec4<f32>(position, 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    // Only the pixel along each edge is drawn, so glyph quads are hollow. Lines are a pixel tall,
+    // so they're drawn whole
+    let edges = min(input.offset, input.size - input.offset);
+    if min(edges.x, edges.y) > 1.0 {
+        discard;
+    }
+
+    switch input.kind {
+        // Glyph quads
+        case 0u: { return vec4<f32>(1.0, 0.0, 1.0, 1.0); }
+        // Ascents
+        case 1u: { return vec4<f32>(0.0, 0.8, 0.0, 1.0); }
+        // Baselines
+        case 2u: { return vec4<f32>(1.0, 0.0, 0.0, 1.0); }
+        // Descents
+        default: { return vec4<f32>(0.0, 0.4, 1.0, 1.0); }
+    }
+}
@@ -0,0 +1,22 @@
+// Added to the text shaders in compatibility mode, where character textures are bound as
+// non-filterable. Samples a texture the way a sampler with clamp-to-edge addressing would.
+fn sample_manual(tex: texture_2d<f32>, uv: vec2<f32>, nearest: bool) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(tex));
+    let max_coord = size - vec2<i32>(1);
+    let pos = uv * vec2<f32>(size);
+
+    if nearest {
+        return textureLoad(tex, clamp(vec2<i32>(floor(pos)), vec2<i32>(0), max_coord), 0);
+    }
+
+    // Texel centres are at half coordinates
+    let coord = pos - 0.5;
+    let base = floor(coord);
+    let weight = coord - base;
+    let p0 = clamp(vec2<i32>(base), vec2<i32>(0), max_coord);
+    let p1 = clamp(vec2<i32>(base) + vec2<i32>(1), vec2<i32>(0), max_coord);
+
+    let top = mix(textureLoad(tex, p0, 0), textureLoad(tex, vec2<i32>(p1.x, p0.y), 0), weight.x);
+    let bottom = mix(textureLoad(tex, vec2<i32>(p0.x, p1.y), 0), textureLoad(tex, p1, 0), weight.x);
+    return mix(top, bottom, weight.y);
+}
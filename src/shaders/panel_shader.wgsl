@@ -0,0 +1,90 @@
+// Draws the rounded rectangle behind a text (see Panel). The rectangle is one instance, placed
+// relative to the text's position like the glyphs of the text shaders
+struct CharacterInstance {
+    @location(0) char_position: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) index: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) vertex_position: vec4<f32>,
+    // How far the fragment is from the centre of the panel
+    @location(0) offset: vec2<f32>,
+    @location(1) @interpolate(flat) half_size: vec2<f32>,
+};
+
+struct PanelSettings {
+    @location(0) text_position: vec2<f32>,
+    @location(1) relative_position: vec2<f32>,
+    @location(2) fill: vec4<f32>,
+    @location(3) border_colour: vec4<f32>,
+    @location(4) corner_radius: f32,
+    @location(5) border_width: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> panel: PanelSettings;
+
+// Projection matrix that allows us to draw in pixel coords
+@group(0) @binding(0)
+var<uniform> screen: mat4x4<f32>;
+
+struct OutputSettings {
+    // Multiplies the colour, e.g. to make white text as bright as paper white on HDR targets
+    brightness: f32,
+    // The renderer's clock in seconds, which drives animations
+    time: f32,
+};
+
+@group(0) @binding(1)
+var<uniform> output: OutputSettings;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, instance: CharacterInstance) -> VertexOutput {
+    var out: VertexOutput;
+
+    // The corners of the quad, drawn as a triangle strip: (0, 0), (0, 1), (1, 0), (1, 1)
+    let corner = vec2<f32>(f32(vertex_index >> 1u), f32(vertex_index & 1u));
+
+    // Positions relative to the target are resolved against its size, from the projection's scale
+    let projection = screen;
+    let target_size = vec2<f32>(2.0 / projection[0][0], -2.0 / projection[1][1]);
+    let relative = round(panel.relative_position * target_size);
+
+    let origin = panel.text_position + relative;
+    let position = origin + instance.char_position + corner * instance.size;
+
+    out.half_size = instance.size * 0.5;
+    out.offset = (corner - 0.5) * instance.size;
+    out.vertex_position = projection * vec4<f32>(position, 0.0, 1.0);
+    return out;
+}
+
+// The signed distance from a rounded rectangle centred on the origin, negative inside it
+fn rounded_rect_distance(offset: vec2<f32>, half_size: vec2<f32>, radius: f32) -> f32 {
+    let corner = abs(offset) - half_size + radius;
+    return length(max(corner, vec2<f32>(0.0))) + min(max(corner.x, corner.y), 0.0) - radius;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let radius = min(panel.corner_radius, min(input.half_size.x, input.half_size.y));
+    let distance = rounded_rect_distance(input.offset, input.half_size, radius);
+
+    // Each edge is antialiased over a pixel
+    let coverage = clamp(0.5 - distance, 0.0, 1.0);
+    var inside = 1.0;
+    if panel.border_width > 0.0 {
+        inside = clamp(0.5 - distance - panel.border_width, 0.0, 1.0);
+    }
+
+    // The fill and border are mixed with their colours multiplied by their alpha
+    let fill = vec4<f32>(panel.fill.rgb * panel.fill.a, panel.fill.a);
+    let border = vec4<f32>(panel.border_colour.rgb * panel.border_colour.a, panel.border_colour.a);
+    let colour = mix(border, fill, inside) * coverage;
+
+    if colour.a <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(colour.rgb / colour.a * output.brightness, colour.a);
+}
@@ -1,15 +1,15 @@
-struct VertexInput {
-    @location(0) tex_coord: vec2<f32>,
-};
-
 struct CharacterInstance {
-    @location(1) char_position: vec2<f32>,
-    @location(2) size: vec2<f32>,
+    @location(0) char_position: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) index: u32,
 };
 
 struct VertexOutput {
     @builtin(position) vertex_position: vec4<f32>,
     @location(0) tex_coord: vec2<f32>,
+    @location(1) @interpolate(flat) colour: vec4<f32>,
+    // How far right of the text's position the fragment is, for the marquee's box
+    @location(2) clip_x: f32,
 };
 
 struct SdfTextSettings {
@@ -19,22 +19,134 @@ struct SdfTextSettings {
     @location(3) outline_width: f32,
     @location(4) sdf_radius: f32,
     @location(5) image_scale: f32,
+    @location(6) distance_scale: f32,
+    @location(7) relative_position: vec2<f32>,
+    // The mode, amplitude and speed of the animation (see GlyphAnimation)
+    @location(8) animation: vec4<f32>,
+    // The start, duration and stagger of the reveal
+    @location(9) reveal: vec4<f32>,
+    // The start, speed, loop length and furthest scroll of the marquee
+    @location(10) marquee: vec4<f32>,
+    // The left and right of the marquee's box, and whether there is one
+    @location(11) marquee_clip: vec4<f32>,
 };
 
 // Projection matrix that allows us to draw in pixel coords
 @group(0) @binding(0)
 var<uniform> screen: mat4x4<f32>;
 
+struct OutputSettings {
+    // Multiplies the colour, e.g. to make white text as bright as paper white on HDR targets
+    brightness: f32,
+    // The renderer's clock in seconds, which drives animations
+    time: f32,
+};
+
+@group(0) @binding(1)
+var<uniform> output: OutputSettings;
+
+// Pipeline-overridable constants, set with TextRendererBuilder
+// Multiplies the width of the anti-aliased edge of sdf text
+override edge_smoothing: f32 = 1.0;
+// Alpha is raised to the power of 1 / gamma, so values above 1 make text heavier
+override gamma: f32 = 1.0;
+// 0 draws text normally, 1 shows the glyph quads and 2 shows the raw texture values
+override debug_mode: u32 = 0u;
+
+// Applies the gamma and debug mode to the colour a fragment shader outputs
+fn finish_colour(colour: vec4<f32>, alpha: f32, value: f32) -> vec4<f32> {
+    let a = pow(alpha, 1.0 / gamma);
+    if debug_mode == 1u {
+        return mix(vec4<f32>(1.0, 0.0, 1.0, 0.3), vec4<f32>(colour.rgb, 1.0), colour.a * a);
+    }
+    if debug_mode == 2u {
+        return vec4<f32>(value, value, value, 1.0);
+    }
+    return vec4<f32>(colour.rgb, colour.a * a);
+}
+
 @group(2) @binding(0)
 var<uniform> settings: SdfTextSettings;
 
+// The PCG hash, for the random offsets of shaking glyphs
+fn hash(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// How far a glyph is moved by the text's animation: 1 is a wave and 2 is a shake
+fn animation_offset(index: u32) -> vec2<f32> {
+    let mode = u32(settings.animation.x);
+    let amplitude = settings.animation.y;
+    let cycles = output.time * settings.animation.z;
+    if mode == 1u {
+        return vec2<f32>(0.0, amplitude * sin(6.2831855 * cycles - f32(index) * 0.5));
+    }
+    if mode == 2u {
+        let random = hash(index ^ hash(u32(i32(floor(cycles)))));
+        let x = f32(random & 0xffffu) / 65535.0;
+        let y = f32(random >> 16u) / 65535.0;
+        return amplitude * (vec2<f32>(x, y) * 2.0 - 1.0);
+    }
+    return vec2<f32>(0.0, 0.0);
+}
+
+// How far through the reveal a glyph is, from 0 (hidden) to 1 (shown), see Text::play_reveal
+fn reveal_progress(index: u32) -> f32 {
+    let duration = settings.reveal.y;
+    if duration <= 0.0 {
+        return 1.0;
+    }
+    let start = settings.reveal.x + f32(index) * settings.reveal.z;
+    return clamp((output.time - start) / duration, 0.0, 1.0);
+}
+
+// Where the marquee moves the left of a glyph to, relative to the text's position. A looping
+// glyph that scrolls out of the left of the box comes back round from the right, going by its
+// right edge so it's only moved once it's completely out
+fn marquee_x(x: f32, width: f32) -> f32 {
+    let scrolled = max(output.time - settings.marquee.x, 0.0) * settings.marquee.y;
+    let period = settings.marquee.z;
+    if period <= 0.0 {
+        return x - clamp(scrolled, 0.0, settings.marquee.w);
+    }
+    let right = x + width - scrolled - settings.marquee_clip.x;
+    return right - period * (ceil(right / period) - 1.0) + settings.marquee_clip.x - width;
+}
+
+// Whether a fragment is outside the marquee's box, if there is one
+fn outside_marquee(x: f32) -> bool {
+    let clip = settings.marquee_clip;
+    return clip.z > 0.0 && (x < clip.x || x >= clip.y);
+}
+
 @vertex
-fn vs_main(vertex: VertexInput, instance: CharacterInstance) -> VertexOutput {
+fn vs_main(@builtin(vertex_index) vertex_index: u32, instance: CharacterInstance) -> VertexOutput {
     var out: VertexOutput;
 
-    var position = instance.char_position + settings.text_position + vertex.tex_coord * instance.size;
-    out.vertex_position = screen * vec4<f32>(position, 0.0, 1.0);
-    out.tex_coord = vertex.tex_coord;
+    // The corners of the quad, drawn as a triangle strip: (0, 0), (0, 1), (1, 0), (1, 1)
+    let tex_coord = vec2<f32>(f32(vertex_index >> 1u), f32(vertex_index & 1u));
+
+    // Positions relative to the target are resolved against its size, from the projection's scale
+    let projection = screen;
+    let target_size = vec2<f32>(2.0 / projection[0][0], -2.0 / projection[1][1]);
+    let relative = round(settings.relative_position * target_size);
+
+    let origin = settings.text_position + relative;
+    let x = marquee_x(instance.char_position.x, instance.size.x);
+    var position = vec2<f32>(x, instance.char_position.y) + origin;
+    position += animation_offset(instance.index);
+
+    // Revealing glyphs slide up into place from a quarter of their height below it
+    let progress = reveal_progress(instance.index);
+    position.y += (1.0 - progress) * (1.0 - progress) * instance.size.y * 0.25;
+
+    position += tex_coord * instance.size;
+    out.clip_x = position.x - origin.x;
+    out.vertex_position = projection * vec4<f32>(position, 0.0, 1.0);
+    out.tex_coord = tex_coord;
+    out.colour = vec4<f32>(settings.outline_colour.rgb, settings.outline_colour.a * progress);
     return out;
 }
 
@@ -52,9 +164,13 @@ fn scale_distance(value: f32, radius: f32) -> f32 {
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
     let value = textureSample(texture, texture_sampler, input.tex_coord).r;
     let distance = scale_distance(value, settings.sdf_radius);
-    let aa_thresh = 1.0 / settings.image_scale;
-    let radius = settings.outline_width / settings.image_scale;
-    let outline_alpha = smoothstep(radius + aa_thresh, radius - aa_thresh, distance) * settings.outline_colour.a;
+    let aa_thresh = edge_smoothing / settings.image_scale;
+    let radius = settings.outline_width * settings.distance_scale / settings.image_scale;
+    let outline_alpha = smoothstep(radius + aa_thresh, radius - aa_thresh, distance);
 
-    return vec4<f32>(settings.outline_colour.rgb, outline_alpha);
+    let colour = vec4<f32>(input.colour.rgb * output.brightness, input.colour.a);
+    if outside_marquee(input.clip_x) {
+        discard;
+    }
+    return finish_colour(colour, outline_alpha, value);
 }
@@ -0,0 +1,102 @@
+//! Buffer writes that are collected over a frame and uploaded together through a staging belt, for
+//! [TextRendererBuilder::with_staging_belt](crate::TextRendererBuilder::with_staging_belt).
+//!
+//! Texts built by a renderer with a staging belt keep a handle to the renderer's list of pending
+//! writes, and add to it instead of writing to the queue. The writes are copied into the staging
+//! belt and recorded into a command encoder by
+//! [TextRenderer::finish_uploads](crate::TextRenderer::finish_uploads).
+
+use std::sync::{Arc, Mutex};
+
+use ahash::AHashMap;
+use wgpu::util::StagingBelt;
+
+/// Data waiting to be written to a buffer.
+#[derive(Debug)]
+struct StagedWrite {
+    buffer: Arc<wgpu::Buffer>,
+    offset: wgpu::BufferAddress,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct StagedWritesInner {
+    writes: Vec<StagedWrite>,
+    /// The index of the write to each range of each buffer, so writing the same range twice in a
+    /// frame (e.g. moving a text every time the mouse moves) only uploads the last one.
+    ranges: AHashMap<(usize, wgpu::BufferAddress, usize), usize>,
+}
+
+/// A list of pending buffer writes, shared between a renderer and the texts it built.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StagedWrites(Arc<Mutex<StagedWritesInner>>);
+
+impl StagedWrites {
+    /// Adds a write, replacing any earlier write to exactly the same range of the same buffer.
+    pub(crate) fn push(
+        &self,
+        buffer: &Arc<wgpu::Buffer>,
+        offset: wgpu::BufferAddress,
+        data: &[u8],
+    ) {
+        let mut inner = self.0.lock().unwrap();
+        let StagedWritesInner { writes, ranges } = &mut *inner;
+
+        // The write holds on to the buffer, so its address can't be reused by another buffer
+        // until the writes are taken
+        let key = (Arc::as_ptr(buffer) as usize, offset, data.len());
+
+        if let Some(&index) = ranges.get(&key) {
+            writes[index].data.copy_from_slice(data);
+        } else {
+            ranges.insert(key, writes.len());
+            writes.push(StagedWrite {
+                buffer: buffer.clone(),
+                offset,
+                data: data.to_vec(),
+            });
+        }
+    }
+
+    fn take(&self) -> Vec<StagedWrite> {
+        let mut inner = self.0.lock().unwrap();
+        inner.ranges.clear();
+        std::mem::take(&mut inner.writes)
+    }
+}
+
+/// A renderer's staging belt and the writes waiting to go through it.
+#[derive(Debug)]
+pub(crate) struct Staging {
+    belt: StagingBelt,
+    pub(crate) writes: StagedWrites,
+}
+
+impl Staging {
+    pub(crate) fn new(chunk_size: wgpu::BufferAddress) -> Self {
+        Self {
+            belt: StagingBelt::new(chunk_size),
+            writes: Default::default(),
+        }
+    }
+
+    /// Copies all the pending writes into the staging belt, recording the copies to their
+    /// buffers into the encoder.
+    pub(crate) fn finish(&mut self, encoder: &mut wgpu::CommandEncoder, device: &wgpu::Device) {
+        for write in self.writes.take() {
+            let Some(size) = wgpu::BufferSize::new(write.data.len() as _) else {
+                continue;
+            };
+
+            self.belt
+                .write_buffer(encoder, &write.buffer, write.offset, size, device)
+                .copy_from_slice(&write.data);
+        }
+
+        self.belt.finish();
+    }
+
+    pub(crate) fn recall(&mut self) {
+        self.belt.recall();
+    }
+}
@@ -0,0 +1,70 @@
+//! The look of a text as one value that can be interpolated, for [TextStyle].
+
+/// The colour, outline, scale and opacity of a [Text](crate::Text), for animating transitions
+/// between them (e.g. when a button is hovered) with [TextStyle::lerp] and
+/// [Text::apply_style](crate::Text::apply_style).
+///
+/// ```rust,no_run
+/// # use kaku::*;
+/// # #[cfg(has_wgpu)]
+/// # fn example(
+/// #     button_text: &mut Text,
+/// #     hover_amount: f32,
+/// #     queue: &wgpu::Queue,
+/// #     text_renderer: &TextRenderer,
+/// # ) -> Result<(), Error> {
+/// let idle = TextStyle { color: [0.6, 0.6, 0.6, 1.], ..Default::default() };
+/// let hovered = TextStyle { color: [1., 1., 1., 1.], scale: 1.1, ..idle };
+///
+/// let style = TextStyle::lerp(&idle, &hovered, hover_amount);
+/// button_text.apply_style(&style, &queue, &text_renderer)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub struct TextStyle {
+    /// The colour of the text (see [TextBuilder::color](crate::TextBuilder::color)).
+    pub color: [f32; 4],
+    /// The colour of the outline, which only sdf text has.
+    pub outline_color: [f32; 4],
+    /// The width of the outline, with 0 meaning no outline (see
+    /// [TextBuilder::outlined](crate::TextBuilder::outlined)).
+    pub outline_width: f32,
+    /// The scale of the text (see [TextBuilder::scale](crate::TextBuilder::scale)).
+    pub scale: f32,
+    /// How opaque the text is (see [TextBuilder::opacity](crate::TextBuilder::opacity)).
+    pub opacity: f32,
+}
+
+impl Default for TextStyle {
+    /// Solid black text with no outline, at a scale of 1, like a new
+    /// [TextBuilder](crate::TextBuilder).
+    fn default() -> Self {
+        Self {
+            color: [0., 0., 0., 1.],
+            outline_color: [0., 0., 0., 0.],
+            outline_width: 0.,
+            scale: 1.,
+            opacity: 1.,
+        }
+    }
+}
+
+impl TextStyle {
+    /// Interpolates linearly between two styles, giving `a` at a `t` of 0 and `b` at 1.
+    ///
+    /// `t` isn't clamped, so easing curves that overshoot work too. To fade an outline in, give
+    /// both styles the outline's colour and only change its width.
+    pub fn lerp(a: &TextStyle, b: &TextStyle, t: f32) -> TextStyle {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let lerp_color = |a: [f32; 4], b: [f32; 4]| [0, 1, 2, 3].map(|i| lerp(a[i], b[i]));
+
+        TextStyle {
+            color: lerp_color(a.color, b.color),
+            outline_color: lerp_color(a.outline_color, b.outline_color),
+            outline_width: lerp(a.outline_width, b.outline_width),
+            scale: lerp(a.scale, b.scale),
+            opacity: lerp(a.opacity, b.opacity),
+        }
+    }
+}
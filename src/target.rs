@@ -0,0 +1,709 @@
+//! The render targets a [TextRenderer](crate::TextRenderer) can draw to, each with its own
+//! pipelines and screen uniform.
+
+use std::{
+    num::{NonZeroU32, NonZeroU64},
+    sync::OnceLock,
+};
+
+use wgpu::{util::DeviceExt, TextureFormat};
+
+use crate::{
+    blur::BlurPipeline,
+    create_text_pipeline, create_text_shader,
+    text::{SdfSettingsUniform, SettingsUniform},
+    Error, ScreenUniform, ShaderConstants,
+};
+
+/// A handle to a render target configuration registered with a
+/// [TextRenderer](crate::TextRenderer).
+///
+/// Every renderer starts with one target, the one it was built for, which is what
+/// [TextRenderer::draw_text](crate::TextRenderer::draw_text) draws to. More can be added with
+/// [TextRenderer::add_target](crate::TextRenderer::add_target).
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Ord, PartialOrd)]
+pub struct TargetId(pub(crate) usize);
+
+/// The kind of render pass text is drawn in: the format and size of the texture it draws to, and
+/// whether the pass is multisampled or has a depth buffer.
+///
+/// Text can only be drawn in render passes that match the pipelines it's drawn with, so each
+/// configuration gets its own pipelines (see [TextRenderer::add_target](crate::TextRenderer::add_target)).
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub struct TargetConfig {
+    pub(crate) format: TextureFormat,
+    pub(crate) size: (u32, u32),
+    pub(crate) msaa_samples: u32,
+    pub(crate) depth_format: Option<TextureFormat>,
+    pub(crate) multiview: Option<NonZeroU32>,
+    /// The bits of the output brightness, so the config can still be hashed.
+    brightness: u32,
+}
+
+impl TargetConfig {
+    /// Creates a target configuration for textures with the given format and size, with no
+    /// multisampling and no depth buffer.
+    pub fn new(format: TextureFormat, size: (u32, u32)) -> Self {
+        Self {
+            format,
+            size,
+            msaa_samples: 1,
+            depth_format: None,
+            multiview: None,
+            brightness: 1f32.to_bits(),
+        }
+    }
+
+    /// Sets the number of samples the render pass uses for multisampling. The default is 1 (no
+    /// multisampling).
+    pub fn with_msaa_sample_count(mut self, samples: u32) -> Self {
+        self.msaa_samples = samples;
+        self
+    }
+
+    /// Sets the format of the render pass's depth buffer. By default the pass has no depth
+    /// buffer.
+    pub fn with_depth(mut self, depth_format: TextureFormat) -> Self {
+        self.depth_format = Some(depth_format);
+        self
+    }
+
+    /// Makes the target a texture array that's drawn to with multiview, e.g. one layer for each
+    /// eye of a VR headset, so text is drawn to every view in one pass. The device needs
+    /// [wgpu::Features::MULTIVIEW].
+    ///
+    /// Each view has its own projection from pixel coordinates, which is the usual screen
+    /// projection for the target's size until it's set with
+    /// [TextRenderer::set_view_projections](crate::TextRenderer::set_view_projections).
+    pub fn with_multiview(mut self, views: NonZeroU32) -> Self {
+        self.multiview = Some(views);
+        self
+    }
+
+    /// Makes the target an HDR target, where text is drawn so that white is as bright as "paper
+    /// white" (the brightness of a white page or UI), given in nits.
+    ///
+    /// This assumes the target uses scRGB (e.g. an `Rgba16Float` surface with the
+    /// `ExtendedSrgbLinear` color space), where 1.0 is 80 nits, so colors are multiplied by
+    /// `paper_white / 80`. For other HDR pipelines, see [TargetConfig::with_output_brightness].
+    pub fn with_paper_white(self, paper_white: f32) -> Self {
+        self.with_output_brightness(paper_white / SCRGB_NITS)
+    }
+
+    /// Sets a multiplier for the color of everything drawn to the target (not including alpha),
+    /// e.g. to match the brightness of the rest of an HDR scene before tone mapping. The default is
+    /// 1.
+    pub fn with_output_brightness(mut self, brightness: f32) -> Self {
+        self.brightness = brightness.to_bits();
+        self
+    }
+
+    /// The number of views drawn to in each pass.
+    fn views(&self) -> usize {
+        self.multiview.map_or(1, |views| views.get() as usize)
+    }
+}
+
+/// The textures besides the one being drawn to that a render pass needs to use a target's
+/// pipelines, for drawing to textures of our own (e.g. in
+/// [TextRenderer::bake](crate::TextRenderer::bake)).
+#[derive(Debug)]
+pub(crate) struct OffscreenAttachments {
+    /// Drawn to instead of the texture and resolved into it, if the target is multisampled.
+    multisampled: Option<wgpu::TextureView>,
+    depth: Option<(wgpu::TextureView, TextureFormat)>,
+}
+
+impl OffscreenAttachments {
+    /// Creates the attachments for drawing to a texture of some size in the target's format.
+    /// `label` makes the labels of the textures.
+    pub(crate) fn new(
+        config: &TargetConfig,
+        size: wgpu::Extent3d,
+        label: impl Fn(&str) -> String,
+        device: &wgpu::Device,
+    ) -> Self {
+        let create_texture = |name: &str, format, sample_count| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(&label(name)),
+                    size,
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+                .create_view(&Default::default())
+        };
+
+        let multisampled = (config.msaa_samples > 1)
+            .then(|| create_texture("multisampled texture", config.format, config.msaa_samples));
+        let depth = config.depth_format.map(|format| {
+            let view = create_texture("depth texture", format, config.msaa_samples);
+            (view, format)
+        });
+
+        Self {
+            multisampled,
+            depth,
+        }
+    }
+
+    /// Starts a render pass that clears `view` and draws to it.
+    pub(crate) fn begin_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        view: &'a wgpu::TextureView,
+        clear: wgpu::Color,
+        label: &str,
+    ) -> wgpu::RenderPass<'a> {
+        let (view, resolve_target) = match &self.multisampled {
+            Some(multisampled) => (multisampled, Some(view)),
+            None => (view, None),
+        };
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: self.depth.as_ref().map(|(view, format)| {
+                wgpu::RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: format.has_depth_aspect().then_some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: format.has_stencil_aspect().then_some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                }
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+/// The brightness of 1.0 in scRGB, in nits.
+pub(crate) const SCRGB_NITS: f32 = 80.;
+
+/// Settings for the colors the fragment shaders output.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Zeroable, bytemuck::Pod)]
+pub(crate) struct OutputUniform {
+    brightness: f32,
+    /// The renderer's clock in seconds, for animations (see [crate::TextRenderer::set_time]).
+    time: f32,
+    _padding: [f32; 2],
+}
+
+impl OutputUniform {
+    fn new(brightness: f32, time: f32) -> Self {
+        Self {
+            brightness,
+            time,
+            _padding: [0.; 2],
+        }
+    }
+}
+
+/// The shaders and pipeline layouts that targets' pipelines are made from. Multiview targets
+/// each have their own, since the shaders depend on the number of views.
+#[derive(Debug)]
+pub(crate) struct TextShaders {
+    /// The screen uniform holds the projection that transforms pixel coords into screen coords,
+    /// for each view, along with the output settings.
+    pub(crate) screen_layout: wgpu::BindGroupLayout,
+    pub(crate) basic_layout: wgpu::PipelineLayout,
+    pub(crate) sdf_layout: wgpu::PipelineLayout,
+    pub(crate) baked_layout: wgpu::PipelineLayout,
+    pub(crate) panel_layout: wgpu::PipelineLayout,
+    pub(crate) debug_layout: wgpu::PipelineLayout,
+    pub(crate) cell_layout: wgpu::PipelineLayout,
+    pub(crate) cell_background_layout: wgpu::PipelineLayout,
+    pub(crate) basic: wgpu::ShaderModule,
+    pub(crate) sdf: wgpu::ShaderModule,
+    pub(crate) outline: wgpu::ShaderModule,
+    /// Draws texts baked into textures (see [TextRenderer::bake](crate::TextRenderer::bake)).
+    pub(crate) baked: wgpu::ShaderModule,
+    /// Draws the panels behind texts (see [Panel](crate::Panel)).
+    pub(crate) panel: wgpu::ShaderModule,
+    /// Draws texts' debug overlays (see
+    /// [TextRenderer::draw_debug](crate::TextRenderer::draw_debug)).
+    pub(crate) debug: wgpu::ShaderModule,
+    /// Draws grids of glyph textures (see
+    /// [TextRenderer::cache_view](crate::TextRenderer::cache_view)).
+    pub(crate) cache_view: wgpu::ShaderModule,
+    /// Draws the glyphs of cell buffers (see [CellBuffer](crate::CellBuffer)).
+    pub(crate) cell: wgpu::ShaderModule,
+    /// Draws the backgrounds of cell buffers' cells.
+    pub(crate) cell_background: wgpu::ShaderModule,
+}
+
+/// The ways the text shaders can be changed from their source.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ShaderVariant {
+    /// The number of views of multiview targets (see [TargetConfig::with_multiview]).
+    pub(crate) views: Option<NonZeroU32>,
+    /// See [TextRendererBuilder::with_compatibility_mode](crate::TextRendererBuilder::with_compatibility_mode).
+    pub(crate) compatibility: bool,
+    /// See [TextRendererBuilder::with_push_constants](crate::TextRendererBuilder::with_push_constants).
+    pub(crate) push_constants: bool,
+}
+
+impl TextShaders {
+    /// Creates the shaders and pipeline layouts for a variant of the shaders. `label` makes the
+    /// labels of the GPU objects.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        variant: ShaderVariant,
+        char_bind_group_layout: &wgpu::BindGroupLayout,
+        settings_layout: &wgpu::BindGroupLayout,
+        sdf_settings_layout: &wgpu::BindGroupLayout,
+        baked_settings_layout: &wgpu::BindGroupLayout,
+        panel_settings_layout: &wgpu::BindGroupLayout,
+        cell_settings_layout: &wgpu::BindGroupLayout,
+        label: impl Fn(&str) -> String,
+        device: &wgpu::Device,
+    ) -> Self {
+        let view_count = variant.views.map_or(1, NonZeroU32::get) as usize;
+
+        let screen_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(&label("screen uniform bind group layout")),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: NonZeroU64::new(
+                                (std::mem::size_of::<ScreenUniform>() * view_count) as _,
+                            ),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: NonZeroU64::new(
+                                std::mem::size_of::<OutputUniform>() as _
+                            ),
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        // With push constants, the text settings are pushed instead of being in bind group 2
+        let pipeline_layout = |name: &str, settings_layout, settings_size: usize| {
+            let push_constant_range = wgpu::PushConstantRange {
+                stages: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                range: 0..settings_size as u32,
+            };
+
+            if variant.push_constants {
+                device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some(&label(name)),
+                    bind_group_layouts: &[&screen_layout, char_bind_group_layout],
+                    push_constant_ranges: &[push_constant_range],
+                })
+            } else {
+                device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some(&label(name)),
+                    bind_group_layouts: &[&screen_layout, char_bind_group_layout, settings_layout],
+                    push_constant_ranges: &[],
+                })
+            }
+        };
+
+        // The render pipeline to use to render the text with no sdf
+        let basic_layout = pipeline_layout(
+            "text rendering pipeline layout",
+            settings_layout,
+            std::mem::size_of::<SettingsUniform>(),
+        );
+
+        let basic = create_text_shader(
+            &label("basic text shader"),
+            include_str!("shaders/text_shader.wgsl"),
+            "settings.nearest_filter != 0.0",
+            variant,
+            device,
+        );
+
+        // The render pipeline to use to render the text with sdf
+        let sdf_layout = pipeline_layout(
+            "sdf text rendering pipeline layout",
+            sdf_settings_layout,
+            std::mem::size_of::<SdfSettingsUniform>(),
+        );
+
+        let sdf = create_text_shader(
+            &label("sdf text shader"),
+            include_str!("shaders/sdf_text_shader.wgsl"),
+            "false",
+            variant,
+            device,
+        );
+
+        let outline = create_text_shader(
+            &label("sdf text outline shader"),
+            include_str!("shaders/sdf_outline_shader.wgsl"),
+            "false",
+            variant,
+            device,
+        );
+
+        // Baked texts are a single quad, so their settings are always in a uniform buffer
+        let baked_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&label("baked text rendering pipeline layout")),
+            bind_group_layouts: &[
+                &screen_layout,
+                char_bind_group_layout,
+                baked_settings_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let baked = create_text_shader(
+            &label("baked text shader"),
+            include_str!("shaders/baked_shader.wgsl"),
+            "false",
+            ShaderVariant {
+                push_constants: false,
+                ..variant
+            },
+            device,
+        );
+
+        let panel_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&label("panel rendering pipeline layout")),
+            bind_group_layouts: &[&screen_layout, panel_settings_layout],
+            push_constant_ranges: &[],
+        });
+
+        // Panels don't sample a texture, so there's nothing to change in compatibility mode
+        let panel = create_text_shader(
+            &label("panel shader"),
+            include_str!("shaders/panel_shader.wgsl"),
+            "false",
+            ShaderVariant {
+                compatibility: false,
+                push_constants: false,
+                ..variant
+            },
+            device,
+        );
+
+        // Cache views are a quad for each glyph texture, placed like baked texts
+        let cache_view = create_text_shader(
+            &label("cache view shader"),
+            include_str!("shaders/cache_view_shader.wgsl"),
+            "false",
+            ShaderVariant {
+                push_constants: false,
+                ..variant
+            },
+            device,
+        );
+
+        // Debug overlays are placed like baked texts, so they use the same settings
+        let debug_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&label("debug overlay rendering pipeline layout")),
+            bind_group_layouts: &[&screen_layout, baked_settings_layout],
+            push_constant_ranges: &[],
+        });
+
+        let debug = create_text_shader(
+            &label("debug overlay shader"),
+            include_str!("shaders/debug_shader.wgsl"),
+            "false",
+            ShaderVariant {
+                compatibility: false,
+                push_constants: false,
+                ..variant
+            },
+            device,
+        );
+
+        // Cell buffers' settings are in a uniform buffer like baked texts', and their backgrounds
+        // don't sample a texture, like panels
+        let cell_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&label("cell buffer rendering pipeline layout")),
+            bind_group_layouts: &[&screen_layout, char_bind_group_layout, cell_settings_layout],
+            push_constant_ranges: &[],
+        });
+
+        let cell = create_text_shader(
+            &label("cell buffer shader"),
+            include_str!("shaders/cell_shader.wgsl"),
+            "false",
+            ShaderVariant {
+                push_constants: false,
+                ..variant
+            },
+            device,
+        );
+
+        let cell_background_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(&label("cell background rendering pipeline layout")),
+                bind_group_layouts: &[&screen_layout, cell_settings_layout],
+                push_constant_ranges: &[],
+            });
+
+        let cell_background = create_text_shader(
+            &label("cell background shader"),
+            include_str!("shaders/cell_background_shader.wgsl"),
+            "false",
+            ShaderVariant {
+                compatibility: false,
+                push_constants: false,
+                ..variant
+            },
+            device,
+        );
+
+        Self {
+            screen_layout,
+            basic_layout,
+            sdf_layout,
+            baked_layout,
+            panel_layout,
+            debug_layout,
+            cell_layout,
+            cell_background_layout,
+            basic,
+            sdf,
+            outline,
+            baked,
+            panel,
+            debug,
+            cache_view,
+            cell,
+            cell_background,
+        }
+    }
+}
+
+/// The pipelines for drawing text in one kind of render pass.
+#[derive(Debug)]
+pub(crate) struct TargetPipelines {
+    pub(crate) basic: wgpu::RenderPipeline,
+    pub(crate) sdf: wgpu::RenderPipeline,
+    pub(crate) outline: wgpu::RenderPipeline,
+    pub(crate) baked: wgpu::RenderPipeline,
+    pub(crate) panel: wgpu::RenderPipeline,
+    pub(crate) debug: wgpu::RenderPipeline,
+    pub(crate) cache_view: wgpu::RenderPipeline,
+    pub(crate) cell: wgpu::RenderPipeline,
+    pub(crate) cell_background: wgpu::RenderPipeline,
+}
+
+/// A registered target, with its pipelines and its own screen uniform, since each target can be a
+/// different size.
+#[derive(Debug)]
+pub(crate) struct RenderTarget {
+    pub(crate) config: TargetConfig,
+    pub(crate) pipelines: TargetPipelines,
+    pub(crate) screen_buffer: wgpu::Buffer,
+    pub(crate) output_buffer: wgpu::Buffer,
+    pub(crate) screen_bind_group: wgpu::BindGroup,
+    /// The pipeline that blurs texts baked for this target, which is only made once one is
+    /// blurred (see [TextRenderer::bake_blurred](crate::TextRenderer::bake_blurred)).
+    pub(crate) blur_pipeline: OnceLock<BlurPipeline>,
+}
+
+impl RenderTarget {
+    /// Creates the pipelines and screen uniform for a target, using shaders made for its number of
+    /// views. `label` makes the labels of the GPU objects.
+    pub(crate) fn new(
+        config: TargetConfig,
+        shaders: &TextShaders,
+        constants: ShaderConstants,
+        time: f32,
+        label: impl Fn(&str) -> String,
+        device: &wgpu::Device,
+    ) -> Self {
+        let pipeline = |name: &str, layout, shader| {
+            let blend = wgpu::BlendState::ALPHA_BLENDING;
+            let constants = Some(constants);
+            create_text_pipeline(
+                &label(name),
+                layout,
+                shader,
+                &config,
+                constants,
+                blend,
+                device,
+            )
+        };
+
+        let pipelines = TargetPipelines {
+            basic: pipeline(
+                "basic text render pipeline",
+                &shaders.basic_layout,
+                &shaders.basic,
+            ),
+            sdf: pipeline(
+                "sdf text render pipeline",
+                &shaders.sdf_layout,
+                &shaders.sdf,
+            ),
+            outline: pipeline(
+                "sdf text outline render pipeline",
+                &shaders.sdf_layout,
+                &shaders.outline,
+            ),
+            // Baked texts' colours have already been multiplied by their alpha
+            baked: create_text_pipeline(
+                &label("baked text render pipeline"),
+                &shaders.baked_layout,
+                &shaders.baked,
+                &config,
+                None,
+                wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+                device,
+            ),
+            panel: create_text_pipeline(
+                &label("panel render pipeline"),
+                &shaders.panel_layout,
+                &shaders.panel,
+                &config,
+                None,
+                wgpu::BlendState::ALPHA_BLENDING,
+                device,
+            ),
+            debug: create_text_pipeline(
+                &label("debug overlay render pipeline"),
+                &shaders.debug_layout,
+                &shaders.debug,
+                &config,
+                None,
+                wgpu::BlendState::ALPHA_BLENDING,
+                device,
+            ),
+            cache_view: create_text_pipeline(
+                &label("cache view render pipeline"),
+                &shaders.baked_layout,
+                &shaders.cache_view,
+                &config,
+                None,
+                wgpu::BlendState::REPLACE,
+                device,
+            ),
+            cell: create_text_pipeline(
+                &label("cell buffer render pipeline"),
+                &shaders.cell_layout,
+                &shaders.cell,
+                &config,
+                None,
+                wgpu::BlendState::ALPHA_BLENDING,
+                device,
+            ),
+            cell_background: create_text_pipeline(
+                &label("cell background render pipeline"),
+                &shaders.cell_background_layout,
+                &shaders.cell_background,
+                &config,
+                None,
+                wgpu::BlendState::ALPHA_BLENDING,
+                device,
+            ),
+        };
+
+        let screen_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&label("screen uniform buffer")),
+            contents: bytemuck::cast_slice(&vec![ScreenUniform::new(config.size); config.views()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let brightness = f32::from_bits(config.brightness);
+        let output_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&label("output uniform buffer")),
+            contents: bytemuck::cast_slice(&[OutputUniform::new(brightness, time)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let screen_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&label("screen uniform bind group")),
+            layout: &shaders.screen_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: screen_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: output_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            config,
+            pipelines,
+            screen_buffer,
+            output_buffer,
+            screen_bind_group,
+            blur_pipeline: OnceLock::new(),
+        }
+    }
+
+    /// Sets the size of the target, which resets the projection of every view.
+    pub(crate) fn resize(&self, new_size: (u32, u32), queue: &wgpu::Queue) {
+        let screen_uniforms = vec![ScreenUniform::new(new_size); self.config.views()];
+        queue.write_buffer(
+            &self.screen_buffer,
+            0,
+            bytemuck::cast_slice(&screen_uniforms),
+        );
+    }
+
+    /// Sets the multiplier for the color of everything drawn to the target.
+    pub(crate) fn set_output_brightness(&self, brightness: f32, queue: &wgpu::Queue) {
+        let offset = std::mem::offset_of!(OutputUniform, brightness);
+        queue.write_buffer(
+            &self.output_buffer,
+            offset as _,
+            bytemuck::bytes_of(&brightness),
+        );
+    }
+
+    /// Sets the time animations are drawn at.
+    pub(crate) fn set_time(&self, time: f32, queue: &wgpu::Queue) {
+        let offset = std::mem::offset_of!(OutputUniform, time);
+        queue.write_buffer(&self.output_buffer, offset as _, bytemuck::bytes_of(&time));
+    }
+
+    /// Sets the projection of each view. Returns an error if there isn't one for every view.
+    pub(crate) fn set_view_projections(
+        &self,
+        projections: &[[[f32; 4]; 4]],
+        queue: &wgpu::Queue,
+    ) -> Result<(), Error> {
+        let views = self.config.views();
+        if projections.len() != views {
+            return Err(Error::WrongViewCount {
+                views: views as u32,
+                projections: projections.len(),
+            });
+        }
+
+        queue.write_buffer(&self.screen_buffer, 0, bytemuck::cast_slice(projections));
+        Ok(())
+    }
+}
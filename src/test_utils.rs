@@ -0,0 +1,159 @@
+//! Helpers for visual regression tests: comparing images of text against known-good "golden"
+//! images, e.g. to check that your text still looks the same after upgrading kaku.
+//!
+//! The images can come from the GPU with
+//! [TextRenderer::render_to_image](crate::TextRenderer::render_to_image), or from the cpu
+//! renderer with the `cpu-render` feature. This module is only available with the `test-utils`
+//! feature, which `cpu-render` enables too.
+
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+
+/// The environment variable that makes [assert_matches_golden] overwrite golden images instead of
+/// comparing against them.
+pub const UPDATE_GOLDEN_VAR: &str = "KAKU_UPDATE_GOLDEN";
+
+/// The differences between two images, created with [compare_images].
+#[derive(Debug, Clone)]
+pub struct ImageDiff {
+    /// The largest difference in any channel of any pixel.
+    pub max_difference: u8,
+    /// The number of pixels that differ by more than the tolerance in some channel.
+    pub differing_pixels: usize,
+    /// The root mean square difference over every channel of every pixel, from 0 to 255. Useful
+    /// for telling a few stray pixels apart from a subtle change across the whole image.
+    pub rmse: f64,
+    /// An image highlighting the differing pixels in red over a faded copy of the expected image.
+    pub diff_image: RgbaImage,
+}
+
+impl ImageDiff {
+    /// Returns whether the images matched (i.e. no pixels differed by more than the tolerance).
+    pub fn is_match(&self) -> bool {
+        self.differing_pixels == 0
+    }
+}
+
+/// Compares two images, counting the pixels that differ by more than `tolerance` in any channel.
+///
+/// A small tolerance (e.g. 2) is useful since floating point results can differ slightly between
+/// platforms. Returns None if the images have different dimensions.
+pub fn compare_images(
+    expected: &RgbaImage,
+    actual: &RgbaImage,
+    tolerance: u8,
+) -> Option<ImageDiff> {
+    if expected.dimensions() != actual.dimensions() {
+        return None;
+    }
+
+    let mut max_difference = 0;
+    let mut differing_pixels = 0;
+    let mut squared_sum = 0.;
+
+    let diff_image = RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let e = expected.get_pixel(x, y);
+        let a = actual.get_pixel(x, y);
+
+        let difference =
+            e.0.iter()
+                .zip(a.0)
+                .map(|(e, a)| e.abs_diff(a))
+                .inspect(|&d| squared_sum += (d as f64).powi(2))
+                .max()
+                .unwrap();
+        max_difference = max_difference.max(difference);
+
+        if difference > tolerance {
+            differing_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let [r, g, b, a] = e.0;
+            Rgba([r / 4, g / 4, b / 4, a])
+        }
+    });
+
+    let channels = (expected.width() as usize * expected.height() as usize * 4).max(1);
+
+    Some(ImageDiff {
+        max_difference,
+        differing_pixels,
+        rmse: (squared_sum / channels as f64).sqrt(),
+        diff_image,
+    })
+}
+
+/// Asserts that an image matches a golden image saved at `path`, within a tolerance (see
+/// [compare_images]).
+///
+/// Golden images are only written when the [UPDATE_GOLDEN_VAR] environment variable is set, in
+/// which case the image is saved as the new golden image instead of being compared. A missing
+/// golden image is a failure otherwise, so a test can't pass by writing its own golden image
+/// (e.g. on CI, or after the image was renamed). If the images don't match, the actual image and
+/// a diff image are saved next to the golden image (as `<name>.actual.png` and `<name>.diff.png`)
+/// to help work out what went wrong.
+///
+/// # Panics
+///
+/// Panics if the images don't match, if the golden image doesn't exist, or if the images can't
+/// be read or written.
+pub fn assert_matches_golden(image: &RgbaImage, path: impl AsRef<Path>, tolerance: u8) {
+    let path = path.as_ref();
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).expect("couldn't create golden image directory");
+    }
+
+    if std::env::var_os(UPDATE_GOLDEN_VAR).is_some() {
+        image.save(path).expect("couldn't save golden image");
+        return;
+    }
+
+    let save_alongside = |suffix: &str, image: &RgbaImage| {
+        let path = path.with_extension(format!("{suffix}.png"));
+        image.save(&path).expect("couldn't save image");
+        path
+    };
+
+    if !path.exists() {
+        let actual_path = save_alongside("actual", image);
+        panic!(
+            "golden image {} doesn't exist (actual image saved to {}). Run with {UPDATE_GOLDEN_VAR} \
+            set to save it",
+            path.display(),
+            actual_path.display(),
+        );
+    }
+
+    let expected = image::open(path)
+        .unwrap_or_else(|e| panic!("couldn't open golden image {}: {e}", path.display()))
+        .into_rgba8();
+
+    let Some(diff) = compare_images(&expected, image, tolerance) else {
+        let actual_path = save_alongside("actual", image);
+        panic!(
+            "image is {:?} but golden image {} is {:?} (actual image saved to {})",
+            image.dimensions(),
+            path.display(),
+            expected.dimensions(),
+            actual_path.display(),
+        );
+    };
+
+    if !diff.is_match() {
+        let actual_path = save_alongside("actual", image);
+        let diff_path = save_alongside("diff", &diff.diff_image);
+
+        panic!(
+            "image differs from golden image {} in {} pixels (max difference {}, rmse {:.3}). \
+            Actual image saved to {}, diff saved to {}",
+            path.display(),
+            diff.differing_pixels,
+            diff.max_difference,
+            diff.rmse,
+            actual_path.display(),
+            diff_path.display(),
+        );
+    }
+}
@@ -4,9 +4,181 @@
 //! piece of text which can be drawn to the screen with a variety of effects.
 
 use ab_glyph::{Font, PxScale};
+use itertools::Itertools;
+use log::warn;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+#[cfg(has_wgpu)]
 use wgpu::util::DeviceExt;
 
-use crate::{FontId, TextRenderer};
+use std::ops::Range;
+#[cfg(has_wgpu)]
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, OnceLock,
+};
+
+use crate::{
+    animation::Reveal,
+    cluster,
+    layout::{PlacedGlyph, Rect},
+    style::TextStyle,
+    Error, FontData, FontId, GlyphAnimation, GlyphKey, Marquee, Panel,
+};
+#[cfg(has_wgpu)]
+use crate::{
+    debug_overlay::DebugOverlay,
+    frame_stats::FrameCounters,
+    layout::{self, LaidOutGlyph, LineBox, MeasuredChar, MeasuredLine, MeasuredText, Reflow},
+    memory::TextMemory,
+    panel::{PanelBuffers, PanelUniform},
+    preedit::{self, PreeditSegment, Underline},
+    settings_pool::SettingsSlot,
+    staging::StagedWrites,
+    uploads::Uploads,
+    windowed::WindowedText,
+    TextLayout, TextRenderer,
+};
+
+/// How many spaces a tab is drawn as by default.
+const TAB_WIDTH: u32 = 4;
+
+/// The characters that start a new line. Windows line endings work too, since the `\r` is removed
+/// like any other control character.
+const LINE_BREAKS: [char; 3] = ['\n', '\u{2028}', '\u{2029}'];
+
+/// Returns whether a character is removed from text before it's laid out. This is control
+/// characters, and format characters that are meant to be invisible (zero width spaces and joiners,
+/// byte order marks, directional marks, soft hyphens, variation selectors etc.). Kaku doesn't
+/// shape text so most of these don't do anything, and fonts are inconsistent about whether they
+/// have glyphs for them. Joiners and variation selectors still pick the glyphs of the characters
+/// around them (see [crate::cluster]).
+fn is_ignored(c: char) -> bool {
+    c.is_control()
+        || cluster::is_variation_selector(c)
+        || matches!(
+            c,
+            '\u{00AD}'
+                | '\u{200B}'..='\u{200F}'
+                | '\u{202A}'..='\u{202E}'
+                | '\u{2060}'..='\u{2064}'
+                | '\u{2066}'..='\u{2069}'
+                | '\u{FEFF}'
+        )
+}
+
+/// Returns the characters that a character of text is laid out as. Tabs that move to the next
+/// column are kept as they are, and measured as spaces.
+fn normalize_char(c: char, tabs: Tabs) -> std::iter::RepeatN<char> {
+    match (c, tabs) {
+        ('\t', Tabs::Spaces(spaces)) => std::iter::repeat_n(' ', spaces as usize),
+        ('\t', Tabs::Columns(_)) => std::iter::repeat_n('\t', 1),
+        (c, _) if is_ignored(c) => std::iter::repeat_n(c, 0),
+        (c, _) => std::iter::repeat_n(c, 1),
+    }
+}
+
+/// Returns the text normalized to NFC if `normalize` is set (see [TextBuilder::normalize]).
+fn normalized(text: String, normalize: bool) -> String {
+    if normalize && !is_nfc(&text) {
+        text.nfc().collect()
+    } else {
+        text
+    }
+}
+
+/// The byte range of each line of a string, without its line break.
+pub(crate) fn line_ranges(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut start = 0;
+
+    text.split(LINE_BREAKS).map(move |line| {
+        let range = start..start + line.len();
+        // Skip over the line break too, which can be more than one byte
+        start = range.end + text[range.end..].chars().next().map_or(0, char::len_utf8);
+        range
+    })
+}
+
+/// The glyph of each instance, the instances and each instance's index in the text.
+type SortedInstances = (Vec<GlyphKey>, Vec<crate::CharacterInstance>, Vec<u32>);
+
+/// Sorts a text's instances by the glyph they draw, and splits off the glyphs. Instances of the
+/// same glyph end up next to each other in the instance buffer, so they can be drawn with one bind
+/// group change and one draw call.
+///
+/// The sort is stable, so characters of the same glyph are still drawn in the order of the text.
+/// Each instance's index before sorting is returned too, and given to the instance for animations.
+fn sort_instances(instances: Vec<(GlyphKey, crate::CharacterInstance)>) -> SortedInstances {
+    let mut instances = (instances.into_iter().enumerate())
+        .map(|(i, (key, instance))| {
+            let index = i as u32;
+            (key, crate::CharacterInstance { index, ..instance }, index)
+        })
+        .collect_vec();
+    instances.sort_by_key(|(key, _, _)| *key);
+    instances.into_iter().multiunzip()
+}
+
+/// The sorted instances (see [sort_instances]) of a text's glyphs after an edit replaced the old
+/// glyphs in `old` with the glyphs in `new`, from the order the old glyphs were sorted in.
+///
+/// The glyphs that weren't replaced stay in the same order, so only the new glyphs are sorted
+/// and then merged in, instead of sorting all of them again.
+fn splice_instances(
+    glyphs: &[PlacedGlyph],
+    (old_keys, old_order): (&[GlyphKey], &[u32]),
+    old: Range<usize>,
+    new: Range<usize>,
+) -> SortedInstances {
+    // The glyphs after the edit move by how many more or fewer glyphs there are
+    let kept = (old_keys.iter().zip(old_order))
+        .filter(|&(_, &i)| !old.contains(&(i as usize)))
+        .map(|(&key, &i)| match i as usize >= old.end {
+            true => (key, (i as usize - old.end + new.end) as u32),
+            false => (key, i),
+        });
+    let mut added = (new.clone())
+        .map(|i| (glyphs[i].key, i as u32))
+        .collect_vec();
+    added.sort_by_key(|&(key, _)| key);
+
+    // Both are sorted by glyph and then by index, like the stable sort in sort_instances
+    kept.merge(added)
+        .map(|(key, index)| {
+            let instance = glyphs[index as usize].instance;
+            (key, crate::CharacterInstance { index, ..instance }, index)
+        })
+        .multiunzip()
+}
+
+/// Groups the consecutive glyphs that are the same, returning each glyph with the range of
+/// indices it covers.
+pub(crate) fn glyph_runs(
+    glyphs: impl IntoIterator<Item = GlyphKey>,
+) -> Vec<(GlyphKey, Range<u32>)> {
+    let mut runs: Vec<(GlyphKey, Range<u32>)> = Vec::new();
+    for (i, key) in glyphs.into_iter().enumerate() {
+        match runs.last_mut() {
+            Some((run_key, range)) if *run_key == key => range.end += 1,
+            _ => runs.push((key, i as u32..i as u32 + 1)),
+        }
+    }
+
+    runs
+}
+
+/// How many of a text's glyphs are for its first `chars` characters (see
+/// [Text::set_visible_chars]), or all of them if it's None.
+fn visible_glyphs(glyphs: &[PlacedGlyph], text: &str, chars: Option<usize>) -> u32 {
+    let Some(chars) = chars else {
+        return u32::MAX;
+    };
+
+    let end = (text.char_indices().nth(chars)).map_or(text.len(), |(index, _)| index);
+
+    // The glyphs are in the same order as the text
+    let visible = glyphs.iter().take_while(|glyph| glyph.index < end);
+    visible.count() as u32
+}
 
 /// Options for a text outline.
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
@@ -15,73 +187,295 @@ pub(crate) struct Outline {
     pub(crate) width: f32,
 }
 
+/// How a text's paragraphs are indented and spaced, in logical pixels.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub(crate) struct ParagraphStyle {
+    pub(crate) first_line_indent: f32,
+    pub(crate) hanging_indent: f32,
+    pub(crate) space_before: f32,
+    pub(crate) space_after: f32,
+}
+
+impl ParagraphStyle {
+    /// Applies a function to every length, e.g. to convert them to physical pixels.
+    pub(crate) fn map(self, f: impl Fn(f32) -> f32) -> Self {
+        Self {
+            first_line_indent: f(self.first_line_indent),
+            hanging_indent: f(self.hanging_indent),
+            space_before: f(self.space_before),
+            space_after: f(self.space_after),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
 pub(crate) struct SdfTextData {
     pub(crate) radius: f32,
+    pub(crate) distance_scale: f32,
     pub(crate) outline: Option<Outline>,
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub(crate) struct TextData {
     pub(crate) text: String,
+    /// Whether the text is normalized to NFC whenever it's set (see [TextBuilder::normalize]).
+    pub(crate) normalize: bool,
     pub(crate) font: FontId,
     pub(crate) position: [f32; 2],
+    /// A fraction of the target's size added to the position (see
+    /// [TextBuilder::relative_position]).
+    pub(crate) relative_position: [f32; 2],
     pub(crate) color: [f32; 4],
+    /// Multiplies the alpha of the text and its outline (see [TextBuilder::opacity]).
+    pub(crate) opacity: f32,
     pub(crate) scale: f32,
     pub(crate) halign: HorizontalAlignment,
     pub(crate) valign: VerticalAlignment,
+    pub(crate) pixel_snap: bool,
+    pub(crate) stem_darkening: f32,
+    /// Whether the font's textures use nearest filtering, which the shaders need to know in
+    /// compatibility mode.
+    pub(crate) nearest_filter: bool,
+    /// How many subpixel offsets the font's glyphs are rasterised at (see
+    /// [FontOptions::with_subpixel_phases](crate::FontOptions::with_subpixel_phases)).
+    pub(crate) subpixel_phases: u32,
+    /// The renderer's scale factor when the text was laid out (see
+    /// [TextRenderer::set_scale_factor]).
+    pub(crate) scale_factor: f32,
+    /// The width lines are wrapped at, in logical pixels.
+    pub(crate) max_width: Option<f32>,
+    pub(crate) tabs: Tabs,
+    /// Whether the vertical alignment is of all the lines rather than the first one.
+    pub(crate) block_align: bool,
+    /// How far the text is moved down after it's aligned, in logical pixels.
+    pub(crate) baseline_offset: f32,
+    /// Whether spaces at the end of lines are left out of their width.
+    pub(crate) ignore_trailing_whitespace: bool,
+    /// The paragraphs (lines before wrapping) with their own horizontal alignment.
+    pub(crate) line_aligns: Vec<(usize, HorizontalAlignment)>,
+    pub(crate) paragraphs: ParagraphStyle,
+    pub(crate) animation: GlyphAnimation,
+    /// The glyphs fading in, if a reveal has been played (see [Text::play_reveal]).
+    pub(crate) reveal: Option<Reveal>,
+    pub(crate) marquee: Option<Marquee>,
+    /// The renderer's time when the marquee started scrolling.
+    pub(crate) marquee_start: f32,
+    pub(crate) panel: Option<Panel>,
 
     pub(crate) sdf: Option<SdfTextData>,
 }
 
 impl TextData {
-    fn settings_uniform(&self) -> SettingsUniform {
+    /// Splits a byte range of the text into lines of the characters that are actually drawn, with
+    /// the byte range of each line (without its line break) and the byte index each character
+    /// came from. The range has to start at the start of a line and end at the end of one.
+    ///
+    /// Layout, drawing and glyph generation all go through this so they agree on which characters
+    /// there are. Tabs are turned into spaces with the same index (unless they move to the next
+    /// column) and invisible characters are removed (see [is_ignored]).
+    pub(crate) fn indexed_lines(
+        &self,
+        bytes: Range<usize>,
+    ) -> impl Iterator<Item = (Range<usize>, impl Iterator<Item = (usize, char)> + '_)> + '_ {
+        let tabs = self.tabs;
+        let offset = bytes.start;
+
+        line_ranges(&self.text[bytes]).map(move |range| {
+            let range = range.start + offset..range.end + offset;
+            let line_start = range.start;
+            let chars = self.text[range.clone()]
+                .char_indices()
+                .flat_map(move |(i, c)| normalize_char(c, tabs).map(move |c| (line_start + i, c)));
+            (range, chars)
+        })
+    }
+
+    /// The alignment proportion of a paragraph, if it has its own alignment.
+    pub(crate) fn line_align(&self, paragraph: usize) -> Option<f32> {
+        (self.line_aligns.iter())
+            .find(|(line, _)| *line == paragraph)
+            .map(|(_, halign)| halign.proportion())
+    }
+
+    /// Whether the glyphs should be snapped to whole pixels. This never applies to sdf text,
+    /// since it's meant to be scaled smoothly.
+    pub(crate) fn snaps_to_pixels(&self) -> bool {
+        self.pixel_snap && self.sdf.is_none()
+    }
+
+    /// The position the text is drawn at in physical pixels, which is rounded if the text snaps
+    /// to pixels.
+    pub(crate) fn draw_position(&self) -> [f32; 2] {
+        let position = self.position.map(|p| p * self.scale_factor);
+
+        if self.snaps_to_pixels() {
+            position.map(f32::round)
+        } else {
+            position
+        }
+    }
+
+    /// A label for the text's profiler scope, with the start of the text and its font.
+    #[cfg(has_wgpu)]
+    pub(crate) fn debug_label(&self, text_renderer: &TextRenderer) -> String {
+        const MAX_CHARS: usize = 24;
+
+        let mut text: String = self.text.chars().take(MAX_CHARS).collect();
+        if self.text.chars().nth(MAX_CHARS).is_some() {
+            text.push('…');
+        }
+
+        text_renderer.label(&format!("text {text:?} (font {})", self.font.0))
+    }
+
+    /// How many physical pixels each pixel of an sdf glyph's texture covers.
+    pub(crate) fn image_scale(&self) -> f32 {
+        self.scale * self.scale_factor
+    }
+
+    /// The widest outline that fits in the font's distance field at the text's current scale, or
+    /// None if the text doesn't use sdf.
+    pub(crate) fn max_outline_width(&self) -> Option<f32> {
+        let sdf = self.sdf?;
+
+        // The outline's antialiased edge reaches a (physical) pixel past its width, so that has to
+        // fit too
+        let physical_width = (sdf.radius * self.image_scale() - 1.) / sdf.distance_scale;
+        Some((physical_width / self.scale_factor).max(0.))
+    }
+
+    /// The width the outline is actually drawn with, which is clamped to the maximum width.
+    pub(crate) fn outline_width(&self) -> f32 {
+        let width = self
+            .sdf
+            .and_then(|sdf| sdf.outline)
+            .map(|o| o.width)
+            .unwrap_or(0.);
+
+        width.min(self.max_outline_width().unwrap_or(0.))
+    }
+
+    /// Logs a warning if the outline is too wide for the distance field and has been clamped.
+    fn warn_if_outline_clamped(&self) {
+        let Some(outline) = self.sdf.and_then(|sdf| sdf.outline) else {
+            return;
+        };
+
+        if outline.width > self.outline_width() {
+            warn!(
+                "Outline width {} is too wide for the font's sdf radius and will be drawn as {}. \
+                Use a wider radius (see SdfSettings) for wider outlines",
+                outline.width,
+                self.outline_width()
+            );
+        }
+    }
+
+    /// A colour of the text with its opacity applied.
+    pub(crate) fn faded(&self, [r, g, b, a]: [f32; 4]) -> [f32; 4] {
+        [r, g, b, a * self.opacity.clamp(0., 1.)]
+    }
+
+    /// The text's settings for the shaders, with the bounds of its layout in physical pixels
+    /// relative to its position (see [Marquee::uniform]).
+    #[cfg(has_wgpu)]
+    fn settings_uniform(&self, bounds: Rect) -> SettingsUniform {
+        let [marquee, marquee_clip] = self.marquee_uniform(bounds);
+
         SettingsUniform {
-            color: self.color,
-            text_position: self.position,
-            _padding: [0.; 2],
+            color: self.faded(self.color),
+            text_position: self.draw_position(),
+            stem_darkening: self.stem_darkening,
+            nearest_filter: if self.nearest_filter { 1. } else { 0. },
+            relative_position: self.relative_position,
+            subpixel_phases: self.subpixel_phases as f32,
+            _padding: 0.,
+            animation: self.animation.uniform(self.scale_factor),
+            reveal: Reveal::uniform(self.reveal),
+            marquee,
+            marquee_clip,
         }
     }
 
-    fn sdf_settings_uniform(&self) -> SdfSettingsUniform {
+    #[cfg(has_wgpu)]
+    fn sdf_settings_uniform(&self, bounds: Rect) -> SdfSettingsUniform {
         let sdf = &self
             .sdf
             .expect("sdf_settings_uniform called but no sdf data found");
-        let outline_color = sdf.outline.map(|o| o.color).unwrap_or([0.; 4]);
-        let outline_width = sdf.outline.map(|o| o.width).unwrap_or(0.);
+        let outline_color = sdf.outline.map_or([0.; 4], |o| self.faded(o.color));
+        let outline_width = self.outline_width() * self.scale_factor;
         let sdf_radius = sdf.radius;
+        let [marquee, marquee_clip] = self.marquee_uniform(bounds);
 
         SdfSettingsUniform {
-            color: self.color,
+            color: self.faded(self.color),
             outline_color,
-            text_position: self.position,
+            text_position: self.draw_position(),
             outline_width,
             sdf_radius,
-            image_scale: self.scale,
-            _padding: [0.; 3],
+            image_scale: self.image_scale(),
+            distance_scale: sdf.distance_scale,
+            relative_position: self.relative_position,
+            animation: self.animation.uniform(self.scale_factor),
+            reveal: Reveal::uniform(self.reveal),
+            marquee,
+            marquee_clip,
         }
     }
+
+    /// Whether anything uploaded with the text's settings depends on the bounds of its layout
+    /// (the marquee and the panel), so has to be uploaded again when it's laid out again.
+    pub(crate) fn bounds_in_settings(&self) -> bool {
+        self.marquee.is_some() || self.panel.is_some()
+    }
+
+    /// The marquee's settings for the shaders (see [Marquee::uniform]), with the bounds of the
+    /// text's layout. They're made bigger by the outline, like [Text::bounds].
+    pub(crate) fn marquee_uniform(&self, bounds: Rect) -> [[f32; 4]; 2] {
+        let outline = self.outline_width() * self.scale_factor;
+        let bounds = Rect {
+            min: bounds.min.map(|p| p - outline),
+            max: bounds.max.map(|p| p + outline),
+        };
+        Marquee::uniform(self.marquee, self.marquee_start, bounds, self.scale_factor)
+    }
 }
 
+/// The size that [FontSize::Em] is relative to when a font is loaded, in px.
+pub(crate) const DEFAULT_EM_PX: f32 = 16.;
+
 /// Settings for font size.
+///
+/// Like all of kaku's sizes, these are in logical pixels (or points), which are multiplied by
+/// the renderer's scale factor (see [TextRenderer::set_scale_factor]). So the same size comes out
+/// the same physical size on 1x and 2x displays, without having to scale it yourself.
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
 pub enum FontSize {
     /// A font's size in pt.
     Pt(f32),
     /// A font's size in px.
     Px(f32),
+    /// A font's size relative to a base size, e.g. 1.5 for one and a half times as big.
+    ///
+    /// With [TextBuilder::font_size], the base is the size the font was loaded with. When
+    /// loading a font, there's nothing to be relative to, so the base is 16px, like the default
+    /// font size of web browsers.
+    Em(f32),
 }
 
 impl FontSize {
-    pub(crate) fn scale(&self, font: &impl Font) -> PxScale {
-        match self {
-            FontSize::Px(px) => font.pt_to_px_scale(*px * (72. / 96.)).unwrap(),
-            FontSize::Pt(pt) => font.pt_to_px_scale(*pt).unwrap(),
-        }
+    pub(crate) fn scale(&self, font: &impl Font) -> Result<PxScale, Error> {
+        let pt = match self {
+            FontSize::Px(px) => *px * (72. / 96.),
+            FontSize::Pt(pt) => *pt,
+            FontSize::Em(em) => *em * DEFAULT_EM_PX * (72. / 96.),
+        };
+
+        font.pt_to_px_scale(pt).ok_or(Error::UnscalableFont)
     }
 
-    pub(crate) fn px_size(&self, font: &impl Font) -> f32 {
-        self.scale(font).y
+    pub(crate) fn px_size(&self, font: &impl Font) -> Result<f32, Error> {
+        Ok(self.scale(font)?.y)
     }
 }
 
@@ -124,6 +518,73 @@ impl HorizontalAlignment {
     }
 }
 
+/// A point of a text's box to put at its position, which sets its horizontal and vertical
+/// alignment together (see [TextBuilder::anchor]).
+///
+/// The vertical alignment is of the whole block of lines (see [TextBuilder::block_align]), so
+/// e.g. [Anchor::Center] centres every line of the text on its position, not just the first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Anchor {
+    /// The top left corner.
+    TopLeft,
+    /// The middle of the top edge.
+    Top,
+    /// The top right corner.
+    TopRight,
+    /// The middle of the left edge.
+    Left,
+    /// The centre.
+    Center,
+    /// The middle of the right edge.
+    Right,
+    /// The bottom left corner.
+    BottomLeft,
+    /// The middle of the bottom edge.
+    Bottom,
+    /// The bottom right corner.
+    BottomRight,
+}
+
+impl Anchor {
+    /// The horizontal alignment that puts this point of the text at its position.
+    pub fn horizontal(&self) -> HorizontalAlignment {
+        match self {
+            Self::TopLeft | Self::Left | Self::BottomLeft => HorizontalAlignment::Left,
+            Self::Top | Self::Center | Self::Bottom => HorizontalAlignment::Center,
+            Self::TopRight | Self::Right | Self::BottomRight => HorizontalAlignment::Right,
+        }
+    }
+
+    /// The vertical alignment (of the whole block of lines) that puts this point of the text at
+    /// its position.
+    pub fn vertical(&self) -> VerticalAlignment {
+        match self {
+            Self::TopLeft | Self::Top | Self::TopRight => VerticalAlignment::Top,
+            Self::Left | Self::Center | Self::Right => VerticalAlignment::Middle,
+            Self::BottomLeft | Self::Bottom | Self::BottomRight => VerticalAlignment::Bottom,
+        }
+    }
+}
+
+/// How wide tabs are, set with [TextBuilder::tabs].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Hash)]
+pub enum Tabs {
+    /// Every tab is as wide as this many spaces, wherever it is.
+    Spaces(u32),
+    /// Tabs move to the next column, where columns are this many spaces wide. This is how code
+    /// editors and terminals draw tabs, so source code and tab separated values line up.
+    ///
+    /// Columns are measured from the start of each line, after it's wrapped. With a monospace
+    /// font, `Columns(4)` lines tabs up with every fourth character.
+    Columns(u32),
+}
+
+impl Default for Tabs {
+    fn default() -> Self {
+        Self::Spaces(TAB_WIDTH)
+    }
+}
+
 /// Settings for vertical text alignment.
 ///
 /// See <https://freetype.org/freetype2/docs/glyphs/glyphs-3.html> for more info on font metrics.
@@ -159,71 +620,180 @@ pub enum VerticalAlignment {
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct TextBuilder {
     text: String,
-    font: FontId,
+    pub(crate) font: FontId,
     position: [f32; 2],
+    relative_position: [f32; 2],
     outline: Option<Outline>,
+    distance_scale: f32,
     color: [f32; 4],
+    opacity: f32,
     scale: f32,
     custom_font_size: Option<FontSize>,
     halign: HorizontalAlignment,
     valign: VerticalAlignment,
+    pixel_snap: bool,
+    stem_darkening: f32,
+    double_buffered: bool,
+    max_width: Option<f32>,
+    tabs: Tabs,
+    block_align: bool,
+    baseline_offset: f32,
+    ignore_trailing_whitespace: bool,
+    normalize: bool,
+    pub(crate) line_aligns: Vec<(usize, HorizontalAlignment)>,
+    pub(crate) paragraphs: ParagraphStyle,
+    animation: GlyphAnimation,
+    marquee: Option<Marquee>,
+    panel: Option<Panel>,
 }
 
 impl TextBuilder {
     /// Creates a new TextBuilder.
+    ///
+    /// Lines are separated by `\n` (or `\r\n`, or the unicode line and paragraph separators).
+    /// Tabs are drawn as four spaces (see [TextBuilder::tabs]), and other control characters and
+    /// invisible formatting characters (like zero width spaces and byte order marks) are ignored.
     pub fn new(text: impl Into<String>, font: FontId, position: [f32; 2]) -> Self {
         Self {
             text: text.into(),
             font,
             position,
 
+            relative_position: [0., 0.],
             outline: None,
+            distance_scale: 1.,
             color: [0., 0., 0., 1.],
+            opacity: 1.,
             scale: 1.,
             custom_font_size: None,
             halign: Default::default(),
             valign: Default::default(),
+            pixel_snap: false,
+            double_buffered: false,
+            stem_darkening: 0.,
+            max_width: None,
+            tabs: Default::default(),
+            block_align: false,
+            baseline_offset: 0.,
+            ignore_trailing_whitespace: false,
+            normalize: false,
+            line_aligns: Vec::new(),
+            paragraphs: Default::default(),
+            animation: Default::default(),
+            marquee: None,
+            panel: None,
         }
     }
 
     /// Creates a new Text object from the current configuration and uploads any necessary data
     /// to the GPU.
+    ///
+    /// Returns an error if the font isn't loaded in the text renderer, or can't be scaled to the
+    /// custom font size.
+    #[cfg(has_wgpu)]
     pub fn build(
         &self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         text_renderer: &mut TextRenderer,
-    ) -> Text {
+    ) -> Result<Text, Error> {
+        let layout = self.layout(device, queue, text_renderer)?;
+        Ok(Text::from_layout(layout, device, queue, text_renderer))
+    }
+
+    /// Lays the text out without creating any GPU buffers for it, generating the textures of any
+    /// characters it needs. The layout can be inspected, kept, and turned into a [Text] later with
+    /// [TextLayout::build].
+    ///
+    /// Returns an error if the font isn't loaded in the text renderer, or can't be scaled to the
+    /// custom font size.
+    #[cfg(has_wgpu)]
+    pub fn layout(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_renderer: &mut TextRenderer,
+    ) -> Result<TextLayout, Error> {
+        let font_data = text_renderer.fonts.get(self.font)?;
+        let mut data = self.text_data(font_data, text_renderer.scale_factor)?;
+        data.marquee_start = text_renderer.time();
+
+        let bytes = 0..data.text.len();
+        text_renderer.generate_text(&data, bytes, device, &Uploads::Queue(queue))?;
+        let mut layout = text_renderer.create_text_layout(data)?;
+        layout.double_buffered = self.double_buffered;
+        Ok(layout)
+    }
+
+    /// Creates a [WindowedText] that only lays out and uploads the lines that are inside a
+    /// viewport of the given height, for documents too long to be one [Text].
+    ///
+    /// Returns an error if the font isn't loaded in the text renderer, or can't be scaled to the
+    /// custom font size.
+    #[cfg(has_wgpu)]
+    pub fn build_windowed(
+        &self,
+        viewport_height: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_renderer: &mut TextRenderer,
+    ) -> Result<WindowedText, Error> {
+        WindowedText::new(self, viewport_height, device, queue, text_renderer)
+    }
+
+    /// The settings of the text, for a font and scale factor.
+    pub(crate) fn text_data(
+        &self,
+        font_data: &FontData,
+        scale_factor: f32,
+    ) -> Result<TextData, Error> {
         let scale = match self.custom_font_size {
             None => self.scale,
-            Some(size) => {
-                let self_size = size.px_size(&text_renderer.fonts.get(self.font).font);
-                let font_size = text_renderer.fonts.get(self.font).px_size;
-
-                self.scale * (self_size / font_size)
-            }
+            Some(size) => self.scale * (font_data.px_size_of(size)? / font_data.px_size),
         };
 
         let data = TextData {
-            text: self.text.clone(),
+            text: normalized(self.text.clone(), self.normalize),
+            normalize: self.normalize,
             font: self.font,
             position: self.position,
+            relative_position: self.relative_position,
             color: self.color,
+            opacity: self.opacity,
             scale,
             halign: self.halign,
             valign: self.valign,
+            pixel_snap: self.pixel_snap,
+            stem_darkening: self.stem_darkening,
+            #[cfg(has_wgpu)]
+            nearest_filter: font_data.filter == wgpu::FilterMode::Nearest,
+            // Measuring doesn't depend on how the glyphs are filtered
+            #[cfg(not(has_wgpu))]
+            nearest_filter: false,
+            subpixel_phases: font_data.subpixel_phases,
+            scale_factor,
+            max_width: self.max_width,
+            tabs: self.tabs,
+            block_align: self.block_align,
+            baseline_offset: self.baseline_offset,
+            ignore_trailing_whitespace: self.ignore_trailing_whitespace,
+            line_aligns: self.line_aligns.clone(),
+            paragraphs: self.paragraphs,
+            animation: self.animation,
+            reveal: None,
+            marquee: self.marquee,
+            marquee_start: 0.,
+            panel: self.panel,
 
-            sdf: text_renderer.font_uses_sdf(self.font).then(|| SdfTextData {
-                radius: text_renderer
-                    .fonts
-                    .get(self.font)
-                    .sdf_settings
-                    .unwrap()
-                    .radius,
+            sdf: font_data.sdf_settings.map(|sdf| SdfTextData {
+                radius: sdf.radius,
+                distance_scale: self.distance_scale,
                 outline: self.outline,
             }),
         };
-        Text::new(data, device, queue, text_renderer)
+        data.warn_if_outline_clamped();
+
+        Ok(data)
     }
 
     /// Sets the content of the text.
@@ -244,6 +814,46 @@ impl TextBuilder {
         self
     }
 
+    /// Moves the text by a fraction of the size of the target it's drawn to, e.g. `[0.5, 0.5]`
+    /// with a position of `[0., 0.]` puts it in the middle of the screen, and `[1., 1.]` with a
+    /// position of `[-10., -10.]` puts it 10 pixels from the bottom right corner. The offset is
+    /// worked out when the text is drawn, so the text stays in place when the target is resized,
+    /// and is rounded to a whole pixel.
+    ///
+    /// The size comes from the screen's projection, so this doesn't mean much with
+    /// [TextRenderer::set_view_projections]. With egui it's the size of the whole window, not of
+    /// the painter's clip rect. [Text::bounds] and the other positions a [Text] returns don't
+    /// include this offset, since they don't know the size of the target.
+    pub fn relative_position(&mut self, relative_position: [f32; 2]) -> &mut Self {
+        self.relative_position = relative_position;
+        self
+    }
+
+    /// Sets an effect that moves or recolours each glyph over time, like wavy or shaking text.
+    /// See [GlyphAnimation] for details. The default is [GlyphAnimation::None].
+    ///
+    /// Every glyph of a text has the same animation, so to animate only some words, put them in
+    /// a text of their own.
+    pub fn animation(&mut self, animation: GlyphAnimation) -> &mut Self {
+        self.animation = animation;
+        self
+    }
+
+    /// Makes the text scroll sideways inside a box, if it's wider than the box (see [Marquee]).
+    /// It starts scrolling when the text is laid out. The default is None, which draws the whole
+    /// text standing still.
+    pub fn marquee(&mut self, marquee: Option<Marquee>) -> &mut Self {
+        self.marquee = marquee;
+        self
+    }
+
+    /// Draws a rounded rectangle behind the text, around its bounds (see [Panel]). The default is
+    /// None, which draws no panel.
+    pub fn panel(&mut self, panel: Option<Panel>) -> &mut Self {
+        self.panel = panel;
+        self
+    }
+
     /// Sets the horizontal alignment of the text.
     ///
     /// See [HorizontalAlignment] for details.
@@ -252,6 +862,14 @@ impl TextBuilder {
         self
     }
 
+    /// Sets how wide tabs are. The default is [Tabs::Spaces] with 4 spaces.
+    ///
+    /// Use [Tabs::Columns] to line tabs up the way code editors do.
+    pub fn tabs(&mut self, tabs: Tabs) -> &mut Self {
+        self.tabs = tabs;
+        self
+    }
+
     /// Sets the vertical alignment of the text.
     ///
     /// See [VerticalAlignment] for details.
@@ -260,12 +878,124 @@ impl TextBuilder {
         self
     }
 
+    /// Gives one of the text's lines its own horizontal alignment, or None to go back to the
+    /// text's alignment. Lines are counted by line breaks, so a line that's wrapped (see
+    /// [TextBuilder::max_width]) is aligned as a whole.
+    ///
+    /// A line with its own alignment is aligned inside the box of the text's widest line, rather
+    /// than around the text's position, so e.g. a centred title is centred over a left aligned
+    /// paragraph below it. The widest line is still aligned by the text's alignment.
+    pub fn line_align(&mut self, line: usize, halign: Option<HorizontalAlignment>) -> &mut Self {
+        self.line_aligns.retain(|(l, _)| *l != line);
+
+        if let Some(halign) = halign {
+            self.line_aligns.push((line, halign));
+        }
+
+        self
+    }
+
+    /// Sets which point of the text's box is at its position, setting its horizontal and vertical
+    /// alignment together.
+    ///
+    /// This also turns on block alignment (see [TextBuilder::block_align]), so the anchor is a
+    /// point of the box around all of the text's lines.
+    pub fn anchor(&mut self, anchor: Anchor) -> &mut Self {
+        self.halign = anchor.horizontal();
+        self.valign = anchor.vertical();
+        self.block_align = true;
+        self
+    }
+
+    /// Sets how far the lines of each paragraph are indented, in the same units as the text's
+    /// position. The default is no indent.
+    ///
+    /// Paragraphs are separated by blank lines. The first line of each one is indented by
+    /// `first_line`, and the rest of its lines (whether they were wrapped or start after a line
+    /// break) by `hanging`. The indent is part of each line's box, and counts towards the width
+    /// the lines are wrapped at (see [TextBuilder::max_width]).
+    pub fn indent(&mut self, first_line: f32, hanging: f32) -> &mut Self {
+        self.paragraphs.first_line_indent = first_line;
+        self.paragraphs.hanging_indent = hanging;
+        self
+    }
+
+    /// Sets how much extra space goes before and after each paragraph, in the same units as the
+    /// text's position. The default is none.
+    ///
+    /// Paragraphs are separated by blank lines, which are still drawn as lines, and the space
+    /// between two paragraphs is the first one's space after plus the second one's space before.
+    /// There's no extra space before the first paragraph or after the last.
+    pub fn paragraph_spacing(&mut self, before: f32, after: f32) -> &mut Self {
+        self.paragraphs.space_before = before;
+        self.paragraphs.space_after = after;
+        self
+    }
+
+    /// Sets whether the vertical alignment is of the whole block of lines, rather than just the
+    /// first line. The default is false.
+    ///
+    /// Normally the alignment only uses the font's metrics, so a text with several lines aligned
+    /// to the [Middle](VerticalAlignment::Middle) of its position has its first line centred
+    /// there and the rest hanging below it. With block alignment, the middle of all the lines is
+    /// at the position instead, [Bottom](VerticalAlignment::Bottom) puts the bottom of the last
+    /// line there, and so on. [Top](VerticalAlignment::Top) and
+    /// [Baseline](VerticalAlignment::Baseline) (the first line's baseline) are the same either
+    /// way.
+    pub fn block_align(&mut self, block_align: bool) -> &mut Self {
+        self.block_align = block_align;
+        self
+    }
+
+    /// Moves the text down by some logical pixels (or up, if it's negative) after it's been
+    /// aligned. The default is 0.
+    ///
+    /// This is for nudging text into line with something else, like an icon or text drawn by
+    /// another renderer whose baseline is a little different, without having to find the right
+    /// [VerticalAlignment::Ratio] by trial and error. The text's bounds, lines and carets move
+    /// with it.
+    pub fn baseline_offset(&mut self, offset: f32) -> &mut Self {
+        self.baseline_offset = offset;
+        self
+    }
+
+    /// Sets whether spaces and tabs at the end of each line are left out of its width. The
+    /// default is false.
+    ///
+    /// Trailing spaces take up room like any other character, so right aligned or centred text
+    /// with spaces at the end of a line (including the space a line was wrapped at) is pushed
+    /// away from where it looks like it should be. With this on, lines are aligned by their last
+    /// visible character instead, and their boxes (and the text's bounds) end there. Carets after
+    /// the spaces are still where the spaces end.
+    pub fn ignore_trailing_whitespace(&mut self, ignore: bool) -> &mut Self {
+        self.ignore_trailing_whitespace = ignore;
+        self
+    }
+
+    /// Sets whether the text is normalized to Unicode NFC when it's set (including by
+    /// [Text::set_text] and [Text::replace_range]). The default is false.
+    ///
+    /// Some characters can be written more than one way, e.g. "é" is either one precomposed
+    /// character or an "e" followed by a combining accent. Text typed or pasted by users can come
+    /// in either form, and without normalizing, the decomposed form is drawn as two glyphs
+    /// (which may not line up as well) and cached and laid out separately. With this on, both
+    /// forms become the precomposed one.
+    ///
+    /// Byte indices into the text (e.g. for carets and [Text::replace_range]) are indices into
+    /// the normalized text, which can be a different length than the string it was set to.
+    pub fn normalize(&mut self, normalize: bool) -> &mut Self {
+        self.normalize = normalize;
+        self
+    }
+
     /// Adds an outline to the text, with given colour and width. If the width is less than or
     /// equal to zero, this turns off the outline.
     ///
     /// Text can only be outlined if it is drawn using sdf, so if the font is not sdf-enabled then
-    /// this won't do anything. The outline can only be as wide as the sdf radius of the font. If
+    /// this won't do anything. The outline can only be as wide as the sdf radius of the font
+    /// (times the scale of the text), so wider outlines are clamped and a warning is logged. If
     /// you want a wider outline, use a wider radius (see [crate::SdfSettings]).
+    /// [Text::max_outline_width] gives the widest outline that will fit.
     pub fn outlined(&mut self, color: [f32; 4], width: f32) -> &mut Self {
         if width > 0. {
             self.outline = Some(Outline { color, width });
@@ -285,6 +1015,20 @@ impl TextBuilder {
         self
     }
 
+    /// Sets the distance scale of the text. The default is 1.0.
+    ///
+    /// The sizes of sdf effects (such as the outline width) are multiplied by this value. Fonts
+    /// loaded at different sizes or with different sdf radii have different distance fields, so
+    /// this can be used to keep effects that were tuned for one font looking consistent when drawn
+    /// with another, e.g. by scaling by the ratio of their sizes. See also
+    /// [TextRenderer::sdf_radius].
+    ///
+    /// This does nothing if the font is not sdf-enabled.
+    pub fn distance_scale(&mut self, distance_scale: f32) -> &mut Self {
+        self.distance_scale = distance_scale;
+        self
+    }
+
     /// Sets the colour of the text, in RGBA (values are in the range 0-1). The default is solid
     /// black.
     pub fn color(&mut self, color: [f32; 4]) -> &mut Self {
@@ -292,6 +1036,26 @@ impl TextBuilder {
         self
     }
 
+    /// Sets the colour, outline, scale and opacity of the text at once (see [TextStyle]).
+    pub fn style(&mut self, style: &TextStyle) -> &mut Self {
+        self.color = style.color;
+        self.outline = (style.outline_width > 0.).then_some(Outline {
+            color: style.outline_color,
+            width: style.outline_width,
+        });
+        self.scale = style.scale;
+        self.opacity = style.opacity;
+        self
+    }
+
+    /// Sets how opaque the whole text is, from 0 (invisible) to 1 (the default). This multiplies
+    /// the alpha of both the text's colour and its outline's, so fading an outlined text out only
+    /// takes one value.
+    pub fn opacity(&mut self, opacity: f32) -> &mut Self {
+        self.opacity = opacity;
+        self
+    }
+
     /// Sets the scale of the text. The default is 1.0.
     ///
     /// If the font is not sdf-enabled, it will be scaled up bilinearly, and you may get
@@ -315,16 +1079,79 @@ impl TextBuilder {
         self.custom_font_size = size;
         self
     }
+
+    /// Sets whether the characters should be snapped to whole pixels. The default is false.
+    ///
+    /// Snapping rounds the position of each character to the nearest pixel, so its texture lines
+    /// up exactly with the pixels of the screen instead of being blurred across them. This makes
+    /// small text much sharper, but the spacing between characters will be slightly off from
+    /// what the font specifies.
+    ///
+    /// This does nothing if the font is sdf-enabled.
+    pub fn pixel_snap(&mut self, pixel_snap: bool) -> &mut Self {
+        self.pixel_snap = pixel_snap;
+        self
+    }
+
+    /// Sets how much to darken (thicken) the strokes of the characters. The default is 0.0, which
+    /// draws them as they were rasterised.
+    ///
+    /// Thin fonts at small sizes can look faint and washed out, especially light text on a dark
+    /// background. Stem darkening boosts the partially covered pixels at the edges of each stroke
+    /// to make up for this. Values around 0.2-0.5 are a good start; larger values make the text
+    /// look bolder. Negative values lighten the text instead.
+    ///
+    /// This does nothing if the font is sdf-enabled.
+    pub fn stem_darkening(&mut self, amount: f32) -> &mut Self {
+        self.stem_darkening = amount;
+        self
+    }
+
+    /// Sets whether the text has two instance buffers that it switches between when it changes.
+    /// The default is false.
+    ///
+    /// Text that changes every frame (e.g. a timer) would otherwise overwrite the buffer the GPU
+    /// may still be drawing the previous frame from, which stalls the pipeline on some drivers.
+    /// A double buffered text writes to its other buffer instead, if the one it's using has been
+    /// drawn since it was last written. This costs a second buffer, so only use it for text that
+    /// changes often.
+    pub fn double_buffered(&mut self, double_buffered: bool) -> &mut Self {
+        self.double_buffered = double_buffered;
+        self
+    }
+
+    /// Sets the width to wrap the text's lines at, in the same units as its position, or None to
+    /// not wrap them. The default is None.
+    ///
+    /// Lines are only broken at spaces, and the spaces stay at the end of the line they were
+    /// broken after. A word that's wider than the width on its own gets a line to itself. The
+    /// width includes the text's scale.
+    ///
+    /// A text's wrap width can be changed cheaply after it's built with [Text::set_max_width].
+    pub fn max_width(&mut self, max_width: Option<f32>) -> &mut Self {
+        self.max_width = max_width;
+        self
+    }
 }
 
+#[cfg(has_wgpu)]
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub(crate) struct SettingsUniform {
     color: [f32; 4],
     text_position: [f32; 2],
-    _padding: [f32; 2],
+    stem_darkening: f32,
+    nearest_filter: f32,
+    relative_position: [f32; 2],
+    subpixel_phases: f32,
+    _padding: f32,
+    animation: [f32; 4],
+    reveal: [f32; 4],
+    marquee: [f32; 4],
+    marquee_clip: [f32; 4],
 }
 
+#[cfg(has_wgpu)]
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub(crate) struct SdfSettingsUniform {
@@ -334,50 +1161,170 @@ pub(crate) struct SdfSettingsUniform {
     outline_width: f32,
     sdf_radius: f32,
     image_scale: f32,
-    _padding: [f32; 3],
+    distance_scale: f32,
+    relative_position: [f32; 2],
+    animation: [f32; 4],
+    reveal: [f32; 4],
+    marquee: [f32; 4],
+    marquee_clip: [f32; 4],
+}
+
+/// How a text's settings get to the shaders.
+#[cfg(has_wgpu)]
+#[derive(Debug)]
+pub(crate) enum TextSettings {
+    /// The settings are in a uniform buffer, bound to bind group 2.
+    Uniform {
+        buffer: Arc<wgpu::Buffer>,
+        bind_group: wgpu::BindGroup,
+    },
+    /// The settings are in a slot of a buffer shared with other texts, bound with a dynamic
+    /// offset.
+    Shared(SettingsSlot),
+    /// The settings are pushed as push constants right before the text is drawn.
+    PushConstants,
 }
 
 /// A piece of text that can be rendered to the screen.
 ///
 /// Create one of these using a [TextBuilder], then render it to a wgpu render pass using
 /// [TextRenderer::draw_text].
+#[cfg(has_wgpu)]
 #[derive(Debug)]
 pub struct Text {
     pub(crate) data: TextData,
-    pub(crate) instance_buffer: wgpu::Buffer,
-    pub(crate) settings_bind_group: wgpu::BindGroup,
+    pub(crate) instance_buffer: Arc<wgpu::Buffer>,
+    pub(crate) settings: TextSettings,
+    /// The character or glyph drawn by each instance in the instance buffer.
+    pub(crate) instance_glyphs: Vec<GlyphKey>,
+    /// Where each instance in the instance buffer was in the text's glyphs before they were
+    /// sorted by glyph.
+    pub(crate) instance_order: Vec<u32>,
+    /// A copy of the instance buffer, for drawing on the CPU.
+    #[cfg(feature = "cpu-render")]
+    pub(crate) instances: Vec<crate::CharacterInstance>,
 
-    settings_buffer: wgpu::Buffer,
     instance_capacity: usize,
+    /// The instance buffer that isn't being used, if the text is double buffered (see
+    /// [TextBuilder::double_buffered]).
+    spare_instance_buffer: Option<Arc<wgpu::Buffer>>,
+    /// Whether the instance buffer has been drawn since it was last written to, in which case a
+    /// double buffered text writes to its spare buffer instead.
+    pub(crate) instances_drawn: AtomicBool,
+    /// Where the text's changes go if the renderer uploads them through a staging belt, instead
+    /// of writing them to the queue.
+    staged_writes: Option<StagedWrites>,
+    /// The text's buffers, as counted by the renderer's memory report.
+    memory: TextMemory,
+    /// The renderer's frame stats, which the text's uploads are counted in.
+    frame_counters: Arc<FrameCounters>,
+    /// The box around the text's lines (see [TextLayout::bounds]) in physical pixels, relative to
+    /// the text's position. Texts laid out by another library use the box around their glyphs.
+    pub(crate) layout_bounds: Rect,
+    /// The characters that have something to draw, in physical pixels relative to the text's
+    /// position.
+    pub(crate) glyphs: Vec<PlacedGlyph>,
+    /// The text's lines (see [TextLayout::lines]) in physical pixels, relative to the text's
+    /// position. Texts laid out by another library don't have any.
+    pub(crate) lines: Vec<LineBox>,
+    /// Where a caret goes in each line (see [TextLayout::caret_rect]), in physical pixels relative
+    /// to the text's position.
+    pub(crate) carets: Vec<(usize, f32)>,
+    /// The size and spacing of the text's characters, for wrapping it again. Texts laid out by
+    /// another library don't have them.
+    pub(crate) measured: Option<Arc<MeasuredText>>,
+    /// The parts of the text an input method is composing (see [Text::set_preedit]).
+    preedit: Vec<PreeditSegment>,
+    /// How many characters are drawn (see [Text::set_visible_chars]).
+    visible_chars: Option<usize>,
+    /// How many of the text's glyphs are drawn, which are the ones for the visible characters.
+    pub(crate) visible_glyphs: u32,
+    /// The buffers of the text's panel, if it has one (see [TextBuilder::panel]).
+    pub(crate) panel_buffers: Option<PanelBuffers>,
+    /// The buffers of the text's debug overlay, once it's been drawn (see
+    /// [TextRenderer::draw_debug]).
+    pub(crate) debug_overlay: OnceLock<DebugOverlay>,
 }
 
+#[cfg(has_wgpu)]
 impl Text {
-    /// Creates a new [Text] object and uploads all necessary data to the GPU.
-    fn new(
+    /// Creates a new [Text] object from a layout and uploads all necessary data to the GPU. The
+    /// characters of the layout have to be cached already.
+    pub(crate) fn from_layout(
+        layout: TextLayout,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_renderer: &TextRenderer,
+    ) -> Self {
+        let instances = layout.instances();
+        let data = layout.data.clone();
+        let mut text = Self::from_instances(data, instances, device, queue, text_renderer);
+        text.set_layout_boxes(&layout);
+
+        // The marquee scrolls the layout's bounds, which can be wider than the instances, and the
+        // panel goes around them
+        if text.data.bounds_in_settings() {
+            text.update_settings_buffer(queue);
+        }
+
+        if layout.double_buffered {
+            text.spare_instance_buffer = Some(text.create_spare_instance_buffer(
+                text.instance_capacity,
+                device,
+                text_renderer,
+            ));
+            text.count_memory();
+        }
+
+        text
+    }
+
+    /// Creates a [Text] object from instances that have already been laid out, uploading them to
+    /// the GPU. The glyphs the instances draw have to be cached already.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(instances = instances.len()))
+    )]
+    pub(crate) fn from_instances(
         data: TextData,
+        instances: Vec<(GlyphKey, crate::CharacterInstance)>,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        text_renderer: &mut TextRenderer,
+        text_renderer: &TextRenderer,
     ) -> Self {
-        text_renderer.generate_char_textures(data.text.chars(), data.font, device, queue);
-        let instances = text_renderer.create_text_instances(&data);
+        let layout_bounds = layout::union(instances.iter().map(|(_, instance)| instance.rect()))
+            .unwrap_or_default();
+        let (instance_glyphs, instances, instance_order) = sort_instances(instances);
+        (text_renderer.frame_counters).add_upload(std::mem::size_of_val(instances.as_slice()));
 
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("kaku text instance buffer"),
-            contents: bytemuck::cast_slice(&instances),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
+        let instance_buffer = Arc::new(device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some(&text_renderer.label("text instance buffer")),
+                contents: bytemuck::cast_slice(&instances),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            },
+        ));
 
-        let (settings_buffer, settings_bind_group) = if text_renderer.font_uses_sdf(data.font) {
-            let text_settings = data.sdf_settings_uniform();
+        let settings = if text_renderer.push_constants {
+            TextSettings::PushConstants
+        } else if let Some(pool) = &text_renderer.settings_pool {
+            let slot = pool.allocate(
+                &text_renderer.settings_layout,
+                &text_renderer.sdf_settings_layout,
+                |name| text_renderer.label(name),
+                device,
+            );
+            TextSettings::Shared(slot)
+        } else if data.sdf.is_some() {
+            let text_settings = data.sdf_settings_uniform(layout_bounds);
             let settings_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("kaku sdf text settings uniform buffer"),
+                label: Some(&text_renderer.label("sdf text settings uniform buffer")),
                 contents: bytemuck::cast_slice(&[text_settings]),
                 usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
             });
 
             let settings_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("kaku sdf text settings uniform bind group"),
+                label: Some(&text_renderer.label("sdf text settings uniform bind group")),
                 layout: &text_renderer.sdf_settings_layout,
                 entries: &[wgpu::BindGroupEntry {
                     binding: 0,
@@ -385,18 +1332,21 @@ impl Text {
                 }],
             });
 
-            (settings_buffer, settings_bind_group)
+            TextSettings::Uniform {
+                buffer: Arc::new(settings_buffer),
+                bind_group: settings_bind_group,
+            }
         } else {
-            let text_settings = data.settings_uniform();
+            let text_settings = data.settings_uniform(layout_bounds);
 
             let settings_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("kaku text settings uniform buffer"),
+                label: Some(&text_renderer.label("text settings uniform buffer")),
                 contents: bytemuck::cast_slice(&[text_settings]),
                 usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
             });
 
             let settings_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("kaku text settings uniform bind group"),
+                label: Some(&text_renderer.label("text settings uniform bind group")),
                 layout: &text_renderer.settings_layout,
                 entries: &[wgpu::BindGroupEntry {
                     binding: 0,
@@ -404,64 +1354,484 @@ impl Text {
                 }],
             });
 
-            (settings_buffer, settings_bind_group)
+            TextSettings::Uniform {
+                buffer: Arc::new(settings_buffer),
+                bind_group: settings_bind_group,
+            }
         };
 
-        Self {
+        let staged_writes = text_renderer.staging.as_ref().map(|s| s.writes.clone());
+        let panel_buffers = (data.panel).map(|_| PanelBuffers::new(text_renderer, device));
+        let mut text = Self {
             data,
             instance_buffer,
-            settings_bind_group,
-            settings_buffer,
+            settings,
+            instance_glyphs,
+            instance_order,
             instance_capacity: instances.len(),
+            spare_instance_buffer: None,
+            instances_drawn: AtomicBool::new(false),
+            staged_writes,
+            memory: TextMemory::new(text_renderer.memory.clone()),
+            frame_counters: text_renderer.frame_counters.clone(),
+            layout_bounds,
+            glyphs: Vec::new(),
+            lines: Vec::new(),
+            carets: Vec::new(),
+            measured: None,
+            preedit: Vec::new(),
+            visible_chars: None,
+            visible_glyphs: u32::MAX,
+            panel_buffers,
+            debug_overlay: OnceLock::new(),
+            #[cfg(feature = "cpu-render")]
+            instances,
+        };
+        text.count_memory();
+
+        // A shared slot starts out with whatever the last text to use it left there
+        if matches!(text.settings, TextSettings::Shared(_)) {
+            text.update_settings_buffer(queue);
         }
+
+        text
     }
 
     /// Changes the text displayed by this text object.
     ///
     /// This is faster than recreating the object because it may reuse its existing gpu buffer
     /// instead of recreating it.
+    ///
+    /// Returns an error if the text's font isn't loaded in the text renderer, in which case the
+    /// text is left unchanged.
     pub fn set_text(
         &mut self,
         text: String,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         text_renderer: &mut TextRenderer,
+    ) -> Result<(), Error> {
+        let uploads = Uploads::Queue(queue);
+        self.replace_text(
+            text,
+            device,
+            &uploads,
+            text_renderer,
+            |this, buffer, offset, data| {
+                this.write_buffer(buffer, offset, data, queue);
+            },
+        )
+    }
+
+    /// Like [Text::set_text], but new character textures and the text's new instances are
+    /// uploaded by copies recorded into `encoder`, instead of being written to the queue.
+    ///
+    /// The encoder has to be submitted before the text is drawn again. This skips the renderer's
+    /// staging belt, if it has one (see
+    /// [TextRendererBuilder::with_staging_belt](crate::TextRendererBuilder::with_staging_belt)).
+    pub fn set_text_with_encoder(
+        &mut self,
+        text: String,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        text_renderer: &mut TextRenderer,
+    ) -> Result<(), Error> {
+        let uploads = Uploads::deferred();
+        let label = text_renderer.label("text staging buffer");
+        let write_buffer = |this: &Self, buffer: &Arc<wgpu::Buffer>, offset, data: &[u8]| {
+            this.frame_counters.add_upload(data.len());
+            uploads.write_buffer(buffer, offset, data, &label, device);
+        };
+        let result = self.replace_text(text, device, &uploads, text_renderer, write_buffer);
+
+        uploads.record(encoder);
+        result
+    }
+
+    /// Replaces a byte range of the text with another string, like [String::replace_range], e.g.
+    /// for typing into a text editor.
+    ///
+    /// Unlike [Text::set_text], only the lines the edit touches are measured and laid out again
+    /// and have their characters generated. The lines before them are kept as they are, the lines
+    /// after them are only moved, and only the instances that changed are uploaded. Instances are
+    /// sorted by glyph, so an edit near the start of a long text can still change most of them.
+    ///
+    /// Texts laid out by another library, laid out before the renderer's scale factor changed, or
+    /// whose lines are aligned against each other (see [TextBuilder::block_align] and
+    /// [TextBuilder::line_align]) are laid out in full like with [Text::set_text].
+    ///
+    /// Returns an error if the text's font isn't loaded in the text renderer, in which case the
+    /// text is left unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or isn't on character boundaries, like
+    /// [String::replace_range].
+    pub fn replace_range(
+        &mut self,
+        range: Range<usize>,
+        replace_with: &str,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_renderer: &mut TextRenderer,
+    ) -> Result<(), Error> {
+        let mut text = self.data.text.clone();
+        text.replace_range(range.clone(), replace_with);
+
+        // An edit that needs normalizing can change characters outside its range (e.g. a
+        // combining accent typed after a letter), so the whole text is laid out again
+        let measured = match &self.measured {
+            Some(measured)
+                if self.data.scale_factor == text_renderer.scale_factor
+                    && (!self.data.normalize || is_nfc(&text)) =>
+            {
+                measured.clone()
+            }
+            _ => return self.set_text(text, device, queue, text_renderer),
+        };
+        let data = TextData {
+            text,
+            ..self.data.clone()
+        };
+
+        // The lines around the edit are measured again too, in case it joins or splits a line
+        // break (e.g. a \r\n)
+        let old_lines = &measured.lines;
+        let line_at = |index| {
+            old_lines
+                .partition_point(|line| line.text.start <= index)
+                .saturating_sub(1)
+        };
+        let first = line_at(range.start).saturating_sub(1);
+        let last = (line_at(range.end) + 1).min(old_lines.len() - 1);
+        let old_bytes = old_lines[first].text.start..old_lines[last].text.end;
+        let new_bytes = old_bytes.start..old_bytes.end + replace_with.len() - range.len();
+
+        let uploads = Uploads::Queue(queue);
+        text_renderer.generate_text(&data, new_bytes.clone(), device, &uploads)?;
+        let font_data = text_renderer.fonts.get(data.font)?;
+        let new_lines = font_data.measure_lines(&data, new_bytes)?;
+
+        // The lines after the edit only move
+        let shift = |index: usize| index + replace_with.len() - range.len();
+        let after = old_lines[last + 1..].iter().map(|line| MeasuredLine {
+            text: shift(line.text.start)..shift(line.text.end),
+            chars: (line.chars.iter())
+                .map(|c| MeasuredChar {
+                    index: shift(c.index),
+                    ..*c
+                })
+                .collect(),
+        });
+        let remeasured = first..first + new_lines.len();
+        let lines = (old_lines[..first].iter().cloned())
+            .chain(new_lines)
+            .chain(after)
+            .collect();
+
+        let measured = Arc::new(MeasuredText {
+            lines,
+            ..(*measured).clone()
+        });
+        let old_remeasured = first..last + 1;
+        let (layout, sorted) = match TextLayout::reflow(
+            data.clone(),
+            measured.clone(),
+            self,
+            remeasured,
+            old_remeasured,
+        ) {
+            Some(Reflow {
+                layout,
+                old_glyphs,
+                new_glyphs,
+            }) => {
+                let old_sorted = (&*self.instance_glyphs, &*self.instance_order);
+                let sorted = splice_instances(&layout.glyphs, old_sorted, old_glyphs, new_glyphs);
+                (layout, sorted)
+            }
+            None => {
+                let layout = TextLayout::flow(data, measured);
+                let sorted = sort_instances(layout.instances());
+                (layout, sorted)
+            }
+        };
+
+        // Only the instances between the first and last that changed are uploaded
+        let old_instances = (self.instance_order.iter()).map(|&i| crate::CharacterInstance {
+            index: i,
+            ..self.glyphs[i as usize].instance
+        });
+        let new_instances = &sorted.1;
+        let same = |(a, b): (crate::CharacterInstance, &crate::CharacterInstance)| {
+            bytemuck::bytes_of(&a) == bytemuck::bytes_of(b)
+        };
+        let start = (old_instances.clone().zip(new_instances))
+            .take_while(|&pair| same(pair))
+            .count();
+        let end = if self.instance_order.len() == new_instances.len() {
+            let unchanged = (old_instances.rev().zip(new_instances.iter().rev()))
+                .take_while(|&pair| same(pair))
+                .count();
+            (new_instances.len() - unchanged).max(start)
+        } else {
+            new_instances.len()
+        };
+
+        self.switch_layout(
+            layout,
+            sorted,
+            start..end,
+            device,
+            text_renderer,
+            |this, buffer, offset, data| {
+                this.write_buffer(buffer, offset, data, queue);
+            },
+        );
+        Ok(())
+    }
+
+    /// Lays the text out again with a new string, generating any characters it needs. If the new
+    /// instances fit in the instance buffer they're written with `write_buffer`, otherwise bigger buffers are created with them in it.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(len = text.len()))
+    )]
+    fn replace_text(
+        &mut self,
+        text: String,
+        device: &wgpu::Device,
+        uploads: &Uploads,
+        text_renderer: &mut TextRenderer,
+        write_buffer: impl Fn(&Self, &Arc<wgpu::Buffer>, wgpu::BufferAddress, &[u8]),
+    ) -> Result<(), Error> {
+        let data = TextData {
+            text: normalized(text, self.data.normalize),
+            scale_factor: text_renderer.scale_factor,
+            ..self.data.clone()
+        };
+
+        text_renderer.generate_text(&data, 0..data.text.len(), device, uploads)?;
+        let layout = text_renderer.create_text_layout(data)?;
+        let sorted = sort_instances(layout.instances());
+        let changed = 0..sorted.1.len();
+        self.switch_layout(layout, sorted, changed, device, text_renderer, write_buffer);
+        Ok(())
+    }
+
+    /// Replaces the text's layout and its sorted instances (see [sort_instances]), and uploads the
+    /// instances. If they fit in the instance buffer, the ones in the range `changed` are written
+    /// with `write_buffer`, otherwise bigger buffers are created with
+    /// them in it.
+    fn switch_layout(
+        &mut self,
+        layout: TextLayout,
+        (instance_glyphs, new_instances, instance_order): SortedInstances,
+        changed: Range<usize>,
+        device: &wgpu::Device,
+        text_renderer: &TextRenderer,
+        write_buffer: impl Fn(&Self, &Arc<wgpu::Buffer>, wgpu::BufferAddress, &[u8]),
     ) {
-        text_renderer.generate_char_textures(text.chars(), self.data.font, device, queue);
-        self.data.text = text;
-        let new_instances = text_renderer.create_text_instances(&self.data);
+        self.set_layout_boxes(&layout);
+        let data = layout.data;
+        self.data = data;
+        self.instance_glyphs = instance_glyphs;
+        self.instance_order = instance_order;
+
+        // The marquee scrolls the layout's bounds, which may have changed size
+        if let (Some(_), Some((buffer, offset))) = (self.data.marquee, self.settings_buffer()) {
+            write_buffer(self, buffer, offset, &self.settings_bytes());
+        }
+        self.write_panel(|buffer, offset, data| write_buffer(self, buffer, offset, data));
 
         if new_instances.len() > self.instance_capacity {
-            self.instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("kaku text instance buffer"),
-                contents: bytemuck::cast_slice(&new_instances),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
+            (self.frame_counters).add_upload(std::mem::size_of_val(new_instances.as_slice()));
+            self.instance_buffer = Arc::new(device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some(&text_renderer.label("text instance buffer")),
+                    contents: bytemuck::cast_slice(&new_instances),
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                },
+            ));
 
             self.instance_capacity = new_instances.len();
+            self.instances_drawn.store(false, Ordering::Relaxed);
+
+            if self.spare_instance_buffer.is_some() {
+                let spare =
+                    self.create_spare_instance_buffer(new_instances.len(), device, text_renderer);
+                self.spare_instance_buffer = Some(spare);
+            }
+
+            self.count_memory();
         } else {
-            queue.write_buffer(
+            // The spare buffer might have older instances than the current one, so all of them
+            // are written if the buffers are swapped
+            self.swap_instance_buffers();
+            let changed = match self.spare_instance_buffer {
+                Some(_) => 0..new_instances.len(),
+                None => changed,
+            };
+            let offset = changed.start * std::mem::size_of::<crate::CharacterInstance>();
+            write_buffer(
+                self,
                 &self.instance_buffer,
-                0,
-                bytemuck::cast_slice(&new_instances),
+                offset as _,
+                bytemuck::cast_slice(&new_instances[changed]),
             );
         }
+
+        #[cfg(feature = "cpu-render")]
+        {
+            self.instances = new_instances;
+        }
     }
 
-    // Uploads the current settings (as described in self.data) to the settings buffer on the GPU.
-    fn update_settings_buffer(&self, queue: &wgpu::Queue) {
+    // Creates an empty instance buffer with room for `capacity` instances, for double buffering.
+    fn create_spare_instance_buffer(
+        &self,
+        capacity: usize,
+        device: &wgpu::Device,
+        text_renderer: &TextRenderer,
+    ) -> Arc<wgpu::Buffer> {
+        Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&text_renderer.label("spare text instance buffer")),
+            size: (capacity * std::mem::size_of::<crate::CharacterInstance>()) as _,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }))
+    }
+
+    // Updates how many bytes the text's buffers use in the renderer's memory report, after they've
+    // been created or replaced.
+    fn count_memory(&mut self) {
+        let instance_buffers = self.instance_buffer.size()
+            + self.spare_instance_buffer.as_ref().map_or(0, |b| b.size());
+        let uniform_buffers = match &self.settings {
+            TextSettings::Uniform { buffer, .. } => buffer.size(),
+            TextSettings::Shared(_) | TextSettings::PushConstants => 0,
+        };
+
+        self.memory.set(instance_buffers, uniform_buffers);
+    }
+
+    // Switches to the spare instance buffer before the instances are written, if the text is
+    // double buffered and the current buffer might still be being drawn from.
+    fn swap_instance_buffers(&mut self) {
+        let Some(spare) = &mut self.spare_instance_buffer else {
+            return;
+        };
+
+        if self.instances_drawn.swap(false, Ordering::Relaxed) {
+            std::mem::swap(&mut self.instance_buffer, spare);
+        }
+    }
+
+    // The current settings (as described in self.data), as they are laid out on the GPU.
+    fn settings_bytes(&self) -> Vec<u8> {
         if self.data.sdf.is_some() {
-            queue.write_buffer(
-                &self.settings_buffer,
-                0,
-                bytemuck::cast_slice(&[self.data.sdf_settings_uniform()]),
-            );
+            bytemuck::bytes_of(&self.data.sdf_settings_uniform(self.layout_bounds)).to_vec()
         } else {
-            queue.write_buffer(
-                &self.settings_buffer,
-                0,
-                bytemuck::cast_slice(&[self.data.settings_uniform()]),
-            );
+            bytemuck::bytes_of(&self.data.settings_uniform(self.layout_bounds)).to_vec()
+        }
+    }
+
+    // Writes data to one of the text's buffers, either straight away or through the renderer's
+    // staging belt.
+    fn write_buffer(
+        &self,
+        buffer: &Arc<wgpu::Buffer>,
+        offset: wgpu::BufferAddress,
+        data: &[u8],
+        queue: &wgpu::Queue,
+    ) {
+        self.frame_counters.add_upload(data.len());
+        match &self.staged_writes {
+            Some(writes) => writes.push(buffer, offset, data),
+            None => queue.write_buffer(buffer, offset, data),
+        }
+    }
+
+    // Uploads the current settings to the settings buffer on the GPU. Texts using push constants
+    // have nothing to upload, their settings are pushed when they're drawn.
+    fn update_settings_buffer(&self, queue: &wgpu::Queue) {
+        if let Some((buffer, offset)) = self.settings_buffer() {
+            self.write_buffer(buffer, offset, &self.settings_bytes(), queue);
+        }
+
+        self.write_panel(|buffer, offset, data| self.write_buffer(buffer, offset, data, queue));
+
+        if let Some(overlay) = self.debug_overlay.get() {
+            let settings = DebugOverlay::settings_uniform(&self.data);
+            let buffer = &overlay.settings_buffer;
+            self.write_buffer(buffer, 0, bytemuck::bytes_of(&settings), queue);
+        }
+    }
+
+    /// Writes the panel's quad and settings with `write_buffer`, if the text has one.
+    fn write_panel(&self, write_buffer: impl Fn(&Arc<wgpu::Buffer>, wgpu::BufferAddress, &[u8])) {
+        let (Some(panel), Some(buffers)) = (&self.data.panel, &self.panel_buffers) else {
+            return;
+        };
+
+        let rect = self.physical_panel_rect(panel);
+        let instance = crate::CharacterInstance {
+            position: rect.min,
+            size: [rect.width(), rect.height()],
+            index: 0,
+        };
+        let settings = PanelUniform::new(panel, &self.data);
+
+        write_buffer(&buffers.instance_buffer, 0, bytemuck::bytes_of(&instance));
+        write_buffer(&buffers.settings_buffer, 0, bytemuck::bytes_of(&settings));
+    }
+
+    /// A panel around the text's bounds (including its outline), in physical pixels relative to
+    /// its position.
+    pub(crate) fn physical_panel_rect(&self, panel: &Panel) -> Rect {
+        let outline = self.physical_outline_width();
+        let bounds = Rect {
+            min: self.layout_bounds.min.map(|p| p - outline),
+            max: self.layout_bounds.max.map(|p| p + outline),
+        };
+        panel.rect(bounds, self.data.scale_factor)
+    }
+
+    // The buffer the text's settings are in and their offset in it, or None if they're pushed as
+    // push constants.
+    fn settings_buffer(&self) -> Option<(&Arc<wgpu::Buffer>, wgpu::BufferAddress)> {
+        match &self.settings {
+            TextSettings::Uniform { buffer, .. } => Some((buffer, 0)),
+            TextSettings::Shared(slot) => Some((&slot.page.buffer, slot.offset as _)),
+            TextSettings::PushConstants => None,
+        }
+    }
+
+    /// Sets the text's settings on the render pass, either by binding its settings bind group or
+    /// by pushing them as push constants. Returns whether a bind group was set.
+    pub(crate) fn bind_settings<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) -> bool {
+        match &self.settings {
+            TextSettings::Uniform { bind_group, .. } => {
+                render_pass.set_bind_group(2, bind_group, &[]);
+                true
+            }
+            TextSettings::Shared(slot) => {
+                let bind_group = if self.data.sdf.is_some() {
+                    &slot.page.sdf_bind_group
+                } else {
+                    &slot.page.bind_group
+                };
+                render_pass.set_bind_group(2, bind_group, &[slot.offset]);
+                true
+            }
+            TextSettings::PushConstants => {
+                render_pass.set_push_constants(
+                    wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    0,
+                    &self.settings_bytes(),
+                );
+                false
+            }
         }
     }
 
@@ -471,9 +1841,142 @@ impl Text {
         self.update_settings_buffer(queue);
     }
 
+    /// Changes how opaque the whole text is, outline included (see [TextBuilder::opacity]).
+    pub fn set_opacity(&mut self, opacity: f32, queue: &wgpu::Queue) {
+        self.data.opacity = opacity;
+        self.update_settings_buffer(queue);
+    }
+
+    /// How opaque the whole text is (see [TextBuilder::opacity]).
+    pub fn opacity(&self) -> f32 {
+        self.data.opacity
+    }
+
+    /// The text's current colour, outline, scale and opacity, e.g. to start a transition from.
+    pub fn style(&self) -> TextStyle {
+        let outline = self.data.sdf.and_then(|sdf| sdf.outline);
+
+        TextStyle {
+            color: self.data.color,
+            outline_color: outline.map_or([0.; 4], |o| o.color),
+            outline_width: outline.map_or(0., |o| o.width),
+            scale: self.data.scale,
+            opacity: self.data.opacity,
+        }
+    }
+
+    /// Changes the text's colour, outline, scale and opacity all at once, e.g. to a style
+    /// interpolated with [TextStyle::lerp] for a hover transition.
+    ///
+    /// If the scale changes, the text is laid out again with the characters it already has, so
+    /// this returns an error if its font isn't loaded in the text renderer or its characters are
+    /// no longer cached. The other changes only update the text's settings. Outlines are only
+    /// drawn on sdf text.
+    pub fn apply_style(
+        &mut self,
+        style: &TextStyle,
+        queue: &wgpu::Queue,
+        text_renderer: &TextRenderer,
+    ) -> Result<(), Error> {
+        if style.scale != self.data.scale {
+            let layout = text_renderer.create_text_layout(TextData {
+                scale: style.scale,
+                scale_factor: text_renderer.scale_factor,
+                ..self.data.clone()
+            })?;
+            self.data.scale = style.scale;
+            self.data.scale_factor = text_renderer.scale_factor;
+            self.rewrite_instances(&layout, queue);
+        }
+
+        self.data.color = style.color;
+        self.data.opacity = style.opacity;
+        if let Some(sdf) = &mut self.data.sdf {
+            sdf.outline = (style.outline_width > 0.).then_some(Outline {
+                color: style.outline_color,
+                width: style.outline_width,
+            });
+        }
+
+        self.data.warn_if_outline_clamped();
+        self.update_settings_buffer(queue);
+        Ok(())
+    }
+
     /// Changes the scale of the text.
     pub fn set_scale(&mut self, scale: f32, queue: &wgpu::Queue) {
         self.data.scale = scale;
+        self.data.warn_if_outline_clamped();
+        self.update_settings_buffer(queue);
+    }
+
+    /// Changes whether the characters are snapped to whole pixels (see [TextBuilder::pixel_snap]).
+    ///
+    /// This has to lay the text out again, so it returns an error if the text's font isn't loaded
+    /// in the text renderer or its characters are no longer cached.
+    pub fn set_pixel_snap(
+        &mut self,
+        pixel_snap: bool,
+        queue: &wgpu::Queue,
+        text_renderer: &TextRenderer,
+    ) -> Result<(), Error> {
+        let layout = text_renderer.create_text_layout(TextData {
+            pixel_snap,
+            scale_factor: text_renderer.scale_factor,
+            ..self.data.clone()
+        })?;
+        self.data.pixel_snap = pixel_snap;
+        self.data.scale_factor = text_renderer.scale_factor;
+        self.rewrite_instances(&layout, queue);
+        self.update_settings_buffer(queue);
+
+        Ok(())
+    }
+
+    /// Changes the width the text's lines are wrapped at (see [TextBuilder::max_width]).
+    ///
+    /// The size and spacing of every character are kept from when the text was laid out, so this
+    /// only moves the characters onto their new lines, without looking them up or generating
+    /// anything again. Texts laid out by cosmic-text are wrapped by cosmic-text, so this does
+    /// nothing to them.
+    pub fn set_max_width(&mut self, max_width: Option<f32>, queue: &wgpu::Queue) {
+        let Some(measured) = self.measured.clone() else {
+            return;
+        };
+
+        self.data.max_width = max_width;
+        let layout = TextLayout::flow(self.data.clone(), measured);
+        self.rewrite_instances(&layout, queue);
+    }
+
+    /// Replaces the text's instances with those of a new layout of the same characters.
+    fn rewrite_instances(&mut self, layout: &TextLayout, queue: &wgpu::Queue) {
+        let (instance_glyphs, instances, instance_order) = sort_instances(layout.instances());
+        self.set_layout_boxes(layout);
+
+        // The characters are the same, so the buffer is always big enough
+        self.swap_instance_buffers();
+        let data = bytemuck::cast_slice(&instances);
+        self.write_buffer(&self.instance_buffer, 0, data, queue);
+        self.instance_glyphs = instance_glyphs;
+        self.instance_order = instance_order;
+
+        if self.data.bounds_in_settings() {
+            self.update_settings_buffer(queue);
+        }
+
+        #[cfg(feature = "cpu-render")]
+        {
+            self.instances = instances;
+        }
+    }
+
+    /// Changes how much the strokes of the characters are darkened (see
+    /// [TextBuilder::stem_darkening]).
+    ///
+    /// This does nothing if the font is rendered with sdf.
+    pub fn set_stem_darkening(&mut self, amount: f32, queue: &wgpu::Queue) {
+        self.data.stem_darkening = amount;
         self.update_settings_buffer(queue);
     }
 
@@ -483,9 +1986,113 @@ impl Text {
         self.update_settings_buffer(queue);
     }
 
+    /// Changes the position of the text relative to the size of its target (see
+    /// [TextBuilder::relative_position]).
+    pub fn set_relative_position(&mut self, relative_position: [f32; 2], queue: &wgpu::Queue) {
+        self.data.relative_position = relative_position;
+        self.update_settings_buffer(queue);
+    }
+
+    /// Changes the text's animation (see [TextBuilder::animation]).
+    pub fn set_animation(&mut self, animation: GlyphAnimation, queue: &wgpu::Queue) {
+        self.data.animation = animation;
+        self.update_settings_buffer(queue);
+    }
+
+    /// The text's animation (see [TextBuilder::animation]).
+    pub fn animation(&self) -> GlyphAnimation {
+        self.data.animation
+    }
+
+    /// Makes the glyphs fade in and slide up into place one after another, starting now (at the
+    /// renderer's [time](TextRenderer::time)). Each glyph takes `duration` seconds to appear, and
+    /// starts `stagger` seconds after the one before it, in the order of the text.
+    ///
+    /// The reveal is played by the shaders as the renderer's time goes on (see
+    /// [TextRenderer::set_time]), so nothing is uploaded while it plays. Playing it again starts
+    /// it over, and the glyphs stay shown once it's done (see [Text::reveal_end]).
+    pub fn play_reveal(
+        &mut self,
+        duration: f32,
+        stagger: f32,
+        text_renderer: &TextRenderer,
+        queue: &wgpu::Queue,
+    ) {
+        self.data.reveal = Some(Reveal {
+            start: text_renderer.time(),
+            duration,
+            stagger,
+        });
+        self.update_settings_buffer(queue);
+    }
+
+    /// Makes the text scroll sideways inside a box, starting now (at the renderer's
+    /// [time](TextRenderer::time)), or draws all of it standing still again with None (see
+    /// [TextBuilder::marquee]).
+    pub fn set_marquee(
+        &mut self,
+        marquee: Option<Marquee>,
+        text_renderer: &TextRenderer,
+        queue: &wgpu::Queue,
+    ) {
+        self.data.marquee = marquee;
+        self.data.marquee_start = text_renderer.time();
+        self.update_settings_buffer(queue);
+    }
+
+    /// The text's marquee (see [TextBuilder::marquee]).
+    pub fn marquee(&self) -> Option<Marquee> {
+        self.data.marquee
+    }
+
+    /// Draws a rounded rectangle behind the text, or removes it with None (see
+    /// [TextBuilder::panel]).
+    pub fn set_panel(
+        &mut self,
+        panel: Option<Panel>,
+        text_renderer: &TextRenderer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        self.data.panel = panel;
+
+        if panel.is_none() {
+            self.panel_buffers = None;
+            return;
+        }
+
+        if self.panel_buffers.is_none() {
+            self.panel_buffers = Some(PanelBuffers::new(text_renderer, device));
+        }
+        self.write_panel(|buffer, offset, data| self.write_buffer(buffer, offset, data, queue));
+    }
+
+    /// The text's panel (see [TextBuilder::panel]).
+    pub fn panel(&self) -> Option<Panel> {
+        self.data.panel
+    }
+
+    /// Returns the rectangle of the text's panel on the screen, in the same units as its
+    /// position, or None if it doesn't have one.
+    pub fn panel_rect(&self) -> Option<Rect> {
+        let panel = self.data.panel.as_ref()?;
+        Some(self.on_screen(self.physical_panel_rect(panel), 0.))
+    }
+
+    /// The renderer time at which the last glyph of the reveal is fully shown, or None if no
+    /// reveal has been played (see [Text::play_reveal]).
+    pub fn reveal_end(&self) -> Option<f32> {
+        let reveal = self.data.reveal?;
+        let last = self.glyphs.len().saturating_sub(1) as f32;
+        Some(reveal.start + reveal.duration.max(0.) + reveal.stagger * last)
+    }
+
     /// Sets the outline to be on with the given options. If the width is less than or equal to zero, it turns
     /// the outline off.
     ///
+    /// If the outline is wider than [Text::max_outline_width], it's clamped and a warning is
+    /// logged.
+    ///
     /// This does nothing if the font is not rendered with sdf.
     pub fn set_outline(&mut self, color: [f32; 4], width: f32, queue: &wgpu::Queue) {
         if let Some(sdf) = &mut self.data.sdf {
@@ -496,9 +2103,206 @@ impl Text {
             }
         }
 
+        self.data.warn_if_outline_clamped();
+        self.update_settings_buffer(queue);
+    }
+
+    /// Changes the distance scale of the text (see [TextBuilder::distance_scale]).
+    ///
+    /// This does nothing if the font is not rendered with sdf.
+    pub fn set_distance_scale(&mut self, distance_scale: f32, queue: &wgpu::Queue) {
+        if let Some(sdf) = &mut self.data.sdf {
+            sdf.distance_scale = distance_scale;
+        }
+
+        self.data.warn_if_outline_clamped();
         self.update_settings_buffer(queue);
     }
 
+    /// Returns the widest outline this text can have at its current scale and distance scale, or
+    /// None if the font isn't rendered with sdf.
+    ///
+    /// Outlines wider than this are clamped to it, since the distance field doesn't extend any
+    /// further past the edges of the characters.
+    pub fn max_outline_width(&self) -> Option<f32> {
+        self.data.max_outline_width()
+    }
+
+    /// Returns the rectangle the text covers on the screen, in the same units as its position.
+    ///
+    /// These are the text's logical bounds: the box around its lines (see [TextLayout::bounds])
+    /// at the text's position, made bigger by the width of its outline if it has one. Characters
+    /// that reach past the font's ascent or descent can go a little outside it. Use
+    /// [Text::ink_bounds] for the box around what's actually drawn.
+    pub fn bounds(&self) -> Rect {
+        self.on_screen(self.layout_bounds, self.physical_outline_width())
+    }
+
+    /// Returns the box around the ink of the text's glyphs on the screen (see
+    /// [TextLayout::ink_bounds]), in the same units as its position. Like [Text::bounds], it's
+    /// made bigger by the width of the text's outline.
+    ///
+    /// This is usually a lot tighter than [Text::bounds], so it's what to use to visually centre
+    /// a single symbol like "•" or "▶". Returns None if the text has nothing to draw.
+    pub fn ink_bounds(&self) -> Option<Rect> {
+        let ink = layout::union(self.glyphs.iter().map(PlacedGlyph::ink))?;
+        Some(self.on_screen(ink, self.physical_outline_width()))
+    }
+
+    /// Returns the characters that have something to draw on the screen, in the order of the text
+    /// and in the same units as its position, e.g. for putting effects or decorations on
+    /// particular characters. Characters without textures (like spaces) aren't included.
+    ///
+    /// For texts laid out by cosmic-text, each glyph can be a whole cluster of characters, and
+    /// its [character](LaidOutGlyph::character) is the first one.
+    pub fn glyphs(&self) -> impl ExactSizeIterator<Item = LaidOutGlyph> + '_ {
+        (self.glyphs.iter()).map(|glyph| glyph.laid_out(|rect| self.on_screen(rect, 0.)))
+    }
+
+    /// Returns the number of lines the text was laid out in.
+    ///
+    /// Texts laid out by cosmic-text don't keep their lines, so this is 0 for them.
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Returns the text's lines from top to bottom, on the screen and in the same units as its
+    /// position, e.g. for highlighting the line under the mouse. Each line's
+    /// [width](LineBox::width), [ascent](LineBox::ascent) and [descent](LineBox::descent) include
+    /// the text's scale.
+    ///
+    /// Texts laid out by cosmic-text don't keep their lines, so they don't have any.
+    pub fn lines(&self) -> impl ExactSizeIterator<Item = LineBox> + '_ {
+        let scale_factor = self.data.scale_factor;
+        let y = self.data.draw_position()[1];
+
+        self.lines.iter().map(move |line| LineBox {
+            rect: self.on_screen(line.rect, 0.),
+            baseline: (line.baseline + y) / scale_factor,
+            glyphs: line.glyphs.clone(),
+            text: line.text.clone(),
+        })
+    }
+
+    /// Returns the rectangle of a text cursor (caret) at a byte index of the text, on the screen
+    /// and in the same units as the text's position. It has no width, and goes from the top of
+    /// the caret's line to the bottom, so it's as tall as the text's lines at its current scale.
+    ///
+    /// See [TextLayout::caret_rect] for where the caret goes at indices that aren't before a
+    /// drawn character. Texts laid out by cosmic-text don't keep their lines, so this returns
+    /// None for them.
+    pub fn caret_rect(&self, byte_index: usize) -> Option<Rect> {
+        if self.lines.is_empty() {
+            return None;
+        }
+
+        let caret = layout::caret_rect(&self.lines, &self.carets, byte_index);
+        Some(self.on_screen(caret, 0.))
+    }
+
+    /// Returns the rectangle to highlight one of the text's lines with, on the screen and in the
+    /// same units as the text's position (see [TextLayout::line_highlight_rect]). It's as wide as
+    /// the text's widest line, so it follows the text's alignment and scale.
+    ///
+    /// Returns None if there's no line with that index, including for texts laid out by
+    /// cosmic-text, which don't keep their lines.
+    pub fn line_highlight_rect(&self, line: usize) -> Option<Rect> {
+        let rect = layout::line_highlight_rect(&self.lines, line)?;
+        Some(self.on_screen(rect, 0.))
+    }
+
+    /// Returns the rectangles covering a byte range of the text, one for each line it's on, on
+    /// the screen and in the same units as the text's position (see [TextLayout::range_rects]).
+    ///
+    /// Texts laid out by cosmic-text don't keep their lines, so this returns nothing for them.
+    pub fn range_rects(&self, range: Range<usize>) -> Vec<Rect> {
+        layout::range_rects(&self.lines, &self.carets, range)
+            .map(|(_, rect)| self.on_screen(rect, 0.))
+            .collect()
+    }
+
+    /// Marks the parts of the text that an input method (IME) is composing, which replaces any
+    /// that were marked before. Pass an empty list once the text is committed.
+    ///
+    /// Kaku doesn't draw the underlines itself: get them from [Text::preedit_underlines] and draw
+    /// them along with the text. The segments stay when the text is changed, so they should be
+    /// set again whenever the input method updates the preedit text.
+    pub fn set_preedit(&mut self, segments: Vec<PreeditSegment>) {
+        self.preedit = segments;
+    }
+
+    /// Draws only the first `chars` characters of the text, or all of it if None, e.g. to type
+    /// dialogue out a character at a time. The default is None.
+    ///
+    /// This just draws fewer of the text's instances, so nothing is laid out or uploaded again and
+    /// it's cheap to change every frame. The text's layout (its bounds, lines etc.) is still of the
+    /// whole text, so it doesn't move or rewrap as it's revealed. The limit stays when the text is
+    /// changed. Characters are counted in chars, including spaces and line breaks.
+    pub fn set_visible_chars(&mut self, chars: Option<usize>) {
+        self.visible_chars = chars;
+        self.visible_glyphs = visible_glyphs(&self.glyphs, &self.data.text, chars);
+    }
+
+    /// How many characters are drawn (see [Text::set_visible_chars]).
+    pub fn visible_chars(&self) -> Option<usize> {
+        self.visible_chars
+    }
+
+    /// The parts of the text an input method is composing (see [Text::set_preedit]).
+    pub fn preedit(&self) -> &[PreeditSegment] {
+        &self.preedit
+    }
+
+    /// Returns the rectangles to fill to underline the preedit segments, on the screen and in the
+    /// same units as the text's position. Each one is a bit shorter than its segment, so the
+    /// underlines of neighbouring segments don't run together.
+    pub fn preedit_underlines(&self) -> Vec<Underline> {
+        preedit::underlines(&self.lines, &self.carets, &self.preedit)
+            .into_iter()
+            .map(|underline| Underline {
+                rect: self.on_screen(underline.rect, 0.),
+                ..underline
+            })
+            .collect()
+    }
+
+    /// Returns the box around a preedit segment, on the screen and in the same units as the text's
+    /// position, e.g. for putting an input method's candidate window next to it (with winit's
+    /// `Window::set_ime_cursor_area`).
+    ///
+    /// Returns None if there's no segment with that index, or it doesn't cover any of the text.
+    pub fn preedit_rect(&self, segment: usize) -> Option<Rect> {
+        let range = self.preedit.get(segment)?.range.clone();
+        layout::union(self.range_rects(range))
+    }
+
+    /// Keeps the boxes of a new layout of the text.
+    fn set_layout_boxes(&mut self, layout: &TextLayout) {
+        self.layout_bounds = layout.physical_bounds();
+        self.glyphs = layout.glyphs.clone();
+        self.lines = layout.lines.clone();
+        self.carets = layout.carets.clone();
+        self.measured = Some(layout.measured.clone());
+        self.visible_glyphs = visible_glyphs(&self.glyphs, &layout.data.text, self.visible_chars);
+        self.debug_overlay = OnceLock::new();
+    }
+
+    /// Moves a box in physical pixels relative to the text's position to where it is on the
+    /// screen in logical pixels, growing it by `outline` physical pixels.
+    fn on_screen(&self, rect: Rect, outline: f32) -> Rect {
+        let rect = rect.translate(self.data.draw_position());
+
+        Rect {
+            min: rect.min.map(|p| (p - outline) / self.data.scale_factor),
+            max: rect.max.map(|p| (p + outline) / self.data.scale_factor),
+        }
+    }
+
+    /// The width of the text's outline in physical pixels, or 0 if it doesn't have one.
+    fn physical_outline_width(&self) -> f32 {
+        self.data.outline_width() * self.data.scale_factor
+    }
+
     /// Removes the outline from the text, if there was one.
     ///
     /// This does nothing if the font is not rendered with sdf.
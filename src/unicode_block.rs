@@ -0,0 +1,69 @@
+//! Named Unicode blocks, for caching large sets of characters ahead of time.
+
+use std::ops::RangeInclusive;
+
+/// A named block of Unicode characters.
+///
+/// These can be passed to [crate::TextRenderer::generate_char_range] to cache every character in
+/// the block at once, e.g. when warming up a CJK font in a loading screen.
+///
+/// See <https://www.unicode.org/charts/> for the contents of each block.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum UnicodeBlock {
+    /// Basic Latin (U+0000 - U+007F), i.e. ASCII.
+    BasicLatin,
+    /// Latin-1 Supplement (U+0080 - U+00FF).
+    Latin1Supplement,
+    /// Latin Extended-A (U+0100 - U+017F).
+    LatinExtendedA,
+    /// Latin Extended-B (U+0180 - U+024F).
+    LatinExtendedB,
+    /// Greek and Coptic (U+0370 - U+03FF).
+    Greek,
+    /// Cyrillic (U+0400 - U+04FF).
+    Cyrillic,
+    /// General Punctuation (U+2000 - U+206F).
+    GeneralPunctuation,
+    /// CJK Symbols and Punctuation (U+3000 - U+303F).
+    CjkSymbolsAndPunctuation,
+    /// Hiragana (U+3040 - U+309F).
+    Hiragana,
+    /// Katakana (U+30A0 - U+30FF).
+    Katakana,
+    /// CJK Unified Ideographs (U+4E00 - U+9FFF).
+    ///
+    /// This block is very large (over 20,000 characters), so caching all of it will take a long
+    /// time and use a lot of GPU memory.
+    CjkUnifiedIdeographs,
+    /// Hangul Syllables (U+AC00 - U+D7AF).
+    HangulSyllables,
+    /// Halfwidth and Fullwidth Forms (U+FF00 - U+FFEF).
+    HalfwidthAndFullwidthForms,
+}
+
+impl UnicodeBlock {
+    /// The range of characters in this block.
+    pub fn range(&self) -> RangeInclusive<char> {
+        match self {
+            Self::BasicLatin => '\u{0000}'..='\u{007F}',
+            Self::Latin1Supplement => '\u{0080}'..='\u{00FF}',
+            Self::LatinExtendedA => '\u{0100}'..='\u{017F}',
+            Self::LatinExtendedB => '\u{0180}'..='\u{024F}',
+            Self::Greek => '\u{0370}'..='\u{03FF}',
+            Self::Cyrillic => '\u{0400}'..='\u{04FF}',
+            Self::GeneralPunctuation => '\u{2000}'..='\u{206F}',
+            Self::CjkSymbolsAndPunctuation => '\u{3000}'..='\u{303F}',
+            Self::Hiragana => '\u{3040}'..='\u{309F}',
+            Self::Katakana => '\u{30A0}'..='\u{30FF}',
+            Self::CjkUnifiedIdeographs => '\u{4E00}'..='\u{9FFF}',
+            Self::HangulSyllables => '\u{AC00}'..='\u{D7AF}',
+            Self::HalfwidthAndFullwidthForms => '\u{FF00}'..='\u{FFEF}',
+        }
+    }
+}
+
+impl From<UnicodeBlock> for RangeInclusive<char> {
+    fn from(block: UnicodeBlock) -> Self {
+        block.range()
+    }
+}
@@ -0,0 +1,158 @@
+//! Where the data of new character textures and text buffers goes: straight to the queue, or into
+//! staging buffers that are copied from by a command encoder later (see
+//! [TextRenderer::generate_char_textures_with_encoder](crate::TextRenderer::generate_char_textures_with_encoder)).
+
+use std::sync::{Arc, Mutex};
+
+use wgpu::util::DeviceExt;
+
+/// A copy out of a staging buffer, waiting to be recorded into an encoder.
+#[derive(Debug)]
+pub(crate) enum PendingCopy {
+    Texture {
+        staging: wgpu::Buffer,
+        texture: Arc<wgpu::Texture>,
+        bytes_per_row: u32,
+        size: wgpu::Extent3d,
+    },
+    Buffer {
+        staging: wgpu::Buffer,
+        buffer: Arc<wgpu::Buffer>,
+        offset: wgpu::BufferAddress,
+    },
+}
+
+/// Where uploads go.
+///
+/// Characters are generated in parallel, so deferred uploads are collected behind a mutex and
+/// recorded into the encoder once they're all done.
+#[derive(Debug)]
+pub(crate) enum Uploads<'a> {
+    Queue(&'a wgpu::Queue),
+    Deferred(Mutex<Vec<PendingCopy>>),
+}
+
+impl Uploads<'_> {
+    pub(crate) fn deferred() -> Self {
+        Self::Deferred(Default::default())
+    }
+
+    /// Uploads the whole of a 2d texture. `data` has to be tightly packed texels, with
+    /// `bytes_per_row` bytes per row.
+    pub(crate) fn write_texture(
+        &self,
+        texture: Arc<wgpu::Texture>,
+        data: &[u8],
+        bytes_per_row: u32,
+        label: &str,
+        device: &wgpu::Device,
+    ) {
+        let size = texture.size();
+
+        let queue = match self {
+            Self::Queue(queue) => queue,
+            Self::Deferred(copies) => {
+                // Copies from buffers need rows aligned to 256 bytes, unlike queue writes
+                let padded_row = bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+                let mut padded = vec![0; (padded_row * size.height) as usize];
+                for (row, texels) in data.chunks_exact(bytes_per_row as usize).enumerate() {
+                    let start = row * padded_row as usize;
+                    padded[start..][..texels.len()].copy_from_slice(texels);
+                }
+
+                let staging = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: &padded,
+                    usage: wgpu::BufferUsages::COPY_SRC,
+                });
+
+                copies.lock().unwrap().push(PendingCopy::Texture {
+                    staging,
+                    texture,
+                    bytes_per_row: padded_row,
+                    size,
+                });
+                return;
+            }
+        };
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(size.height),
+            },
+            size,
+        );
+    }
+
+    /// Writes data to a buffer. The buffer has to have been created with
+    /// [wgpu::BufferUsages::COPY_DST].
+    pub(crate) fn write_buffer(
+        &self,
+        buffer: &Arc<wgpu::Buffer>,
+        offset: wgpu::BufferAddress,
+        data: &[u8],
+        label: &str,
+        device: &wgpu::Device,
+    ) {
+        match self {
+            Self::Queue(queue) => queue.write_buffer(buffer, offset, data),
+            Self::Deferred(copies) => {
+                let staging = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: data,
+                    usage: wgpu::BufferUsages::COPY_SRC,
+                });
+
+                copies.lock().unwrap().push(PendingCopy::Buffer {
+                    staging,
+                    buffer: buffer.clone(),
+                    offset,
+                });
+            }
+        }
+    }
+
+    /// Records the deferred uploads into an encoder. Uploads to the queue have already happened,
+    /// so this does nothing for them.
+    pub(crate) fn record(self, encoder: &mut wgpu::CommandEncoder) {
+        let Self::Deferred(copies) = self else {
+            return;
+        };
+
+        for copy in copies.into_inner().unwrap() {
+            match copy {
+                PendingCopy::Texture {
+                    staging,
+                    texture,
+                    bytes_per_row,
+                    size,
+                } => encoder.copy_buffer_to_texture(
+                    wgpu::ImageCopyBuffer {
+                        buffer: &staging,
+                        layout: wgpu::ImageDataLayout {
+                            offset: 0,
+                            bytes_per_row: Some(bytes_per_row),
+                            rows_per_image: Some(size.height),
+                        },
+                    },
+                    texture.as_image_copy(),
+                    size,
+                ),
+                PendingCopy::Buffer {
+                    staging,
+                    buffer,
+                    offset,
+                } => encoder.copy_buffer_to_buffer(&staging, 0, &buffer, offset, staging.size()),
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Drawing only the visible lines of a long document, for [WindowedText].
+
+use std::ops::Range;
+
+use crate::{text::line_ranges, Error, Text, TextBuilder, TextRenderer};
+
+/// A long text (e.g. a log with thousands of lines) that only lays out and uploads the lines
+/// inside a viewport, made with [TextBuilder::build_windowed].
+///
+/// The whole document is kept on the CPU, but the [Text] that's drawn only has the lines that can
+/// be seen at the current scroll offset. When the text is scrolled, the text is moved, and its
+/// lines are only replaced when different lines come into view. Its buffers are reused as long as
+/// the new lines fit in them, which they usually do, since the window stays about the same size.
+///
+/// The viewport starts at the text's position and goes down by its height, so the text should be
+/// aligned with [VerticalAlignment::Top](crate::VerticalAlignment::Top). Lines that are only
+/// partly inside it are drawn in full, so you may want to clip them (e.g. with a scissor rect).
+/// Every line has to be the same height, so lines aren't wrapped (see [TextBuilder::max_width]),
+/// paragraphs have no extra space (see [TextBuilder::paragraph_spacing]) and the vertical
+/// alignment is only of the first line (see [TextBuilder::block_align]). Lines can't have their
+/// own alignment or indent either (see [TextBuilder::line_align] and [TextBuilder::indent]),
+/// since only some of them are laid out at a time.
+///
+/// ```rust,no_run
+/// # use kaku::*;
+/// # fn example(
+/// #     contents: &str,
+/// #     font: FontId,
+/// #     device: &wgpu::Device,
+/// #     queue: &wgpu::Queue,
+/// #     mut text_renderer: TextRenderer,
+/// # ) -> Result<(), Error> {
+/// let mut log = TextBuilder::new(contents, font, [10., 10.])
+///     .vertical_align(VerticalAlignment::Top)
+///     .build_windowed(600., &device, &queue, &mut text_renderer)?;
+///
+/// log.set_scroll(log.content_height() - 600., &device, &queue, &mut text_renderer)?;
+/// # let mut encoder = device.create_command_encoder(&Default::default());
+/// # let mut render_pass = encoder.begin_render_pass(&Default::default());
+/// text_renderer.draw_text(&mut render_pass, log.text())?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WindowedText {
+    /// The lines in the window.
+    text: Text,
+    /// The whole document.
+    source: String,
+    /// The byte range of each line of the document.
+    lines: Vec<Range<usize>>,
+    position: [f32; 2],
+    /// The distance between two lines' baselines, in logical pixels.
+    line_spacing: f32,
+    scroll: f32,
+    viewport_height: f32,
+    /// The lines in the window, as indices into `lines`.
+    visible: Range<usize>,
+}
+
+impl WindowedText {
+    pub(crate) fn new(
+        builder: &TextBuilder,
+        viewport_height: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_renderer: &mut TextRenderer,
+    ) -> Result<Self, Error> {
+        let font_data = text_renderer.fonts.get(builder.font)?;
+        let data = builder.text_data(font_data, text_renderer.scale_factor)?;
+        let line_spacing = font_data.line_spacing(data.scale);
+        let lines: Vec<_> = line_ranges(&data.text).collect();
+
+        let visible = visible_lines(lines.len(), line_spacing, 0., viewport_height);
+        let mut builder = builder.clone();
+        builder.line_aligns.clear();
+        builder.paragraphs = Default::default();
+
+        let text = builder
+            .max_width(None)
+            .block_align(false)
+            .text(window_text(&data.text, &lines, &visible).to_string())
+            .position(window_position(data.position, line_spacing, 0., &visible))
+            .build(device, queue, text_renderer)?;
+
+        Ok(Self {
+            text,
+            source: data.text,
+            lines,
+            position: data.position,
+            line_spacing,
+            scroll: 0.,
+            viewport_height,
+            visible,
+        })
+    }
+
+    /// The text that's drawn, which only has the lines in the window. Draw it like any other
+    /// [Text].
+    ///
+    /// Byte indices into it (e.g. for [Text::caret_rect]) start at the beginning of the window,
+    /// see [WindowedText::visible_bytes].
+    pub fn text(&self) -> &Text {
+        &self.text
+    }
+
+    /// The whole document.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// How far the document is scrolled down, in logical pixels.
+    pub fn scroll(&self) -> f32 {
+        self.scroll
+    }
+
+    /// Scrolls the document so that the top of the viewport is `scroll` logical pixels below the
+    /// top of the document.
+    ///
+    /// Returns an error if the text's font isn't loaded in the text renderer, in which case the
+    /// window is left unchanged.
+    pub fn set_scroll(
+        &mut self,
+        scroll: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_renderer: &mut TextRenderer,
+    ) -> Result<(), Error> {
+        self.update(scroll, self.viewport_height, device, queue, text_renderer)
+    }
+
+    /// The height of the viewport, in logical pixels.
+    pub fn viewport_height(&self) -> f32 {
+        self.viewport_height
+    }
+
+    /// Changes the height of the viewport, e.g. when the window it's in is resized.
+    ///
+    /// Returns an error if the text's font isn't loaded in the text renderer, in which case the
+    /// window is left unchanged.
+    pub fn set_viewport_height(
+        &mut self,
+        viewport_height: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_renderer: &mut TextRenderer,
+    ) -> Result<(), Error> {
+        self.update(self.scroll, viewport_height, device, queue, text_renderer)
+    }
+
+    /// The number of lines in the document.
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The height of the whole document, in logical pixels, e.g. for sizing a scrollbar.
+    pub fn content_height(&self) -> f32 {
+        self.lines.len() as f32 * self.line_spacing
+    }
+
+    /// The distance between two lines, in logical pixels.
+    pub fn line_spacing(&self) -> f32 {
+        self.line_spacing
+    }
+
+    /// The lines of the document that are in the window, and so are laid out and drawn.
+    pub fn visible_lines(&self) -> Range<usize> {
+        self.visible.clone()
+    }
+
+    /// The byte range of the document that's in the window.
+    pub fn visible_bytes(&self) -> Range<usize> {
+        window_bytes(&self.lines, &self.visible)
+    }
+
+    fn update(
+        &mut self,
+        scroll: f32,
+        viewport_height: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_renderer: &mut TextRenderer,
+    ) -> Result<(), Error> {
+        let visible = visible_lines(self.lines.len(), self.line_spacing, scroll, viewport_height);
+
+        if visible != self.visible {
+            let window = window_text(&self.source, &self.lines, &visible);
+            self.text
+                .set_text(window.to_string(), device, queue, text_renderer)?;
+            self.visible = visible;
+        }
+
+        self.scroll = scroll;
+        self.viewport_height = viewport_height;
+        let position = window_position(self.position, self.line_spacing, scroll, &self.visible);
+        self.text.set_position(position, queue);
+
+        Ok(())
+    }
+}
+
+/// The lines that are at least partly inside the viewport.
+fn visible_lines(
+    line_count: usize,
+    line_spacing: f32,
+    scroll: f32,
+    viewport_height: f32,
+) -> Range<usize> {
+    if line_spacing <= 0. {
+        return 0..line_count;
+    }
+
+    let first = (scroll / line_spacing).floor().max(0.) as usize;
+    let end = ((scroll + viewport_height) / line_spacing).ceil().max(0.) as usize;
+    first.min(line_count)..end.min(line_count)
+}
+
+/// The byte range of some lines, including the line breaks between them.
+fn window_bytes(lines: &[Range<usize>], visible: &Range<usize>) -> Range<usize> {
+    if visible.is_empty() {
+        return 0..0;
+    }
+
+    lines[visible.start].start..lines[visible.end - 1].end
+}
+
+fn window_text<'a>(source: &'a str, lines: &[Range<usize>], visible: &Range<usize>) -> &'a str {
+    &source[window_bytes(lines, visible)]
+}
+
+/// Where the window's text goes, so that its first line is where it would be in the whole
+/// document.
+fn window_position(
+    position: [f32; 2],
+    line_spacing: f32,
+    scroll: f32,
+    visible: &Range<usize>,
+) -> [f32; 2] {
+    [
+        position[0],
+        position[1] + visible.start as f32 * line_spacing - scroll,
+    ]
+}
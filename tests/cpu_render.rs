@@ -0,0 +1,5699 @@
+//! Golden image tests, using the cpu renderer to check layout and effects.
+//!
+//! Run with `cargo test --features cpu-render`. If a change to the output is intentional, rerun
+//! with the `KAKU_UPDATE_GOLDEN` environment variable set to regenerate the golden images. New
+//! tests need it set once to write theirs.
+
+#![cfg(feature = "cpu-render")]
+
+use ab_glyph::FontRef;
+use image::{DynamicImage, Rgba, RgbaImage};
+// Through kaku, so the tests use whichever version of wgpu it was built with
+use kaku::wgpu;
+use kaku::{
+    assert_matches_golden, compare_images, Anchor, BitmapFont, CacheView, CellBuffer, Error,
+    FontId, FontOptions, FontSize, FrameStats, HorizontalAlignment, LaidOutGlyph, LineBox,
+    MissingGlyphs, Panel, PreeditSegment, ReadGlyphError, Rect, SdfFormat, SdfSettings, SdfSource,
+    Tabs, Text, TextBuilder, TextRenderer, TextRendererBuilder, UnderlineStyle, VerticalAlignment,
+};
+
+const FONT: &[u8] = include_bytes!("../examples/fonts/FiraSans-Regular.ttf");
+const SIZE: (u32, u32) = (320, 120);
+const TOLERANCE: u8 = 2;
+
+/// Creating text objects still needs a device, even though nothing is drawn on the GPU. Any
+/// adapter will do (including software ones), but if there isn't one the tests are skipped.
+fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    device_with_limits(Default::default())
+}
+
+fn device_with_limits(limits: wgpu::Limits) -> Option<(wgpu::Device, wgpu::Queue)> {
+    device_with_features(wgpu::Features::empty(), limits)
+}
+
+fn device_with_features(
+    features: wgpu::Features,
+    limits: wgpu::Limits,
+) -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::default();
+    let adapter = pollster::block_on(instance.request_adapter(&Default::default()));
+
+    let Some(adapter) = adapter else {
+        eprintln!("No wgpu adapter available, skipping test");
+        return None;
+    };
+
+    let descriptor = wgpu::DeviceDescriptor {
+        required_features: features,
+        required_limits: limits,
+        ..Default::default()
+    };
+
+    pollster::block_on(adapter.request_device(&descriptor, None)).ok()
+}
+
+fn renderer(device: &wgpu::Device) -> TextRenderer {
+    renderer_builder().build(device)
+}
+
+fn renderer_builder() -> TextRendererBuilder {
+    TextRendererBuilder::new(wgpu::TextureFormat::Rgba8Unorm, SIZE)
+}
+
+fn background() -> RgbaImage {
+    RgbaImage::from_pixel(SIZE.0, SIZE.1, Rgba([255, 255, 255, 255]))
+}
+
+fn golden_path(name: &str) -> String {
+    format!("{}/tests/golden/{name}.png", env!("CARGO_MANIFEST_DIR"))
+}
+
+/// What most tests start with: a device, and a renderer that draws onto [background] with the
+/// cpu renderer.
+struct Harness {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    renderer: TextRenderer,
+}
+
+impl Harness {
+    /// Returns None if there's no adapter, in which case the test is skipped.
+    fn new() -> Option<Self> {
+        let (device, queue) = device()?;
+        let renderer = renderer(&device);
+        Some(Self {
+            device,
+            queue,
+            renderer,
+        })
+    }
+
+    fn font(&mut self, size: f32) -> FontId {
+        load_font(&mut self.renderer, size)
+    }
+
+    fn sdf_font(&mut self, size: f32) -> FontId {
+        load_sdf_font(&mut self.renderer, size)
+    }
+
+    fn font_with_options(&mut self, options: FontOptions) -> FontId {
+        let font = FontRef::try_from_slice(FONT).unwrap();
+        (self.renderer)
+            .load_font_with_options(font, options, &self.device, &self.queue)
+            .unwrap()
+    }
+
+    fn bitmap_font(&mut self, font: &BitmapFont) -> FontId {
+        (self.renderer).load_bitmap_font(font, &self.device, &self.queue)
+    }
+
+    fn build(&mut self, builder: &TextBuilder) -> Text {
+        (builder.build(&self.device, &self.queue, &mut self.renderer)).unwrap()
+    }
+
+    fn draw<'a>(&self, texts: impl IntoIterator<Item = &'a Text>) -> RgbaImage {
+        draw_cpu(&self.renderer, texts)
+    }
+
+    fn draw_onto<'a>(
+        &self,
+        image: RgbaImage,
+        texts: impl IntoIterator<Item = &'a Text>,
+    ) -> RgbaImage {
+        draw_cpu_onto(&self.renderer, image, texts)
+    }
+}
+
+/// Loads the test font as a raster font.
+fn load_font(renderer: &mut TextRenderer, size: f32) -> FontId {
+    let font = FontRef::try_from_slice(FONT).unwrap();
+    renderer.load_font(font, FontSize::Px(size)).unwrap()
+}
+
+/// Loads the test font with the default sdf settings.
+fn load_sdf_font(renderer: &mut TextRenderer, size: f32) -> FontId {
+    let font = FontRef::try_from_slice(FONT).unwrap();
+    (renderer.load_font_with_sdf(font, FontSize::Px(size), SdfSettings::default())).unwrap()
+}
+
+/// Draws texts onto a copy of [background] with the cpu renderer.
+fn draw_cpu<'a>(renderer: &TextRenderer, texts: impl IntoIterator<Item = &'a Text>) -> RgbaImage {
+    draw_cpu_onto(renderer, background(), texts)
+}
+
+fn draw_cpu_onto<'a>(
+    renderer: &TextRenderer,
+    mut image: RgbaImage,
+    texts: impl IntoIterator<Item = &'a Text>,
+) -> RgbaImage {
+    for text in texts {
+        renderer.draw_text_to_image(text, &mut image).unwrap();
+    }
+    image
+}
+
+/// The box around the pixels of an image that aren't the colour of its top left pixel (the
+/// background), in pixels.
+fn drawn_bounds(image: &RgbaImage) -> Option<Rect> {
+    let background = *image.get_pixel(0, 0);
+    let drawn = (image.enumerate_pixels()).filter(|&(_, _, pixel)| *pixel != background);
+    drawn
+        .map(|(x, y, _)| Rect {
+            min: [x as f32, y as f32],
+            max: [x as f32 + 1., y as f32 + 1.],
+        })
+        .reduce(|a, b| a.union(b))
+}
+
+/// Asserts that two boxes are the same, give or take `slack` on each side (e.g. for antialiased
+/// edges).
+#[track_caller]
+fn assert_close(a: Rect, b: Rect, slack: f32) {
+    let close = (0..2)
+        .all(|i| (a.min[i] - b.min[i]).abs() <= slack && (a.max[i] - b.max[i]).abs() <= slack);
+    assert!(close, "{a:?} and {b:?} differ by more than {slack}");
+}
+
+#[test]
+fn basic_text() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let font = h.font(32.);
+    let text = h.build(&TextBuilder::new("Hello, world!", font, [10., 50.]));
+    let image = h.draw([&text]);
+    assert_matches_golden(&image, golden_path("basic_text"), TOLERANCE);
+
+    // The glyphs are drawn where the layout says their ink is, on a baseline at the position
+    assert_close(
+        drawn_bounds(&image).unwrap(),
+        text.ink_bounds().unwrap(),
+        1.,
+    );
+    assert_eq!(text.lines().next().unwrap().baseline, 50.);
+}
+
+#[test]
+fn sdf_outline() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let font = h.sdf_font(32.);
+    let text = h.build(
+        TextBuilder::new("Outlined", font, [10., 50.])
+            .color([1., 1., 1., 1.])
+            .outlined([0.8, 0.2, 0.2, 1.], 4.)
+            .scale(1.5),
+    );
+    let image = h.draw([&text]);
+    assert_matches_golden(&image, golden_path("sdf_outline"), TOLERANCE);
+
+    // The white glyphs are drawn inside a red outline, which is what the ink bounds go around.
+    // They're drawn on black to tell the glyphs apart from the background.
+    let black = RgbaImage::from_pixel(SIZE.0, SIZE.1, Rgba([0, 0, 0, 255]));
+    let on_black = h.draw_onto(black, [&text]);
+    let bounds_of = |f: fn(&Rgba<u8>) -> bool| {
+        let mut only = RgbaImage::new(SIZE.0, SIZE.1);
+        for (pixel, only) in on_black.pixels().zip(only.pixels_mut()) {
+            only.0[3] = f(pixel) as u8;
+        }
+        drawn_bounds(&only).unwrap()
+    };
+    let outline = bounds_of(|&Rgba([r, g, b, _])| r > 150 && g < 100 && b < 100);
+    let fill = bounds_of(|&Rgba([r, g, b, _])| r > 240 && g > 240 && b > 240);
+    for i in 0..2 {
+        assert!(outline.min[i] < fill.min[i] && fill.max[i] < outline.max[i]);
+    }
+    assert_close(outline, text.ink_bounds().unwrap(), 2.);
+}
+
+#[test]
+fn outline_and_raster_sources_agree() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    // Both sources describe the same glyph shapes, so they should only differ by the raster's
+    // approximation error along the edges
+    let draw = |source| {
+        let mut renderer = renderer(&device);
+        let font = renderer
+            .load_font_with_sdf(
+                FontRef::try_from_slice(FONT).unwrap(),
+                FontSize::Px(32.),
+                SdfSettings {
+                    source,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let text = TextBuilder::new("Sources", font, [10., 60.])
+            .scale(2.)
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+
+        draw_cpu(&renderer, [&text])
+    };
+
+    let raster = draw(SdfSource::Raster);
+    let outline = draw(SdfSource::Outline);
+    let diff = compare_images(&raster, &outline, TOLERANCE).unwrap();
+    let blank = compare_images(&background(), &outline, TOLERANCE).unwrap();
+
+    // Edge pixels can be off by a fair amount where the raster's estimate of the edge is off by a
+    // fraction of a pixel, but the glyphs should be in the same place with the same shape
+    assert!(diff.max_difference < 128, "{}", diff.max_difference);
+    assert!(
+        diff.rmse < blank.rmse / 4.,
+        "{} vs {}",
+        diff.rmse,
+        blank.rmse
+    );
+}
+
+#[test]
+fn distance_scale_scales_outlines() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_sdf_font(&mut renderer, 32.);
+
+    let mut draw = |width, distance_scale| {
+        let text = TextBuilder::new("Scaled", font, [10., 60.])
+            .color([1., 1., 1., 1.])
+            .outlined([0.8, 0.2, 0.2, 1.], width)
+            .distance_scale(distance_scale)
+            .scale(1.5)
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+
+        draw_cpu(&renderer, [&text])
+    };
+
+    let outline_pixels = |image: &RgbaImage| {
+        image
+            .pixels()
+            .filter(|Rgba([r, g, _, _])| *r > 128 && *g < 128)
+            .count()
+    };
+
+    let unscaled = draw(2., 1.);
+    let scaled = draw(2., 2.);
+    let wide = draw(4., 1.);
+
+    // Doubling the distance scale doubles the outline width, making the edge of the text wider
+    assert!(
+        outline_pixels(&scaled) > outline_pixels(&unscaled) * 3 / 2,
+        "{} vs {}",
+        outline_pixels(&scaled),
+        outline_pixels(&unscaled)
+    );
+    assert!(compare_images(&wide, &scaled, TOLERANCE)
+        .unwrap()
+        .is_match());
+}
+
+#[test]
+fn multiline_alignment() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let font = h.sdf_font(24.);
+    let text = h.build(
+        TextBuilder::new("Centred text\nacross lines", font, [160., 60.])
+            .horizontal_align(HorizontalAlignment::Center)
+            .vertical_align(VerticalAlignment::Middle),
+    );
+    let image = h.draw([&text]);
+    assert_matches_golden(&image, golden_path("multiline_alignment"), TOLERANCE);
+
+    // The first line is centred on the position, and every line is centred horizontally
+    let centre = |rect: Rect| [0, 1].map(|i| (rect.min[i] + rect.max[i]) / 2.);
+    let [x, y] = centre(text.lines().next().unwrap().rect);
+    assert!((x - 160.).abs() < 0.01 && (y - 60.).abs() < 0.01, "{x} {y}");
+
+    for line in text.lines() {
+        let (top, height) = (line.rect.min[1] as u32, line.rect.height() as u32);
+        let row = image::imageops::crop_imm(&image, 0, top, SIZE.0, height).to_image();
+        let drawn = drawn_bounds(&row).unwrap();
+        assert!((centre(drawn)[0] - 160.).abs() < 2., "{drawn:?}");
+    }
+}
+
+#[cfg(feature = "swash")]
+#[test]
+fn hinted_text() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let hinted = h.font_with_options(FontOptions::new(FontSize::Px(13.)).with_hinting(true));
+    let unhinted = h.font(13.);
+    let builder = TextBuilder::new("Small hinted UI text, 13px", hinted, [10., 30.]);
+    let text = h.build(&builder);
+    let image = h.draw([&text]);
+    assert_matches_golden(&image, golden_path("hinted_text"), TOLERANCE);
+
+    // Hinting moves the outlines onto the pixel grid, but the text stays in the same place
+    let plain = h.build(builder.clone().font(unhinted));
+    let plain = h.draw([&plain]);
+    assert_ne!(image, plain);
+    assert_close(
+        drawn_bounds(&image).unwrap(),
+        drawn_bounds(&plain).unwrap(),
+        2.,
+    );
+}
+
+#[test]
+fn pixel_snapped_text() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let font = h.font(13.);
+    let builder = TextBuilder::new("Snapped to pixels, 13px", font, [10.3, 30.6])
+        .pixel_snap(true)
+        .clone();
+    let text = h.build(&builder);
+    let image = h.draw([&text]);
+    assert_matches_golden(&image, golden_path("pixel_snapped_text"), TOLERANCE);
+
+    // Snapping draws the text exactly like it would be at the nearest whole pixel
+    let rounded = h.build(builder.clone().position([10., 31.]));
+    assert_eq!(image, h.draw([&rounded]));
+    let unsnapped = h.build(builder.clone().pixel_snap(false));
+    assert_ne!(image, h.draw([&unsnapped]));
+}
+
+#[test]
+fn stem_darkening() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let font = h.font(13.);
+    let builder = TextBuilder::new("Light on dark, darkened", font, [10., 30.])
+        .color([1., 1., 1., 1.])
+        .stem_darkening(0.4)
+        .clone();
+    let text = h.build(&builder);
+    let dark = RgbaImage::from_pixel(SIZE.0, SIZE.1, Rgba([20, 20, 30, 255]));
+    let image = h.draw_onto(dark.clone(), [&text]);
+    assert_matches_golden(&image, golden_path("stem_darkening"), TOLERANCE);
+
+    // Darkening makes the stems heavier, so there's more light text on the dark background
+    let plain = h.build(builder.clone().stem_darkening(0.));
+    let plain = h.draw_onto(dark, [&plain]);
+    let ink = |image: &RgbaImage| image.pixels().map(|p| p[0] as u64 - 20).sum::<u64>();
+    assert!(
+        ink(&image) > ink(&plain) * 11 / 10,
+        "{} {}",
+        ink(&image),
+        ink(&plain)
+    );
+}
+
+#[test]
+fn supersampled_text() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let font = h.font_with_options(FontOptions::new(FontSize::Px(16.)).with_supersampling(4));
+    let plain_font = h.font(16.);
+    let builder = TextBuilder::new("Supersampled 4x", font, [10., 30.]);
+    let text = h.build(&builder);
+    let image = h.draw([&text]);
+    assert_matches_golden(&image, golden_path("supersampled_text"), TOLERANCE);
+
+    // The glyphs are rasterised at 4 times the size and scaled down, so their edges are a little
+    // different, but they're the same size and in the same place
+    let plain = h.build(builder.clone().font(plain_font));
+    let plain = h.draw([&plain]);
+    let diff = compare_images(&plain, &image, TOLERANCE).unwrap();
+    assert!(!diff.is_match());
+    assert!(diff.rmse < 16., "{}", diff.rmse);
+    assert_close(
+        drawn_bounds(&image).unwrap(),
+        drawn_bounds(&plain).unwrap(),
+        1.,
+    );
+}
+
+#[test]
+fn bitmap_font() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let bitmap_font = BitmapFont::from_bdf(include_str!("fonts/test.bdf")).unwrap();
+    let font = h.bitmap_font(&bitmap_font);
+    let text = h.build(TextBuilder::new("AB gAB\nBAg", font, [10., 30.]).scale(3.));
+    let image = h.draw([&text]);
+    assert_matches_golden(&image, golden_path("bitmap_font"), TOLERANCE);
+
+    // Bitmap glyphs are scaled up without smoothing, so every pixel is either ink or background
+    assert!(image
+        .pixels()
+        .all(|p| *p == Rgba([255; 4]) || *p == Rgba([0, 0, 0, 255])));
+}
+
+#[test]
+fn pcf_font() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    // test.pcf has the same glyphs as test.bdf, so it draws the same image
+    let pcf = BitmapFont::from_pcf(include_bytes!("fonts/test.pcf")).unwrap();
+    let bdf = BitmapFont::from_bdf(include_str!("fonts/test.bdf")).unwrap();
+    let [pcf, bdf] = [&pcf, &bdf].map(|font| {
+        let font = h.bitmap_font(font);
+        let text = h.build(TextBuilder::new("AB gAB\nBAg", font, [10., 30.]).scale(3.));
+        h.draw([&text])
+    });
+    assert_eq!(pcf, bdf);
+    assert_matches_golden(&pcf, golden_path("bitmap_font"), TOLERANCE);
+}
+
+#[test]
+fn pcf_fonts_reject_malformed_input() {
+    use kaku::{BitmapFont, BitmapFontError};
+
+    let pcf = include_bytes!("fonts/test.pcf");
+    let pcf_error = |data: &[u8]| match BitmapFont::from_pcf(data) {
+        Err(BitmapFontError::Pcf(message)) => message,
+        result => panic!("expected a PCF error, got {result:?}"),
+    };
+
+    assert_eq!(pcf_error(b"STARTFONT 2.1\n"), "not a PCF file");
+
+    // The offset of a table, from the table of contents
+    let table = |ty: u32| {
+        let count = u32::from_le_bytes(pcf[4..8].try_into().unwrap()) as usize;
+        (0..count)
+            .map(|i| &pcf[8 + i * 16..][..16])
+            .find(|entry| u32::from_le_bytes(entry[..4].try_into().unwrap()) == ty)
+            .map(|entry| u32::from_le_bytes(entry[12..].try_into().unwrap()) as usize)
+            .unwrap()
+    };
+
+    // The tables are big endian, and their counts and offsets come straight from the file
+    let patched = |offset: usize, value: u32| {
+        let mut data = pcf.to_vec();
+        data[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+        data
+    };
+
+    let properties = table(1 << 0);
+    let bitmaps = table(1 << 3);
+    let bdf_accelerators = table(1 << 8);
+
+    // Cutting the file off anywhere before the font's ascent and descent at the start of the last
+    // table loses something that's needed
+    for len in 0..bdf_accelerators + 20 {
+        pcf_error(&pcf[..len]);
+    }
+
+    // A property count far bigger than the file can hold
+    assert_eq!(
+        pcf_error(&patched(properties + 4, u32::MAX)),
+        "unexpected end of file"
+    );
+    // The offset of the second glyph's bitmap (the first is an empty space)
+    assert_eq!(
+        pcf_error(&patched(bitmaps + 12, 0x00ff_ffff)),
+        "glyph bitmap out of bounds"
+    );
+}
+
+#[test]
+fn bmfont_kerning() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let page = image::load_from_memory(include_bytes!("fonts/test_bmfont_0.png")).unwrap();
+    let bitmap_font =
+        BitmapFont::from_bmfont(include_str!("fonts/test_bmfont.fnt"), &[page]).unwrap();
+    let font = h.bitmap_font(&bitmap_font);
+
+    // The A and V pairs are kerned closer together
+    let text = h.build(TextBuilder::new("AVAVA AA VV", font, [10., 30.]).scale(3.));
+    let image = h.draw([&text]);
+    assert_matches_golden(&image, golden_path("bmfont_kerning"), TOLERANCE);
+
+    let x = text.glyphs().map(|g| g.rect.min[0]).collect::<Vec<_>>();
+    let (av, va, aa, vv) = (x[1] - x[0], x[2] - x[1], x[6] - x[5], x[8] - x[7]);
+    assert!(av < aa && va < vv, "{x:?}");
+}
+
+/// A small distance field atlas page, with a different value in every pixel so that crops from
+/// the wrong place show up.
+fn atlas_page() -> DynamicImage {
+    let page = image::GrayImage::from_fn(8, 8, |x, y| image::Luma([(y * 8 + x) as u8 * 4]));
+    DynamicImage::ImageLuma8(page)
+}
+
+fn sdf_bmfont(chars: &str) -> String {
+    format!(
+        "info face=\"test\" size=16\n\
+         common lineHeight=20 base=15 scaleW=8 scaleH=8 pages=1\n\
+         page id=0 file=\"test_0.png\"\n\
+         {chars}"
+    )
+}
+
+#[test]
+fn sdf_atlas_from_bmfont() {
+    use kaku::{AtlasError, SdfAtlas};
+
+    let glyphs = "char id=65 x=2 y=1 width=4 height=6 xoffset=1 yoffset=2 xadvance=8 page=0\n\
+                  char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=4 page=0\n";
+    let atlas = SdfAtlas::from_bmfont(&sdf_bmfont(glyphs), &[atlas_page()], 4.).unwrap();
+    assert_eq!(atlas.chars().collect::<Vec<_>>(), ['A', ' ']);
+    assert_eq!(atlas.radius(), 4.);
+
+    // Page ids aren't trusted to size anything
+    let descriptor = sdf_bmfont(glyphs).replace("page id=0", "page id=4000000000");
+    assert!(SdfAtlas::from_bmfont(&descriptor, &[atlas_page()], 4.).is_ok());
+
+    // Rects off the edge of the page, including ones whose ends don't fit in a u32
+    for rect in [
+        "x=6 y=0 width=4 height=2",
+        "x=0 y=7 width=2 height=2",
+        "x=4294967295 y=0 width=2 height=2",
+        "x=0 y=2 width=1 height=4294967295",
+    ] {
+        let glyph = format!("char id=65 {rect} xoffset=0 yoffset=0 xadvance=8 page=0\n");
+        let result = SdfAtlas::from_bmfont(&sdf_bmfont(&glyph), &[atlas_page()], 4.);
+        assert!(
+            matches!(result, Err(AtlasError::GlyphOutOfBounds('A'))),
+            "{rect}: {result:?}"
+        );
+    }
+
+    let glyph = "char id=65 x=0 y=0 width=2 height=2 xoffset=0 yoffset=0 xadvance=8 page=1\n";
+    let result = SdfAtlas::from_bmfont(&sdf_bmfont(glyph), &[atlas_page()], 4.);
+    assert!(matches!(result, Err(AtlasError::MissingPage(1))));
+}
+
+#[test]
+fn sdf_atlas_bmfont_channels() {
+    use kaku::SdfAtlas;
+
+    let glyphs = "char id=65 x=2 y=1 width=4 height=6 xoffset=1 yoffset=2 xadvance=8 page=0\n";
+    let export = |descriptor: &str, page: DynamicImage| {
+        let atlas = SdfAtlas::from_bmfont(descriptor, &[page], 4.).unwrap();
+        atlas.to_bmfont("exported.png")
+    };
+
+    let gray = atlas_page().to_luma8();
+    let expected = export(&sdf_bmfont(glyphs), atlas_page());
+    let value = |x, y| gray.get_pixel(x, y).0[0];
+
+    // Hiero draws the field into the alpha channel of white glyphs, without saying so
+    let hiero = image::RgbaImage::from_fn(8, 8, |x, y| Rgba([255, 255, 255, value(x, y)]));
+    let hiero_glyphs = glyphs.replace("page=0", "page=0 chnl=0");
+    assert_eq!(
+        export(&sdf_bmfont(&hiero_glyphs), hiero.clone().into()),
+        expected
+    );
+
+    // Descriptors that say where the glyphs are are followed, whether or not the page has alpha
+    let common = "scaleH=8 pages=1";
+    let in_alpha = sdf_bmfont(glyphs).replace(common, &format!("{common} alphaChnl=0 redChnl=4"));
+    assert_eq!(export(&in_alpha, hiero.into()), expected);
+
+    let red = image::RgbaImage::from_fn(8, 8, |x, y| Rgba([value(x, y), 0, 0, 0]));
+    let in_red = sdf_bmfont(glyphs).replace(common, &format!("{common} alphaChnl=3 redChnl=0"));
+    assert_eq!(export(&in_red, red.into()), expected);
+
+    // Packed glyphs each have a channel of their own
+    let packed = image::RgbaImage::from_fn(8, 8, |x, y| Rgba([0, value(x, y), 0, 255]));
+    let packed_glyphs = glyphs.replace("page=0", "page=0 chnl=2");
+    assert_eq!(export(&sdf_bmfont(&packed_glyphs), packed.into()), expected);
+
+    let descriptor = sdf_bmfont(glyphs).replace(common, &format!("{common} alphaChnl=7"));
+    let result = SdfAtlas::from_bmfont(&descriptor, &[atlas_page()], 4.);
+    assert!(result.is_err());
+}
+
+#[test]
+fn sdf_atlas_bmfont_round_trip() {
+    use kaku::SdfAtlas;
+
+    let glyphs = "char id=65 x=2 y=1 width=4 height=6 xoffset=1 yoffset=2 xadvance=8 page=0\n\
+                  char id=66 x=0 y=5 width=3 height=3 xoffset=0 yoffset=-1 xadvance=5 page=0\n\
+                  char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=4 page=0\n";
+    let page = atlas_page();
+    let atlas =
+        SdfAtlas::from_bmfont(&sdf_bmfont(glyphs), std::slice::from_ref(&page), 4.).unwrap();
+
+    let (descriptor, exported) = atlas.to_bmfont("exported.png");
+    let reloaded =
+        SdfAtlas::from_bmfont(&descriptor, &[exported.clone().into()], atlas.radius()).unwrap();
+    assert_eq!(reloaded.chars().collect::<Vec<_>>(), ['A', 'B', ' ']);
+
+    // Exporting again gives exactly the same atlas
+    assert_eq!(
+        reloaded.to_bmfont("exported.png"),
+        (descriptor.clone(), exported.clone())
+    );
+
+    // Each glyph's pixels are copied over as they were
+    let page = page.to_luma8();
+    for line in descriptor
+        .lines()
+        .filter(|line| line.starts_with("char id=6"))
+    {
+        let value = |key: &str| -> u32 {
+            let pair = line
+                .split(' ')
+                .find(|pair| pair.starts_with(&format!("{key}=")));
+            pair.unwrap()[key.len() + 1..].parse().unwrap()
+        };
+        let (x, y, width, height) = (value("x"), value("y"), value("width"), value("height"));
+        let (source_x, source_y) = match value("id") {
+            65 => (2, 1),
+            _ => (0, 5),
+        };
+
+        for (dx, dy) in (0..width).flat_map(|dx| (0..height).map(move |dy| (dx, dy))) {
+            assert_eq!(
+                exported.get_pixel(x + dx, y + dy),
+                page.get_pixel(source_x + dx, source_y + dy)
+            );
+        }
+    }
+}
+
+#[test]
+fn glyphs_with_no_area_are_drawn_as_nothing_with_their_advance() {
+    use kaku::SdfAtlas;
+
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    // '|' and ',' have no width or no height, so they're like spaces with the same advance
+    let glyphs = "char id=65 x=2 y=1 width=4 height=6 xoffset=1 yoffset=2 xadvance=8 page=0\n\
+                  char id=124 x=0 y=0 width=0 height=6 xoffset=0 yoffset=2 xadvance=3 page=0\n\
+                  char id=44 x=0 y=0 width=2 height=0 xoffset=0 yoffset=2 xadvance=3 page=0\n\
+                  char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=3 page=0\n";
+    let descriptor = sdf_bmfont(glyphs);
+    let atlas = SdfAtlas::from_bmfont(&descriptor, &[atlas_page()], 4.).unwrap();
+    let bitmap = BitmapFont::from_bmfont(&descriptor, &[atlas_page()]).unwrap();
+    let sdf_font = (h.renderer).load_sdf_atlas(&atlas, &h.device, &h.queue);
+    let bitmap_font = h.bitmap_font(&bitmap);
+
+    for font in [sdf_font, bitmap_font] {
+        for c in ['|', ','] {
+            assert!(h.renderer.cached_chars(font).any(|cached| cached == c));
+            assert!(h.renderer.glyph_texture(font, c).unwrap().is_none());
+        }
+
+        let text = h.build(TextBuilder::new("A|A,A", font, [10., 30.]).scale(2.));
+        let spaced = h.build(TextBuilder::new("A A A", font, [10., 30.]).scale(2.));
+        let x = text.glyphs().map(|g| g.rect.min[0]).collect::<Vec<_>>();
+        assert_eq!(x.len(), 3);
+        assert_eq!([x[1] - x[0], x[2] - x[1]], [22.; 2]);
+        assert_eq!(h.draw([&text]), h.draw([&spaced]));
+    }
+}
+
+#[test]
+fn exported_sdf_atlas_round_trip() {
+    use kaku::SdfAtlas;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_sdf_font(&mut renderer, 32.);
+    let plain_font = load_font(&mut renderer, 32.);
+
+    let draw = |renderer: &mut TextRenderer, font| {
+        let text = TextBuilder::new("Baked sdf", font, [10., 60.])
+            .outlined([0.8, 0.2, 0.2, 1.], 3.)
+            .scale(1.5)
+            .build(&device, &queue, renderer)
+            .unwrap();
+
+        draw_cpu(renderer, [&text])
+    };
+
+    let generated = draw(&mut renderer, font);
+
+    assert!(renderer.export_sdf(font, ' ').is_none());
+    assert!(renderer.export_sdf(plain_font, 'B').is_none());
+    assert!(renderer.export_sdf_atlas(plain_font).is_none());
+
+    // The atlas has every character the text cached, saved as a BMFont and loaded again
+    let atlas = renderer.export_sdf_atlas(font).unwrap();
+    assert_eq!(
+        atlas.chars().collect::<Vec<_>>(),
+        [' ', 'B', 'a', 'd', 'e', 'f', 'k', 's']
+    );
+
+    let (descriptor, page) = atlas.to_bmfont("baked.png");
+    let reloaded =
+        SdfAtlas::from_bmfont(&descriptor, &[page.clone().into()], atlas.radius()).unwrap();
+    assert_eq!(
+        reloaded.to_bmfont("baked.png"),
+        (descriptor.clone(), page.clone())
+    );
+
+    // A glyph's distance field on the page is the same as exporting it on its own
+    let b = renderer.export_sdf(font, 'B').unwrap();
+    let line = descriptor
+        .lines()
+        .find(|line| line.starts_with("char id=66 "))
+        .unwrap();
+    let value = |key: &str| -> u32 {
+        let pair = line
+            .split(' ')
+            .find(|pair| pair.starts_with(&format!("{key}=")));
+        pair.unwrap()[key.len() + 1..].parse().unwrap()
+    };
+    assert_eq!((value("width"), value("height")), b.dimensions());
+    assert_eq!(
+        image::imageops::crop_imm(&page, value("x"), value("y"), b.width(), b.height()).to_image(),
+        b
+    );
+
+    // Drawing with the baked atlas looks the same as with the generated distance fields, apart
+    // from them being rounded to 8 bits, which moves the edges by a fraction of a pixel
+    let baked_font = renderer.load_sdf_atlas(&reloaded, &device, &queue);
+    let baked = draw(&mut renderer, baked_font);
+    let diff = compare_images(&generated, &baked, 24).unwrap();
+    assert!(diff.is_match(), "{} pixels differ", diff.differing_pixels);
+}
+
+#[cfg(feature = "msdf-json")]
+fn msdf_json(y_origin: &str, plane: [f32; 4], atlas: [f32; 4], metrics: [f32; 2]) -> String {
+    let bounds = |[left, bottom, right, top]: [f32; 4]| {
+        format!(r#"{{"left": {left}, "bottom": {bottom}, "right": {right}, "top": {top}}}"#)
+    };
+
+    format!(
+        r#"{{
+            "atlas": {{"type": "sdf", "distanceRange": 4, "size": 16, "yOrigin": "{y_origin}"}},
+            "metrics": {{"lineHeight": 1.2, "ascender": {}, "descender": {}}},
+            "glyphs": [
+                {{"unicode": 32, "advance": 0.25}},
+                {{"unicode": 65, "advance": 0.5, "planeBounds": {}, "atlasBounds": {}}}
+            ]
+        }}"#,
+        metrics[0],
+        metrics[1],
+        bounds(plane),
+        bounds(atlas),
+    )
+}
+
+#[test]
+#[cfg(feature = "msdf-json")]
+fn sdf_atlas_from_msdf_json() {
+    use kaku::{AtlasError, SdfAtlas};
+
+    // The same glyph (the top half of the page) with the y axis pointing up and down
+    let bottom = msdf_json(
+        "bottom",
+        [0.1, -0.1, 0.4, 0.5],
+        [0., 4., 4., 8.],
+        [0.8, -0.2],
+    );
+    let top = msdf_json("top", [0.1, 0.1, 0.4, -0.5], [0., 4., 4., 0.], [-0.8, 0.2]);
+
+    let bottom = SdfAtlas::from_msdf_json(&bottom, &atlas_page()).unwrap();
+    let top = SdfAtlas::from_msdf_json(&top, &atlas_page()).unwrap();
+    assert_eq!(bottom.chars().collect::<Vec<_>>(), [' ', 'A']);
+    assert_eq!(bottom.radius(), 2.);
+    assert_eq!(bottom.to_bmfont("page.png"), top.to_bmfont("page.png"));
+
+    // Bounds off the page, before the start of it, and too big to be a pixel position
+    for atlas in [
+        [0., 4., 9., 8.],
+        [-1., 4., 4., 8.],
+        [0., -1., 4., 4.],
+        [0., 4., 1e12, 8.],
+    ] {
+        let json = msdf_json("bottom", [0.1, -0.1, 0.4, 0.5], atlas, [0.8, -0.2]);
+        let result = SdfAtlas::from_msdf_json(&json, &atlas_page());
+        assert!(
+            matches!(result, Err(AtlasError::GlyphOutOfBounds('A'))),
+            "{atlas:?}: {result:?}"
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "msdf-json")]
+fn msdf_glyphs_with_no_area_are_drawn_as_nothing() {
+    use kaku::SdfAtlas;
+
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    // A glyph with no width on the atlas, like a hairline that was rounded away
+    let json = msdf_json("top", [0., 0., 0., 0.5], [2., 6., 2., 2.], [1., -0.25]);
+    let atlas = SdfAtlas::from_msdf_json(&json, &atlas_page()).unwrap();
+    let font = (h.renderer).load_sdf_atlas(&atlas, &h.device, &h.queue);
+    assert!(h.renderer.glyph_texture(font, 'A').unwrap().is_none());
+
+    let text = h.build(&TextBuilder::new("AA", font, [10., 30.]));
+    assert_eq!(text.glyphs().count(), 0);
+    assert_eq!(text.bounds().width(), 16.);
+    assert_eq!(h.draw([&text]), background());
+}
+
+#[test]
+fn bitmap_fonts_reject_out_of_range_input() {
+    use kaku::{BitmapFont, BitmapFontError};
+
+    let page = || image::load_from_memory(include_bytes!("fonts/test_bmfont_0.png")).unwrap();
+    let descriptor = include_str!("fonts/test_bmfont.fnt");
+
+    // Rects whose ends don't fit in a u32 are off the page, rather than wrapping around onto it
+    for rect in [
+        "x=4294967295 y=0 width=5 height=7",
+        "x=8 y=0 width=5 height=4294967295",
+    ] {
+        let descriptor = descriptor.replace("x=8 y=0 width=5 height=7", rect);
+        assert_eq!(
+            BitmapFont::from_bmfont(&descriptor, &[page()]).unwrap_err(),
+            BitmapFontError::GlyphOutOfBounds('V')
+        );
+    }
+
+    let bdf = include_str!("fonts/test.bdf");
+    for (from, to, line) in [
+        ("BBX 5 7 0 0", "BBX 5 7 0 2147483647", 22),
+        (
+            "FONTBOUNDINGBOX 6 8 0 -1",
+            "FONTBOUNDINGBOX 6 8 0 -2147483648",
+            4,
+        ),
+    ] {
+        let error = BitmapFont::from_bdf(&bdf.replacen(from, to, 1)).unwrap_err();
+        assert!(
+            matches!(error, BitmapFontError::Bdf { line: l, .. } if l == line),
+            "{error}"
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "disk-cache")]
+fn disk_cache_round_trip() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let dir = std::env::temp_dir().join(format!("kaku-disk-cache-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let draw = |queue: &wgpu::Queue| {
+        let mut renderer = renderer_builder().with_disk_cache(&dir).build(&device);
+        let font = load_sdf_font(&mut renderer, 32.);
+        let text = TextBuilder::new("Hi", font, [10., 60.])
+            .build(&device, queue, &mut renderer)
+            .unwrap();
+
+        draw_cpu(&renderer, [&text])
+    };
+
+    let generated = draw(&queue);
+    let mut files = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    files.sort();
+
+    // The file names only depend on the font, glyph and settings, so they never change between
+    // builds or platforms
+    let names = files
+        .iter()
+        .map(|path| path.file_name().unwrap().to_str().unwrap());
+    assert_eq!(
+        names.collect::<Vec<_>>(),
+        ["16286a9fd1f1bc44.sdf", "88fc2b3d4ed17a43.sdf"]
+    );
+
+    // Loading the fields back draws the same thing
+    assert_eq!(draw(&queue), generated);
+
+    // and they really are loaded, rather than generated again. The header is 20 bytes long
+    for path in &files {
+        let mut bytes = std::fs::read(path).unwrap();
+        bytes[20..].fill(0);
+        std::fs::write(path, bytes).unwrap();
+    }
+    assert_ne!(draw(&queue), generated);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg(feature = "disk-cache")]
+fn failed_disk_cache_writes_leave_no_files_behind() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let dir = std::env::temp_dir().join(format!("kaku-disk-cache-write-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // A directory where one of the fields goes, so it can't be renamed into place
+    let blocked = dir.join("16286a9fd1f1bc44.sdf");
+    std::fs::create_dir_all(&blocked).unwrap();
+
+    let mut renderer = renderer_builder().with_disk_cache(&dir).build(&device);
+    let font = load_sdf_font(&mut renderer, 32.);
+    TextBuilder::new("Hi", font, [10., 60.])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    // The other field was written, and the temporary file of the blocked one was removed
+    let mut names = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["16286a9fd1f1bc44.sdf", "88fc2b3d4ed17a43.sdf"]);
+    assert!(blocked.is_dir());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg(feature = "rayon")]
+fn characters_are_generated_on_the_renderers_thread_pool() {
+    use kaku::rayon::ThreadPoolBuilder;
+    use std::sync::{mpsc, Arc};
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let pool = Arc::new(ThreadPoolBuilder::new().num_threads(1).build().unwrap());
+    let mut renderer = renderer(&device);
+    renderer.set_thread_pool(Some(pool.clone()));
+    let font = load_font(&mut renderer, 16.);
+
+    // Keep the pool's only thread busy, so characters can't be generated until it's released
+    let (release, released) = mpsc::channel::<()>();
+    pool.spawn(move || {
+        let _ = released.recv();
+    });
+
+    let (done, finished) = mpsc::channel();
+    std::thread::scope(|scope| {
+        let renderer = &mut renderer;
+        let (device, queue) = (&device, &queue);
+        scope.spawn(move || {
+            let generated = renderer.generate_char_range('a'..='z', font, device, queue);
+            done.send(generated).unwrap();
+        });
+
+        let timeout = std::time::Duration::from_millis(200);
+        assert!(finished.recv_timeout(timeout).is_err());
+        release.send(()).unwrap();
+        finished.recv().unwrap().unwrap();
+    });
+
+    assert_eq!(
+        renderer
+            .cached_chars(font)
+            .filter(char::is_ascii_lowercase)
+            .count(),
+        26
+    );
+}
+
+#[test]
+fn unknown_font_is_an_error() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 16.);
+
+    // A font id from a different renderer
+    let mut other_renderer = self::renderer(&device);
+    let result =
+        TextBuilder::new("Hello", font, [0., 0.]).build(&device, &queue, &mut other_renderer);
+    assert_eq!(result.err(), Some(Error::FontNotFound(font)));
+}
+
+#[test]
+fn uncached_glyphs_are_skipped() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 16.);
+
+    let text = TextBuilder::new("Hello", font, [10., 30.])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let expected = draw_cpu(&renderer, [&text]);
+
+    // With the cache cleared, nothing is drawn but drawing doesn't fail
+    renderer.clear_cache(font).unwrap();
+    let image = draw_cpu(&renderer, [&text]);
+    assert_eq!(image, background());
+
+    // Preparing the text brings the characters back
+    renderer.prepare_text(&text, &device, &queue).unwrap();
+    let image = draw_cpu(&renderer, [&text]);
+    assert_eq!(image, expected);
+}
+
+#[test]
+fn cached_chars_and_clear_cache_track_each_fonts_characters() {
+    use std::collections::BTreeSet;
+
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let cleared = h.font(16.);
+    let kept = h.font(24.);
+    let cached = |h: &Harness, font| h.renderer.cached_chars(font).collect::<BTreeSet<_>>();
+    assert!(cached(&h, cleared).is_empty());
+
+    h.build(&TextBuilder::new("Hello", cleared, [10., 30.]));
+    h.build(&TextBuilder::new("World", kept, [10., 30.]));
+    assert_eq!(cached(&h, cleared), BTreeSet::from(['H', 'e', 'l', 'o']));
+    assert_eq!(cached(&h, kept), BTreeSet::from(['W', 'o', 'r', 'l', 'd']));
+    let kept_bytes = {
+        let mut only_kept = Harness::new().unwrap();
+        let font = only_kept.font(24.);
+        only_kept.build(&TextBuilder::new("World", font, [10., 30.]));
+        only_kept.renderer.memory_usage().glyph_textures
+    };
+    assert!(h.renderer.memory_usage().glyph_textures > kept_bytes);
+
+    // Clearing one font frees its textures and leaves the other's alone
+    h.renderer.clear_cache(cleared).unwrap();
+    assert!(cached(&h, cleared).is_empty());
+    assert_eq!(cached(&h, kept).len(), 5);
+    assert_eq!(h.renderer.memory_usage().glyph_textures, kept_bytes);
+
+    // Fonts that aren't loaded have nothing cached and can't be cleared
+    let Some(mut other) = Harness::new() else {
+        return;
+    };
+    let unknown = (0..3).map(|_| other.font(16.)).last().unwrap();
+    assert!(cached(&h, unknown).is_empty());
+    assert_eq!(
+        h.renderer.clear_cache(unknown),
+        Err(Error::FontNotFound(unknown))
+    );
+}
+
+#[test]
+fn generate_char_range_caches_every_character_in_a_block() {
+    use kaku::UnicodeBlock;
+    use std::collections::BTreeSet;
+
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let latin = h.font(16.);
+    (h.renderer)
+        .generate_char_range(UnicodeBlock::BasicLatin, latin, &h.device, &h.queue)
+        .unwrap();
+
+    // Control characters like '\n' and DEL are skipped
+    let cached = h.renderer.cached_chars(latin).collect::<BTreeSet<_>>();
+    assert_eq!(cached, (' '..='~').collect());
+
+    let noto = FontRef::try_from_slice(include_bytes!("../examples/fonts/NotoSansJP-Regular.ttf"));
+    let noto = (h.renderer.load_font(noto.unwrap(), FontSize::Px(24.))).unwrap();
+    (h.renderer)
+        .generate_char_range('ぁ'..='ゖ', noto, &h.device, &h.queue)
+        .unwrap();
+    assert_eq!(h.renderer.cached_chars(noto).count(), 86);
+
+    // Text made of the block's characters doesn't generate anything new
+    let before = h.renderer.memory_usage().glyph_textures;
+    h.build(&TextBuilder::new("ひらがな", noto, [10., 40.]));
+    h.build(&TextBuilder::new("Hello, world!", latin, [10., 40.]));
+    assert_eq!(h.renderer.memory_usage().glyph_textures, before);
+    assert_eq!(h.renderer.cached_chars(noto).count(), 86);
+}
+
+#[test]
+fn preloaded_characters_are_not_generated_again() {
+    use kaku::Preload;
+    use std::collections::BTreeSet;
+
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let none = h.font_with_options(FontOptions::new(FontSize::Px(16.)));
+    let chars = h.font_with_options(
+        FontOptions::new(FontSize::Px(16.)).with_preload(Preload::Chars(vec!['k', 'a'])),
+    );
+    let ascii =
+        h.font_with_options(FontOptions::new(FontSize::Px(16.)).with_preload(Preload::Ascii));
+
+    let cached = |h: &Harness, font| h.renderer.cached_chars(font).collect::<BTreeSet<_>>();
+    assert!(cached(&h, none).is_empty());
+    assert_eq!(cached(&h, chars), BTreeSet::from(['a', 'k']));
+    assert_eq!(cached(&h, ascii), (' '..='~').collect());
+
+    // Building text with the preloaded characters doesn't rasterise them again
+    let preloaded = h.renderer.memory_usage().glyph_textures;
+    let ascii_text = h.build(&TextBuilder::new("Hello, world!", ascii, [10., 30.]));
+    h.build(&TextBuilder::new("kaka", chars, [10., 30.]));
+    assert_eq!(h.renderer.memory_usage().glyph_textures, preloaded);
+
+    // ...but characters that weren't preloaded are
+    h.build(&TextBuilder::new("Hello, world!", none, [10., 30.]));
+    assert!(h.renderer.memory_usage().glyph_textures > preloaded);
+
+    // Clearing the cache evicts the preloaded characters, and they're generated again when needed
+    h.renderer.clear_cache(ascii).unwrap();
+    assert!(cached(&h, ascii).is_empty());
+    let image = h.draw([&ascii_text]);
+    assert_eq!(image, background());
+
+    h.renderer
+        .prepare_text(&ascii_text, &h.device, &h.queue)
+        .unwrap();
+    assert_eq!(cached(&h, ascii).len(), 10);
+    assert_ne!(h.draw([&ascii_text]), background());
+}
+
+#[test]
+fn has_glyph_tells_which_characters_a_font_defines() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let fira = h.font(24.);
+    assert!(h.renderer.has_glyph(fira, 'a'));
+    assert!(h.renderer.has_glyph(fira, 'é'));
+    assert!(!h.renderer.has_glyph(fira, '\u{4e2d}'));
+    assert!(!h.renderer.has_glyph(fira, '\u{e000}'));
+
+    let noto = FontRef::try_from_slice(include_bytes!("../examples/fonts/NotoSansJP-Regular.ttf"));
+    let noto = (h.renderer.load_font(noto.unwrap(), FontSize::Px(24.))).unwrap();
+    assert!(h.renderer.has_glyph(noto, '\u{4e2d}'));
+
+    // The test font only has A, B and g
+    let bitmap_font = BitmapFont::from_bdf(include_str!("fonts/test.bdf")).unwrap();
+    let bitmap = h.bitmap_font(&bitmap_font);
+    assert!(h.renderer.has_glyph(bitmap, 'g'));
+    assert!(!h.renderer.has_glyph(bitmap, 'a'));
+
+    // Ids past the fonts this renderer has loaded have no glyphs
+    let Some(mut other) = Harness::new() else {
+        return;
+    };
+    let other_font = (0..4).map(|_| other.font(24.)).last().unwrap();
+    assert!(!h.renderer.has_glyph(other_font, 'a'));
+    assert!(other.renderer.has_glyph(other_font, 'a'));
+}
+
+#[test]
+fn notdef_boxes() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let bitmap_font = BitmapFont::from_bdf(include_str!("fonts/test.bdf")).unwrap();
+    let font = h.bitmap_font(&bitmap_font);
+    h.renderer
+        .set_missing_glyphs(font, MissingGlyphs::Notdef)
+        .unwrap();
+
+    // The test font only has A, B and g
+    let text = h.build(TextBuilder::new("AB?xB", font, [10., 40.]).scale(3.));
+    assert!(!h.renderer.has_glyph(font, 'x'));
+
+    let image = h.draw([&text]);
+    assert_matches_golden(&image, golden_path("notdef_boxes"), TOLERANCE);
+
+    // Both missing characters are drawn as the same box, with ink where it is
+    let glyphs = text.glyphs().collect::<Vec<_>>();
+    assert_eq!(glyphs.len(), 5);
+    let [question, x] = [&glyphs[2], &glyphs[3]];
+    assert_eq!(question.ink.width(), x.ink.width());
+    assert_eq!(question.ink.height(), x.ink.height());
+    let [left, top] = x.ink.min.map(|p| p.round() as u32);
+    assert_ne!(*image.get_pixel(left, top + 1), Rgba([255; 4]));
+}
+
+#[test]
+fn hex_boxes() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let font = h.font_with_options(
+        FontOptions::new(FontSize::Px(32.))
+            .with_sdf(SdfSettings::default())
+            .with_missing_glyphs(MissingGlyphs::HexBox),
+    );
+    let text = h.build(&TextBuilder::new("Hi \u{4e2d}\u{1f600}!", font, [10., 60.]));
+    let image = h.draw([&text]);
+    assert_matches_golden(&image, golden_path("hex_boxes"), TOLERANCE);
+
+    // Characters outside the Basic Multilingual Plane need 6 digits rather than 4, so their boxes
+    // have 3 columns of digits instead of 2
+    let glyphs = text.glyphs().collect::<Vec<_>>();
+    let [cjk, emoji] = [&glyphs[2], &glyphs[3]];
+    assert_eq!((cjk.character, emoji.character), ('\u{4e2d}', '\u{1f600}'));
+    assert!(emoji.ink.width() > cjk.ink.width() * 1.3);
+    assert_eq!(emoji.ink.height(), cjk.ink.height());
+}
+
+#[test]
+fn wide_outlines_are_clamped() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = renderer
+        .load_font_with_sdf(
+            FontRef::try_from_slice(FONT).unwrap(),
+            FontSize::Px(32.),
+            SdfSettings {
+                radius: 6.,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let mut builder = TextBuilder::new("Clamped", font, [10., 60.]);
+    builder.color([1., 1., 1., 1.]).scale(1.5);
+
+    let too_wide = builder
+        .outlined([0.2, 0.2, 0.8, 1.], 100.)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    // The outline can reach 6 * 1.5 pixels, minus one for antialiasing
+    let max_width = too_wide.max_outline_width().unwrap();
+    assert_eq!(max_width, 8.);
+
+    let widest = builder
+        .outlined([0.2, 0.2, 0.8, 1.], max_width)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let expected = draw_cpu(&renderer, [&widest]);
+    let image = draw_cpu(&renderer, [&too_wide]);
+    assert_eq!(image, expected);
+}
+
+#[test]
+fn control_characters_are_normalized() {
+    use kaku::{FontOptions, MissingGlyphs};
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    // Hex boxes make any characters that aren't filtered out show up
+    let mut renderer = renderer(&device);
+    let font = renderer
+        .load_font_with_options(
+            FontRef::try_from_slice(FONT).unwrap(),
+            FontOptions::new(FontSize::Px(20.)).with_missing_glyphs(MissingGlyphs::HexBox),
+            &device,
+            &queue,
+        )
+        .unwrap();
+
+    let messy = "A\tB\u{200b}C\u{feff}\u{7}D\r\nE\u{2028}F";
+    let clean = "A    BCD\nE\nF";
+
+    let [messy, clean] = [messy, clean].map(|s| {
+        let text = TextBuilder::new(s, font, [10., 30.])
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+
+        draw_cpu(&renderer, [&text])
+    });
+
+    assert_eq!(messy, clean);
+}
+
+#[test]
+fn decomposed_text_is_normalized() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 32.);
+
+    let draw = |renderer: &TextRenderer, text: &Text| draw_cpu(renderer, [text]);
+
+    let [precomposed, decomposed] = ["caf\u{e9}", "cafe\u{301}"].map(|s| {
+        TextBuilder::new(s, font, [10., 60.])
+            .normalize(true)
+            .build(&device, &queue, &mut renderer)
+            .unwrap()
+    });
+
+    assert_eq!(decomposed.glyphs().len(), 4);
+    let expected = draw(&renderer, &precomposed);
+    assert_eq!(draw(&renderer, &decomposed), expected);
+
+    // Typing a combining accent after the last letter composes it with that letter
+    let mut text = TextBuilder::new("cafe", font, [10., 60.])
+        .normalize(true)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+    text.replace_range(4..4, "\u{301}", &device, &queue, &mut renderer)
+        .unwrap();
+    assert_eq!(text.glyphs().len(), 4);
+    assert_eq!(draw(&renderer, &text), expected);
+}
+
+#[test]
+fn zwj_sequences_are_drawn_as_ligatures() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 32.);
+
+    let [joined, plain, unknown, separate] = ["f\u{200d}i", "fi", "a\u{200d}b", "ab"].map(|s| {
+        TextBuilder::new(s, font, [10., 60.])
+            .build(&device, &queue, &mut renderer)
+            .unwrap()
+    });
+
+    // Fira Sans has an "fi" ligature, which the joiner asks for like it would for an emoji
+    // sequence. Without one, kaku doesn't shape text so the characters are drawn separately
+    let glyphs = joined.glyphs().collect::<Vec<_>>();
+    assert_eq!(glyphs.len(), 1);
+    assert_eq!((glyphs[0].character, glyphs[0].index), ('f', 0));
+    assert_eq!(plain.glyphs().len(), 2);
+    assert!(joined.bounds().width() < plain.bounds().width());
+
+    // Sequences the font has no ligature for are drawn as their characters, without the joiner
+    let draw = |text: &Text| draw_cpu(&renderer, [text]);
+    assert_eq!(unknown.glyphs().len(), 2);
+    assert_eq!(draw(&unknown), draw(&separate));
+}
+
+#[test]
+fn variation_selectors_pick_variants() {
+    use kaku::{FontOptions, MissingGlyphs};
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    // Hex boxes show any selectors that are drawn as characters
+    let mut renderer = renderer(&device);
+    let font = renderer
+        .load_font_with_options(
+            FontRef::try_from_slice(include_bytes!("../examples/fonts/NotoSansJP-Regular.ttf"))
+                .unwrap(),
+            FontOptions::new(FontSize::Px(40.)).with_missing_glyphs(MissingGlyphs::HexBox),
+            &device,
+            &queue,
+        )
+        .unwrap();
+
+    let [plain, variant, default, text_style] = ["葛", "葛\u{e0100}", "葛\u{e0101}", "葛\u{fe0e}"]
+        .map(|s| {
+            let text = TextBuilder::new(s, font, [10., 60.])
+                .build(&device, &queue, &mut renderer)
+                .unwrap();
+
+            assert_eq!(text.glyphs().len(), 1);
+            draw_cpu(&renderer, [&text])
+        });
+
+    // Noto Sans JP has a variant of 葛 for the first ideographic variation selector. The second
+    // asks for its default glyph, and it doesn't have a text style version
+    assert_ne!(variant, plain);
+    assert_eq!(default, plain);
+    assert_eq!(text_style, plain);
+}
+
+#[test]
+fn runs_are_drawn_with_one_fallback_font() {
+    use ab_glyph::FontArc;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let noto = || {
+        FontArc::try_from_slice(include_bytes!("../examples/fonts/NotoSansJP-Regular.ttf")).unwrap()
+    };
+
+    let mut renderer = renderer(&device);
+    let fira = FontRef::try_from_slice(FONT).unwrap();
+    let font = renderer.load_font(fira.clone(), FontSize::Px(32.)).unwrap();
+    renderer.set_fallback_fonts(font, vec![noto()]).unwrap();
+    let fira = renderer.load_font(fira, FontSize::Px(32.)).unwrap();
+    let noto = renderer.load_font(noto(), FontSize::Px(32.)).unwrap();
+
+    let mut advances = |s: &str, font| {
+        let text = TextBuilder::new(s, font, [10., 60.])
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+        let glyphs = text.glyphs().collect::<Vec<_>>();
+        (glyphs.windows(2))
+            .map(|pair| pair[1].rect.min[0] - pair[0].rect.min[0])
+            .collect::<Vec<_>>()
+    };
+
+    // Fira Sans has the digits but not 年, so the whole run is drawn with Noto Sans JP
+    let fallback = advances("2024年", font);
+    assert_eq!(fallback.len(), 4);
+    for (fallback, noto) in fallback.iter().zip(advances("2024年", noto)) {
+        assert!((fallback - noto).abs() < 0.01);
+    }
+    assert_ne!(fallback, advances("2024年", fira));
+
+    // Greek is in Fira Sans, so it isn't drawn with the fallback
+    assert_eq!(advances("Ωμέγα", font), advances("Ωμέγα", fira));
+}
+
+#[test]
+fn oversized_glyphs_are_shrunk() {
+    let limits = wgpu::Limits {
+        max_texture_dimension_2d: 32,
+        ..Default::default()
+    };
+    let Some((device, queue)) = device_with_limits(limits) else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = renderer
+        .load_font_with_sdf(
+            FontRef::try_from_slice(FONT).unwrap(),
+            FontSize::Px(80.),
+            SdfSettings {
+                radius: 8.,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    // The glyphs are far bigger than 32 pixels, but are still uploaded and drawn at full size
+    let text = TextBuilder::new("Hi", font, [10., 90.])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let image = draw_cpu(&renderer, [&text]);
+
+    let drawn = image.enumerate_pixels().filter(|(_, _, p)| p.0[0] < 128);
+    let (min_x, max_x) = drawn.fold((u32::MAX, 0), |(min, max), (x, _, _)| {
+        (min.min(x), max.max(x))
+    });
+    assert!(
+        max_x - min_x > 50,
+        "text was drawn from x={min_x} to x={max_x}"
+    );
+}
+
+/// Creates a texture the size of the test images, lets `draw` render to it, and reads it back.
+fn draw_on_gpu(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    draw: impl FnOnce(&mut wgpu::CommandEncoder, &wgpu::TextureView),
+) -> RgbaImage {
+    let data = draw_on_gpu_with_format(device, queue, wgpu::TextureFormat::Rgba8Unorm, draw);
+    RgbaImage::from_raw(SIZE.0, SIZE.1, data).unwrap()
+}
+
+/// Like [draw_on_gpu], but with a texture of any format, returning the texture's data as it is.
+fn draw_on_gpu_with_format(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    format: wgpu::TextureFormat,
+    draw: impl FnOnce(&mut wgpu::CommandEncoder, &wgpu::TextureView),
+) -> Vec<u8> {
+    let pixel_bytes = format.block_copy_size(None).unwrap();
+
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: SIZE.0,
+            height: SIZE.1,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = target.create_view(&Default::default());
+
+    // The width of the image is a multiple of 256 bytes, so rows don't need padding
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (SIZE.0 * SIZE.1 * pixel_bytes) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    draw(&mut encoder, &view);
+
+    encoder.copy_texture_to_buffer(
+        target.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(SIZE.0 * pixel_bytes),
+                rows_per_image: Some(SIZE.1),
+            },
+        },
+        target.size(),
+    );
+    queue.submit([encoder.finish()]);
+
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+
+    let data = buffer.slice(..).get_mapped_range().to_vec();
+    data
+}
+
+/// Starts a render pass that clears the target to white, like [background].
+fn begin_white_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    view: &'a wgpu::TextureView,
+) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        ..Default::default()
+    })
+}
+
+/// Draws some raster, sdf and bitmap text on the GPU in compatibility mode and reads the result
+/// back, returning it along with the same text drawn by the cpu renderer.
+fn draw_in_compatibility_mode(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> (RgbaImage, RgbaImage) {
+    use kaku::BitmapFont;
+
+    let mut renderer = TextRendererBuilder::new(wgpu::TextureFormat::Rgba8Unorm, SIZE)
+        .with_compatibility_mode(true)
+        .build(device);
+
+    let raster = load_font(&mut renderer, 24.);
+    let sdf = load_sdf_font(&mut renderer, 24.);
+    let bitmap = BitmapFont::from_bdf(include_str!("fonts/test.bdf")).unwrap();
+    let bitmap = renderer.load_bitmap_font(&bitmap, device, queue);
+
+    let texts = [
+        TextBuilder::new("Raster", raster, [10., 30.]).build(device, queue, &mut renderer),
+        TextBuilder::new("Outlined", sdf, [120., 30.])
+            .outlined([0.8, 0.2, 0.2, 1.], 3.)
+            .scale(1.3)
+            .build(device, queue, &mut renderer),
+        TextBuilder::new("AB gAB", bitmap, [10., 90.])
+            .scale(3.)
+            .build(device, queue, &mut renderer),
+    ]
+    .map(Result::unwrap);
+
+    let gpu_image = draw_on_gpu(device, queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        for text in &texts {
+            renderer.draw_text(&mut pass, text).unwrap();
+        }
+    });
+
+    let cpu_image = draw_cpu(&renderer, &texts);
+
+    (gpu_image, cpu_image)
+}
+
+#[test]
+fn compatibility_mode_matches_cpu_renderer() {
+    let Some((device, queue)) = device_with_limits(wgpu::Limits::downlevel_webgl2_defaults())
+    else {
+        return;
+    };
+
+    // The shaders filter by hand in compatibility mode, the same way the cpu renderer does
+    let (gpu_image, cpu_image) = draw_in_compatibility_mode(&device, &queue);
+    assert_ne!(cpu_image, background(), "nothing was drawn");
+
+    let diff = compare_images(&cpu_image, &gpu_image, TOLERANCE).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the GPU output differs by up to {}",
+        diff.max_difference
+    );
+}
+
+#[test]
+fn draw_text_for_target() {
+    use kaku::TargetConfig;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    // The renderer's own pipelines are for a multisampled BGRA swapchain of a different size, so
+    // the text only comes out right if the target's pipelines and screen uniform are used
+    let mut renderer = TextRendererBuilder::new(wgpu::TextureFormat::Bgra8UnormSrgb, (800, 600))
+        .with_msaa_sample_count(4)
+        .build(&device);
+    let target = renderer.add_target(
+        TargetConfig::new(wgpu::TextureFormat::Rgba8Unorm, SIZE),
+        &device,
+    );
+
+    let raster = load_font(&mut renderer, 24.);
+    let sdf = load_sdf_font(&mut renderer, 24.);
+
+    let texts = [
+        TextBuilder::new("Raster", raster, [10., 30.])
+            .pixel_snap(true)
+            .build(&device, &queue, &mut renderer),
+        TextBuilder::new("Outlined", sdf, [120., 80.])
+            .outlined([0.8, 0.2, 0.2, 1.], 3.)
+            .pixel_snap(true)
+            .build(&device, &queue, &mut renderer),
+    ]
+    .map(Result::unwrap);
+
+    let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        for text in &texts {
+            renderer
+                .draw_text_for_target(&mut pass, target, text)
+                .unwrap();
+        }
+    });
+
+    let cpu_image = draw_cpu(&renderer, &texts);
+
+    // The GPU filters the distance field slightly differently to the cpu renderer, but a wrong
+    // pipeline or screen uniform would be way off
+    let diff = compare_images(&cpu_image, &gpu_image, 8).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the GPU output differs by up to {}",
+        diff.max_difference
+    );
+
+    // Targets belong to the renderer they were added to
+    let other_renderer =
+        TextRendererBuilder::new(wgpu::TextureFormat::Rgba8Unorm, SIZE).build(&device);
+    assert_eq!(
+        other_renderer.resize_target(target, SIZE, &queue),
+        Err(Error::TargetNotFound(target))
+    );
+}
+
+#[test]
+fn multiview_draws_each_view_with_its_projection() {
+    use kaku::TargetConfig;
+    use std::num::NonZeroU32;
+
+    const VIEWS: u32 = 2;
+    const OFFSET: f32 = 50.;
+
+    if let Some((device, queue)) = device() {
+        let renderer = renderer(&device);
+        let projection = [[0.; 4]; 4];
+        assert_eq!(
+            renderer.set_view_projections(renderer.main_target(), &[projection; 2], &queue),
+            Err(Error::WrongViewCount {
+                views: 1,
+                projections: 2
+            })
+        );
+    }
+
+    let Some((device, queue)) = device_with_features(wgpu::Features::MULTIVIEW, Default::default())
+    else {
+        eprintln!("No adapter with multiview available, skipping test");
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let target = renderer.add_target(
+        TargetConfig::new(wgpu::TextureFormat::Rgba8Unorm, SIZE)
+            .with_multiview(NonZeroU32::new(VIEWS).unwrap()),
+        &device,
+    );
+
+    // The second view is moved to the right, like a second eye
+    let [sx, sy] = [2. / SIZE.0 as f32, -2. / SIZE.1 as f32];
+    let projections = [0., OFFSET].map(|offset| {
+        [
+            [sx, 0., 0., 0.],
+            [0., sy, 0., 0.],
+            [0., 0., 1., 0.],
+            [-1. + offset * sx, 1., 0., 1.],
+        ]
+    });
+    renderer
+        .set_view_projections(target, &projections, &queue)
+        .unwrap();
+
+    let font = load_font(&mut renderer, 24.);
+    let text = TextBuilder::new("Multiview", font, [10., 50.])
+        .pixel_snap(true)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let size = wgpu::Extent3d {
+        width: SIZE.0,
+        height: SIZE.1,
+        depth_or_array_layers: VIEWS,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::D2Array),
+        ..Default::default()
+    });
+
+    // The width of the image is a multiple of 256 bytes, so rows don't need padding
+    let layer_bytes = (SIZE.0 * SIZE.1 * 4) as u64;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: layer_bytes * VIEWS as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        renderer
+            .draw_text_for_target(&mut pass, target, &text)
+            .unwrap();
+    }
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(SIZE.0 * 4),
+                rows_per_image: Some(SIZE.1),
+            },
+        },
+        size,
+    );
+    queue.submit([encoder.finish()]);
+
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let data = buffer.slice(..).get_mapped_range().to_vec();
+
+    for (layer, offset) in data.chunks_exact(layer_bytes as usize).zip([0., OFFSET]) {
+        let image = RgbaImage::from_raw(SIZE.0, SIZE.1, layer.to_vec()).unwrap();
+
+        let moved = TextBuilder::new("Multiview", font, [10. + offset, 50.])
+            .pixel_snap(true)
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+        let expected = draw_cpu(&renderer, [&moved]);
+
+        let diff = compare_images(&expected, &image, TOLERANCE).unwrap();
+        assert_eq!(
+            diff.differing_pixels, 0,
+            "the view moved by {offset} differs by up to {}",
+            diff.max_difference
+        );
+    }
+}
+
+#[test]
+fn paper_white_scales_hdr_output() {
+    use half::f16;
+    use kaku::TargetConfig;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let format = wgpu::TextureFormat::Rgba16Float;
+    let mut renderer = TextRendererBuilder::new(format, SIZE)
+        .with_paper_white(240.)
+        .build(&device);
+    let sdr = renderer.add_target(TargetConfig::new(format, SIZE), &device);
+
+    let font = load_font(&mut renderer, 64.);
+    let text = TextBuilder::new("HDR", font, [10., 80.])
+        .color([1.; 4])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    // The brightest red value drawn to a target over a black background
+    let brightest = |renderer: &TextRenderer, target| {
+        let data = draw_on_gpu_with_format(&device, &queue, format, |encoder, view| {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            renderer
+                .draw_text_for_target(&mut pass, target, &text)
+                .unwrap();
+        });
+
+        data.chunks_exact(8)
+            .map(|pixel| f16::from_le_bytes([pixel[0], pixel[1]]).to_f32())
+            .fold(0., f32::max)
+    };
+
+    // 240 nits is three times as bright as scRGB's 1.0
+    let hdr = brightest(&renderer, renderer.main_target());
+    assert!((hdr - 3.).abs() < 0.01, "white was drawn as {hdr}");
+
+    let sdr = brightest(&renderer, sdr);
+    assert!((sdr - 1.).abs() < 0.01, "white was drawn as {sdr}");
+
+    renderer
+        .set_paper_white(renderer.main_target(), 80., &queue)
+        .unwrap();
+    let reset = brightest(&renderer, renderer.main_target());
+    assert!((reset - 1.).abs() < 0.01, "white was drawn as {reset}");
+}
+
+#[test]
+fn shader_constants_match_cpu_renderer() {
+    use kaku::ShaderDebugMode;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    // Draws outlined sdf text and raster text with a renderer, on the GPU and the CPU
+    let draw = |builder: TextRendererBuilder| {
+        let mut renderer = builder.build(&device);
+        let raster = load_font(&mut renderer, 24.);
+        let sdf = load_sdf_font(&mut renderer, 24.);
+
+        let texts = [
+            TextBuilder::new("Raster", raster, [10., 30.])
+                .pixel_snap(true)
+                .build(&device, &queue, &mut renderer),
+            TextBuilder::new("Outlined", sdf, [120., 80.])
+                .outlined([0.8, 0.2, 0.2, 1.], 3.)
+                .pixel_snap(true)
+                .build(&device, &queue, &mut renderer),
+        ]
+        .map(Result::unwrap);
+
+        let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            for text in &texts {
+                renderer.draw_text(&mut pass, text).unwrap();
+            }
+        });
+
+        let cpu_image = draw_cpu(&renderer, &texts);
+
+        (gpu_image, cpu_image)
+    };
+
+    let (_, default_image) = draw(renderer_builder());
+
+    for builder in [
+        renderer_builder().with_edge_smoothing(4.).with_gamma(2.),
+        renderer_builder().with_debug_mode(ShaderDebugMode::GlyphQuads),
+        renderer_builder().with_debug_mode(ShaderDebugMode::TextureValues),
+    ] {
+        let (gpu_image, cpu_image) = draw(builder.clone());
+        assert_ne!(cpu_image, default_image, "{builder:?} changed nothing");
+
+        // The GPU filters the distance field slightly differently to the cpu renderer
+        let diff = compare_images(&cpu_image, &gpu_image, 8).unwrap();
+        assert_eq!(
+            diff.differing_pixels, 0,
+            "the GPU output with {builder:?} differs by up to {}",
+            diff.max_difference
+        );
+    }
+}
+
+#[cfg(feature = "egui")]
+#[test]
+fn egui_callback_draws_in_its_rect() {
+    use egui::epaint::{ClippedPrimitive, Primitive};
+    use kaku::EguiTextCallback;
+    use std::sync::Arc;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut text_renderer = renderer(&device);
+    let font = load_font(&mut text_renderer, 24.);
+
+    // The text is too long for the rect, so its end should be cut off
+    let text = TextBuilder::new("Inside an egui panel", font, [10., 30.])
+        .build(&device, &queue, &mut text_renderer)
+        .unwrap();
+    let rect = egui::Rect::from_min_size(egui::pos2(60., 20.), egui::vec2(150., 60.));
+
+    // The same text drawn at the rect's position, with everything outside the rect cleared
+    let expected_text = TextBuilder::new("Inside an egui panel", font, [70., 50.])
+        .build(&device, &queue, &mut text_renderer)
+        .unwrap();
+    let mut expected = draw_cpu(&text_renderer, [&expected_text]);
+    for (x, y, pixel) in expected.enumerate_pixels_mut() {
+        if !rect.contains(egui::pos2(x as f32 + 0.5, y as f32 + 0.5)) {
+            *pixel = Rgba([255; 4]);
+        }
+    }
+
+    let mut egui_renderer =
+        egui_wgpu::Renderer::new(&device, wgpu::TextureFormat::Rgba8Unorm, None, 1);
+    egui_renderer.callback_resources.insert(text_renderer);
+
+    let primitives = [ClippedPrimitive {
+        clip_rect: egui::Rect::EVERYTHING,
+        primitive: Primitive::Callback(EguiTextCallback::paint_callback(rect, [Arc::new(text)])),
+    }];
+    let screen = egui_wgpu::ScreenDescriptor {
+        size_in_pixels: [SIZE.0, SIZE.1],
+        pixels_per_point: 1.,
+    };
+
+    let image = draw_on_gpu(&device, &queue, |encoder, view| {
+        egui_renderer.update_buffers(&device, &queue, encoder, &primitives, &screen);
+        let mut pass = begin_white_pass(encoder, view);
+        egui_renderer.render(&mut pass, &primitives, &screen);
+    });
+
+    let diff = compare_images(&expected, &image, TOLERANCE).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the egui output differs by up to {}",
+        diff.max_difference
+    );
+}
+
+#[cfg(feature = "cosmic-text")]
+#[test]
+fn cosmic_text_layout() {
+    use kaku::cosmic_text::{fontdb, Attrs, Buffer, Color, Family, FontSystem, Metrics, Shaping};
+    use kaku::{CosmicTextAdapter, FontOptions};
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut text_renderer = renderer(&device);
+
+    let mut db = fontdb::Database::new();
+    db.load_font_data(FONT.to_vec());
+    let mut font_system = FontSystem::new_with_locale_and_db("en-US".into(), db);
+
+    // The text is wrapped onto two lines by cosmic-text, and has a span in a different color
+    let attrs = Attrs::new().family(Family::Name("Fira Sans"));
+    let mut buffer = Buffer::new(&mut font_system, Metrics::new(24., 30.));
+    buffer.set_size(&mut font_system, Some(280.), None);
+    buffer.set_rich_text(
+        &mut font_system,
+        [
+            ("Laid out by ", attrs),
+            ("cosmic-text", attrs.color(Color::rgb(200, 40, 40))),
+            (", drawn by kaku", attrs),
+        ],
+        attrs,
+        Shaping::Advanced,
+    );
+    buffer.shape_until_scroll(&mut font_system, false);
+
+    let mut adapter = CosmicTextAdapter::new(
+        FontOptions::new(FontSize::Px(32.)).with_sdf(SdfSettings::default()),
+    );
+    let texts = adapter
+        .build(
+            &buffer,
+            &mut font_system,
+            [10., 20.],
+            [0., 0., 0., 1.],
+            &device,
+            &queue,
+            &mut text_renderer,
+        )
+        .unwrap();
+
+    // One text for each color
+    assert_eq!(texts.len(), 2);
+
+    // Each glyph knows where its character is in its text
+    let glyphs: Vec<_> = texts[1].glyphs().collect();
+    assert_eq!(
+        glyphs.iter().map(|g| g.character).collect::<String>(),
+        "cosmic-text"
+    );
+    assert!(glyphs.iter().enumerate().all(|(i, g)| g.index == i));
+
+    let image = draw_cpu(&text_renderer, &texts);
+    assert_matches_golden(&image, golden_path("cosmic_text_layout"), TOLERANCE);
+}
+
+#[test]
+fn scale_factor_matches_scaled_text() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    // Text drawn at a scale factor of 2 should look the same as text that is twice as big, in
+    // the same place in physical pixels
+    let draw = |scale_factor: f64, size: f32, outline: f32| {
+        let position = [20., 40.].map(|p| p / scale_factor as f32);
+
+        let mut text_renderer = renderer(&device);
+        text_renderer.set_scale_factor(scale_factor);
+
+        let raster_font = text_renderer
+            .load_font(FontRef::try_from_slice(FONT).unwrap(), FontSize::Px(size))
+            .unwrap();
+        let sdf_font = load_sdf_font(&mut text_renderer, 16.);
+
+        let raster = TextBuilder::new("Raster text", raster_font, position)
+            .pixel_snap(true)
+            .build(&device, &queue, &mut text_renderer)
+            .unwrap();
+        let sdf = TextBuilder::new("Sdf text", sdf_font, position.map(|p| p * 2.5))
+            .scale(size / 16.)
+            .color([1., 1., 1., 1.])
+            .outlined([0.2, 0.4, 0.8, 1.], outline)
+            .build(&device, &queue, &mut text_renderer)
+            .unwrap();
+
+        draw_cpu(&text_renderer, [&raster, &sdf])
+    };
+
+    let expected = draw(1., 32., 4.);
+    let actual = draw(2., 16., 2.);
+    assert_ne!(expected, background(), "nothing was drawn");
+
+    let diff = compare_images(&expected, &actual, TOLERANCE).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the scaled output differs by up to {}",
+        diff.max_difference
+    );
+}
+
+#[cfg(feature = "headless")]
+#[test]
+fn render_to_image_matches_cpu_renderer() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut text_renderer = renderer(&device);
+    let font = load_font(&mut text_renderer, 32.);
+    let text = TextBuilder::new("Headless", font, [10., 50.])
+        .pixel_snap(true)
+        .build(&device, &queue, &mut text_renderer)
+        .unwrap();
+
+    let expected = draw_cpu(&text_renderer, [&text]);
+
+    let image = text_renderer
+        .render_to_image([&text], SIZE, wgpu::Color::WHITE, &device, &queue)
+        .unwrap();
+
+    let diff = compare_images(&expected, &image, TOLERANCE).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the GPU output differs by up to {}",
+        diff.max_difference
+    );
+}
+
+#[test]
+fn kaku_uses_the_wgpu_version_its_features_pick() {
+    // With both features enabled the oldest version wins, so that crates on it (like egui-wgpu)
+    // can share its types
+    #[cfg(feature = "wgpu-0_20")]
+    use ::wgpu as picked;
+    #[cfg(not(feature = "wgpu-0_20"))]
+    use wgpu_22 as picked;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+    let (device, queue): (&picked::Device, &picked::Queue) = (&device, &queue);
+
+    // Everything works through the picked version's types, down to drawing on the GPU
+    let mut renderer =
+        TextRendererBuilder::new(picked::TextureFormat::Rgba8Unorm, SIZE).build(device);
+    let font = load_font(&mut renderer, 32.);
+    let text = TextBuilder::new("wgpu", font, [10., 50.])
+        .build(device, queue, &mut renderer)
+        .unwrap();
+    let image = renderer
+        .render_to_image([&text], SIZE, picked::Color::WHITE, device, queue)
+        .unwrap();
+    assert_close(
+        drawn_bounds(&image).unwrap(),
+        text.ink_bounds().unwrap(),
+        1.5,
+    );
+}
+
+#[cfg(feature = "headless")]
+#[test]
+fn render_to_image_handles_any_target() {
+    use kaku::RenderToImageError;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    // The width isn't a multiple of 64 pixels, so the rows have to be padded for the copy
+    let size = (250, 90);
+    let draw = |builder: TextRendererBuilder| {
+        let mut text_renderer = builder.build(&device);
+        let font = load_font(&mut text_renderer, 32.);
+        let text = TextBuilder::new("Any target", font, [10., 50.])
+            .color([0.8, 0.2, 0.1, 1.])
+            .build(&device, &queue, &mut text_renderer)
+            .unwrap();
+
+        text_renderer.render_to_image([&text], size, wgpu::Color::WHITE, &device, &queue)
+    };
+
+    let expected = draw(TextRendererBuilder::new(
+        wgpu::TextureFormat::Rgba8Unorm,
+        size,
+    ))
+    .unwrap();
+    let image = draw(
+        TextRendererBuilder::new(wgpu::TextureFormat::Bgra8Unorm, size)
+            .with_msaa_sample_count(4)
+            .with_depth(wgpu::TextureFormat::Depth24PlusStencil8),
+    )
+    .unwrap();
+
+    assert!(
+        expected.pixels().any(|p| *p != Rgba([255; 4])),
+        "nothing was drawn"
+    );
+    let diff = compare_images(&expected, &image, TOLERANCE).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the output differs by up to {}",
+        diff.max_difference
+    );
+
+    let unsupported = draw(TextRendererBuilder::new(
+        wgpu::TextureFormat::Rgba16Float,
+        size,
+    ));
+    assert_eq!(
+        unsupported,
+        Err(RenderToImageError::UnsupportedFormat(
+            wgpu::TextureFormat::Rgba16Float
+        ))
+    );
+}
+
+#[test]
+fn push_constants_match_cpu_renderer() {
+    // wgpu's GL backend reads push constants without checking their alignment, which it loses
+    // after the debug group labels of each text
+    let instance = wgpu::Instance::default();
+    let adapter = pollster::block_on(instance.request_adapter(&Default::default()));
+    if adapter.is_some_and(|adapter| adapter.get_info().backend == wgpu::Backend::Gl) {
+        eprintln!("Push constants are unreliable on GL, skipping test");
+        return;
+    }
+
+    let limits = wgpu::Limits {
+        max_push_constant_size: 128,
+        ..Default::default()
+    };
+    let Some((device, queue)) = device_with_features(wgpu::Features::PUSH_CONSTANTS, limits) else {
+        eprintln!("Push constants not supported, skipping test");
+        return;
+    };
+
+    let mut renderer = renderer_builder().with_push_constants(true).build(&device);
+    let raster = load_font(&mut renderer, 24.);
+    let sdf = load_sdf_font(&mut renderer, 24.);
+
+    let mut texts = [
+        TextBuilder::new("Raster", raster, [10., 30.])
+            .pixel_snap(true)
+            .build(&device, &queue, &mut renderer),
+        TextBuilder::new("Outlined", sdf, [120., 80.])
+            .outlined([0.8, 0.2, 0.2, 1.], 3.)
+            .pixel_snap(true)
+            .build(&device, &queue, &mut renderer),
+    ]
+    .map(Result::unwrap);
+
+    // Settings changed after the text is built have to be pushed too
+    for text in &mut texts {
+        text.set_color([0.1, 0.3, 0.9, 1.], &queue);
+    }
+
+    let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        for text in &texts {
+            renderer.draw_text(&mut pass, text).unwrap();
+        }
+    });
+
+    let cpu_image = draw_cpu(&renderer, &texts);
+
+    let diff = compare_images(&cpu_image, &gpu_image, 8).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the GPU output differs by up to {}",
+        diff.max_difference
+    );
+}
+
+#[test]
+fn shared_settings_buffer_matches_cpu_renderer() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer_builder()
+        .with_shared_settings_buffer(true)
+        .build(&device);
+    let raster = load_font(&mut renderer, 24.);
+    let sdf = load_sdf_font(&mut renderer, 24.);
+
+    // Enough texts to fill more than one buffer
+    let mut filler: Vec<_> = (0..300)
+        .map(|i| {
+            TextBuilder::new("x", raster, [i as f32, 0.])
+                .build(&device, &queue, &mut renderer)
+                .unwrap()
+        })
+        .collect();
+
+    // The new text reuses the dropped text's slot, and has to overwrite its settings
+    filler.remove(10);
+    let mut texts = vec![TextBuilder::new("Raster", raster, [10., 30.])
+        .pixel_snap(true)
+        .build(&device, &queue, &mut renderer)
+        .unwrap()];
+    texts.extend(filler.drain(280..).take(1));
+    texts.push(
+        TextBuilder::new("Outlined", sdf, [120., 80.])
+            .outlined([0.8, 0.2, 0.2, 1.], 3.)
+            .pixel_snap(true)
+            .build(&device, &queue, &mut renderer)
+            .unwrap(),
+    );
+
+    for text in &mut texts {
+        text.set_color([0.1, 0.3, 0.9, 1.], &queue);
+    }
+    texts[1].set_position([60., 120.], &queue);
+
+    let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        for text in &texts {
+            renderer.draw_text(&mut pass, text).unwrap();
+        }
+    });
+
+    let cpu_image = draw_cpu(&renderer, &texts);
+
+    let diff = compare_images(&cpu_image, &gpu_image, 8).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the GPU output differs by up to {}",
+        diff.max_difference
+    );
+}
+
+#[test]
+fn staging_belt_uploads_match_cpu_renderer() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer_builder()
+        .with_staging_belt(1024)
+        .with_shared_settings_buffer(true)
+        .build(&device);
+    let font = load_font(&mut renderer, 24.);
+
+    let mut texts = ["One", "Two", "Three"].map(|s| {
+        TextBuilder::new(s, font, [10., 30.])
+            .pixel_snap(true)
+            .build(&device, &queue, &mut renderer)
+            .unwrap()
+    });
+
+    let draw_frame = |renderer: &mut TextRenderer, texts: &[Text]| {
+        let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+            renderer.finish_uploads(encoder, &device);
+            let mut pass = begin_white_pass(encoder, view);
+            for text in texts {
+                renderer.draw_text(&mut pass, text).unwrap();
+            }
+        });
+        renderer.recall_uploads();
+
+        let cpu_image = draw_cpu(renderer, texts);
+
+        let diff = compare_images(&cpu_image, &gpu_image, TOLERANCE).unwrap();
+        assert_eq!(
+            diff.differing_pixels, 0,
+            "the GPU output differs by up to {}",
+            diff.max_difference
+        );
+    };
+
+    // Several changes to the same text in a frame, where only the last should be uploaded
+    for (i, text) in texts.iter_mut().enumerate() {
+        for x in 0..10 {
+            text.set_position([10. + x as f32 * 5., 30. + i as f32 * 40.], &queue);
+        }
+    }
+    texts[1].set_color([0.9, 0.2, 0.2, 1.], &queue);
+    draw_frame(&mut renderer, &texts);
+
+    // The belt's buffers are reused in the next frame
+    texts[0]
+        .set_text("Four".into(), &device, &queue, &mut renderer)
+        .unwrap();
+    texts[2].set_color([0.1, 0.6, 0.2, 1.], &queue);
+    draw_frame(&mut renderer, &texts);
+}
+
+#[test]
+fn encoder_uploads_match_cpu_renderer() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 24.);
+
+    let mut texts = [[10., 30.], [10., 80.]].map(|position| {
+        TextBuilder::new("Long text to start with", font, position)
+            .pixel_snap(true)
+            .build(&device, &queue, &mut renderer)
+            .unwrap()
+    });
+
+    // Every new character and the new instances of both texts are uploaded through the encoder:
+    // the first text still fits in its instance buffer, the second needs a bigger one
+    let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+        renderer
+            .generate_char_textures_with_encoder("0123456789".chars(), font, &device, encoder)
+            .unwrap();
+        texts[0]
+            .set_text_with_encoder("Queued 42".into(), &device, encoder, &mut renderer)
+            .unwrap();
+        texts[1]
+            .set_text_with_encoder(
+                "Much longer text with QXZ 789".into(),
+                &device,
+                encoder,
+                &mut renderer,
+            )
+            .unwrap();
+
+        let mut pass = begin_white_pass(encoder, view);
+        for text in &texts {
+            renderer.draw_text(&mut pass, text).unwrap();
+        }
+    });
+
+    let cpu_image = draw_cpu(&renderer, &texts);
+
+    let diff = compare_images(&cpu_image, &gpu_image, TOLERANCE).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the GPU output differs by up to {}",
+        diff.max_difference
+    );
+}
+
+#[test]
+fn double_buffered_text_matches_cpu_renderer() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 24.);
+
+    let mut text = TextBuilder::new("Frame 0", font, [10., 50.])
+        .pixel_snap(true)
+        .double_buffered(true)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let check_frame = |renderer: &TextRenderer, text: &Text| {
+        let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            renderer.draw_text(&mut pass, text).unwrap();
+        });
+
+        let cpu_image = draw_cpu(renderer, [text]);
+
+        let diff = compare_images(&cpu_image, &gpu_image, TOLERANCE).unwrap();
+        assert_eq!(
+            diff.differing_pixels, 0,
+            "the GPU output differs by up to {}",
+            diff.max_difference
+        );
+    };
+
+    check_frame(&renderer, &text);
+
+    // Written to the spare buffer, then to the same one again since it hasn't been drawn
+    for s in ["Frame 1", "Frame 2", "Frame 3"] {
+        text.set_text(s.into(), &device, &queue, &mut renderer)
+            .unwrap();
+        check_frame(&renderer, &text);
+    }
+    text.set_text("Frame 4".into(), &device, &queue, &mut renderer)
+        .unwrap();
+    text.set_text("Frame 5".into(), &device, &queue, &mut renderer)
+        .unwrap();
+    check_frame(&renderer, &text);
+
+    // Growing replaces both buffers, and the spare has to be big enough afterwards
+    text.set_text("A much longer frame".into(), &device, &queue, &mut renderer)
+        .unwrap();
+    check_frame(&renderer, &text);
+    text.set_text("A much shorter one".into(), &device, &queue, &mut renderer)
+        .unwrap();
+    check_frame(&renderer, &text);
+}
+
+#[test]
+fn each_glyph_is_drawn_with_one_call() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 24.);
+
+    let mut text = TextBuilder::new("aaaab abab\nbbaab cabbage", font, [10., 30.])
+        .pixel_snap(true)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let check = |renderer: &TextRenderer, text: &Text, source: &str| {
+        renderer.reset_frame_stats();
+        let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            renderer.draw_text(&mut pass, text).unwrap();
+        });
+
+        // One draw per glyph, however many times it comes up
+        let glyphs = (source.chars())
+            .filter(|c| !c.is_whitespace())
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(renderer.frame_stats().draw_calls, glyphs.len() as u64);
+
+        let cpu_image = draw_cpu(renderer, [text]);
+
+        let diff = compare_images(&cpu_image, &gpu_image, TOLERANCE).unwrap();
+        assert_eq!(
+            diff.differing_pixels, 0,
+            "the GPU output differs by up to {}",
+            diff.max_difference
+        );
+    };
+
+    check(&renderer, &text, "aaaab abab\nbbaab cabbage");
+
+    // Sorting the new instances into the existing buffer, then into a bigger one
+    for source in ["baba cab", "abcabcabc cab\ncabbage baggage"] {
+        text.set_text(source.into(), &device, &queue, &mut renderer)
+            .unwrap();
+        check(&renderer, &text, source);
+    }
+}
+
+#[test]
+fn glyph_sorted_draws_match_cpu_renderer() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let raster = load_font(&mut renderer, 24.);
+    let sdf = load_sdf_font(&mut renderer, 24.);
+
+    let mut texts = vec![
+        TextBuilder::new("aaaaaab baaaaa", raster, [10., 30.])
+            .pixel_snap(true)
+            .build(&device, &queue, &mut renderer)
+            .unwrap(),
+        TextBuilder::new("abab cabbage", sdf, [10., 80.])
+            .outlined([0.8, 0.2, 0.2, 1.], 3.)
+            .pixel_snap(true)
+            .build(&device, &queue, &mut renderer)
+            .unwrap(),
+    ];
+
+    let check = |renderer: &TextRenderer, texts: &[Text], golden: &str| {
+        let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            for text in texts {
+                renderer.draw_text(&mut pass, text).unwrap();
+            }
+        });
+
+        let cpu_image = draw_cpu(renderer, texts);
+
+        // Against a golden image too, since the cpu renderer draws the same runs as the GPU
+        assert_matches_golden(&cpu_image, golden_path(golden), TOLERANCE);
+
+        let diff = compare_images(&cpu_image, &gpu_image, 8).unwrap();
+        assert_eq!(
+            diff.differing_pixels, 0,
+            "the GPU output differs by up to {}",
+            diff.max_difference
+        );
+    };
+
+    check(&renderer, &texts, "glyph_sorted_draws");
+
+    // The instances are sorted again whenever they're recreated
+    texts[0]
+        .set_text("baggage bab".into(), &device, &queue, &mut renderer)
+        .unwrap();
+    texts[1].set_pixel_snap(false, &queue, &renderer).unwrap();
+    check(&renderer, &texts, "glyph_sorted_draws_changed");
+}
+
+#[test]
+fn memory_usage_tracks_textures_and_buffers() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 16.);
+
+    // Just the screen uniforms of the renderer's target
+    let empty = renderer.memory_usage();
+    assert_eq!(empty.glyph_textures, 0);
+    assert_eq!(empty.instance_buffers, 0);
+    assert!(empty.uniform_buffers > 0);
+
+    let mut text = TextBuilder::new("Hello", font, [10., 30.])
+        .double_buffered(true)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let report = renderer.memory_usage();
+    assert!(report.glyph_textures > 0);
+    assert!(report.instance_buffers > 0);
+    assert!(report.uniform_buffers > empty.uniform_buffers);
+    assert_eq!(
+        report.total(),
+        report.glyph_textures + report.instance_buffers + report.uniform_buffers
+    );
+
+    // Growing the text replaces both of its instance buffers with bigger ones (the space has no
+    // instance)
+    text.set_text("Hello, world!".into(), &device, &queue, &mut renderer)
+        .unwrap();
+    let grown = renderer.memory_usage();
+    assert!(grown.glyph_textures > report.glyph_textures);
+    assert_eq!(grown.instance_buffers, report.instance_buffers * 12 / 5);
+
+    // Dropped texts and cleared caches stop being counted
+    drop(text);
+    renderer.clear_cache(font).unwrap();
+    assert_eq!(renderer.memory_usage(), empty);
+}
+
+#[test]
+fn text_layout_matches_built_text() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 24.);
+
+    let builder = TextBuilder::new("Laid out\nin advance", font, [160., 60.])
+        .horizontal_align(HorizontalAlignment::Center)
+        .vertical_align(VerticalAlignment::Middle)
+        .clone();
+    let layout = builder.layout(&device, &queue, &mut renderer).unwrap();
+
+    // The space has nothing to draw, so it has no glyph
+    let lines: Vec<_> = layout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(layout.glyphs().len(), 16);
+    assert_eq!(lines[0].glyphs, 0..7);
+    assert_eq!(lines[1].glyphs, 7..16);
+
+    // Lines are centred on the position and stacked from top to bottom
+    for line in &lines {
+        assert!((line.rect.min[0] + line.rect.max[0]).abs() < 0.01);
+        assert!(line.rect.min[1] < line.baseline && line.baseline < line.rect.max[1]);
+    }
+    assert!(lines[0].rect.max[1] <= lines[1].rect.min[1]);
+
+    let bounds = layout.bounds();
+    assert_eq!(bounds, lines[0].rect.union(lines[1].rect));
+    for glyph in layout.glyphs() {
+        let centre = [0, 1].map(|i| (glyph.rect.min[i] + glyph.rect.max[i]) / 2.);
+        assert!(bounds.contains(centre), "{glyph:?} is outside {bounds:?}");
+    }
+
+    // Building the layout gives the same text as building it straight away
+    let draw = |renderer: &TextRenderer, text: &Text| draw_cpu(renderer, [text]);
+
+    let text = builder.build(&device, &queue, &mut renderer).unwrap();
+    let from_layout = layout.build(&device, &queue, &mut renderer).unwrap();
+    assert_eq!(draw(&renderer, &from_layout), draw(&renderer, &text));
+
+    // A layout made at another scale factor is laid out again
+    renderer.set_scale_factor(2.);
+    let text = builder.build(&device, &queue, &mut renderer).unwrap();
+    let from_layout = layout.build(&device, &queue, &mut renderer).unwrap();
+    assert_eq!(draw(&renderer, &from_layout), draw(&renderer, &text));
+}
+
+#[test]
+fn substitutions_draw_characters_as_others() {
+    use std::collections::HashMap;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer_builder().with_layout_cache(4).build(&device);
+    let font = load_font(&mut renderer, 24.);
+    assert!(renderer.has_glyph(font, '|'));
+
+    let substitutions = HashMap::from([('"', '“'), ('\'', '’'), ('|', '•'), ('x', '\u{e000}')]);
+    let builder = TextBuilder::new("\"Don't\" | x", font, [10., 30.]);
+    let draw = |renderer: &TextRenderer, text: &Text| draw_cpu(renderer, [text]);
+    let plain = builder.build(&device, &queue, &mut renderer).unwrap();
+
+    renderer.set_substitutions(font, substitutions).unwrap();
+    assert!(!renderer.has_glyph(font, 'x'));
+    let substituted = builder.build(&device, &queue, &mut renderer).unwrap();
+    let expected = (builder.clone())
+        .text("“Don’t“ • \u{e000}".into())
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    // The glyphs are the substitutes, but still at the indices of the characters they replace
+    let characters = |text: &Text| text.glyphs().map(|g| g.character).collect::<String>();
+    assert_eq!(characters(&substituted), characters(&expected));
+    assert_eq!(
+        substituted.glyphs().map(|g| g.index).collect::<Vec<_>>(),
+        plain.glyphs().map(|g| g.index).collect::<Vec<_>>()
+    );
+    assert_eq!(substituted.bounds(), expected.bounds());
+    assert_eq!(draw(&renderer, &substituted), draw(&renderer, &expected));
+
+    // The substitutions can be taken away again, even though the layout was cached
+    renderer.set_substitutions(font, HashMap::new()).unwrap();
+    let unsubstituted = builder.build(&device, &queue, &mut renderer).unwrap();
+    assert!(renderer.has_glyph(font, 'x'));
+    assert_eq!(draw(&renderer, &unsubstituted), draw(&renderer, &plain));
+}
+
+#[test]
+fn registered_glyphs_are_drawn_in_text() {
+    use image::GrayImage;
+    use kaku::GlyphMetrics;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let raster = load_font(&mut renderer, 24.);
+    let sdf = load_sdf_font(&mut renderer, 32.);
+
+    // A solid square sitting on the baseline, with 2 pixels of space either side
+    let icon = GrayImage::from_pixel(12, 12, image::Luma([255]));
+    let metrics = GlyphMetrics {
+        offset: [2., -12.],
+        advance: 16.,
+    };
+    assert_eq!(GlyphMetrics::on_baseline(&icon).offset, [0., -12.]);
+
+    // 'x' is cached before it's registered, and is drawn as the icon afterwards anyway
+    TextBuilder::new("ax", raster, [10., 30.])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+    assert!(!renderer.has_glyph(raster, '\u{e000}'));
+    for c in ['\u{e000}', 'x'] {
+        for font in [raster, sdf] {
+            renderer
+                .register_glyph(font, c, icon.clone(), metrics)
+                .unwrap();
+        }
+    }
+    assert!(renderer.has_glyph(raster, '\u{e000}'));
+
+    let build = |renderer: &mut TextRenderer, text: &str| {
+        [(raster, [10., 30.]), (sdf, [10., 80.])].map(|(font, position)| {
+            TextBuilder::new(text, font, position)
+                .build(&device, &queue, renderer)
+                .unwrap()
+        })
+    };
+    let texts = build(&mut renderer, "a\u{e000}x");
+
+    // The icon's ink is where its metrics put it, and it moves the next character along
+    let line = texts[0].lines().next().unwrap();
+    let icon_glyph = texts[0].glyphs().nth(1).unwrap();
+    assert_eq!(icon_glyph.character, '\u{e000}');
+    assert_eq!(icon_glyph.ink.width(), 12.);
+    assert_eq!(icon_glyph.ink.max[1], line.baseline);
+    let x_glyph = texts[0].glyphs().nth(2).unwrap();
+    assert_eq!(x_glyph.ink.min[0] - icon_glyph.ink.min[0], 16.);
+    let first = build(&mut renderer, "\u{e000}");
+    assert_eq!(first[0].glyphs().next().unwrap().ink.min[0], 10. + 2.);
+
+    // It's drawn in the text's colour
+    let cpu_image = draw_cpu(&renderer, &texts);
+    let [x, y] = [icon_glyph.ink.min[0] + 6., icon_glyph.ink.min[1] + 6.];
+    assert_eq!(cpu_image.get_pixel(x as u32, y as u32).0, [0, 0, 0, 255]);
+
+    let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        for text in &texts {
+            renderer.draw_text(&mut pass, text).unwrap();
+        }
+    });
+
+    // The GPU filters the distance field slightly differently to the cpu renderer
+    let diff = compare_images(&cpu_image, &gpu_image, 8).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the GPU output differs by up to {}",
+        diff.max_difference
+    );
+
+    // On a 2x screen the icon is scaled up with the rest of the text
+    renderer.set_scale_factor(2.);
+    let scaled = build(&mut renderer, "a\u{e000}x");
+    let scaled_glyph = scaled[0].glyphs().nth(1).unwrap();
+    assert_eq!(scaled_glyph.ink.width(), 12.);
+    assert_eq!(scaled[0].bounds(), texts[0].bounds());
+}
+
+#[cfg(feature = "measure")]
+#[test]
+fn measured_layout_matches_renderer() {
+    use kaku::{FontOptions, MissingGlyphs, TextMeasurer};
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let mut measurer = TextMeasurer::new();
+    renderer.set_scale_factor(1.5);
+    measurer.set_scale_factor(1.5);
+
+    // Loaded in the same order, so the ids match
+    let options = [
+        FontOptions::new(FontSize::Px(18.)),
+        FontOptions::new(FontSize::Px(18.)).with_sdf(SdfSettings::default()),
+        FontOptions::new(FontSize::Px(18.)).with_missing_glyphs(MissingGlyphs::HexBox),
+    ];
+
+    for options in options {
+        let font = FontRef::try_from_slice(FONT).unwrap();
+        let font = renderer
+            .load_font_with_options(font, options.clone(), &device, &queue)
+            .unwrap();
+        let measured_font = measurer
+            .load_font_with_options(FontRef::try_from_slice(FONT).unwrap(), options)
+            .unwrap();
+        assert_eq!(font, measured_font);
+
+        let builder = TextBuilder::new(
+            "A wrapped\tparagraph,\nmeasured ☃ on a server",
+            font,
+            [0., 0.],
+        )
+        .horizontal_align(HorizontalAlignment::Right)
+        .scale(1.25)
+        .clone();
+
+        let measured = measurer.layout(&builder).unwrap();
+        let laid_out = builder.layout(&device, &queue, &mut renderer).unwrap();
+
+        assert_eq!(measured.lines().collect::<Vec<_>>().len(), 2);
+        assert_eq!(measured.glyphs().len(), 0);
+        assert_eq!(measured.bounds(), laid_out.bounds());
+        for (measured, laid_out) in measured.lines().zip(laid_out.lines()) {
+            assert_eq!(measured.rect, laid_out.rect);
+            assert_eq!(measured.baseline, laid_out.baseline);
+        }
+
+        // Building a measured layout lays it out again with the renderer's glyphs
+        let draw = |renderer: &TextRenderer, text: &Text| draw_cpu(renderer, [text]);
+        let text = builder.build(&device, &queue, &mut renderer).unwrap();
+        let from_measured = measured.build(&device, &queue, &mut renderer).unwrap();
+        assert_eq!(draw(&renderer, &from_measured), draw(&renderer, &text));
+    }
+}
+
+#[test]
+fn text_bounds_contain_drawn_text() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_sdf_font(&mut renderer, 24.);
+
+    let builder = TextBuilder::new("Hover over me\nfor a tooltip", font, [160., 60.])
+        .horizontal_align(HorizontalAlignment::Center)
+        .vertical_align(VerticalAlignment::Middle)
+        .outlined([0.8, 0.2, 0.2, 1.], 3.)
+        .scale(1.2)
+        .clone();
+    let mut text = builder.build(&device, &queue, &mut renderer).unwrap();
+
+    // The layout's box, moved to the text's position and grown by the outline
+    let layout = builder.layout(&device, &queue, &mut renderer).unwrap();
+    let expected = layout.bounds().translate([160., 60.]);
+    let bounds = text.bounds();
+    for i in 0..2 {
+        assert!((bounds.min[i] - (expected.min[i] - 3.)).abs() < 0.01);
+        assert!((bounds.max[i] - (expected.max[i] + 3.)).abs() < 0.01);
+    }
+
+    let assert_drawn_inside = |renderer: &TextRenderer, text: &Text| {
+        let bounds = text.bounds();
+        let image = draw_cpu(renderer, [text]);
+
+        let mut drawn = 0;
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if *pixel != Rgba([255, 255, 255, 255]) {
+                drawn += 1;
+                // Antialiased edges can reach half a pixel further
+                let point = [x as f32 + 0.5, y as f32 + 0.5];
+                let grown = kaku::Rect {
+                    min: bounds.min.map(|p| p - 1.),
+                    max: bounds.max.map(|p| p + 1.),
+                };
+                assert!(grown.contains(point), "({x}, {y}) is outside {bounds:?}");
+            }
+        }
+        assert!(drawn > 0);
+    };
+
+    assert_drawn_inside(&renderer, &text);
+
+    // Moving the text or changing it moves its bounds with it
+    text.set_position([150., 50.], &queue);
+    assert_eq!(text.bounds(), bounds.translate([-10., -10.]));
+    assert_drawn_inside(&renderer, &text);
+
+    text.set_text("Hover".into(), &device, &queue, &mut renderer)
+        .unwrap();
+    assert!(text.bounds().width() < bounds.width());
+    assert!(text.bounds().height() < bounds.height());
+    assert_drawn_inside(&renderer, &text);
+}
+
+#[test]
+fn ink_bounds_are_tight_around_drawn_glyphs() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let raster = load_font(&mut renderer, 40.);
+    let sdf = load_sdf_font(&mut renderer, 40.);
+
+    for font in [raster, sdf] {
+        let builder = TextBuilder::new("•", font, [100., 60.])
+            .horizontal_align(HorizontalAlignment::Center)
+            .vertical_align(VerticalAlignment::Middle)
+            .clone();
+        let text = builder.build(&device, &queue, &mut renderer).unwrap();
+
+        let bounds = text.bounds();
+        let ink = text.ink_bounds().unwrap();
+        let layout_ink = builder
+            .layout(&device, &queue, &mut renderer)
+            .unwrap()
+            .ink_bounds()
+            .unwrap();
+        assert_eq!(ink, layout_ink.translate([100., 60.]));
+
+        // A bullet's ink is much smaller than its line
+        assert!(ink.height() < bounds.height() * 0.5);
+        assert!(ink.width() < bounds.width());
+        assert!(bounds.contains(ink.min) && bounds.contains(ink.max));
+
+        let image = draw_cpu(&renderer, [&text]);
+        // The ink box is the box around the drawn pixels, give or take antialiasing
+        assert_close(drawn_bounds(&image).unwrap(), ink, 1.5);
+    }
+
+    let spaces = TextBuilder::new("   ", raster, [0., 0.])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+    assert_eq!(spaces.ink_bounds(), None);
+}
+
+#[test]
+fn line_metrics_match_layout() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 24.);
+
+    let builder = TextBuilder::new("Short\nA much longer line\nMiddling", font, [160., 80.])
+        .horizontal_align(HorizontalAlignment::Center)
+        .vertical_align(VerticalAlignment::Middle)
+        .clone();
+    let mut text = builder.build(&device, &queue, &mut renderer).unwrap();
+    let layout = builder.layout(&device, &queue, &mut renderer).unwrap();
+
+    assert_eq!(text.line_count(), 3);
+    for (line, expected) in text.lines().zip(layout.lines()) {
+        assert_eq!(line.rect, expected.rect.translate([160., 80.]));
+        assert_eq!(line.baseline, expected.baseline + 80.);
+        assert_eq!(line.glyphs, expected.glyphs);
+        assert!((line.ascent() + line.descent() - line.rect.height()).abs() < 0.001);
+    }
+
+    let lines: Vec<_> = text.lines().collect();
+    assert!(lines[0].width() < lines[2].width() && lines[2].width() < lines[1].width());
+    // Every line is centred on the text's position, and they're evenly spaced
+    for line in &lines {
+        assert!((line.rect.min[0] + line.rect.max[0] - 320.).abs() < 0.001);
+        assert!((line.ascent() - lines[0].ascent()).abs() < 0.001);
+        assert!((line.descent() - lines[0].descent()).abs() < 0.001);
+    }
+    // Highlights are as tall as their line and as wide as the widest one
+    for (i, line) in lines.iter().enumerate() {
+        let highlight = text.line_highlight_rect(i).unwrap();
+        assert_eq!(
+            [highlight.min[1], highlight.max[1]],
+            [line.rect.min[1], line.rect.max[1]]
+        );
+        assert_eq!(
+            [highlight.min[0], highlight.max[0]],
+            [lines[1].rect.min[0], lines[1].rect.max[0]]
+        );
+        let expected = layout
+            .line_highlight_rect(i)
+            .unwrap()
+            .translate([160., 80.]);
+        assert!((highlight.width() - expected.width()).abs() < 0.001);
+    }
+    assert_eq!(text.line_highlight_rect(3), None);
+
+    let spacing = lines[1].baseline - lines[0].baseline;
+    assert!(spacing > lines[0].rect.height() - 0.001);
+    assert!((lines[2].baseline - lines[1].baseline - spacing).abs() < 0.001);
+
+    // The lines move with the text
+    text.set_position([100., 100.], &queue);
+    let moved = text.lines().next().unwrap().rect;
+    let expected = lines[0].rect.translate([-60., 20.]);
+    for i in 0..2 {
+        assert!((moved.min[i] - expected.min[i]).abs() < 0.001);
+        assert!((moved.max[i] - expected.max[i]).abs() < 0.001);
+    }
+
+    text.set_text("One line".into(), &device, &queue, &mut renderer)
+        .unwrap();
+    assert_eq!(text.line_count(), 1);
+}
+
+#[test]
+fn line_highlights_cover_every_line_at_the_same_width() {
+    let Some(mut h) = Harness::new() else {
+        return;
+    };
+
+    let font = h.font(24.);
+    let mut text = h.build(
+        TextBuilder::new("Short\nA much longer line\nMiddling", font, [300., 30.])
+            .horizontal_align(HorizontalAlignment::Right)
+            .scale(1.2),
+    );
+    let image = h.draw([&text]);
+
+    let highlights: Vec<Rect> = (0..3)
+        .map(|line| text.line_highlight_rect(line).unwrap())
+        .collect();
+    let glyphs: Vec<_> = text.glyphs().collect();
+    let bounds = drawn_bounds(&image).unwrap();
+    for (line, highlight) in text.lines().zip(&highlights) {
+        // Each line's glyphs are drawn inside its highlight (give or take their bearings), which
+        // spans all of the text
+        for glyph in &glyphs[line.glyphs] {
+            let Rect { min, max } = glyph.ink;
+            assert!(min[0] > highlight.min[0] - 1. && max[0] < highlight.max[0] + 1.);
+            assert!(min[1] > highlight.min[1] - 1. && max[1] < highlight.max[1] + 1.);
+        }
+        let [left, right] = [highlight.min[0], highlight.max[0]];
+        assert!((left - bounds.min[0]).abs() <= 1. && (right - bounds.max[0]).abs() <= 1.);
+    }
+
+    // The highlights stack on top of each other with no gaps between them
+    for pair in highlights.windows(2) {
+        assert_eq!(
+            [pair[0].min[0], pair[0].max[0]],
+            [pair[1].min[0], pair[1].max[0]]
+        );
+        assert!((pair[0].max[1] - pair[1].min[1]).abs() < 0.001);
+    }
+
+    // They move with the text
+    text.set_position([260., 30.], &h.queue);
+    let moved = text.line_highlight_rect(1).unwrap();
+    assert_close(moved, highlights[1].translate([-40., 0.]), 0.001);
+}
+
+#[test]
+fn glyphs_are_where_they_are_drawn() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 24.);
+
+    let string = "né\tx\n→ ü";
+    let builder = TextBuilder::new(string, font, [160., 60.])
+        .horizontal_align(HorizontalAlignment::Center)
+        .clone();
+    let text = builder.build(&device, &queue, &mut renderer).unwrap();
+    let layout = builder.layout(&device, &queue, &mut renderer).unwrap();
+
+    // Spaces and the tab have nothing to draw, and the indices are byte indices
+    let glyphs: Vec<_> = text.glyphs().collect();
+    let found: Vec<_> = glyphs.iter().map(|g| (g.index, g.character)).collect();
+    assert_eq!(found, [(0, 'n'), (1, 'é'), (4, 'x'), (6, '→'), (10, 'ü')]);
+    for glyph in &glyphs {
+        assert!(string[glyph.index..].starts_with(glyph.character));
+    }
+
+    // They're the layout's glyphs at the text's position, and the lines point at them
+    for (glyph, expected) in glyphs.iter().zip(layout.glyphs()) {
+        assert_eq!(glyph.rect, expected.rect.translate([160., 60.]));
+        assert_eq!(glyph.ink, expected.ink.translate([160., 60.]));
+    }
+    let lines: Vec<_> = text.lines().map(|line| line.glyphs).collect();
+    assert_eq!(lines, [0..3, 3..5]);
+
+    // Everything drawn is inside one of the glyphs' quads
+    let image = draw_cpu(&renderer, [&text]);
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if *pixel != Rgba([255, 255, 255, 255]) {
+            let point = [x as f32 + 0.5, y as f32 + 0.5];
+            assert!(glyphs.iter().any(|g| g.rect.contains(point)), "({x}, {y})");
+        }
+    }
+}
+
+#[test]
+fn caret_rects_follow_the_text() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    renderer.set_scale_factor(2.);
+    let font = load_font(&mut renderer, 24.);
+
+    let string = "ab\tc\nAVé";
+    let builder = TextBuilder::new(string, font, [160., 60.])
+        .horizontal_align(HorizontalAlignment::Center)
+        .vertical_align(VerticalAlignment::Middle)
+        .scale(1.5)
+        .clone();
+    let text = builder.build(&device, &queue, &mut renderer).unwrap();
+    let layout = builder.layout(&device, &queue, &mut renderer).unwrap();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines[0].text, 0..4);
+    assert_eq!(lines[1].text, 5..9);
+
+    let caret = |i| text.caret_rect(i).unwrap();
+    let near = |a: f32, b: f32| (a - b).abs() < 0.01;
+
+    for i in 0..=string.len() {
+        // The same as the layout's, at the text's position
+        let expected = layout.caret_rect(i).translate([160., 60.]);
+        assert!(near(caret(i).min[0], expected.min[0]) && near(caret(i).min[1], expected.min[1]));
+
+        // Carets have no width and are as tall as their line
+        let line = if i <= 4 { &lines[0] } else { &lines[1] };
+        assert_eq!(caret(i).width(), 0.);
+        assert!(near(caret(i).min[1], line.rect.min[1]) && near(caret(i).max[1], line.rect.max[1]));
+    }
+
+    // Lines start and end at their carets, and the line break goes at the end of its line
+    assert!(near(caret(0).min[0], lines[0].rect.min[0]));
+    assert!(near(caret(4).min[0], lines[0].rect.max[0]));
+    assert!(near(caret(5).min[0], lines[1].rect.min[0]));
+    assert!(near(caret(9).min[0], lines[1].rect.max[0]));
+    assert_eq!(caret(100), caret(9));
+
+    // Characters are in order, and a tab is as wide as several characters
+    for i in 0..4 {
+        assert!(caret(i).min[0] < caret(i + 1).min[0]);
+    }
+    assert!(caret(3).min[0] - caret(2).min[0] > 1.5 * (caret(2).min[0] - caret(1).min[0]));
+
+    // The middle of "é" goes before the next character, which is the end of the line
+    assert_eq!(caret(8), caret(9));
+    assert!(caret(7).min[0] < caret(8).min[0]);
+
+    // Each drawn character starts near its caret
+    for glyph in text.glyphs() {
+        assert!((glyph.rect.min[0] - caret(glyph.index).min[0]).abs() < 6.);
+    }
+}
+
+#[test]
+fn windowed_text_matches_whole_document() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 20.);
+
+    let document: String = (0..2000)
+        .map(|i| format!("Log line number {i}\n"))
+        .collect();
+    let builder = TextBuilder::new(document.clone(), font, [10., 0.])
+        .vertical_align(VerticalAlignment::Top)
+        .clone();
+    let mut windowed = builder
+        .build_windowed(SIZE.1 as f32, &device, &queue, &mut renderer)
+        .unwrap();
+
+    assert_eq!(windowed.line_count(), 2001);
+    assert_eq!(windowed.visible_lines().start, 0);
+    assert!(windowed.visible_lines().len() < 10);
+    assert!(windowed.text().glyphs().len() < 200);
+
+    for scroll in [0., 1234.5, 30000.] {
+        windowed
+            .set_scroll(scroll, &device, &queue, &mut renderer)
+            .unwrap();
+        let visible = windowed.visible_bytes();
+        assert!(document[visible].starts_with("Log line number"));
+
+        // The window draws the same as the whole document scrolled to the same place
+        let mut expected = background();
+        let whole = builder
+            .clone()
+            .position([10., -scroll])
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+        renderer.draw_text_to_image(&whole, &mut expected).unwrap();
+
+        let mut image = background();
+        renderer
+            .draw_text_to_image(windowed.text(), &mut image)
+            .unwrap();
+        let diff = compare_images(&expected, &image, TOLERANCE).unwrap();
+        assert_eq!(
+            diff.differing_pixels, 0,
+            "the window scrolled by {scroll} differs by up to {}",
+            diff.max_difference
+        );
+    }
+
+    // Scrolling within the same lines only moves the text
+    let lines = windowed.visible_lines();
+    let bytes = windowed.visible_bytes();
+    let spacing = windowed.line_spacing();
+    windowed
+        .set_scroll(
+            windowed.scroll() + spacing * 0.01,
+            &device,
+            &queue,
+            &mut renderer,
+        )
+        .unwrap();
+    assert!(windowed.visible_lines() == lines || windowed.visible_lines().end == lines.end + 1);
+    assert_eq!(windowed.visible_bytes().start, bytes.start);
+
+    // Past the end there's nothing to draw
+    windowed
+        .set_scroll(
+            windowed.content_height() + 10.,
+            &device,
+            &queue,
+            &mut renderer,
+        )
+        .unwrap();
+    assert!(windowed.visible_lines().is_empty());
+    assert_eq!(windowed.text().glyphs().len(), 0);
+}
+
+#[test]
+fn wrapping_moves_words_onto_new_lines() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 20.);
+
+    let string = "The quick brown fox jumps over the lazy dog";
+    let mut builder = TextBuilder::new(string, font, [160., 10.])
+        .horizontal_align(HorizontalAlignment::Center)
+        .vertical_align(VerticalAlignment::Top)
+        .max_width(Some(150.))
+        .clone();
+    let mut text = builder.build(&device, &queue, &mut renderer).unwrap();
+
+    let lines: Vec<_> = text.lines().collect();
+    assert!(lines.len() > 1);
+    for line in &lines {
+        // Wrapped lines end with the space they were wrapped at
+        let words = string[line.text.clone()].trim_end();
+        assert!(!words.is_empty() && !words.starts_with(' '));
+
+        let glyphs: Vec<_> = text
+            .glyphs()
+            .skip(line.glyphs.start)
+            .take(line.glyphs.len())
+            .collect();
+        let ink = glyphs
+            .iter()
+            .map(|g| g.rect)
+            .reduce(|a, b| a.union(b))
+            .unwrap();
+        assert!(
+            ink.width() <= 150. + 2.,
+            "{words:?} is {} wide",
+            ink.width()
+        );
+    }
+    let rejoined: String = lines.iter().map(|l| &string[l.text.clone()]).collect();
+    assert_eq!(rejoined, string);
+
+    // A caret where a line was wrapped goes at the start of the next line
+    let wrap = lines[1].text.start;
+    let caret = text.caret_rect(wrap).unwrap();
+    assert_eq!(caret.min[1], lines[1].rect.min[1]);
+    assert!((caret.min[0] - lines[1].rect.min[0]).abs() < 0.01);
+
+    // Changing the width matches building the text with it
+    for max_width in [Some(90.), Some(300.), None] {
+        text.set_max_width(max_width, &queue);
+        let built = builder
+            .max_width(max_width)
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+
+        let lines = |text: &Text| text.lines().map(|l| l.text).collect::<Vec<_>>();
+        assert_eq!(lines(&text), lines(&built));
+
+        let expected = draw_cpu(&renderer, [&built]);
+        let image = draw_cpu(&renderer, [&text]);
+        assert!(image == expected, "wrapping at {max_width:?} differs");
+    }
+    assert_eq!(text.line_count(), 1);
+
+    // Words wider than the width get a line to themselves
+    text.set_max_width(Some(1.), &queue);
+    assert_eq!(text.line_count(), 9);
+}
+
+#[test]
+fn cached_layouts_are_reused() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut uncached = renderer(&device);
+    let mut renderer = renderer_builder().with_layout_cache(2).build(&device);
+    let font = load_font(&mut renderer, 24.);
+    load_font(&mut uncached, 24.);
+
+    let builder = TextBuilder::new("Cached text", font, [10., 40.]);
+    builder.build(&device, &queue, &mut renderer).unwrap();
+    assert_eq!(renderer.cached_layout_count(), 1);
+
+    // Moving or recolouring the text reuses its layout, but is still drawn where it's asked
+    let moved = builder
+        .clone()
+        .position([30., 80.])
+        .color([1., 0., 0., 1.])
+        .clone();
+    let text = moved.build(&device, &queue, &mut renderer).unwrap();
+    assert_eq!(renderer.cached_layout_count(), 1);
+
+    let expected_text = moved.build(&device, &queue, &mut uncached).unwrap();
+    let expected = draw_cpu(&uncached, [&expected_text]);
+    let image = draw_cpu(&renderer, [&text]);
+    let diff = compare_images(&expected, &image, 0).unwrap();
+    assert_eq!(diff.differing_pixels, 0);
+
+    // The oldest layout is forgotten when the cache is full
+    for string in ["Second", "Third"] {
+        (builder.clone().text(string.to_string()))
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+    }
+    assert_eq!(renderer.cached_layout_count(), 2);
+
+    // Wrapping differently is a different layout
+    (builder
+        .clone()
+        .text("Third".to_string())
+        .max_width(Some(20.)))
+    .build(&device, &queue, &mut renderer)
+    .unwrap();
+    assert_eq!(renderer.cached_layout_count(), 2);
+
+    renderer.clear_cache(font).unwrap();
+    assert_eq!(renderer.cached_layout_count(), 0);
+}
+
+#[test]
+fn tabs_line_up_in_columns() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 20.);
+
+    let mut x_of_last_glyph = |string: &str, tabs| {
+        let text = TextBuilder::new(string, font, [10., 30.])
+            .tabs(tabs)
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+        text.glyphs().last().unwrap().rect.min[0]
+    };
+    let near = |a: f32, b: f32| (a - b).abs() < 0.01;
+
+    // Text after a tab starts at the next column, however far into the column the tab is
+    let column = x_of_last_glyph("\tX", Tabs::Spaces(8));
+    for string in ["\tX", "a\tX", "ab\tX", "a b\tX"] {
+        assert!(near(x_of_last_glyph(string, Tabs::Columns(8)), column));
+    }
+    assert!(near(
+        x_of_last_glyph("\t\tX", Tabs::Columns(8)),
+        x_of_last_glyph("\tX", Tabs::Spaces(16)),
+    ));
+
+    // Fixed width tabs don't line up
+    assert!(x_of_last_glyph("ab\tX", Tabs::Spaces(8)) > column + 1.);
+
+    // The columns are the same on every line
+    let text = TextBuilder::new("a\tX\nabc\tX", font, [10., 30.])
+        .tabs(Tabs::Columns(8))
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+    let xs: Vec<_> = text
+        .glyphs()
+        .filter(|g| g.character == 'X')
+        .map(|g| g.rect.min[0])
+        .collect();
+    assert!(near(xs[0], xs[1]));
+    assert!(near(
+        text.caret_rect(2).unwrap().min[0],
+        text.caret_rect(8).unwrap().min[0]
+    ));
+}
+
+#[test]
+fn preedit_segments_are_underlined() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    renderer.set_scale_factor(2.);
+    let font = load_font(&mut renderer, 20.);
+
+    let string = "henkan chuu no tekisuto";
+    let mut text = TextBuilder::new(string, font, [20., 30.])
+        .max_width(Some(120.))
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    let near = |a: f32, b: f32| (a - b).abs() < 0.01;
+
+    // Ranges over a wrap cover the end of one line and the start of the next
+    let range = 3..17;
+    let rects = text.range_rects(range.clone());
+    assert_eq!(rects.len(), 2);
+    assert!(near(rects[0].min[0], text.caret_rect(3).unwrap().min[0]));
+    assert!(near(rects[0].max[0], lines[0].rect.max[0]));
+    assert!(near(rects[1].min[0], lines[1].rect.min[0]));
+    assert!(near(rects[1].max[0], text.caret_rect(17).unwrap().min[0]));
+    assert_eq!(rects[1].min[1], lines[1].rect.min[1]);
+    assert!(text.range_rects(4..4).is_empty());
+
+    text.set_preedit(vec![
+        PreeditSegment::new(0..6, UnderlineStyle::Thick),
+        PreeditSegment::new(7..14, UnderlineStyle::Dashed),
+        PreeditSegment::new(range, UnderlineStyle::Solid),
+    ]);
+    let underlines = text.preedit_underlines();
+
+    // Thick underlines are twice as thick as thin ones, and dashed ones are split into dashes
+    let of = |segment| underlines.iter().filter(move |u| u.segment == segment);
+    let thick: Vec<_> = of(0).collect();
+    assert_eq!(thick.len(), 1);
+    assert!(near(
+        thick[0].rect.height(),
+        of(2).next().unwrap().rect.height() * 2.
+    ));
+    assert!(of(1).count() > 2);
+    assert_eq!(of(2).count(), 2);
+
+    // Underlines are under their segment, below the baseline but inside the line
+    for underline in &underlines {
+        let segment = text.preedit_rect(underline.segment).unwrap();
+        let line = lines
+            .iter()
+            .find(|line| {
+                line.rect.min[1] <= underline.rect.min[1]
+                    && underline.rect.max[1] <= line.rect.max[1]
+            })
+            .unwrap();
+        assert!(underline.rect.min[1] > line.baseline);
+        assert!(segment.min[0] < underline.rect.min[0] && underline.rect.max[0] < segment.max[0]);
+        assert!(underline.rect.width() > 0.);
+    }
+
+    // The candidate window goes by the whole segment
+    assert_eq!(text.preedit_rect(0), Some(text.range_rects(0..6)[0]));
+    assert!(text.preedit_rect(2).unwrap().height() > lines[0].rect.height() * 1.5);
+    assert_eq!(text.preedit_rect(3), None);
+}
+
+#[test]
+fn block_alignment_aligns_every_line() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 20.);
+    let near = |a: f32, b: f32| (a - b).abs() < 0.01;
+
+    let mut builder = TextBuilder::new("One\nTwo\nThree", font, [160., 60.])
+        .block_align(true)
+        .clone();
+    let mut bounds = |valign| {
+        builder
+            .vertical_align(valign)
+            .build(&device, &queue, &mut renderer)
+            .unwrap()
+            .bounds()
+    };
+
+    let middle = bounds(VerticalAlignment::Middle);
+    assert!(near((middle.min[1] + middle.max[1]) / 2., 60.));
+    assert!(near(bounds(VerticalAlignment::Bottom).max[1], 60.));
+    assert!(near(bounds(VerticalAlignment::Top).min[1], 60.));
+    assert_eq!(bounds(VerticalAlignment::Ratio(0.5)), middle);
+
+    // A single line is aligned the same either way
+    let mut single = builder.text("One".to_string()).clone();
+    for valign in [VerticalAlignment::Middle, VerticalAlignment::Bottom] {
+        let block = single
+            .vertical_align(valign)
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+        let line = single
+            .block_align(false)
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+        single.block_align(true);
+
+        assert!(near(block.bounds().min[1], line.bounds().min[1]));
+    }
+}
+
+#[test]
+fn lines_can_have_their_own_alignment() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 20.);
+    let near = |a: f32, b: f32| (a - b).abs() < 0.01;
+
+    let text = TextBuilder::new("Title\nA longer body line\nEnd", font, [20., 30.])
+        .line_align(0, Some(HorizontalAlignment::Center))
+        .line_align(2, Some(HorizontalAlignment::Right))
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+    let lines: Vec<_> = text.lines().collect();
+
+    // The body is left aligned at the position, and the other lines are aligned over it
+    let body = lines[1].rect;
+    assert!(near(body.min[0], 20.));
+    let centre = |rect: Rect| (rect.min[0] + rect.max[0]) / 2.;
+    assert!(near(centre(lines[0].rect), centre(body)));
+    assert!(near(lines[2].rect.max[0], body.max[0]));
+
+    // Glyphs and carets move with their lines
+    let title_glyph = text.glyphs().next().unwrap();
+    assert!(title_glyph.rect.min[0] >= lines[0].rect.min[0] - 2.);
+    assert!(near(
+        text.caret_rect(0).unwrap().min[0],
+        lines[0].rect.min[0]
+    ));
+    assert!(near(text.caret_rect(100).unwrap().min[0], body.max[0]));
+
+    // Without any overrides, it's laid out as usual
+    let plain = TextBuilder::new("Title\nA longer body line\nEnd", font, [20., 30.])
+        .line_align(0, Some(HorizontalAlignment::Center))
+        .line_align(0, None)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+    assert!(plain.lines().all(|line| near(line.rect.min[0], 20.)));
+}
+
+#[test]
+fn paragraphs_are_indented_and_spaced() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 16.);
+    let near = |a: f32, b: f32| (a - b).abs() < 0.01;
+
+    let string = "A paragraph that wraps onto more lines\nand a line break\n\nNext paragraph";
+    let mut builder = TextBuilder::new(string, font, [20., 20.])
+        .max_width(Some(150.))
+        .clone();
+    let plain = builder.build(&device, &queue, &mut renderer).unwrap();
+    let text = builder
+        .indent(30., 10.)
+        .paragraph_spacing(5., 7.)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let lines: Vec<_> = text.lines().collect();
+    let starts: Vec<_> = lines.iter().map(|l| l.text.start).collect();
+    let x = |i| text.caret_rect(i).unwrap().min[0];
+    let second_paragraph = string.find("Next").unwrap();
+    let line_break = string.find("and").unwrap();
+
+    // The first line of each paragraph has the first line indent, and the rest hang
+    assert!(near(x(0), 50.));
+    assert!(near(x(second_paragraph), 50.));
+    assert!(near(x(line_break), 30.));
+    assert!(starts.len() > 4);
+    assert!(near(x(starts[1]), 30.));
+
+    // Lines still fit in the wrap width with their indent
+    for line in &lines {
+        assert!(line.rect.max[0] <= 20. + 150. + 0.01);
+        assert!(near(line.rect.min[0], 20.));
+    }
+
+    // Only the space between paragraphs grows
+    let plain_lines: Vec<_> = plain.lines().collect();
+    let n = lines.len();
+    let gap = |lines: &[LineBox], i: usize| lines[i].rect.min[1] - lines[i - 1].rect.max[1];
+    assert!(near(
+        gap(&lines, n - 1),
+        gap(&plain_lines, plain_lines.len() - 1) + 12.
+    ));
+    assert!(near(
+        gap(&lines, n - 2),
+        gap(&plain_lines, plain_lines.len() - 2)
+    ));
+    assert!(near(lines[0].rect.min[1], plain_lines[0].rect.min[1]));
+}
+
+#[test]
+fn anchors_put_a_point_of_the_box_at_the_position() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 20.);
+    let near = |a: f32, b: f32| (a - b).abs() < 0.01;
+
+    let anchors = [
+        (Anchor::TopLeft, [0., 0.]),
+        (Anchor::Top, [0.5, 0.]),
+        (Anchor::TopRight, [1., 0.]),
+        (Anchor::Left, [0., 0.5]),
+        (Anchor::Center, [0.5, 0.5]),
+        (Anchor::Right, [1., 0.5]),
+        (Anchor::BottomLeft, [0., 1.]),
+        (Anchor::Bottom, [0.5, 1.]),
+        (Anchor::BottomRight, [1., 1.]),
+    ];
+
+    for (anchor, [x, y]) in anchors {
+        let bounds = TextBuilder::new("Anchored\ntext", font, [160., 60.])
+            .anchor(anchor)
+            .build(&device, &queue, &mut renderer)
+            .unwrap()
+            .bounds();
+
+        let point = [
+            bounds.min[0] + bounds.width() * x,
+            bounds.min[1] + bounds.height() * y,
+        ];
+        assert!(near(point[0], 160.) && near(point[1], 60.), "{anchor:?}");
+    }
+}
+
+#[test]
+fn relative_positions_follow_the_target_size() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let raster = load_font(&mut renderer, 24.);
+    let sdf = load_sdf_font(&mut renderer, 24.);
+
+    // Half of SIZE, and a quarter of it plus 10 pixels
+    let relative = [
+        TextBuilder::new("Middle", raster, [0., 0.])
+            .relative_position([0.5, 0.5])
+            .build(&device, &queue, &mut renderer),
+        TextBuilder::new("Outlined", sdf, [10., 10.])
+            .relative_position([0.25, 0.25])
+            .outlined([0.8, 0.2, 0.2, 1.], 3.)
+            .build(&device, &queue, &mut renderer),
+    ]
+    .map(Result::unwrap);
+    let absolute = [
+        TextBuilder::new("Middle", raster, [160., 60.]).build(&device, &queue, &mut renderer),
+        TextBuilder::new("Outlined", sdf, [90., 40.])
+            .outlined([0.8, 0.2, 0.2, 1.], 3.)
+            .build(&device, &queue, &mut renderer),
+    ]
+    .map(Result::unwrap);
+
+    let cpu_image = draw_cpu(&renderer, &relative);
+    let diff = compare_images(&cpu_image, &draw_cpu(&renderer, &absolute), 0).unwrap();
+    assert_eq!(diff.differing_pixels, 0);
+
+    let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        for text in &relative {
+            renderer.draw_text(&mut pass, text).unwrap();
+        }
+    });
+
+    // See draw_text_for_target for why this is a bit off
+    let diff = compare_images(&cpu_image, &gpu_image, 8).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the GPU output differs by up to {}",
+        diff.max_difference
+    );
+
+    // The offset is left out of the text's bounds
+    let near = |a: f32, b: f32| (a - b).abs() < 0.01;
+    let bounds = [relative[0].bounds(), absolute[0].bounds()];
+    assert!(near(bounds[0].min[0] + 160., bounds[1].min[0]));
+}
+
+#[test]
+fn subpixel_phases_are_drawn_whole_pixels_apart() {
+    use kaku::FontOptions;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let plain = load_font(&mut renderer, 24.);
+    let phased = renderer
+        .load_font_with_options(
+            FontRef::try_from_slice(FONT).unwrap(),
+            FontOptions::new(FontSize::Px(24.)).with_subpixel_phases(4),
+            &device,
+            &queue,
+        )
+        .unwrap();
+
+    let mut draw = |text: &str, font, x: f32| {
+        let text = TextBuilder::new(text, font, [x, 60.])
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+        let image = draw_cpu(&renderer, [&text]);
+        (text, image)
+    };
+
+    // At a whole pixel, the first phase is the glyph as it is normally rasterised
+    let (_, plain_image) = draw("S", plain, 10.);
+    let (_, whole) = draw("S", phased, 10.);
+    assert_eq!(
+        compare_images(&plain_image, &whole, 0)
+            .unwrap()
+            .differing_pixels,
+        0
+    );
+
+    // Positions between two phases are drawn with the nearest one, instead of being blurred
+    let (_, quarter) = draw("S", phased, 10.25);
+    let (_, nearly_quarter) = draw("S", phased, 10.3);
+    assert_eq!(
+        compare_images(&quarter, &nearly_quarter, 0)
+            .unwrap()
+            .differing_pixels,
+        0
+    );
+
+    let (_, half) = draw("S", phased, 10.5);
+    assert!(compare_images(&quarter, &half, 0).unwrap().differing_pixels > 0);
+
+    // Every glyph of a longer text has its own phase
+    let (text, cpu_image) = draw("Scrolling text", phased, 10.25);
+    let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        renderer.draw_text(&mut pass, &text).unwrap();
+    });
+    let diff = compare_images(&cpu_image, &gpu_image, TOLERANCE).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the GPU output differs by up to {}",
+        diff.max_difference
+    );
+}
+
+#[test]
+fn em_sizes_are_relative_to_the_loaded_size() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let em_font = renderer
+        .load_font(FontRef::try_from_slice(FONT).unwrap(), FontSize::Em(1.5))
+        .unwrap();
+    let px_font = load_font(&mut renderer, 24.);
+
+    let mut bounds = |font, size| {
+        TextBuilder::new("Relative", font, [10., 60.])
+            .font_size(size)
+            .build(&device, &queue, &mut renderer)
+            .unwrap()
+            .bounds()
+    };
+
+    // Loading a font, an em is 16px
+    assert_eq!(bounds(em_font, None), bounds(px_font, None));
+
+    // Otherwise it's the size the font was loaded with
+    let doubled = bounds(px_font, Some(FontSize::Px(48.)));
+    assert_eq!(bounds(px_font, Some(FontSize::Em(2.))), doubled);
+    assert_eq!(bounds(em_font, Some(FontSize::Em(2.))), doubled);
+}
+
+#[test]
+fn baseline_offsets_move_aligned_text() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 20.);
+
+    let mut builder = TextBuilder::new("Nudged", font, [10., 60.]);
+    builder.vertical_align(VerticalAlignment::Middle);
+    let aligned = builder.build(&device, &queue, &mut renderer).unwrap();
+    let nudged = builder
+        .baseline_offset(-3.5)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let near = |a: f32, b: f32| (a - b).abs() < 0.01;
+    let [aligned, nudged] = [aligned.bounds(), nudged.bounds()];
+    assert!(near(nudged.min[0], aligned.min[0]));
+    assert!(near(nudged.min[1], aligned.min[1] - 3.5));
+    assert!(near(nudged.height(), aligned.height()));
+}
+
+#[test]
+fn trailing_whitespace_can_be_left_out_of_alignment() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 20.);
+
+    let mut build = |text: &str, ignore| {
+        TextBuilder::new(text, font, [300., 60.])
+            .horizontal_align(HorizontalAlignment::Right)
+            .ignore_trailing_whitespace(ignore)
+            .build(&device, &queue, &mut renderer)
+            .unwrap()
+    };
+
+    // Normally the spaces are what ends at the position, and the word is pushed left
+    let trimmed = build("Right", false).bounds();
+    let spaced = build("Right   ", false);
+    assert!(spaced.bounds().min[0] < trimmed.min[0] - 10.);
+
+    let ignored = build("Right   ", true);
+    assert_eq!(ignored.bounds(), trimmed);
+    // The caret after the spaces is still after the spaces
+    assert!(ignored.caret_rect(8).unwrap().min[0] > trimmed.max[0]);
+}
+
+#[test]
+fn visible_chars_limit_what_is_drawn() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 24.);
+
+    let mut build = |text: &str| {
+        TextBuilder::new(text, font, [10., 60.])
+            .build(&device, &queue, &mut renderer)
+            .unwrap()
+    };
+    let mut typing = build("Hello there");
+    let typed = build("Hello t");
+
+    // The space counts as a character
+    typing.set_visible_chars(Some(7));
+    let image = draw_cpu(&renderer, [&typing]);
+    let diff = compare_images(&draw_cpu(&renderer, [&typed]), &image, 0).unwrap();
+    assert_eq!(diff.differing_pixels, 0);
+
+    let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        renderer.draw_text(&mut pass, &typing).unwrap();
+    });
+    let diff = compare_images(&image, &gpu_image, TOLERANCE).unwrap();
+    assert_eq!(diff.differing_pixels, 0);
+
+    typing.set_visible_chars(Some(0));
+    let diff = compare_images(&background(), &draw_cpu(&renderer, [&typing]), 0).unwrap();
+    assert_eq!(diff.differing_pixels, 0);
+    assert_eq!(typing.visible_chars(), Some(0));
+}
+
+#[test]
+fn glyph_animations_match_cpu_renderer() {
+    use kaku::GlyphAnimation;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let raster = load_font(&mut renderer, 24.);
+    let sdf = load_sdf_font(&mut renderer, 24.);
+
+    let animations = [
+        GlyphAnimation::Wave {
+            amplitude: 4.,
+            speed: 0.5,
+        },
+        GlyphAnimation::Shake {
+            amplitude: 2.,
+            speed: 10.,
+        },
+        GlyphAnimation::Rainbow { speed: 0.25 },
+    ];
+    let mut texts = [
+        TextBuilder::new("Wavy text", raster, [10., 30.])
+            .animation(animations[0])
+            .build(&device, &queue, &mut renderer),
+        TextBuilder::new("Shaking", raster, [10., 70.])
+            .animation(animations[1])
+            .build(&device, &queue, &mut renderer),
+        TextBuilder::new("Rainbow", sdf, [160., 70.])
+            .animation(animations[2])
+            .outlined([0.2, 0.2, 0.2, 1.], 2.)
+            .build(&device, &queue, &mut renderer),
+    ]
+    .map(Result::unwrap);
+
+    renderer.set_time(1.3, &queue);
+    assert_eq!(renderer.time(), 1.3);
+
+    let cpu_image = draw_cpu(&renderer, &texts);
+
+    let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        for text in &texts {
+            renderer.draw_text(&mut pass, text).unwrap();
+        }
+    });
+
+    // The GPU filters the distance field slightly differently to the cpu renderer
+    let diff = compare_images(&cpu_image, &gpu_image, 8).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the GPU output differs by up to {}",
+        diff.max_difference
+    );
+
+    // Rainbow glyphs aren't drawn in the text's colour
+    assert!(cpu_image
+        .pixels()
+        .any(|pixel| pixel.0[0].abs_diff(pixel.0[2]) > 100));
+
+    // Each animation moves or recolours the glyphs
+    for (i, animation) in animations.into_iter().enumerate() {
+        texts[i].set_animation(GlyphAnimation::None, &queue);
+        let still = draw_cpu(&renderer, &texts);
+        assert!(
+            compare_images(&cpu_image, &still, 0)
+                .unwrap()
+                .differing_pixels
+                > 0
+        );
+
+        texts[i].set_animation(animation, &queue);
+        assert_eq!(texts[i].animation(), animation);
+    }
+}
+
+#[test]
+fn reveals_fade_glyphs_in_one_after_another() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let raster = load_font(&mut renderer, 24.);
+    let sdf = load_sdf_font(&mut renderer, 32.);
+
+    let builders = [
+        TextBuilder::new("Reveal", raster, [10., 30.]),
+        TextBuilder::new("Outlined", sdf, [10., 80.])
+            .outlined([0.8, 0.2, 0.2, 1.], 2.)
+            .clone(),
+    ];
+    let mut build = || {
+        builders
+            .each_ref()
+            .map(|b| b.build(&device, &queue, &mut renderer).unwrap())
+    };
+    let still = build();
+    let mut texts = build();
+
+    renderer.set_time(2., &queue);
+    for text in &mut texts {
+        assert_eq!(text.reveal_end(), None);
+        text.play_reveal(1., 0.25, &renderer, &queue);
+    }
+    // Six glyphs, the last of which starts 1.25 seconds in
+    assert_eq!(texts[0].reveal_end(), Some(4.25));
+
+    // Nothing is shown until the reveal starts, and everything is once it's over
+    assert_eq!(draw_cpu(&renderer, &texts), background());
+    renderer.set_time(5., &queue);
+    assert_eq!(draw_cpu(&renderer, &texts), draw_cpu(&renderer, &still));
+
+    renderer.set_time(2.6, &queue);
+    let cpu_image = draw_cpu(&renderer, &texts);
+    assert_ne!(cpu_image, background());
+    assert_ne!(cpu_image, draw_cpu(&renderer, &still));
+
+    let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        for text in &texts {
+            renderer.draw_text(&mut pass, text).unwrap();
+        }
+    });
+
+    // The GPU filters the distance field slightly differently to the cpu renderer
+    let diff = compare_images(&cpu_image, &gpu_image, 8).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the GPU output differs by up to {}",
+        diff.max_difference
+    );
+}
+
+#[test]
+fn marquees_scroll_text_inside_their_box() {
+    use kaku::Marquee;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let raster = load_font(&mut renderer, 24.);
+    let sdf = load_sdf_font(&mut renderer, 32.);
+
+    let looping = Marquee {
+        width: 80.,
+        speed: 50.,
+        gap: 30.,
+        looping: true,
+    };
+    let stopping = Marquee {
+        looping: false,
+        ..looping
+    };
+
+    let builders = [
+        TextBuilder::new("Scrolling along", raster, [10., 30.]),
+        TextBuilder::new("Outlined", sdf, [10., 80.])
+            .outlined([0.8, 0.2, 0.2, 1.], 2.)
+            .clone(),
+    ];
+    let still = builders.each_ref().map(|builder| {
+        let text = builder.build(&device, &queue, &mut renderer).unwrap();
+        assert_eq!(text.marquee(), None);
+        text
+    });
+
+    renderer.set_time(1., &queue);
+    let mut texts = [
+        builders[0].clone().marquee(Some(looping)).clone(),
+        builders[1].clone().marquee(Some(stopping)).clone(),
+    ]
+    .map(|builder| builder.build(&device, &queue, &mut renderer).unwrap());
+    assert_eq!(texts[0].marquee(), Some(looping));
+
+    // The box starts at the left of the text's bounds, which includes the outline
+    let outside_box = |renderer: &TextRenderer, text: &Text, drawn: &Text| {
+        let left = text.bounds().min[0];
+        let image = draw_cpu(renderer, std::slice::from_ref(drawn));
+        (image.enumerate_pixels())
+            .filter(|(x, ..)| !(left..left + 80.).contains(&(*x as f32 + 0.5)))
+            .any(|(.., pixel)| pixel.0 != [255; 4])
+    };
+
+    // The texts start where they are, cut off at the box, and scroll once the time moves on
+    let start = draw_cpu(&renderer, &texts);
+    for (text, still) in texts.iter().zip(&still) {
+        assert!(!outside_box(&renderer, text, text));
+        assert!(outside_box(&renderer, text, still));
+    }
+    renderer.set_time(1.5, &queue);
+    assert!(texts.iter().all(|text| !outside_box(&renderer, text, text)));
+    assert_ne!(draw_cpu(&renderer, &texts), start);
+
+    // A looping text comes round again after scrolling its width and the gap
+    let period = (texts[0].bounds().width() + looping.gap) / looping.speed;
+    renderer.set_time(1.5 + period, &queue);
+    let looped = draw_cpu(&renderer, &texts[..1]);
+    renderer.set_time(1.5, &queue);
+    let diff = compare_images(&draw_cpu(&renderer, &texts[..1]), &looped, TOLERANCE).unwrap();
+    assert!(diff.is_match(), "{} pixels differ", diff.differing_pixels);
+
+    // The other stops once its end is in the box
+    renderer.set_time(10., &queue);
+    let stopped = draw_cpu(&renderer, &texts[1..]);
+    renderer.set_time(20., &queue);
+    assert_eq!(draw_cpu(&renderer, &texts[1..]), stopped);
+
+    // Texts that fit in the box don't scroll, and texts without marquees aren't cut off
+    texts[1].set_marquee(Some(Marquee::new(300.)), &renderer, &queue);
+    assert_eq!(
+        draw_cpu(&renderer, &texts[1..]),
+        draw_cpu(&renderer, &still[1..])
+    );
+    texts[0].set_marquee(None, &renderer, &queue);
+    assert_eq!(
+        draw_cpu(&renderer, &texts[..1]),
+        draw_cpu(&renderer, &still[..1])
+    );
+
+    texts[0].set_marquee(Some(looping), &renderer, &queue);
+    texts[1].set_marquee(Some(stopping), &renderer, &queue);
+
+    // Far enough along for the looping text to have come round again
+    renderer.set_time(23.9, &queue);
+    let cpu_image = draw_cpu(&renderer, &texts);
+    let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        for text in &texts {
+            renderer.draw_text(&mut pass, text).unwrap();
+        }
+    });
+
+    // The GPU filters the distance field slightly differently to the cpu renderer
+    let diff = compare_images(&cpu_image, &gpu_image, 8).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the GPU output differs by up to {}",
+        diff.max_difference
+    );
+}
+
+#[test]
+fn opacity_fades_the_text_and_its_outline() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let sdf = load_sdf_font(&mut renderer, 32.);
+
+    let mut faded = TextBuilder::new("Faded", sdf, [10., 50.])
+        .color([0.1, 0.3, 0.8, 1.])
+        .outlined([0.8, 0.2, 0.2, 0.8], 3.)
+        .opacity(0.5)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+    let halved = TextBuilder::new("Faded", sdf, [10., 50.])
+        .color([0.1, 0.3, 0.8, 0.5])
+        .outlined([0.8, 0.2, 0.2, 0.4], 3.)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+    assert_eq!(faded.opacity(), 0.5);
+
+    let cpu_image = draw_cpu(&renderer, [&faded]);
+    assert_eq!(cpu_image, draw_cpu(&renderer, [&halved]));
+
+    let draw_gpu = |text: &Text| {
+        draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            renderer.draw_text(&mut pass, text).unwrap();
+        })
+    };
+    assert_eq!(draw_gpu(&faded), draw_gpu(&halved));
+
+    // The GPU filters the distance field slightly differently to the cpu renderer
+    let diff = compare_images(&cpu_image, &draw_gpu(&faded), 8).unwrap();
+    assert_eq!(
+        diff.differing_pixels, 0,
+        "the GPU output differs by up to {}",
+        diff.max_difference
+    );
+
+    faded.set_opacity(0., &queue);
+    assert_eq!(draw_cpu(&renderer, [&faded]), background());
+}
+
+#[test]
+fn applied_styles_match_built_styles() {
+    use kaku::TextStyle;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let sdf = load_sdf_font(&mut renderer, 32.);
+
+    let idle = TextStyle {
+        color: [0.2, 0.2, 0.2, 1.],
+        outline_color: [0.9, 0.6, 0.1, 1.],
+        ..Default::default()
+    };
+    let hovered = TextStyle {
+        color: [0.1, 0.3, 0.9, 1.],
+        outline_width: 3.,
+        scale: 1.5,
+        opacity: 0.6,
+        ..idle
+    };
+
+    let halfway = TextStyle::lerp(&idle, &hovered, 0.5);
+    assert_eq!(halfway.scale, 1.25);
+    assert_eq!(halfway.outline_width, 1.5);
+    assert_eq!(TextStyle::lerp(&idle, &hovered, 1.), hovered);
+
+    let mut text = TextBuilder::new("Hover me", sdf, [10., 60.])
+        .style(&idle)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+    let built = TextBuilder::new("Hover me", sdf, [10., 60.])
+        .style(&halfway)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let idle_width = text.bounds().width();
+    text.apply_style(&halfway, &queue, &renderer).unwrap();
+    assert_eq!(text.style(), halfway);
+    assert!(text.bounds().width() > idle_width * 1.2);
+    assert_eq!(text.bounds(), built.bounds());
+
+    assert_eq!(draw_cpu(&renderer, [&text]), draw_cpu(&renderer, [&built]));
+
+    let draw_gpu = |text: &Text| {
+        draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            renderer.draw_text(&mut pass, text).unwrap();
+        })
+    };
+    assert_eq!(draw_gpu(&text), draw_gpu(&built));
+}
+
+#[test]
+fn replaced_ranges_match_texts_built_from_scratch() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 16.);
+
+    let mut builder = TextBuilder::new(String::new(), font, [160., 20.]);
+    builder
+        .horizontal_align(HorizontalAlignment::Center)
+        .max_width(Some(150.))
+        .paragraph_spacing(2., 3.);
+
+    // Double buffered texts can't reuse the old instances, so they're written in full
+    for double_buffered in [false, true] {
+        let mut expected = String::from("fn main() {\r\n\tedit();\n}");
+        builder
+            .text(expected.clone())
+            .double_buffered(double_buffered);
+        let mut text = builder.build(&device, &queue, &mut renderer).unwrap();
+
+        let edits = [
+            // Typing in the middle of a line
+            (9..9, "x: u8"),
+            // Splitting and joining lines, including a \r\n
+            (13..13, "\n"),
+            (13..15, ""),
+            (14..14, "\r"),
+            // Deleting across lines, and a long line that wraps
+            (
+                3..20,
+                "main() { wrapped words that are longer than the width",
+            ),
+            // Characters that weren't cached yet
+            (0..0, "// ✓ ünïcödé\n"),
+            (usize::MAX..usize::MAX, "!"),
+            // Blank lines between paragraphs, which are spaced apart
+            (2..2, "\n\n"),
+            (3..3, "\n"),
+            (2..4, ""),
+            // Emptying the text and typing into it again
+            (0..usize::MAX, ""),
+            (0..0, "a\r\n"),
+        ];
+
+        for (range, replace_with) in edits {
+            let range = range.start.min(expected.len())..range.end.min(expected.len());
+            expected.replace_range(range.clone(), replace_with);
+            text.replace_range(range, replace_with, &device, &queue, &mut renderer)
+                .unwrap();
+
+            let built = builder
+                .text(expected.clone())
+                .build(&device, &queue, &mut renderer)
+                .unwrap();
+            // The lines after the edit are moved rather than laid out again, which can round
+            // differently
+            let close = |a: f32, b: f32| (a - b).abs() < 1e-3;
+            let same_rect = |a: Rect, b: Rect| {
+                (0..2).all(|i| close(a.min[i], b.min[i]) && close(a.max[i], b.max[i]))
+            };
+            let same_glyph = |(a, b): (LaidOutGlyph, LaidOutGlyph)| {
+                (a.character, a.index) == (b.character, b.index)
+                    && same_rect(a.rect, b.rect)
+                    && same_rect(a.ink, b.ink)
+            };
+            let same_line = |(a, b): (LineBox, LineBox)| {
+                (&a.glyphs, &a.text) == (&b.glyphs, &b.text)
+                    && same_rect(a.rect, b.rect)
+                    && close(a.baseline, b.baseline)
+            };
+            assert_eq!(text.glyphs().len(), built.glyphs().len(), "{expected:?}");
+            assert!(
+                text.glyphs().zip(built.glyphs()).all(same_glyph),
+                "{expected:?}"
+            );
+            assert_eq!(text.lines().len(), built.lines().len(), "{expected:?}");
+            assert!(
+                text.lines().zip(built.lines()).all(same_line),
+                "{expected:?}"
+            );
+
+            let draw_gpu = |text: &Text| {
+                draw_on_gpu(&device, &queue, |encoder, view| {
+                    let mut pass = begin_white_pass(encoder, view);
+                    renderer.draw_text(&mut pass, text).unwrap();
+                })
+            };
+            assert_eq!(draw_gpu(&text), draw_gpu(&built), "{expected:?}");
+        }
+    }
+}
+
+#[test]
+fn replaced_ranges_only_lay_out_the_lines_they_touch() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 12.);
+
+    let old_text = (10..40).map(|i| format!("line {i}")).collect::<Vec<_>>();
+    let old_text = old_text.join("\n");
+    let mut text = TextBuilder::new(old_text.clone(), font, [10., 10.])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+    let old_lines = text.lines().collect::<Vec<_>>();
+    let old_glyphs = text.glyphs().collect::<Vec<_>>();
+
+    // "line 25" becomes "line 35", which has the same width
+    let edit = old_text.find("line 25").unwrap() + "line ".len();
+    renderer.reset_frame_stats();
+    text.replace_range(edit..edit + 1, "3", &device, &queue, &mut renderer)
+        .unwrap();
+    let replaced = renderer.frame_stats().bytes_uploaded;
+
+    // Only the edited line changed, and only from the edit onwards
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines[..15], old_lines[..15]);
+    assert_eq!(lines[16..], old_lines[16..]);
+    assert_eq!(lines[15].text, old_lines[15].text);
+
+    let changed = (text.glyphs().zip(&old_glyphs))
+        .filter(|(glyph, old)| glyph != *old)
+        .map(|(glyph, _)| glyph.index)
+        .collect::<Vec<_>>();
+    assert_eq!(changed, [edit, edit + 1]);
+
+    // Only the instances between the old and new glyph's places in the instance buffer are
+    // uploaded, not the whole text's
+    renderer.reset_frame_stats();
+    let mut string = old_text.clone();
+    string.replace_range(edit..edit + 1, "3");
+    text.set_text(string, &device, &queue, &mut renderer)
+        .unwrap();
+    let set = renderer.frame_stats().bytes_uploaded;
+    assert!(replaced * 4 < set, "{replaced} {set}");
+}
+
+#[test]
+fn baked_texts_look_like_the_texts_they_were_baked_from() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let raster = load_font(&mut renderer, 24.);
+    let sdf = load_sdf_font(&mut renderer, 24.);
+
+    let mut texts = [
+        TextBuilder::new("Baked once", raster, [10., 30.]).build(&device, &queue, &mut renderer),
+        // Overlapping outlines and fills, at a fraction of a pixel and relative to the target
+        TextBuilder::new("Outlined", sdf, [100.5, -10.])
+            .outlined([0.8, 0.2, 0.2, 0.6], 3.)
+            .color([0.1, 0.3, 0.9, 0.8])
+            .relative_position([0., 0.5])
+            .build(&device, &queue, &mut renderer),
+    ]
+    .map(Result::unwrap);
+    let mut baked = texts
+        .each_ref()
+        .map(|text| renderer.bake(text, &device, &queue).unwrap());
+
+    for (text, baked) in texts.iter().zip(&baked) {
+        let text_bounds = text.bounds();
+        let bounds = baked.bounds();
+        assert!(bounds.min[0] <= text_bounds.min[0] && bounds.max[0] >= text_bounds.max[0]);
+        assert!(bounds.min[1] <= text_bounds.min[1] && bounds.max[1] >= text_bounds.max[1]);
+    }
+
+    for position in [None, Some([40., 70.])] {
+        if let Some(position) = position {
+            texts[0].set_position(position, &queue);
+            baked[0].set_position(position, &queue);
+        }
+
+        let direct = draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            for text in &texts {
+                renderer.draw_text(&mut pass, text).unwrap();
+            }
+        });
+        let from_baked = draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            for baked in &baked {
+                renderer.draw_baked(&mut pass, baked);
+            }
+        });
+        assert_ne!(direct, background(), "nothing was drawn");
+
+        let diff = compare_images(&direct, &from_baked, TOLERANCE).unwrap();
+        assert_eq!(
+            diff.differing_pixels, 0,
+            "the baked text at {position:?} differs by up to {}",
+            diff.max_difference
+        );
+    }
+}
+
+#[test]
+fn blurred_baked_texts_spread_out_their_ink() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 32.);
+    let text = TextBuilder::new("Shadow", font, [60., 40.])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let sharp = renderer.bake(&text, &device, &queue).unwrap();
+    let blurred = renderer.bake_blurred(&text, 3., &device, &queue).unwrap();
+
+    // The texture grows by three standard deviations on each side, so the blur isn't cut off
+    let [sharp_bounds, blurred_bounds] = [&sharp, &blurred].map(|baked| baked.bounds());
+    assert_eq!(blurred_bounds.min[0], sharp_bounds.min[0] - 9.);
+    assert_eq!(blurred_bounds.max[1], sharp_bounds.max[1] + 9.);
+
+    let [sharp_image, blurred_image] = [&sharp, &blurred].map(|baked| {
+        draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            renderer.draw_baked(&mut pass, baked);
+        })
+    });
+
+    let ink = |image: &RgbaImage| -> u64 { image.pixels().map(|p| 255 - p[0] as u64).sum() };
+    let darkest = |image: &RgbaImage| image.pixels().map(|p| p[0]).min().unwrap();
+    let ink_bounds = text.ink_bounds().unwrap();
+
+    // Blurring spreads the ink out past the glyphs and lightens it, without losing any of it
+    let (ink_sharp, ink_blurred) = (ink(&sharp_image), ink(&blurred_image));
+    assert!(ink_sharp.abs_diff(ink_blurred) * 50 < ink_sharp);
+    assert!(darkest(&blurred_image) > darkest(&sharp_image) + 40);
+
+    let below = (ink_bounds.max[1] + 3.) as u32;
+    let row_ink = |image: &RgbaImage| (0..SIZE.0).any(|x| image.get_pixel(x, below)[0] < 250);
+    assert!(!row_ink(&sharp_image));
+    assert!(row_ink(&blurred_image));
+}
+
+#[test]
+fn texts_can_be_baked_for_other_targets() {
+    use kaku::TargetConfig;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let hdr = renderer.add_target(
+        TargetConfig::new(wgpu::TextureFormat::Rgba16Float, SIZE),
+        &device,
+    );
+    let font = load_font(&mut renderer, 32.);
+    let text = TextBuilder::new("Shadow", font, [60., 40.])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    // Each target blurs with a pipeline for its own format
+    let baked = renderer.bake_blurred(&text, 2., &device, &queue).unwrap();
+    let hdr_baked = renderer
+        .bake_blurred_for_target(hdr, &text, 2., &device, &queue)
+        .unwrap();
+    assert_eq!(baked.texture().format(), wgpu::TextureFormat::Rgba8Unorm);
+    assert_eq!(
+        hdr_baked.texture().format(),
+        wgpu::TextureFormat::Rgba16Float
+    );
+
+    // Either texture can be drawn to the main target, and they look the same
+    let [image, hdr_image] = [&baked, &hdr_baked].map(|baked| {
+        draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            renderer.draw_baked(&mut pass, baked);
+        })
+    });
+    assert_ne!(image, background(), "nothing was drawn");
+    assert!(compare_images(&image, &hdr_image, TOLERANCE)
+        .unwrap()
+        .is_match());
+
+    // Targets belong to the renderer they were added to
+    let other_renderer = renderer_builder().build(&device);
+    assert_eq!(
+        other_renderer
+            .bake_for_target(hdr, &text, &device, &queue)
+            .unwrap_err(),
+        Error::TargetNotFound(hdr)
+    );
+}
+
+#[test]
+fn texts_too_big_to_bake_are_an_error() {
+    let limits = wgpu::Limits {
+        max_texture_dimension_2d: 256,
+        ..Default::default()
+    };
+    let Some((device, queue)) = device_with_limits(limits) else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 32.);
+    let text = TextBuilder::new("A line of text wider than the limit", font, [10., 40.])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    // The texture isn't made at all, rather than wgpu rejecting it
+    let Err(Error::TextureTooLarge { size, max }) = renderer.bake(&text, &device, &queue) else {
+        panic!("the text was baked");
+    };
+    assert!(size.0 > 256 && size.1 <= 256);
+    assert_eq!(max, 256);
+}
+
+#[test]
+fn panels_are_drawn_behind_texts() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let raster = load_font(&mut renderer, 20.);
+    let sdf = load_sdf_font(&mut renderer, 20.);
+
+    let bordered = Panel {
+        corner_radius: 8.,
+        border_color: [0.9, 0.6, 0.1, 1.],
+        border_width: 2.,
+        ..Panel::new([0.1, 0.1, 0.3, 0.8])
+    };
+    let mut texts = [
+        TextBuilder::new("Tooltip", raster, [20., 30.])
+            .color([1.; 4])
+            .panel(Some(bordered))
+            .build(&device, &queue, &mut renderer),
+        TextBuilder::new("Speech", sdf, [150., -20.])
+            .relative_position([0., 0.5])
+            .outlined([1., 1., 1., 1.], 2.)
+            .panel(Some(Panel::new([0.2, 0.7, 0.3, 0.5])))
+            .build(&device, &queue, &mut renderer),
+    ]
+    .map(Result::unwrap);
+
+    // The panel is the text's bounds with the padding around them
+    let bounds = texts[0].bounds();
+    let panel = texts[0].panel_rect().unwrap();
+    assert_eq!(panel.min, [bounds.min[0] - 8., bounds.min[1] - 4.]);
+    assert_eq!(panel.max, [bounds.max[0] + 8., bounds.max[1] + 4.]);
+
+    let draw = |texts: &[Text], renderer: &TextRenderer| {
+        let cpu_image = draw_cpu(renderer, texts);
+
+        let gpu_image = draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            for text in texts {
+                renderer.draw_text(&mut pass, text).unwrap();
+            }
+        });
+
+        // The GPU filters the distance field slightly differently to the cpu renderer
+        let diff = compare_images(&cpu_image, &gpu_image, 8).unwrap();
+        assert_eq!(
+            diff.differing_pixels, 0,
+            "the GPU output differs by up to {}",
+            diff.max_difference
+        );
+        gpu_image
+    };
+
+    let image = draw(&texts, &renderer);
+    // Inside the border, the fill is blended over the background
+    let [x, y] = [panel.min[0] as u32 + 4, panel.max[1] as u32 - 4];
+    let fill = image.get_pixel(x, y).0;
+    assert!(fill[2] > fill[0] && fill[0] < 80, "{fill:?}");
+    // The corners are rounded off
+    assert_eq!(
+        *image.get_pixel(panel.min[0] as u32, panel.min[1] as u32),
+        Rgba([255; 4])
+    );
+
+    // The panel follows the text when it's changed, moved or faded
+    texts[0]
+        .set_text("Longer tooltip".into(), &device, &queue, &mut renderer)
+        .unwrap();
+    texts[1].set_position([120., -10.], &queue);
+    texts[1].set_opacity(0.5, &queue);
+    assert!(texts[0].panel_rect().unwrap().max[0] > panel.max[0]);
+    draw(&texts, &renderer);
+
+    texts[1].set_panel(None, &renderer, &device, &queue);
+    assert_eq!(texts[1].panel_rect(), None);
+    draw(&texts, &renderer);
+}
+
+#[test]
+fn debug_overlays_outline_glyphs_and_lines() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 24.);
+    let mut text = TextBuilder::new("Ag\nxy", font, [20., 40.])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let draw = |text: &Text, renderer: &TextRenderer| {
+        draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            renderer.draw_debug(&mut pass, text, &device);
+        })
+    };
+
+    // Whether a pixel is close to a colour, in a row near `y`, since lines are a pixel tall
+    let has_color_near = |image: &RgbaImage, x: f32, y: f32, color: [u8; 3]| {
+        let y = y as i32;
+        (y - 1..=y + 1).any(|y| {
+            let pixel = image.get_pixel(x as u32, y as u32).0;
+            (0..3).all(|i| pixel[i].abs_diff(color[i]) <= TOLERANCE)
+        })
+    };
+    let red = [255, 0, 0];
+    let green = [0, 204, 0];
+    let blue = [0, 102, 255];
+    let magenta = [255, 0, 255];
+
+    let check = |text: &Text, image: &RgbaImage| {
+        for line in text.lines() {
+            let x = (line.rect.min[0] + line.rect.max[0]) / 2.;
+            assert!(has_color_near(image, x, line.rect.min[1], green));
+            assert!(has_color_near(image, x, line.baseline, red));
+            assert!(has_color_near(image, x, line.rect.max[1] - 1., blue));
+        }
+
+        // Each glyph's quad is hollow
+        for glyph in text.glyphs() {
+            let x = glyph.rect.min[0] + 0.5;
+            let y = (glyph.rect.min[1] + glyph.rect.max[1]) / 2.;
+            let pixel = image.get_pixel(x as u32, y as u32).0;
+            assert_eq!(pixel[..3], magenta, "{glyph:?}");
+        }
+    };
+
+    let image = draw(&text, &renderer);
+    assert_eq!(text.line_count(), 2);
+    check(&text, &image);
+    let rect = text.glyphs().next().unwrap().rect;
+    let centre = [0, 1].map(|i| (rect.min[i] + rect.max[i]) / 2.);
+    assert_eq!(
+        *image.get_pixel(centre[0] as u32, centre[1] as u32),
+        Rgba([255; 4])
+    );
+
+    // The overlay follows the text when it's moved or laid out again
+    text.set_position([60., 60.], &queue);
+    let image = draw(&text, &renderer);
+    check(&text, &image);
+
+    text.set_text("One line".into(), &device, &queue, &mut renderer)
+        .unwrap();
+    let image = draw(&text, &renderer);
+    assert_eq!(text.line_count(), 1);
+    check(&text, &image);
+}
+
+#[test]
+fn cache_views_show_the_cached_glyph_textures() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let raster = load_font(&mut renderer, 24.);
+    let sdf = load_sdf_font(&mut renderer, 24.);
+    for font in [raster, sdf] {
+        TextBuilder::new("kaku", font, [0.; 2])
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+    }
+
+    let draw = |views: &[&CacheView], renderer: &TextRenderer| {
+        draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            for view in views {
+                renderer.draw_cache_view(&mut pass, view).unwrap();
+            }
+        })
+    };
+
+    // Only characters with textures get a cell
+    let raster_view = renderer
+        .cache_view(raster, [4., 4.], 150., &device)
+        .unwrap();
+    let sdf_view = renderer.cache_view(sdf, [160., 4.], 150., &device).unwrap();
+    assert_eq!(raster_view.glyph_count(), 3);
+    assert_eq!(sdf_view.glyph_count(), 3);
+    assert_eq!(raster_view.bounds().min, [4., 4.]);
+
+    let image = draw(&[&raster_view, &sdf_view], &renderer);
+    for view in [&raster_view, &sdf_view] {
+        let bounds = view.bounds();
+        let pixels = (bounds.min[1] as u32..bounds.max[1] as u32)
+            .flat_map(|y| (bounds.min[0] as u32..bounds.max[0] as u32).map(move |x| (x, y)))
+            .map(|(x, y)| image.get_pixel(x, y).0)
+            .collect::<Vec<_>>();
+
+        // The textures are drawn in grey. The distance field is 0.5 on the edge of the glyph, and
+        // darker inside it
+        assert!(pixels.iter().all(|p| p[0] == p[1] && p[1] == p[2]));
+        assert!(pixels.iter().any(|p| p[0] < 120));
+        assert!(pixels.iter().any(|p| p[0] > 200));
+    }
+    // The sdf textures reach further around their glyphs, so they take up more room
+    assert!(sdf_view.bounds().height() > raster_view.bounds().height());
+
+    // Evicted glyphs aren't drawn
+    renderer.clear_cache(raster).unwrap();
+    let image = draw(&[&raster_view], &renderer);
+    assert!(image.pixels().all(|p| *p == Rgba([255; 4])));
+    let raster_view = renderer
+        .cache_view(raster, [4., 4.], 150., &device)
+        .unwrap();
+    assert_eq!(raster_view.glyph_count(), 0);
+    draw(&[&raster_view], &renderer);
+}
+
+#[test]
+fn cell_buffers_draw_backgrounds_and_glyphs() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let raster = load_font(&mut renderer, 16.);
+    let sdf = load_sdf_font(&mut renderer, 16.);
+    let blue = [0., 0., 1., 1.];
+    let red = [1., 0., 0., 1.];
+
+    for font in [raster, sdf] {
+        let mut cells = renderer
+            .cell_buffer(font, 12, 3, [10., 10.], &device, &queue)
+            .unwrap();
+        let [width, height] = cells.cell_size();
+        assert_eq!(cells.bounds().min, [10., 10.]);
+        assert_eq!(cells.bounds().max, [10. + 12. * width, 10. + 3. * height]);
+        assert!(cells.damaged_rows().eq(0..3));
+
+        for (x, c) in (0..).zip("kaku".chars()) {
+            cells.set_cell(x, 0, c, [0., 0., 0., 1.], blue);
+        }
+        cells.set_cell(5, 2, 'X', red, [0.; 4]);
+        assert_eq!(cells.cell(5, 2), Some(('X', red, [0.; 4])));
+        assert_eq!(cells.cell(12, 0), None);
+
+        renderer
+            .prepare_cell_buffer(&mut cells, &device, &queue)
+            .unwrap();
+        assert_eq!(cells.damaged_rows().count(), 0);
+
+        let image = draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            renderer.draw_cell_buffer(&mut pass, &cells).unwrap();
+        });
+
+        // The cell at column x of row y, in pixels
+        let cell = |x: f32, y: f32| Rect {
+            min: [10. + x * width, 10. + y * height],
+            max: [10. + (x + 1.) * width, 10. + (y + 1.) * height],
+        };
+        let pixels = |rect: Rect| {
+            (rect.min[1] as u32..rect.max[1] as u32)
+                .flat_map(move |y| (rect.min[0] as u32..rect.max[0] as u32).map(move |x| (x, y)))
+                .map(|(x, y)| image.get_pixel(x, y).0)
+                .collect::<Vec<_>>()
+        };
+
+        // The backgrounds fill their cells with no gaps between them, and the glyphs are drawn
+        // over them
+        let first_row = Rect {
+            min: cell(0., 0.).min,
+            max: cell(3., 0.).max,
+        };
+        let row = pixels(first_row);
+        assert!(row.iter().all(|p| p[0] == 0 && p[1] == 0), "{font:?}");
+        assert!(row.iter().any(|p| p == &[0, 0, 255, 255]));
+        assert!(row.iter().any(|p| p[2] < 60), "{font:?}");
+
+        // Blank cells with transparent backgrounds are left alone, and the glyphs are in their
+        // own colour
+        assert!(pixels(cell(4., 0.)).iter().all(|p| p == &[255; 4]));
+        let x = pixels(cell(5., 2.));
+        assert!(x.iter().any(|p| p[0] == 255 && p[1] < 60 && p[2] < 60));
+        assert!(x.iter().all(|p| p[0] == 255 && p[1] == p[2]));
+
+        // Nothing is drawn outside the grid, and only the cells that were set are drawn in
+        let drawn = drawn_bounds(&image).unwrap();
+        assert!(drawn.min[0] >= first_row.min[0] && drawn.min[1] >= first_row.min[1]);
+        assert!(drawn.max[0] <= cells.bounds().max[0] && drawn.max[1] <= cells.bounds().max[1]);
+    }
+}
+
+#[test]
+fn cell_buffers_only_upload_damaged_rows() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 16.);
+    let mut cells = renderer
+        .cell_buffer(font, 8, 4, [0.; 2], &device, &queue)
+        .unwrap();
+    let white = [1.; 4];
+    let black = [0., 0., 0., 1.];
+    for y in 0..4 {
+        for (x, c) in (0..).zip("aabcab".chars()) {
+            cells.set_cell(x, y, c, black, white);
+        }
+    }
+    renderer
+        .prepare_cell_buffer(&mut cells, &device, &queue)
+        .unwrap();
+
+    let draw = |renderer: &mut TextRenderer, cells: &CellBuffer| {
+        renderer.reset_frame_stats();
+        draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            renderer.draw_cell_buffer(&mut pass, cells).unwrap();
+        });
+        renderer.frame_stats()
+    };
+
+    // Every cell's background is one draw, then each glyph is bound once and drawn for each row
+    assert_eq!(
+        draw(&mut renderer, &cells),
+        FrameStats {
+            texts_drawn: 0,
+            instances: 32 + 24,
+            draw_calls: 1 + 3 * 4,
+            bind_group_switches: 3 + 3,
+            bytes_uploaded: 0,
+        }
+    );
+
+    // Setting a cell to what it already is doesn't damage its row
+    renderer.reset_frame_stats();
+    cells.set_cell(1, 2, 'a', black, white);
+    assert_eq!(cells.damaged_rows().count(), 0);
+    renderer
+        .prepare_cell_buffer(&mut cells, &device, &queue)
+        .unwrap();
+    assert_eq!(renderer.frame_stats().bytes_uploaded, 0);
+
+    // Changing one only uploads its row: the backgrounds and the glyphs in it
+    cells.set_cell(1, 2, 'c', black, white);
+    cells.set_cell(7, 2, ' ', black, [1., 0., 0., 1.]);
+    assert!(cells.damaged_rows().eq([2]));
+    renderer
+        .prepare_cell_buffer(&mut cells, &device, &queue)
+        .unwrap();
+    let instance_size = 20;
+    assert_eq!(
+        renderer.frame_stats().bytes_uploaded,
+        (8 + 6) * instance_size
+    );
+    assert_eq!(cells.damaged_rows().count(), 0);
+    let image = draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        renderer.draw_cell_buffer(&mut pass, &cells).unwrap();
+    });
+    let [width, height] = cells.cell_size();
+    let red = [7.5 * width, 2.5 * height].map(|p| p as u32);
+    assert_eq!(image.get_pixel(red[0], red[1]).0, [255, 0, 0, 255]);
+
+    // Evicted glyphs are generated again even though no rows are damaged
+    renderer.clear_cache(font).unwrap();
+    let stats = draw(&mut renderer, &cells);
+    assert_eq!(stats.draw_calls, 1);
+    renderer
+        .prepare_cell_buffer(&mut cells, &device, &queue)
+        .unwrap();
+    let stats = draw(&mut renderer, &cells);
+    assert_eq!(stats.instances, 32 + 24);
+}
+
+#[test]
+fn frame_stats_count_draws_and_uploads() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 20.);
+    assert_eq!(renderer.frame_stats(), FrameStats::default());
+
+    // Building a text uploads its glyphs' textures and its instances
+    let mut text = TextBuilder::new("hello", font, [10., 40.])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+    let stats = renderer.frame_stats();
+    assert!(stats.bytes_uploaded > 5 * 20, "{stats:?}");
+    assert_eq!(stats.texts_drawn, 0);
+
+    renderer.reset_frame_stats();
+    draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        renderer.draw_text(&mut pass, &text).unwrap();
+        renderer.draw_text(&mut pass, &text).unwrap();
+    });
+
+    // Each of the 4 different glyphs is a draw with its own texture, and the screen and the
+    // text's settings are bound too
+    assert_eq!(
+        renderer.frame_stats(),
+        FrameStats {
+            texts_drawn: 2,
+            instances: 10,
+            draw_calls: 8,
+            bind_group_switches: 12,
+            bytes_uploaded: 0,
+        }
+    );
+
+    // Changing the text's settings only uploads them
+    renderer.reset_frame_stats();
+    text.set_position([20., 40.], &queue);
+    let stats = renderer.frame_stats();
+    assert!(
+        stats.bytes_uploaded > 0 && stats.bytes_uploaded < 1024,
+        "{stats:?}"
+    );
+    assert_eq!(stats.draw_calls, 0);
+}
+
+#[test]
+fn glyph_textures_can_be_read_back() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    // Reading back is off by default
+    let mut renderer = renderer(&device);
+    let font = load_font(&mut renderer, 24.);
+    TextBuilder::new("A", font, [0.; 2])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+    assert_eq!(
+        renderer.read_glyph(font, 'A', &device, &queue),
+        Err(ReadGlyphError::ReadbackDisabled)
+    );
+
+    let mut renderer = renderer_builder().with_glyph_readback(true).build(&device);
+    let raster = load_font(&mut renderer, 24.);
+    let sdf = renderer
+        .load_font_with_sdf(
+            FontRef::try_from_slice(FONT).unwrap(),
+            FontSize::Px(24.),
+            SdfSettings {
+                format: SdfFormat::R16Float,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    for font in [raster, sdf] {
+        TextBuilder::new("A B", font, [0.; 2])
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+    }
+
+    // Raster glyphs are coverage, surrounded by transparent padding
+    let image = renderer
+        .read_glyph(raster, 'A', &device, &queue)
+        .unwrap()
+        .unwrap();
+    let DynamicImage::ImageLuma8(coverage) = image else {
+        panic!("expected an 8 bit image, got {:?}", image.color());
+    };
+    assert_eq!(coverage.get_pixel(0, 0).0, [0]);
+    assert!(coverage.pixels().any(|p| p.0[0] == 255));
+
+    // The distance field goes from below 0.5 inside the glyph to 1 far outside it
+    let image = renderer
+        .read_glyph(sdf, 'A', &device, &queue)
+        .unwrap()
+        .unwrap();
+    let DynamicImage::ImageLuma16(distance) = image else {
+        panic!("expected a 16 bit image, got {:?}", image.color());
+    };
+    assert_eq!(distance.get_pixel(0, 0).0, [u16::MAX]);
+    assert!(distance.pixels().any(|p| p.0[0] < u16::MAX / 2));
+
+    // Spaces and characters that haven't been cached don't have textures
+    assert_eq!(renderer.read_glyph(sdf, ' ', &device, &queue), Ok(None));
+    assert_eq!(renderer.read_glyph(sdf, 'Z', &device, &queue), Ok(None));
+}
+
+#[test]
+fn raster_glyphs_are_padded_with_transparency() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer_builder().with_glyph_readback(true).build(&device);
+    let mut font = |padding| {
+        let options = FontOptions::new(FontSize::Px(24.)).with_glyph_padding(padding);
+        let font = FontRef::try_from_slice(FONT).unwrap();
+        (renderer.load_font_with_options(font, options, &device, &queue)).unwrap()
+    };
+    let [unpadded, padded] = [font(0), font(3)];
+
+    let texts = [unpadded, padded].map(|font| {
+        let builder = TextBuilder::new("I", font, [20., 40.]);
+        let scaled = builder.clone().scale(4.).position([20., 150.]).clone();
+        [builder, scaled].map(|builder| builder.build(&device, &queue, &mut renderer).unwrap())
+    });
+
+    // The padding is a border of transparent pixels around the same coverage
+    let read = |font| match renderer.read_glyph(font, 'I', &device, &queue) {
+        Ok(Some(DynamicImage::ImageLuma8(image))) => image,
+        other => panic!("expected an 8 bit glyph, got {other:?}"),
+    };
+    let [unpadded_glyph, padded_glyph] = [read(unpadded), read(padded)];
+    assert_eq!(padded_glyph.width(), unpadded_glyph.width() + 6);
+    assert_eq!(padded_glyph.height(), unpadded_glyph.height() + 6);
+    for (x, y, pixel) in padded_glyph.enumerate_pixels() {
+        let inside = (3..padded_glyph.width() - 3).contains(&x)
+            && (3..padded_glyph.height() - 3).contains(&y);
+        let expected = match inside {
+            true => unpadded_glyph.get_pixel(x - 3, y - 3).0,
+            false => [0],
+        };
+        assert_eq!(pixel.0, expected, "pixel ({x}, {y})");
+    }
+
+    // At its own size the padding doesn't change anything, but scaled up the padded glyph's edges
+    // fade out instead of stopping at the quad
+    let [[unpadded_text, unpadded_scaled], [padded_text, padded_scaled]] = &texts;
+    assert_eq!(
+        draw_cpu(&renderer, [unpadded_text]),
+        draw_cpu(&renderer, [padded_text])
+    );
+    let unpadded_bounds = drawn_bounds(&draw_cpu(&renderer, [unpadded_scaled])).unwrap();
+    let padded_bounds = drawn_bounds(&draw_cpu(&renderer, [padded_scaled])).unwrap();
+    assert!(padded_bounds.width() > unpadded_bounds.width());
+    assert!(padded_bounds.min[0] < unpadded_bounds.min[0]);
+    assert!(padded_bounds.max[0] > unpadded_bounds.max[0]);
+}
+
+/// Draws an outlined sdf text with distance fields in the given format on the GPU, along with the
+/// same text drawn by the cpu renderer.
+fn draw_sdf_format(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    format: SdfFormat,
+) -> Result<(RgbaImage, RgbaImage), Error> {
+    let mut renderer = renderer(device);
+    let font = renderer.load_font_with_sdf(
+        FontRef::try_from_slice(FONT).unwrap(),
+        FontSize::Px(24.),
+        SdfSettings {
+            radius: 16.,
+            format,
+            ..Default::default()
+        },
+    )?;
+
+    let text = TextBuilder::new("Wide glow", font, [10., 60.])
+        .outlined([0.8, 0.2, 0.2, 1.], 12.)
+        .scale(2.)
+        .build(device, queue, &mut renderer)?;
+
+    let gpu_image = draw_on_gpu(device, queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        renderer.draw_text(&mut pass, &text).unwrap();
+    });
+
+    let mut cpu_image = background();
+    renderer.draw_text_to_image(&text, &mut cpu_image)?;
+    Ok((gpu_image, cpu_image))
+}
+
+#[test]
+fn r16_float_distance_fields() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    // The GPU samples the 16 bit field the way the cpu renderer expects
+    let (gpu_image, cpu_image) = draw_sdf_format(&device, &queue, SdfFormat::R16Float).unwrap();
+    let diff = compare_images(&cpu_image, &gpu_image, TOLERANCE).unwrap();
+    assert!(diff.is_match(), "{} pixels differ", diff.differing_pixels);
+
+    // It looks like the 8 bit field, apart from the 8 bit one's steps, which move edges by up to
+    // a quarter of a pixel with this radius and scale
+    let (eight_bit, _) = draw_sdf_format(&device, &queue, SdfFormat::R8Unorm).unwrap();
+    let diff = compare_images(&eight_bit, &gpu_image, TOLERANCE).unwrap();
+    assert!(diff.differing_pixels > 0);
+    assert!(diff.max_difference < 96, "{}", diff.max_difference);
+    assert!(diff.rmse < 4., "{}", diff.rmse);
+}
+
+#[test]
+fn r16_unorm_distance_fields_need_the_device_feature() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let feature = wgpu::Features::TEXTURE_FORMAT_16BIT_NORM;
+
+    if !device.features().contains(feature) {
+        assert_eq!(
+            draw_sdf_format(&device, &queue, SdfFormat::R16Unorm).unwrap_err(),
+            Error::MissingFeatures(feature)
+        );
+
+        // Compatibility mode falls back to 8 bits instead
+        let mut renderer = renderer_builder()
+            .with_compatibility_mode(true)
+            .build(&device);
+        let settings = SdfSettings {
+            format: SdfFormat::R16Unorm,
+            ..Default::default()
+        };
+        let font = FontRef::try_from_slice(FONT).unwrap();
+        assert!(renderer
+            .load_font_with_sdf(font, FontSize::Px(24.), settings)
+            .is_ok());
+    }
+
+    // Where the adapter has the feature, the fields draw like any other
+    let Some((device, queue)) = device_with_features(feature, Default::default()) else {
+        return;
+    };
+
+    let (gpu_image, cpu_image) = draw_sdf_format(&device, &queue, SdfFormat::R16Unorm).unwrap();
+    let diff = compare_images(&cpu_image, &gpu_image, TOLERANCE).unwrap();
+    assert!(diff.is_match(), "{} pixels differ", diff.differing_pixels);
+}
+
+#[test]
+fn image_comparisons_report_difference_metrics() {
+    let expected = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+    let mut actual = expected.clone();
+    actual.put_pixel(1, 2, Rgba([140, 100, 100, 255]));
+
+    let diff = compare_images(&expected, &actual, TOLERANCE).unwrap();
+    assert!(!diff.is_match());
+    assert_eq!(diff.differing_pixels, 1);
+    assert_eq!(diff.max_difference, 40);
+    // One channel out of 64 differs by 40
+    assert!((diff.rmse - 5.).abs() < 1e-9);
+    assert_eq!(diff.diff_image.get_pixel(1, 2).0, [255, 0, 0, 255]);
+
+    let diff = compare_images(&expected, &expected, 0).unwrap();
+    assert!(diff.is_match());
+    assert_eq!(diff.rmse, 0.);
+
+    assert!(compare_images(&expected, &RgbaImage::new(2, 2), TOLERANCE).is_none());
+}
+
+#[test]
+fn missing_golden_images_fail_instead_of_being_written() {
+    // Golden images are only written when KAKU_UPDATE_GOLDEN is set
+    if std::env::var_os(kaku::UPDATE_GOLDEN_VAR).is_some() {
+        return;
+    }
+
+    let dir = std::env::temp_dir().join(format!("kaku-golden-{}", std::process::id()));
+    let path = dir.join("missing.png");
+    let image = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+
+    let result = std::panic::catch_unwind(|| assert_matches_golden(&image, &path, TOLERANCE));
+    assert!(result.is_err());
+    assert!(!path.exists());
+    // The image is still saved to look at
+    assert!(dir.join("missing.actual.png").exists());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fonts_can_request_extra_texture_usages() {
+    use kaku::{FontOptions, Preload};
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let mut renderer = renderer(&device);
+    let plain = renderer
+        .load_font_with_options(
+            FontRef::try_from_slice(FONT).unwrap(),
+            FontOptions::new(FontSize::Px(24.)).with_preload(Preload::Chars(vec!['A', ' '])),
+            &device,
+            &queue,
+        )
+        .unwrap();
+    let copyable = renderer
+        .load_font_with_options(
+            FontRef::try_from_slice(FONT).unwrap(),
+            FontOptions::new(FontSize::Px(24.))
+                .with_preload(Preload::Chars(vec!['A', ' ']))
+                .with_texture_usages(wgpu::TextureUsages::COPY_SRC),
+            &device,
+            &queue,
+        )
+        .unwrap();
+
+    let texture = renderer.glyph_texture(plain, 'A').unwrap().unwrap();
+    assert_eq!(
+        texture.usage(),
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+    );
+
+    let texture = renderer.glyph_texture(copyable, 'A').unwrap().unwrap();
+    assert!(texture.usage().contains(wgpu::TextureUsages::COPY_SRC));
+    assert_eq!(texture.format(), wgpu::TextureFormat::R8Unorm);
+
+    // Spaces and characters that haven't been cached don't have textures
+    assert!(renderer.glyph_texture(copyable, ' ').unwrap().is_none());
+    assert!(renderer.glyph_texture(copyable, 'Z').unwrap().is_none());
+}
+
+#[test]
+fn sampler_settings_can_be_changed_for_renderers_and_fonts() {
+    use kaku::{FontOptions, SamplerSettings};
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let draw = |builder: TextRendererBuilder, options: FontOptions| {
+        let mut renderer = builder.build(&device);
+        let font = renderer
+            .load_font_with_options(
+                FontRef::try_from_slice(FONT).unwrap(),
+                options,
+                &device,
+                &queue,
+            )
+            .unwrap();
+        let text = TextBuilder::new("Sample", font, [10., 80.])
+            .scale(2.5)
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+
+        draw_on_gpu(&device, &queue, |encoder, view| {
+            let mut pass = begin_white_pass(encoder, view);
+            renderer.draw_text(&mut pass, &text).unwrap();
+        })
+    };
+
+    let options = FontOptions::new(FontSize::Px(24.));
+    let expected = draw(renderer_builder(), options.clone());
+
+    // Glyph textures are padded with empty space, so the address mode shouldn't change how they
+    // look
+    let repeating = SamplerSettings {
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::MirrorRepeat,
+        ..Default::default()
+    };
+    let image = draw(renderer_builder(), options.clone().with_sampler(repeating));
+    let diff = compare_images(&expected, &image, TOLERANCE).unwrap();
+    assert!(diff.is_match(), "{} pixels differ", diff.differing_pixels);
+
+    // Anisotropic filtering is allowed, but how it looks depends on the GPU
+    let anisotropic = SamplerSettings {
+        mipmap_filter: wgpu::FilterMode::Linear,
+        anisotropy_clamp: 16,
+        ..Default::default()
+    };
+    let image = draw(
+        renderer_builder().with_sampler(anisotropic),
+        options.clone(),
+    );
+    assert!(image.pixels().any(|pixel| pixel.0 != [255; 4]));
+
+    // Samplers that can't be anisotropic (here because the mipmap filter is nearest) leave it off
+    let nearest_mipmaps = SamplerSettings {
+        anisotropy_clamp: 16,
+        ..Default::default()
+    };
+    let image = draw(renderer_builder(), options.with_sampler(nearest_mipmaps));
+    let diff = compare_images(&expected, &image, TOLERANCE).unwrap();
+    assert!(diff.is_match(), "{} pixels differ", diff.differing_pixels);
+}
+
+#[test]
+fn glyphs_share_the_renderers_samplers() {
+    use kaku::SamplerSettings;
+
+    // An instance of its own, so its report only counts this test's samplers
+    let instance = wgpu::Instance::default();
+    let Some(adapter) = pollster::block_on(instance.request_adapter(&Default::default())) else {
+        return;
+    };
+    let backend = adapter.get_info().backend;
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&Default::default(), None)).unwrap();
+    let samplers = || {
+        let report = instance.generate_report().unwrap();
+        report.hub_report(backend).samplers.num_kept_from_user
+    };
+
+    let mut h = Harness {
+        renderer: renderer(&device),
+        device,
+        queue,
+    };
+    let built = samplers();
+
+    // However many glyphs there are, in however many fonts, they use the renderer's samplers
+    let raster = h.font(24.);
+    let sdf = h.sdf_font(24.);
+    let bitmap = h.bitmap_font(&BitmapFont::from_bdf(include_str!("fonts/test.bdf")).unwrap());
+    for font in [raster, sdf] {
+        h.build(&TextBuilder::new(
+            "The quick brown fox jumps",
+            font,
+            [0., 30.],
+        ));
+    }
+    h.build(&TextBuilder::new("ABg", bitmap, [0., 30.]));
+    assert!(h.renderer.cached_chars(raster).count() > 10);
+    assert_eq!(samplers(), built);
+
+    // A font with its own sampler settings has one sampler for all of its glyphs
+    let repeating = SamplerSettings {
+        address_mode_u: wgpu::AddressMode::Repeat,
+        ..Default::default()
+    };
+    let font = h.font_with_options(FontOptions::new(FontSize::Px(24.)).with_sampler(repeating));
+    h.build(&TextBuilder::new(
+        "The quick brown fox jumps",
+        font,
+        [0., 30.],
+    ));
+    assert_eq!(samplers(), built + 1);
+}
+
+#[test]
+fn glyph_quads_are_made_from_the_vertex_index() {
+    use kaku::ShaderDebugMode;
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    // The quads are drawn in, so each glyph's quad should be filled out to all four corners
+    let mut renderer = renderer_builder()
+        .with_debug_mode(ShaderDebugMode::GlyphQuads)
+        .build(&device);
+    let font = load_sdf_font(&mut renderer, 32.);
+    let text = TextBuilder::new("I  .  W", font, [20., 60.])
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let image = draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        renderer.draw_text(&mut pass, &text).unwrap();
+    });
+
+    let quads: Vec<Rect> = text.glyphs().map(|glyph| glyph.rect).collect();
+    assert_eq!(quads.len(), 3);
+    for quad in &quads {
+        let Rect { min, max } = *quad;
+        let corners = [
+            [min[0], min[1]],
+            [min[0], max[1]],
+            [max[0], min[1]],
+            [max[0], max[1]],
+        ];
+        for [x, y] in corners {
+            // The pixel just inside the corner, towards the middle of the quad
+            let x = if x == min[0] { x + 1. } else { x - 2. };
+            let y = if y == min[1] { y + 1. } else { y - 2. };
+            let pixel = image.get_pixel(x as u32, y as u32);
+            assert_ne!(*pixel, Rgba([255; 4]), "the corner of {quad:?} is empty");
+        }
+    }
+
+    // Nothing is drawn outside of the quads
+    let union = quads.into_iter().reduce(|a, b| a.union(b)).unwrap();
+    assert_close(drawn_bounds(&image).unwrap(), union, 1.);
+}
+
+#[test]
+#[cfg(feature = "wgpu-profiler")]
+fn text_draws_are_profiled() {
+    use kaku::wgpu_profiler::{GpuProfiler, GpuProfilerSettings};
+
+    let features = wgpu::Features::TIMESTAMP_QUERY
+        | wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS
+        | wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES;
+    let Some((device, queue)) = device_with_features(features, Default::default()) else {
+        return;
+    };
+
+    let mut renderer = renderer_builder().with_label_prefix("ui").build(&device);
+    let font = load_sdf_font(&mut renderer, 32.);
+
+    let text = TextBuilder::new("Outlined", font, [10., 50.])
+        .outlined([0.8, 0.2, 0.2, 1.], 4.)
+        .build(&device, &queue, &mut renderer)
+        .unwrap();
+
+    let mut profiler = GpuProfiler::new(GpuProfilerSettings::default()).unwrap();
+    let profiled = draw_on_gpu(&device, &queue, |encoder, view| {
+        {
+            let mut pass = begin_white_pass(encoder, view);
+            (renderer.draw_text_profiled(&mut pass, &text, &profiler, &device)).unwrap();
+        }
+        profiler.resolve_queries(encoder);
+    });
+    profiler.end_frame().unwrap();
+    device.poll(wgpu::Maintain::Wait);
+
+    // The text is timed in a scope named after it and its font, with its outline inside it
+    let results = (profiler.process_finished_frame(queue.get_timestamp_period())).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].label, "ui text \"Outlined\" (font 0)");
+    assert!(results[0].time.is_some());
+    let nested: Vec<_> = (results[0].nested_queries.iter())
+        .map(|query| query.label.as_str())
+        .collect();
+    assert_eq!(nested, ["ui outline"]);
+
+    // The scopes don't change what's drawn
+    let unprofiled = draw_on_gpu(&device, &queue, |encoder, view| {
+        let mut pass = begin_white_pass(encoder, view);
+        renderer.draw_text(&mut pass, &text).unwrap();
+    });
+    assert_eq!(profiled, unprofiled);
+}
+
+#[test]
+#[cfg(all(feature = "tracing", feature = "rayon"))]
+fn glyph_generation_and_uploads_are_traced() {
+    use std::fmt::{Debug, Write};
+    use std::sync::{Arc, Mutex};
+    use tracing::field::Field;
+    use tracing::{span, Event, Metadata, Subscriber};
+
+    /// Records the name and fields of every span that's opened.
+    #[derive(Clone, Default)]
+    struct Spans(Arc<Mutex<Vec<String>>>);
+
+    impl Subscriber for Spans {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &span::Attributes) -> span::Id {
+            let mut description = span.metadata().name().to_string();
+            span.record(&mut |field: &Field, value: &dyn Debug| {
+                write!(description, " {}={value:?}", field.name()).unwrap();
+            });
+            let mut spans = self.0.lock().unwrap();
+            spans.push(description);
+            span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record) {}
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, _: &Event) {}
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    // Glyphs are generated on the pool's thread, so it records its spans too
+    let spans = Spans::default();
+    let pool = {
+        let spans = spans.clone();
+        kaku::rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .spawn_handler(move |thread| {
+                let spans = spans.clone();
+                std::thread::spawn(move || {
+                    tracing::subscriber::with_default(spans, || thread.run())
+                });
+                Ok(())
+            })
+            .build()
+            .unwrap()
+    };
+
+    let mut renderer = renderer(&device);
+    renderer.set_thread_pool(Some(Arc::new(pool)));
+    let raster = load_font(&mut renderer, 16.);
+    let sdf = load_sdf_font(&mut renderer, 16.);
+    tracing::subscriber::with_default(spans.clone(), || {
+        for (text, font) in [("Hi", raster), ("H", sdf)] {
+            TextBuilder::new(text, font, [10., 30.])
+                .build(&device, &queue, &mut renderer)
+                .unwrap();
+        }
+    });
+
+    // Each glyph is generated and uploaded in spans saying which glyph it is, and each text's
+    // instances in one span
+    let mut spans = spans.0.lock().unwrap().clone();
+    spans.sort();
+    assert_eq!(
+        spans,
+        [
+            "create_char glyph='H' sdf=false",
+            "create_char glyph='H' sdf=true",
+            "create_char glyph='i' sdf=false",
+            "create_char_bind_group glyph='H' width=11 height=14",
+            "create_char_bind_group glyph='H' width=29 height=32",
+            "create_char_bind_group glyph='i' width=5 height=15",
+            "create_sdf_image glyph='H'",
+            "from_instances instances=1",
+            "from_instances instances=2",
+            "generate_chars font=0",
+            "generate_chars font=1",
+            "generate_glyph_textures font=0",
+            "generate_glyph_textures font=1",
+        ]
+    );
+}
+
+// wgpu 0.20's validation errors don't say which object they're about, but later versions name it
+// by its label
+#[test]
+#[cfg(not(feature = "wgpu-0_20"))]
+fn gpu_objects_are_labelled_with_the_renderers_prefix() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+
+    let label_of_glyph_texture = |builder: TextRendererBuilder| {
+        let mut renderer = builder.build(&device);
+        let font = load_font(&mut renderer, 16.);
+        TextBuilder::new("A", font, [10., 30.])
+            .build(&device, &queue, &mut renderer)
+            .unwrap();
+
+        // Viewing a destroyed texture is an error that names it
+        let texture = renderer.glyph_texture(font, 'A').unwrap().unwrap();
+        texture.destroy();
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        texture.create_view(&Default::default());
+        let error = pollster::block_on(device.pop_error_scope()).unwrap();
+        let error = error.to_string();
+        let label = error.split("Texture with '").nth(1).unwrap();
+        label[..label.find("' label").unwrap()].to_string()
+    };
+
+    assert_eq!(
+        label_of_glyph_texture(renderer_builder()),
+        "kaku texture for 'A'"
+    );
+    assert_eq!(
+        label_of_glyph_texture(renderer_builder().with_label_prefix("world text")),
+        "world text texture for 'A'"
+    );
+}
@@ -0,0 +1,57 @@
+//! Layout tests that also run on wasm32, to check that text is laid out the same way on the web as
+//! it is natively.
+//!
+//! Run natively with `cargo test --features measure`, and on the web with
+//! `cargo test --target wasm32-unknown-unknown --features measure --test web`, which needs Node.js
+//! and `wasm-bindgen-test-runner` from `wasm-bindgen-cli` (at the same version as kaku's
+//! `wasm-bindgen`). They only need the measurer, so they also check that kaku still builds without
+//! wgpu: `cargo test --no-default-features --features measure`.
+
+#![cfg(feature = "measure")]
+
+use ab_glyph::FontRef;
+use kaku::{
+    FontOptions, FontSize, HorizontalAlignment, Rect, SdfSettings, TextBuilder, TextMeasurer,
+};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_test::wasm_bindgen_test as test;
+
+const FONT: &[u8] = include_bytes!("../examples/fonts/FiraSans-Regular.ttf");
+
+#[test]
+fn text_is_laid_out_the_same_as_natively() {
+    let mut measurer = TextMeasurer::new();
+    measurer.set_scale_factor(1.5);
+    let options = [
+        FontOptions::new(FontSize::Px(18.)),
+        FontOptions::new(FontSize::Pt(18.)).with_sdf(SdfSettings::default()),
+    ];
+
+    let mut layouts = Vec::new();
+    for options in options {
+        let font = FontRef::try_from_slice(FONT).unwrap();
+        let font = measurer.load_font_with_options(font, options).unwrap();
+        let builder = TextBuilder::new("Kerning: AV To\twrapped text, laid out", font, [0., 0.])
+            .max_width(Some(180.))
+            .horizontal_align(HorizontalAlignment::Center)
+            .clone();
+
+        let layout = measurer.layout(&builder).unwrap();
+        let lines = (layout.lines()).map(|line| {
+            let Rect { min, max } = line.rect;
+            [min[0], min[1], max[0], max[1], line.baseline]
+        });
+        layouts.extend(lines);
+    }
+
+    // What it comes to natively, as [left, top, right, bottom, baseline] for each line
+    let expected = [
+        [-67.986, -16.830002, 67.986, 4.7700005, 0.0],
+        [-76.338, 4.769999, 76.338, 26.37, 21.6],
+        [-13.743001, 26.37, 13.743001, 47.97, 43.2],
+        [-90.648, -22.44, 90.648, 6.36, 0.0],
+        [-78.0, 6.3600006, 78.0, 35.16, 28.800001],
+        [-42.108, 35.16, 42.108, 63.960003, 57.600002],
+    ];
+    assert_eq!(layouts, expected);
+}